@@ -1,84 +1,176 @@
 pub mod apt;
 pub mod backlight;
+pub mod backup;
 pub mod base_block;
 pub mod battery;
 pub mod bluetooth;
+pub mod calendar;
+pub mod cgroup;
+pub mod charge_limit;
+pub mod ci_status;
+pub mod countdown;
 pub mod cpu;
+pub mod cpufreq;
+pub mod currency;
 pub mod custom;
 pub mod custom_dbus;
+pub mod custom_socket;
 pub mod disk_space;
+pub mod displays;
+pub mod dns;
 pub mod docker;
+pub mod external_ip;
+pub mod fan;
+pub mod feeds;
 pub mod focused_window;
+pub mod git;
 pub mod github;
+pub mod gitlab;
+pub mod http;
 pub mod hueshift;
 pub mod ibus;
+pub mod idle_inhibitor;
+pub mod imap;
+pub mod jira;
+pub mod journal;
 pub mod kdeconnect;
+pub mod kernel;
 pub mod keyboard_layout;
+pub mod kubernetes;
 pub mod load;
+pub mod lock_keys;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+pub mod mdraid;
 pub mod memory;
 pub mod music;
 pub mod net;
 pub mod networkmanager;
+pub mod notifications;
 pub mod notify;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 pub mod nvidia_gpu;
 pub mod pacman;
+pub mod ping;
+pub mod plugin;
 pub mod pomodoro;
+pub mod power_profiles;
+pub mod pressure;
+pub mod privacy;
+pub mod processes;
+pub mod scratchpad;
+pub mod screencast;
 pub mod sound;
 pub mod speedtest;
+pub mod ssh;
+pub mod stocks;
+pub mod stopwatch;
+pub mod sway_mode;
+pub mod systemd_failed;
+pub mod systemd_timers;
+pub mod tailscale;
 pub mod taskwarrior;
 pub mod temperature;
 pub mod template;
 pub mod time;
 pub mod toggle;
+pub mod ups;
 pub mod uptime;
 pub mod watson;
 pub mod weather;
+pub mod wireguard;
+pub mod workspaces;
 pub mod xrandr;
+pub mod zfs;
+pub mod zypper;
 
 use self::apt::*;
 use self::backlight::*;
+use self::backup::*;
 use self::base_block::*;
 use self::battery::*;
 use self::bluetooth::*;
+use self::calendar::*;
+use self::cgroup::*;
+use self::charge_limit::*;
+use self::ci_status::*;
+use self::countdown::*;
 use self::cpu::*;
+use self::cpufreq::*;
+use self::currency::*;
 use self::custom::*;
 use self::custom_dbus::*;
+use self::custom_socket::*;
 use self::disk_space::*;
+use self::displays::*;
+use self::dns::*;
 use self::docker::*;
+use self::external_ip::*;
+use self::fan::*;
+use self::feeds::*;
 use self::focused_window::*;
+use self::git::*;
 use self::github::*;
+use self::gitlab::*;
+use self::http::*;
 use self::hueshift::*;
 use self::ibus::*;
+use self::idle_inhibitor::*;
+use self::imap::*;
+use self::jira::*;
+use self::journal::*;
 use self::kdeconnect::*;
+use self::kernel::*;
 use self::keyboard_layout::*;
+use self::kubernetes::*;
 use self::load::*;
+use self::lock_keys::*;
 #[cfg(feature = "maildir")]
 use self::maildir::*;
+use self::mdraid::*;
 use self::memory::*;
 use self::music::*;
 use self::net::*;
 use self::networkmanager::*;
+use self::notifications::*;
 use self::notify::*;
 #[cfg(feature = "notmuch")]
 use self::notmuch::*;
 use self::nvidia_gpu::*;
 use self::pacman::*;
+use self::ping::*;
+use self::plugin::*;
 use self::pomodoro::*;
+use self::power_profiles::*;
+use self::pressure::*;
+use self::privacy::*;
+use self::processes::*;
+use self::scratchpad::*;
+use self::screencast::*;
 use self::sound::*;
 use self::speedtest::*;
+use self::ssh::*;
+use self::stocks::*;
+use self::stopwatch::*;
+use self::sway_mode::*;
+use self::systemd_failed::*;
+use self::systemd_timers::*;
+use self::tailscale::*;
 use self::taskwarrior::*;
 use self::temperature::*;
 use self::template::*;
 use self::time::*;
 use self::toggle::*;
+use self::ups::*;
 use self::uptime::*;
 use self::watson::*;
 use self::weather::*;
+use self::wireguard::*;
+use self::workspaces::*;
 use self::xrandr::*;
+use self::zfs::*;
+use self::zypper::*;
 
 use std::time::Duration;
 
@@ -90,6 +182,9 @@ use crate::config::SharedConfig;
 use crate::errors::*;
 use crate::input::I3BarEvent;
 use crate::scheduler::Task;
+use crate::signals::convert_to_valid_signal;
+use crate::util::FormatTemplate;
+use crate::widgets::i3block_data::{I3BlockAlign, I3BlockMinWidth};
 use crate::widgets::I3BarWidget;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -110,6 +205,14 @@ impl Into<Update> for Duration {
     }
 }
 
+/// The state of a block changed by the user at runtime, by hiding it or by clicking to show its
+/// `format_alt`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlockState {
+    pub hidden: bool,
+    pub format_alt_shown: bool,
+}
+
 /// The ConfigBlock trait combines a constructor (new(...)) and an associated configuration type
 /// to form a block that can be instantiated from a piece of TOML (from the block configuration).
 /// The associated type has to be a deserializable struct, which you can then use to get your
@@ -120,9 +223,17 @@ impl Into<Update> for Duration {
 /// thread, provide you know the Block's ID. This advanced feature can be used to reduce
 /// the number of system calls by asynchronously waiting for events. A usage example can be found
 /// in the Music block, which updates only when dbus signals a new song.
+///
+/// Sending fails once the block has been dropped, e.g. after the config was reloaded, so threads
+/// sending update requests should stop then rather than unwrap the result.
 pub trait ConfigBlock: Block {
     type Config;
 
+    /// The options of the block that are format strings, with the placeholders each one knows.
+    /// `format_alt` and `short_format` know those of `format`. Options that are not listed, e.g.
+    /// the strftime `format` of the time block, are not checked by `--check`.
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[];
+
     /// Creates a new block from the relevant configuration.
     fn new(
         id: usize,
@@ -167,6 +278,62 @@ pub trait Block {
         Ok(())
     }
 
+    /// Gives access to the main format of the block, so that `BaseBlock` can swap it with the
+    /// `format_alt` of the block config. Blocks without a `FormatTemplate` don't support it.
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        None
+    }
+
+    /// Hides the block, or shows it again if it was hidden. This is implemented once for all
+    /// blocks by `BaseBlock`, so blocks do not need to override it.
+    fn toggle_hidden(&mut self) {}
+
+    /// A value in percent shown by the block, e.g. the charge of a battery, which is given to
+    /// Waybar to pick an icon.
+    fn percentage(&self) -> Option<u64> {
+        None
+    }
+
+    /// Swaps `format` with `format_alt` and updates the block, if it has both. This is implemented
+    /// once for all blocks by `BaseBlock`.
+    fn toggle_format(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The state the user gave the block at runtime, which is kept when the config is reloaded.
+    /// This is implemented once for all blocks by `BaseBlock`.
+    fn state(&self) -> BlockState {
+        BlockState::default()
+    }
+
+    /// Gives the block back the state of the block it replaces after a reload.
+    fn restore_state(&mut self, _state: BlockState) {}
+
+    /// The separator in front of the block, if it replaces the one of the theme. This is
+    /// implemented once for all blocks by `BaseBlock`, from the `separator` option.
+    fn separator(&self) -> Option<&Separator> {
+        None
+    }
+
+    /// The minimum width of the first widget of the block, if set by the `min_width` option.
+    /// This is implemented once for all blocks by `BaseBlock`.
+    fn min_width(&self) -> Option<&I3BlockMinWidth> {
+        None
+    }
+
+    /// The alignment of the first widget of the block within its minimum width, if set by the
+    /// `align` option. This is implemented once for all blocks by `BaseBlock`.
+    fn align(&self) -> Option<I3BlockAlign> {
+        None
+    }
+
+    /// The text shown instead of `full_text`, the text of the first widget of the block, when the
+    /// bar is too narrow. This is implemented once for all blocks by `BaseBlock`, from the
+    /// `short_format` option.
+    fn short_text(&self, _full_text: &str) -> Option<String> {
+        None
+    }
+
     /// Sends click events to the block.
     ///
     /// Here you can react to the user clicking your block. The I3BarEvent instance contains all
@@ -190,27 +357,246 @@ macro_rules! block {
         if let Some(ref overrides) = common_config.theme_overrides {
             $shared_config.theme_override(overrides)?;
         }
+        if let Some(ref overrides) = common_config.icons_overrides {
+            $shared_config.icons_override(overrides);
+        }
         if let Some(overrides) = common_config.icons_format {
             $shared_config.icons_format_override(overrides);
         }
 
+        // If the signal is not in the valid range we return an error
+        let signal = match common_config.signal {
+            Some(signal) => Some(convert_to_valid_signal(signal)?),
+            None => None,
+        };
+
         // Extract block-specific config
         let block_config = <$block_type as ConfigBlock>::Config::deserialize($block_config)
             .configuration_error("Failed to deserialize block config.")?;
 
-        let mut block = $block_type::new($id, block_config, $shared_config, $update_request)?;
+        $shared_config.pango_markup = common_config.markup == Markup::Pango;
+        let mut block =
+            $block_type::new($id, block_config, $shared_config.clone(), $update_request)?;
         if let Some(overrided) = block.override_on_click() {
             *overrided = common_config.on_click.take();
         }
 
+        let mut format_alt = match common_config.format_alt {
+            Some(ref format_alt) if block.format_mut().is_some() => Some(
+                FormatTemplate::new(format_alt, &$shared_config)
+                    .configuration_error("Failed to parse format_alt.")?,
+            ),
+            Some(_) => {
+                return Err(BlockError(
+                    stringify!($block_type).to_string(),
+                    "`format_alt` is not supported by this block".to_string(),
+                ))
+            }
+            None => None,
+        };
+
+        match common_config.short_format {
+            Some(ref short_format) if block.format_mut().is_some() => {
+                let short_format = FormatTemplate::new(short_format, &$shared_config)
+                    .configuration_error("Failed to parse short_format.")?;
+                if let Some(ref mut format_alt) = format_alt {
+                    format_alt.set_short(short_format.clone());
+                }
+                if let Some(format) = block.format_mut() {
+                    format.set_short(short_format);
+                }
+            }
+            Some(_) => {
+                return Err(BlockError(
+                    stringify!($block_type).to_string(),
+                    "`short_format` is not supported by this block".to_string(),
+                ))
+            }
+            None => (),
+        }
+
         Ok(Box::new(BaseBlock {
             name: stringify!($block_type).to_string(),
             inner: block,
             on_click: common_config.on_click,
+            hidden: false,
+            signal,
+            format_alt,
+            format_alt_button: common_config.format_alt_button,
+            short_text: None,
+            error_policy: common_config.error_policy,
+            separator: common_config.separator,
+            min_width: common_config.min_width,
+            align: common_config.align,
+            error: None,
+            retries: 0,
+            interval: None,
+            format_alt_shown: false,
+            shared_config: $shared_config,
         }) as Box<dyn Block>)
     }};
 }
 
+macro_rules! check {
+    ($block_type:ident, $block_config:expr, $shared_config:expr) => {{
+        let common_value = BaseBlockConfig::extract(&mut $block_config);
+        let common_config = BaseBlockConfig::deserialize(common_value.clone())
+            .configuration_error("Failed to deserialize common block config.")?;
+        if let Some(ref overrides) = common_config.theme_overrides {
+            $shared_config.theme_override(overrides)?;
+        }
+        if let Some(signal) = common_config.signal {
+            convert_to_valid_signal(signal)?;
+        }
+
+        $shared_config.pango_markup = common_config.markup == Markup::Pango;
+        check_formats(
+            &common_value,
+            &$block_config,
+            <$block_type as ConfigBlock>::FORMATS,
+            &$shared_config,
+        )?;
+        <$block_type as ConfigBlock>::Config::deserialize($block_config)
+            .configuration_error("Failed to deserialize block config.")?;
+        Ok(())
+    }};
+}
+
+/// Expands to a `match` of the name of a block, which calls `$action!` with the type of the block
+/// followed by `$args`.
+macro_rules! with_block_type {
+    ($name:expr, $action:ident!($($args:expr),*)) => {
+        match $name {
+            // Please keep these in alphabetical order.
+            "apt" => $action!(Apt, $($args),*),
+            "backlight" => $action!(Backlight, $($args),*),
+            "backup" => $action!(Backup, $($args),*),
+            "battery" => $action!(Battery, $($args),*),
+            "bluetooth" => $action!(Bluetooth, $($args),*),
+            "calendar" => $action!(Calendar, $($args),*),
+            "cgroup" => $action!(Cgroup, $($args),*),
+            "charge_limit" => $action!(ChargeLimit, $($args),*),
+            "ci_status" => $action!(CiStatus, $($args),*),
+            "countdown" => $action!(Countdown, $($args),*),
+            "cpu" => $action!(Cpu, $($args),*),
+            "cpufreq" => $action!(Cpufreq, $($args),*),
+            "currency" => $action!(Currency, $($args),*),
+            "custom" => $action!(Custom, $($args),*),
+            "custom_dbus" => $action!(CustomDBus, $($args),*),
+            "custom_socket" => $action!(CustomSocket, $($args),*),
+            "disk_space" => $action!(DiskSpace, $($args),*),
+            "displays" => $action!(Displays, $($args),*),
+            "dns" => $action!(Dns, $($args),*),
+            "docker" => $action!(Docker, $($args),*), ///////
+            "external_ip" => $action!(ExternalIp, $($args),*),
+            "fan" => $action!(Fan, $($args),*),
+            "feeds" => $action!(Feeds, $($args),*),
+            "focused_window" => $action!(FocusedWindow, $($args),*),
+            "git" => $action!(Git, $($args),*),
+            "github" => $action!(Github, $($args),*),
+            "gitlab" => $action!(Gitlab, $($args),*),
+            "http" => $action!(Http, $($args),*),
+            "hueshift" => $action!(Hueshift, $($args),*),
+            "ibus" => $action!(IBus, $($args),*),
+            "idle_inhibitor" => $action!(IdleInhibitor, $($args),*),
+            "imap" => $action!(Imap, $($args),*),
+            "jira" => $action!(Jira, $($args),*),
+            "journal" => $action!(Journal, $($args),*),
+            "kdeconnect" => $action!(KDEConnect, $($args),*),
+            "kernel" => $action!(Kernel, $($args),*),
+            "keyboard_layout" => $action!(KeyboardLayout, $($args),*),
+            "kubernetes" => $action!(Kubernetes, $($args),*),
+            "load" => $action!(Load, $($args),*),
+            "lock_keys" => $action!(LockKeys, $($args),*),
+            #[cfg(feature = "maildir")]
+            "maildir" => $action!(Maildir, $($args),*),
+            "mdraid" => $action!(Mdraid, $($args),*),
+            "memory" => $action!(Memory, $($args),*),
+            "music" => $action!(Music, $($args),*),
+            "net" => $action!(Net, $($args),*),
+            "networkmanager" => $action!(NetworkManager, $($args),*),
+            "notifications" => $action!(Notifications, $($args),*),
+            "notify" => $action!(Notify, $($args),*),
+            #[cfg(feature = "notmuch")]
+            "notmuch" => $action!(Notmuch, $($args),*),
+            "nvidia_gpu" => $action!(NvidiaGpu, $($args),*),
+            "pacman" => $action!(Pacman, $($args),*),
+            "ping" => $action!(Ping, $($args),*),
+            "plugin" => $action!(Plugin, $($args),*),
+            "pomodoro" => $action!(Pomodoro, $($args),*),
+            "power_profiles" => $action!(PowerProfiles, $($args),*),
+            "pressure" => $action!(Pressure, $($args),*),
+            "privacy" => $action!(Privacy, $($args),*),
+            "processes" => $action!(Processes, $($args),*),
+            "scratchpad" => $action!(Scratchpad, $($args),*),
+            "screencast" => $action!(Screencast, $($args),*),
+            "sound" => $action!(Sound, $($args),*),
+            "speedtest" => $action!(SpeedTest, $($args),*),
+            "ssh" => $action!(Ssh, $($args),*),
+            "stocks" => $action!(Stocks, $($args),*),
+            "stopwatch" => $action!(Stopwatch, $($args),*),
+            "sway_mode" => $action!(SwayMode, $($args),*),
+            "systemd_failed" => $action!(SystemdFailed, $($args),*),
+            "systemd_timers" => $action!(SystemdTimers, $($args),*),
+            "tailscale" => $action!(Tailscale, $($args),*),
+            "taskwarrior" => $action!(Taskwarrior, $($args),*),
+            "temperature" => $action!(Temperature, $($args),*),
+            "template" => $action!(Template, $($args),*),
+            "time" => $action!(Time, $($args),*), /////////
+            "toggle" => $action!(Toggle, $($args),*),
+            "ups" => $action!(Ups, $($args),*),
+            "uptime" => $action!(Uptime, $($args),*),
+            "watson" => $action!(Watson, $($args),*),
+            "weather" => $action!(Weather, $($args),*),
+            "wireguard" => $action!(Wireguard, $($args),*),
+            "workspaces" => $action!(Workspaces, $($args),*),
+            "xrandr" => $action!(Xrandr, $($args),*),
+            "zfs" => $action!(Zfs, $($args),*),
+            "zypper" => $action!(Zypper, $($args),*),
+            other => Err(BlockError(other.to_string(), "Unknown block!".to_string())),
+        }
+    };
+}
+
+/// Parses the formats among the options of a block, i.e. the options named `format`, `format_*`
+/// or `*_format`.
+/// Parses the formats of a block that are set in its config, and checks that they only use
+/// placeholders the block knows.
+fn check_formats(
+    common_config: &Value,
+    block_config: &Value,
+    formats: &[(&str, &[&str])],
+    shared_config: &SharedConfig,
+) -> Result<()> {
+    let check = |config: &Value, key: &str, placeholders: &[&str]| -> Result<()> {
+        let format = match config.get(key).and_then(Value::as_str) {
+            Some(format) => FormatTemplate::new(format, shared_config)
+                .configuration_error(&format!("Failed to parse {}.", key))?,
+            None => return Ok(()),
+        };
+        match format
+            .placeholders()
+            .into_iter()
+            .find(|placeholder| !placeholders.contains(placeholder))
+        {
+            Some(unknown) => Err(ConfigurationError(
+                format!("Unknown placeholder {} in {}.", unknown, key),
+                (String::new(), String::new()),
+            )),
+            None => Ok(()),
+        }
+    };
+
+    for &(key, placeholders) in formats {
+        check(block_config, key, placeholders)?;
+        if key == "format" {
+            check(common_config, "format_alt", placeholders)?;
+            check(common_config, "short_format", placeholders)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn create_block(
     id: usize,
     name: &str,
@@ -218,65 +604,42 @@ pub fn create_block(
     mut shared_config: SharedConfig,
     update_request: Sender<Task>,
 ) -> Result<Box<dyn Block>> {
-    match name {
-        // Please keep these in alphabetical order.
-        "apt" => block!(Apt, id, block_config, shared_config, update_request),
-        "backlight" => block!(Backlight, id, block_config, shared_config, update_request),
-        "battery" => block!(Battery, id, block_config, shared_config, update_request),
-        "bluetooth" => block!(Bluetooth, id, block_config, shared_config, update_request),
-        "cpu" => block!(Cpu, id, block_config, shared_config, update_request),
-        "custom" => block!(Custom, id, block_config, shared_config, update_request),
-        "custom_dbus" => block!(CustomDBus, id, block_config, shared_config, update_request),
-        "disk_space" => block!(DiskSpace, id, block_config, shared_config, update_request),
-        "docker" => block!(Docker, id, block_config, shared_config, update_request), ///////
-        "focused_window" => block!(
-            FocusedWindow,
-            id,
-            block_config,
-            shared_config,
-            update_request
-        ),
-        "github" => block!(Github, id, block_config, shared_config, update_request),
-        "hueshift" => block!(Hueshift, id, block_config, shared_config, update_request),
-        "ibus" => block!(IBus, id, block_config, shared_config, update_request),
-        "kdeconnect" => block!(KDEConnect, id, block_config, shared_config, update_request),
-        "keyboard_layout" => block!(
-            KeyboardLayout,
-            id,
-            block_config,
-            shared_config,
-            update_request
-        ),
-        "load" => block!(Load, id, block_config, shared_config, update_request),
-        #[cfg(feature = "maildir")]
-        "maildir" => block!(Maildir, id, block_config, shared_config, update_request),
-        "memory" => block!(Memory, id, block_config, shared_config, update_request),
-        "music" => block!(Music, id, block_config, shared_config, update_request),
-        "net" => block!(Net, id, block_config, shared_config, update_request),
-        "networkmanager" => block!(
-            NetworkManager,
-            id,
-            block_config,
-            shared_config,
-            update_request
-        ),
-        "notify" => block!(Notify, id, block_config, shared_config, update_request),
-        #[cfg(feature = "notmuch")]
-        "notmuch" => block!(Notmuch, id, block_config, shared_config, update_request),
-        "nvidia_gpu" => block!(NvidiaGpu, id, block_config, shared_config, update_request),
-        "pacman" => block!(Pacman, id, block_config, shared_config, update_request),
-        "pomodoro" => block!(Pomodoro, id, block_config, shared_config, update_request),
-        "sound" => block!(Sound, id, block_config, shared_config, update_request),
-        "speedtest" => block!(SpeedTest, id, block_config, shared_config, update_request),
-        "taskwarrior" => block!(Taskwarrior, id, block_config, shared_config, update_request),
-        "temperature" => block!(Temperature, id, block_config, shared_config, update_request),
-        "template" => block!(Template, id, block_config, shared_config, update_request),
-        "time" => block!(Time, id, block_config, shared_config, update_request), /////////
-        "toggle" => block!(Toggle, id, block_config, shared_config, update_request),
-        "uptime" => block!(Uptime, id, block_config, shared_config, update_request),
-        "watson" => block!(Watson, id, block_config, shared_config, update_request),
-        "weather" => block!(Weather, id, block_config, shared_config, update_request),
-        "xrandr" => block!(Xrandr, id, block_config, shared_config, update_request),
-        other => Err(BlockError(other.to_string(), "Unknown block!".to_string())),
+    with_block_type!(
+        name,
+        block!(id, block_config, shared_config, update_request)
+    )
+}
+
+/// Checks the config of a block like `create_block` does, but without creating the block, so
+/// that no thread is started and nothing is read from the system.
+pub fn check_block(
+    name: &str,
+    mut block_config: Value,
+    mut shared_config: SharedConfig,
+) -> Result<()> {
+    with_block_type!(name, check!(block_config, shared_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_block;
+    use crate::config::SharedConfig;
+
+    fn check(name: &str, config: &str) -> bool {
+        let config = config.parse().unwrap();
+        check_block(name, config, SharedConfig::default()).is_ok()
+    }
+
+    #[test]
+    fn test_check_block_placeholders() {
+        assert!(check("uptime", r#"format = "up {uptime}""#));
+        assert!(!check("uptime", r#"format = "up {uptme}""#));
+        assert!(!check("uptime", r#"format_alt = "{load}""#));
+        assert!(!check(
+            "wireguard",
+            "interface = \"wg0\"\nformat_down = \"{rx}\""
+        ));
+        // Not a format string of the template syntax
+        assert!(check("time", r#"format = "%a %d/%m %R""#));
     }
 }
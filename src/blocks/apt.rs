@@ -28,6 +28,8 @@ pub struct Apt {
     warning_updates_regex: Option<Regex>,
     critical_updates_regex: Option<Regex>,
     config_path: String,
+    /// Whether the formats show the phased updates, which take another apt command
+    phased: bool,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -85,6 +87,12 @@ impl AptConfig {
 impl ConfigBlock for Apt {
     type Config = AptConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        ("format", &["{count}", "{phased}", "{security}"]),
+        ("format_singular", &["{count}", "{phased}", "{security}"]),
+        ("format_up_to_date", &["{count}", "{phased}", "{security}"]),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -111,17 +119,24 @@ impl ConfigBlock for Apt {
             .block_error("apt", "Failed to create config file")?;
         write!(config_file, "{}", apt_conf).block_error("apt", "Failed to write to config file")?;
 
-        let output = TextWidget::new(id, 0, shared_config).with_icon("update");
+        let output = TextWidget::new(id, 0, shared_config.clone()).with_icon("update");
+        let format = FormatTemplate::new(&block_config.format, &shared_config)
+            .block_error("apt", "Invalid format specified for apt::format")?;
+        let format_singular = FormatTemplate::new(&block_config.format_singular, &shared_config)
+            .block_error("apt", "Invalid format specified for apt::format_singular")?;
+        let format_up_to_date =
+            FormatTemplate::new(&block_config.format_up_to_date, &shared_config)
+                .block_error("apt", "Invalid format specified for apt::format_up_to_date")?;
+        let phased = [&format, &format_singular, &format_up_to_date]
+            .iter()
+            .any(|format| format.has_placeholder("{phased}"));
 
         Ok(Apt {
             id,
             update_interval: block_config.interval,
-            format: FormatTemplate::from_string(&block_config.format)
-                .block_error("apt", "Invalid format specified for apt::format")?,
-            format_singular: FormatTemplate::from_string(&block_config.format_singular)
-                .block_error("apt", "Invalid format specified for apt::format_singular")?,
-            format_up_to_date: FormatTemplate::from_string(&block_config.format_up_to_date)
-                .block_error("apt", "Invalid format specified for apt::format_up_to_date")?,
+            format,
+            format_singular,
+            format_up_to_date,
             output,
             warning_updates_regex: match block_config.warning_updates_regex {
                 None => None, // no regex configured
@@ -154,6 +169,7 @@ impl ConfigBlock for Apt {
                 }
             },
             config_path: cache_dir.into_os_string().into_string().unwrap(),
+            phased,
         })
     }
 }
@@ -192,7 +208,57 @@ fn get_update_count(updates: &str) -> usize {
         .count()
 }
 
+/// The number of updates from a security archive, e.g. `jammy-security` or `bullseye-security`,
+/// in lines such as `vim/jammy-updates,jammy-security 2:8.2.3995-1ubuntu2.1 amd64 [upgradable from: ...]`.
+fn get_security_count(updates: &str) -> usize {
+    updates
+        .lines()
+        .filter(|line| line.contains("[upgradable"))
+        .filter(|line| {
+            line.split(' ')
+                .next()
+                .and_then(|package| package.split('/').nth(1))
+                .map_or(false, |archives| {
+                    archives
+                        .split(',')
+                        .any(|archive| archive.ends_with("-security"))
+                })
+        })
+        .count()
+}
+
+fn get_upgrade_simulation(config_path: &str) -> Result<String> {
+    String::from_utf8(
+        Command::new("sh")
+            .env("APT_CONFIG", config_path)
+            .env("LC_ALL", "C")
+            .args(&["-c", "apt-get --simulate upgrade"])
+            .output()
+            .block_error("apt", "Problem running apt-get command")?
+            .stdout,
+    )
+    .block_error("apt", "Problem capturing apt-get command output")
+}
+
+/// The number of updates held back until their phased rollout reaches this machine, which a
+/// simulated upgrade lists as indented package names after a header.
+fn get_phased_count(simulation: &str) -> usize {
+    simulation
+        .lines()
+        .skip_while(|line| {
+            !line.starts_with("The following upgrades have been deferred due to phasing:")
+        })
+        .skip(1)
+        .take_while(|line| line.starts_with(' '))
+        .map(|line| line.split_whitespace().count())
+        .sum()
+}
+
 impl Block for Apt {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -205,7 +271,16 @@ impl Block for Apt {
         let (formatting_map, warning, critical, cum_count) = {
             let updates_list = get_updates_list(&self.config_path)?;
             let count = get_update_count(&updates_list);
-            let formatting_map = map!("{count}" => count);
+            let phased = if self.phased {
+                get_phased_count(&get_upgrade_simulation(&self.config_path)?)
+            } else {
+                0
+            };
+            let formatting_map = map!(
+                "{count}" => count,
+                "{security}" => get_security_count(&updates_list),
+                "{phased}" => phased
+            );
 
             let warning = self
                 .warning_updates_regex
@@ -245,3 +320,36 @@ impl Block for Apt {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::apt::{get_phased_count, get_security_count, get_update_count};
+
+    #[test]
+    fn test_get_security_count() {
+        let updates = concat!(
+            "Listing...\n",
+            "libssl3/jammy-updates,jammy-security 3.0.2-0ubuntu1.7 amd64 [upgradable from: 3.0.2-0ubuntu1.6]\n",
+            "snapd/jammy-updates 2.57.5+22.04 amd64 [upgradable from: 2.56.2+22.04ubuntu1]\n",
+            "curl/stable-security 7.74.0-1.3+deb11u3 amd64 [upgradable from: 7.74.0-1.3+deb11u2]\n",
+        );
+        assert_eq!(get_update_count(updates), 3);
+        assert_eq!(get_security_count(updates), 2);
+    }
+
+    #[test]
+    fn test_get_phased_count() {
+        let simulation = concat!(
+            "Reading package lists...\n",
+            "Calculating upgrade...\n",
+            "The following upgrades have been deferred due to phasing:\n",
+            "  gnome-shell gnome-shell-common\n",
+            "  mutter-common\n",
+            "The following packages will be upgraded:\n",
+            "  libssl3 snapd\n",
+            "2 upgraded, 0 newly installed, 0 to remove and 3 not upgraded.\n",
+        );
+        assert_eq!(get_phased_count(simulation), 3);
+        assert_eq!(get_phased_count("0 upgraded, 0 newly installed\n"), 0);
+    }
+}
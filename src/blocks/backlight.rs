@@ -6,10 +6,14 @@
 //! does not depend on `xrandr` (and thus it works on Wayland). To set
 //! brightness levels using `xrandr`, see the
 //! [`Xrandr`](../xrandr/struct.Xrandr.html) block.
+//!
+//! External monitors, which have no `sysfs` entry, are supported through
+//! DDC/CI with `ddcutil`.
 
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -20,6 +24,7 @@ use serde_derive::Deserialize;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::config::{LogicalDirection, Scrolling};
+use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::input::I3BarEvent;
 use crate::scheduler::Task;
@@ -42,6 +47,12 @@ fn read_brightness(device_file: &Path) -> Result<u64> {
         .block_error("backlight", "Failed to read value from brightness file")
 }
 
+/// A device whose brightness level can be queried and set, as a percent.
+pub trait BrightnessDevice {
+    fn brightness(&self) -> Result<u64>;
+    fn set_brightness(&self, value: u64) -> Result<()>;
+}
+
 /// Represents a physical backlit device whose brightness level can be queried.
 pub struct BacklitDevice {
     max_brightness: u64,
@@ -58,6 +69,26 @@ fn clamp_root_scaling(root_scaling: f64) -> f64 {
     }
 }
 
+/// Convert a raw brightness value to a percent.
+fn raw_to_percent(raw: u64, max_brightness: u64, root_scaling: f64) -> u64 {
+    let brightness_ratio = (raw as f64 / max_brightness as f64).powf(root_scaling.recip());
+    let brightness = (brightness_ratio * 100.0).round() as u64;
+    match brightness {
+        0..=100 => brightness,
+        _ => 100,
+    }
+}
+
+/// Convert a percent to a raw brightness value, which is never 0 so that the screen stays lit.
+fn percent_to_raw(value: u64, max_brightness: u64, root_scaling: f64) -> u64 {
+    let safe_value = match value {
+        0..=100 => value,
+        _ => 100,
+    };
+    let ratio = (safe_value as f64 / 100.0).powf(root_scaling);
+    std::cmp::max(1, (ratio * (max_brightness as f64)).round() as u64)
+}
+
 impl BacklitDevice {
     /// Use the default backlit device, i.e. the first one found in the
     /// `/sys/class/backlight` directory.
@@ -104,44 +135,6 @@ impl BacklitDevice {
         })
     }
 
-    /// Query the brightness value for this backlit device, as a percent.
-    pub fn brightness(&self) -> Result<u64> {
-        let raw = read_brightness(&self.brightness_file())?;
-        let brightness_ratio =
-            (raw as f64 / self.max_brightness as f64).powf(self.root_scaling.recip());
-        let brightness = (brightness_ratio * 100.0).round() as u64;
-        match brightness {
-            0..=100 => Ok(brightness),
-            _ => Ok(100),
-        }
-    }
-
-    /// Set the brightness value for this backlit device, as a percent.
-    pub fn set_brightness(&self, value: u64) -> Result<()> {
-        let safe_value = match value {
-            0..=100 => value,
-            _ => 100,
-        };
-        let ratio = (safe_value as f64 / 100.0).powf(self.root_scaling);
-        let raw = std::cmp::max(1, (ratio * (self.max_brightness as f64)).round() as u64);
-
-        let file = OpenOptions::new()
-            .write(true)
-            .open(self.device_path.join("brightness"));
-        if file.is_err() {
-            // TODO: Find a way to issue a non-fatal error, since this is likely
-            // due to a permissions issue and not the fault of the user. It
-            // should not crash the bar.
-            // Error: "Failed to open brightness file for writing"
-            return self.set_brightness_via_dbus(raw);
-        }
-
-        // It's safe to unwrap() here because we checked for errors above.
-        file.unwrap()
-            .write_fmt(format_args!("{}", raw))
-            .block_error("backlight", "Failed to write into brightness file")
-    }
-
     fn set_brightness_via_dbus(&self, raw_value: u64) -> Result<()> {
         let device_name = self
             .device_path
@@ -178,14 +171,122 @@ impl BacklitDevice {
     }
 }
 
+impl BrightnessDevice for BacklitDevice {
+    /// Query the brightness value for this backlit device, as a percent.
+    fn brightness(&self) -> Result<u64> {
+        let raw = read_brightness(&self.brightness_file())?;
+        Ok(raw_to_percent(raw, self.max_brightness, self.root_scaling))
+    }
+
+    /// Set the brightness value for this backlit device, as a percent.
+    fn set_brightness(&self, value: u64) -> Result<()> {
+        let raw = percent_to_raw(value, self.max_brightness, self.root_scaling);
+
+        let file = OpenOptions::new()
+            .write(true)
+            .open(self.device_path.join("brightness"));
+        if file.is_err() {
+            // TODO: Find a way to issue a non-fatal error, since this is likely
+            // due to a permissions issue and not the fault of the user. It
+            // should not crash the bar.
+            // Error: "Failed to open brightness file for writing"
+            return self.set_brightness_via_dbus(raw);
+        }
+
+        // It's safe to unwrap() here because we checked for errors above.
+        file.unwrap()
+            .write_fmt(format_args!("{}", raw))
+            .block_error("backlight", "Failed to write into brightness file")
+    }
+}
+
+/// VCP feature code of the brightness of a monitor, in the DDC/CI protocol.
+const DDC_BRIGHTNESS: &str = "10";
+
+/// Parses the output of `ddcutil --brief getvcp 10`, e.g. `VCP 10 C 50 100`, into the current
+/// and maximum values.
+fn parse_ddc_brightness(output: &str) -> Option<(u64, u64)> {
+    let fields: Vec<&str> = output.split_whitespace().collect();
+    match fields.as_slice() {
+        ["VCP", DDC_BRIGHTNESS, "C", current, max] => {
+            Some((current.parse().ok()?, max.parse().ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// Represents an external monitor whose brightness is queried and set over DDC/CI, using
+/// `ddcutil`.
+pub struct DdcDevice {
+    serial: String,
+    root_scaling: f64,
+}
+
+impl DdcDevice {
+    /// Use the monitor with the serial number `serial`, as shown by `ddcutil detect`.
+    pub fn from_serial(serial: String, root_scaling: f64) -> Result<Self> {
+        let device = DdcDevice {
+            serial,
+            root_scaling: clamp_root_scaling(root_scaling),
+        };
+        // Fail early if the monitor is not found or does not support DDC/CI
+        device.get_vcp()?;
+        Ok(device)
+    }
+
+    fn ddcutil(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("ddcutil")
+            .args(&["--brief", "--sn", &self.serial])
+            .args(args)
+            .output()
+            .block_error("backlight", "Failed to run ddcutil")?;
+        if !output.status.success() {
+            return Err(BlockError(
+                "backlight".to_string(),
+                format!(
+                    "ddcutil failed for monitor '{}': {}",
+                    self.serial,
+                    String::from_utf8_lossy(&output.stdout).trim()
+                ),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Query the current and maximum raw brightness values of the monitor.
+    fn get_vcp(&self) -> Result<(u64, u64)> {
+        parse_ddc_brightness(&self.ddcutil(&["getvcp", DDC_BRIGHTNESS])?)
+            .block_error("backlight", "Failed to parse the brightness from ddcutil")
+    }
+}
+
+impl BrightnessDevice for DdcDevice {
+    fn brightness(&self) -> Result<u64> {
+        let (raw, max_brightness) = self.get_vcp()?;
+        Ok(raw_to_percent(raw, max_brightness, self.root_scaling))
+    }
+
+    fn set_brightness(&self, value: u64) -> Result<()> {
+        // The maximum is reported by the monitor, so it is queried again in case the monitor
+        // connected to the port changed meanwhile
+        let (_, max_brightness) = self.get_vcp()?;
+        let raw = percent_to_raw(value, max_brightness, self.root_scaling);
+        self.ddcutil(&["setvcp", DDC_BRIGHTNESS, &raw.to_string()])
+            .map(|_| ())
+    }
+}
+
 /// A block for displaying the brightness of a backlit device.
 pub struct Backlight {
     id: usize,
     output: TextWidget,
-    device: BacklitDevice,
+    device: Box<dyn BrightnessDevice>,
+    /// Only set for monitors over DDC/CI, whose changes are not notified
+    update_interval: Option<Duration>,
     step_width: u64,
     scrolling: Scrolling,
     invert_icons: bool,
+    brightness: Option<u64>,
 }
 
 /// Configuration for the [`Backlight`](./struct.Backlight.html) block.
@@ -196,6 +297,18 @@ pub struct BacklightConfig {
     #[serde(default = "BacklightConfig::default_device")]
     pub device: Option<String>,
 
+    /// The serial number of an external monitor to control over DDC/CI instead, as shown by
+    /// `ddcutil detect`.
+    #[serde(default = "BacklightConfig::default_ddc_serial")]
+    pub ddc_serial: Option<String>,
+
+    /// How often to poll the brightness of the monitor set by `ddc_serial`.
+    #[serde(
+        default = "BacklightConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
     /// The steps brightness is in/decreased for the selected screen (When greater than 50 it gets limited to 50)
     #[serde(default = "BacklightConfig::default_step_width")]
     pub step_width: u64,
@@ -219,6 +332,14 @@ impl BacklightConfig {
         None
     }
 
+    fn default_ddc_serial() -> Option<String> {
+        None
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
     fn default_step_width() -> u64 {
         5
     }
@@ -241,53 +362,75 @@ impl ConfigBlock for Backlight {
         shared_config: SharedConfig,
         tx_update_request: Sender<Task>,
     ) -> Result<Self> {
-        let device = match block_config.device {
-            Some(path) => BacklitDevice::from_device(path, block_config.root_scaling),
-            None => BacklitDevice::default(block_config.root_scaling),
-        }?;
+        if block_config.device.is_some() && block_config.ddc_serial.is_some() {
+            return Err(BlockError(
+                "backlight".to_string(),
+                "`device` and `ddc_serial` are mutually exclusive".to_string(),
+            ));
+        }
 
-        let brightness_file = device.brightness_file();
+        let (device, update_interval): (Box<dyn BrightnessDevice>, _) =
+            match block_config.ddc_serial {
+                Some(serial) => (
+                    Box::new(DdcDevice::from_serial(serial, block_config.root_scaling)?),
+                    Some(block_config.interval),
+                ),
+                None => {
+                    let device = match block_config.device {
+                        Some(path) => BacklitDevice::from_device(path, block_config.root_scaling),
+                        None => BacklitDevice::default(block_config.root_scaling),
+                    }?;
+
+                    let brightness_file = device.brightness_file();
+
+                    // Spin up a thread to watch for changes to the brightness file for the
+                    // device, and schedule an update if needed.
+                    thread::Builder::new()
+                        .name("backlight".into())
+                        .spawn(move || {
+                            let mut notify = Inotify::init().expect("Failed to start inotify");
+                            notify
+                                .add_watch(brightness_file, WatchMask::MODIFY)
+                                .expect("Failed to watch brightness file");
+
+                            let mut buffer = [0; 1024];
+                            loop {
+                                let mut events = notify
+                                    .read_events_blocking(&mut buffer)
+                                    .expect("Error while reading inotify events");
+
+                                if events.any(|event| event.mask.contains(EventMask::MODIFY))
+                                    && tx_update_request
+                                        .send(Task {
+                                            id,
+                                            update_time: Instant::now(),
+                                        })
+                                        .is_err()
+                                {
+                                    return;
+                                }
+
+                                // Avoid update spam.
+                                thread::sleep(Duration::from_millis(250))
+                            }
+                        })
+                        .unwrap();
+
+                    (Box::new(device), None)
+                }
+            };
 
         let backlight = Backlight {
             id,
             device,
+            update_interval,
             step_width: block_config.step_width,
             scrolling: shared_config.scrolling,
             output: TextWidget::new(id, 0, shared_config),
             invert_icons: block_config.invert_icons,
+            brightness: None,
         };
 
-        // Spin up a thread to watch for changes to the brightness file for the
-        // device, and schedule an update if needed.
-        thread::Builder::new()
-            .name("backlight".into())
-            .spawn(move || {
-                let mut notify = Inotify::init().expect("Failed to start inotify");
-                notify
-                    .add_watch(brightness_file, WatchMask::MODIFY)
-                    .expect("Failed to watch brightness file");
-
-                let mut buffer = [0; 1024];
-                loop {
-                    let mut events = notify
-                        .read_events_blocking(&mut buffer)
-                        .expect("Error while reading inotify events");
-
-                    if events.any(|event| event.mask.contains(EventMask::MODIFY)) {
-                        tx_update_request
-                            .send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
-                    }
-
-                    // Avoid update spam.
-                    thread::sleep(Duration::from_millis(250))
-                }
-            })
-            .unwrap();
-
         Ok(backlight)
     }
 }
@@ -296,6 +439,7 @@ impl Block for Backlight {
     fn update(&mut self) -> Result<Option<Update>> {
         let mut brightness = self.device.brightness()?;
         self.output.set_text(format!("{}%", brightness));
+        self.brightness = Some(brightness);
         if self.invert_icons {
             brightness = 100 - brightness;
         }
@@ -317,13 +461,17 @@ impl Block for Backlight {
             _ => "backlight_full",
         });
 
-        Ok(None)
+        Ok(self.update_interval.map(Update::Every))
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![&self.output]
     }
 
+    fn percentage(&self) -> Option<u64> {
+        self.brightness
+    }
+
     fn click(&mut self, event: &I3BarEvent) -> Result<()> {
         let brightness = self.device.brightness()?;
         use LogicalDirection::*;
@@ -341,6 +489,11 @@ impl Block for Backlight {
             None => {}
         }
 
+        // There is no notification of the change to update on
+        if self.update_interval.is_some() {
+            self.update()?;
+        }
+
         Ok(())
     }
 
@@ -348,3 +501,22 @@ impl Block for Backlight {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ddc_brightness, percent_to_raw, raw_to_percent};
+
+    #[test]
+    fn test_parse_ddc_brightness() {
+        assert_eq!(parse_ddc_brightness("VCP 10 C 50 100\n"), Some((50, 100)));
+        assert_eq!(parse_ddc_brightness("VCP 10 ERR\n"), None);
+    }
+
+    #[test]
+    fn test_percent_scaling() {
+        assert_eq!(raw_to_percent(50, 100, 1.0), 50);
+        assert_eq!(percent_to_raw(50, 100, 1.0), 50);
+        assert_eq!(percent_to_raw(0, 100, 1.0), 1);
+        assert_eq!(raw_to_percent(percent_to_raw(40, 937, 2.4), 937, 2.4), 40);
+    }
+}
@@ -0,0 +1,295 @@
+use std::fs::metadata;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Where the time of the last backup is found.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub enum BackupSource {
+    /// Snapshots of a restic repository, by default the one of `$RESTIC_REPOSITORY`
+    Restic {
+        repository: Option<String>,
+        password_file: Option<String>,
+    },
+    /// Archives of a borg repository
+    Borg { repository: String },
+    /// Modification time of a file, touched by a backup script when it succeeds
+    File { path: String },
+}
+
+impl Default for BackupSource {
+    fn default() -> Self {
+        BackupSource::Restic {
+            repository: None,
+            password_file: None,
+        }
+    }
+}
+
+impl BackupSource {
+    /// The time of the most recent backup, or `None` if there is none yet.
+    fn last_backup(&self) -> Result<Option<DateTime<Local>>> {
+        match self {
+            BackupSource::Restic {
+                repository,
+                password_file,
+            } => {
+                // Without a lock, a backup running at the same time doesn't get in the way
+                let mut args = vec!["snapshots", "--json", "--no-lock"];
+                if let Some(repository) = repository {
+                    args.extend(&["--repo", repository.as_str()]);
+                }
+                if let Some(password_file) = password_file {
+                    args.extend(&["--password-file", password_file.as_str()]);
+                }
+                Ok(parse_restic(&run_json("restic", &args)?))
+            }
+            BackupSource::Borg { repository } => Ok(parse_borg(&run_json(
+                "borg",
+                &["list", "--json", "--bypass-lock", "--last", "1", repository],
+            )?)),
+            BackupSource::File { path } => {
+                let path = match path.strip_prefix("~/") {
+                    Some(rest) => {
+                        PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest)
+                    }
+                    None => PathBuf::from(path),
+                };
+                match metadata(&path).and_then(|metadata| metadata.modified()) {
+                    Ok(modified) => Ok(Some(modified.into())),
+                    // The file is only created by the first backup
+                    Err(_) if !path.exists() => Ok(None),
+                    Err(_) => Err(BlockError(
+                        "backup".to_string(),
+                        format!("failed to read the time of {}", path.display()),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+fn run_json(program: &str, args: &[&str]) -> Result<JsonValue> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .block_error("backup", &format!("Failed to run `{}`", program))?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "backup".to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    serde_json::from_slice(&output.stdout)
+        .block_error("backup", &format!("unexpected output of `{}`", program))
+}
+
+/// The time of the newest snapshot listed by `restic snapshots --json`.
+fn parse_restic(json: &JsonValue) -> Option<DateTime<Local>> {
+    json.as_array()?
+        .iter()
+        .filter_map(|snapshot| DateTime::parse_from_rfc3339(snapshot["time"].as_str()?).ok())
+        .max()
+        .map(|time| time.with_timezone(&Local))
+}
+
+/// The time of the newest archive listed by `borg list --json`, which is in local time.
+fn parse_borg(json: &JsonValue) -> Option<DateTime<Local>> {
+    json["archives"]
+        .as_array()?
+        .iter()
+        .filter_map(|archive| {
+            NaiveDateTime::parse_from_str(archive["time"].as_str()?, "%Y-%m-%dT%H:%M:%S%.f").ok()
+        })
+        .max()
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+}
+
+pub struct Backup {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    source: BackupSource,
+    warning: Duration,
+    critical: Duration,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "BackupConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "BackupConfig::default_format")]
+    pub format: String,
+
+    /// Where the time of the last backup is found
+    #[serde(default)]
+    pub source: BackupSource,
+
+    /// Age of the last backup, in hours, from which the state is warning
+    #[serde(default = "BackupConfig::default_warning_hours")]
+    pub warning_hours: u64,
+
+    /// Age of the last backup, in hours, from which the state is critical
+    #[serde(default = "BackupConfig::default_critical_hours")]
+    pub critical_hours: u64,
+}
+
+impl BackupConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_format() -> String {
+        "{age}".to_owned()
+    }
+
+    fn default_warning_hours() -> u64 {
+        24
+    }
+
+    fn default_critical_hours() -> u64 {
+        72
+    }
+}
+
+impl ConfigBlock for Backup {
+    type Config = BackupConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{age}", "{time}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Backup {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("backup"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("backup", "Invalid format specified")?,
+            source: block_config.source,
+            warning: Duration::from_secs(block_config.warning_hours * 3600),
+            critical: Duration::from_secs(block_config.critical_hours * 3600),
+        })
+    }
+}
+
+impl Block for Backup {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let last_backup = self.source.last_backup()?;
+        // A clock set back leaves a backup from the future, which is as fresh as can be
+        let age = last_backup.map(|time| {
+            (Local::now() - time)
+                .to_std()
+                .unwrap_or_else(|_| Duration::from_secs(0))
+        });
+
+        let values = match (last_backup, age) {
+            (Some(time), Some(age)) => map!(
+                "{age}" => Value::duration(age),
+                "{time}" => Value::text(time.format("%Y-%m-%d %H:%M").to_string())
+            ),
+            _ => map!(
+                "{age}" => Value::text("never".to_string()),
+                "{time}" => Value::text("never".to_string())
+            ),
+        };
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(match age {
+            Some(age) if age < self.warning => State::Idle,
+            Some(age) if age < self.critical => State::Warning,
+            _ => State::Critical,
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button == MouseButton::Left {
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Local, TimeZone};
+    use serde_json::json;
+
+    use super::{parse_borg, parse_restic};
+
+    #[test]
+    fn test_parse_restic() {
+        let json = json!([
+            {"time": "2021-03-30T02:00:12.123456789+02:00", "hostname": "laptop", "id": "aaa"},
+            {"time": "2021-03-31T02:00:08.987654321+02:00", "hostname": "laptop", "id": "bbb"},
+            {"time": "2021-03-29T02:00:10+02:00", "hostname": "laptop", "id": "ccc"}
+        ]);
+        assert_eq!(
+            parse_restic(&json),
+            Some(
+                FixedOffset::east(2 * 3600)
+                    .ymd(2021, 3, 31)
+                    .and_hms_nano(2, 0, 8, 987_654_321)
+                    .with_timezone(&Local)
+            )
+        );
+        assert_eq!(parse_restic(&json!([])), None);
+    }
+
+    #[test]
+    fn test_parse_borg() {
+        let json = json!({
+            "archives": [
+                {"archive": "laptop-2021-03-31", "time": "2021-03-31T02:00:08.000000"}
+            ],
+            "repository": {"location": "/mnt/backup"}
+        });
+        assert_eq!(
+            parse_borg(&json),
+            Some(Local.ymd(2021, 3, 31).and_hms(2, 0, 8))
+        );
+        assert_eq!(parse_borg(&json!({"archives": []})), None);
+    }
+}
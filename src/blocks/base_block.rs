@@ -1,23 +1,92 @@
 //! A Base block for common behavior for all blocks
 
 use std::collections::HashMap;
+use std::time::Duration;
 
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::util::FormatTemplate;
+use crate::widgets::i3block_data::{I3BlockAlign, I3BlockMinWidth};
+use crate::widgets::text::TextWidget;
 use crate::{
-    blocks::Update,
+    blocks::{BlockState, Update},
     input::{I3BarEvent, MouseButton},
     subprocess::spawn_child_async,
-    widgets::I3BarWidget,
+    widgets::{I3BarWidget, State},
     Block,
 };
 
 use serde_derive::Deserialize;
 use toml::{value::Table, Value};
 
+/// Interval at which a failing block is updated again, until it has updated successfully once
+const ERROR_INTERVAL: Duration = Duration::from_secs(5);
+
 pub(super) struct BaseBlock<T: Block> {
     pub name: String,
     pub inner: T,
     pub on_click: Option<String>,
+    pub hidden: bool,
+    pub signal: Option<i32>,
+    /// Format swapped with the main format of the block on every click
+    pub format_alt: Option<FormatTemplate>,
+    /// Whether `format_alt` is currently the main format of the block
+    pub format_alt_shown: bool,
+    pub format_alt_button: MouseButton,
+    /// The last full and short texts of the main format, if the block has a `short_format`
+    pub short_text: Option<(String, String)>,
+    pub error_policy: ErrorPolicy,
+    pub separator: Option<Separator>,
+    pub min_width: Option<I3BlockMinWidth>,
+    pub align: Option<I3BlockAlign>,
+    /// Set while the block is failing, unless the policy is `fail`
+    pub error: Option<TextWidget>,
+    /// Number of consecutive failed updates
+    pub retries: u32,
+    /// Update interval of the block, as returned by its last successful update
+    pub interval: Option<Duration>,
+    pub shared_config: SharedConfig,
+}
+
+impl<T: Block> BaseBlock<T> {
+    /// Swaps the main format of the block with `format_alt`. Returns whether the block has one.
+    fn swap_formats(&mut self) -> bool {
+        match (self.inner.format_mut(), &mut self.format_alt) {
+            (Some(format), Some(format_alt)) => {
+                std::mem::swap(format, format_alt);
+                self.format_alt_shown = !self.format_alt_shown;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Applies the error policy to a failed update.
+    fn handle_error(&mut self, error: Error) -> Result<Option<Update>> {
+        if let ErrorPolicy::Fail = self.error_policy {
+            return Err(error);
+        }
+
+        self.error = Some(
+            TextWidget::new(self.id(), 0, self.shared_config.clone())
+                .with_state(State::Critical)
+                .with_text(&format!("{}", error)),
+        );
+        self.retries = self.retries.saturating_add(1);
+
+        // Keep updating the block, even if it only updates on events, so that it can recover
+        match self.error_policy {
+            ErrorPolicy::Retry { interval, max } if max.map_or(true, |max| self.retries <= max) => {
+                // Double the delay after each failed attempt
+                let backoff = 2u32.saturating_pow(self.retries.min(16) - 1);
+                Ok(Some(Update::Every(interval * backoff)))
+            }
+            // Out of attempts, keep showing the error until the block asks for an update itself
+            ErrorPolicy::Retry { .. } => Ok(Some(Update::Once)),
+            _ => Ok(Some(Update::Every(self.interval.unwrap_or(ERROR_INTERVAL)))),
+        }
+    }
 }
 
 impl<T: Block> Block for BaseBlock<T> {
@@ -26,18 +95,75 @@ impl<T: Block> Block for BaseBlock<T> {
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        self.inner.view()
+        if self.hidden {
+            return vec![];
+        }
+        match (&self.error, &self.error_policy) {
+            (Some(_), ErrorPolicy::Hide) => vec![],
+            (Some(error), _) => vec![error as &dyn I3BarWidget],
+            (None, _) => self.inner.view(),
+        }
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
-        self.inner.update()
+        match self.inner.update() {
+            Ok(update) => {
+                if let Some(Update::Every(interval)) = update {
+                    self.interval = Some(interval);
+                }
+                self.error = None;
+                self.retries = 0;
+                self.short_text = self.inner.format_mut().and_then(|f| f.last_render());
+                Ok(update)
+            }
+            Err(error) => self.handle_error(error),
+        }
     }
 
     fn signal(&mut self, signal: i32) -> Result<()> {
+        if self.signal == Some(signal) {
+            self.update()?;
+        }
         self.inner.signal(signal)
     }
 
+    fn percentage(&self) -> Option<u64> {
+        match self.error {
+            Some(_) => None,
+            None => self.inner.percentage(),
+        }
+    }
+
+    fn toggle_hidden(&mut self) {
+        self.hidden = !self.hidden;
+    }
+
+    fn state(&self) -> BlockState {
+        BlockState {
+            hidden: self.hidden,
+            format_alt_shown: self.format_alt_shown,
+        }
+    }
+
+    fn toggle_format(&mut self) -> Result<()> {
+        if self.swap_formats() {
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn restore_state(&mut self, state: BlockState) {
+        self.hidden = state.hidden;
+        if state.format_alt_shown != self.format_alt_shown && self.swap_formats() {
+            self.short_text = None;
+        }
+    }
+
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.button == self.format_alt_button && self.format_alt.is_some() {
+            return self.toggle_format();
+        }
+
         match &self.on_click {
             Some(cmd) => {
                 if let MouseButton::Left = e.button {
@@ -46,9 +172,37 @@ impl<T: Block> Block for BaseBlock<T> {
                 }
                 Ok(())
             }
-            None => self.inner.click(e),
+            None => {
+                self.inner.click(e)?;
+                self.short_text = self.inner.format_mut().and_then(|f| f.last_render());
+                Ok(())
+            }
         }
     }
+
+    fn separator(&self) -> Option<&Separator> {
+        self.separator.as_ref()
+    }
+
+    fn min_width(&self) -> Option<&I3BlockMinWidth> {
+        self.min_width.as_ref()
+    }
+
+    fn align(&self) -> Option<I3BlockAlign> {
+        self.align
+    }
+
+    fn short_text(&self, full_text: &str) -> Option<String> {
+        if self.error.is_some() {
+            return None;
+        }
+        // The full text also holds the icon and spacing, which the short text keeps
+        let (full, short) = self.short_text.as_ref()?;
+        if full.is_empty() || !full_text.contains(full.as_str()) {
+            return None;
+        }
+        Some(full_text.replacen(full.as_str(), short, 1))
+    }
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -57,11 +211,66 @@ pub(super) struct BaseBlockConfig {
     pub on_click: Option<String>,
 
     pub theme_overrides: Option<HashMap<String, String>>,
+    pub icons_overrides: Option<HashMap<String, String>>,
     pub icons_format: Option<String>,
+
+    /// Realtime signal (as an offset from SIGRTMIN) that triggers an update of the block
+    pub signal: Option<i32>,
+
+    /// Format shown instead of `format` after a click, swapped back on the next one
+    pub format_alt: Option<String>,
+
+    /// Mouse button that toggles `format_alt`
+    #[serde(default)]
+    pub format_alt_button: MouseButton,
+
+    /// Format of the text shown when the bar is too narrow
+    pub short_format: Option<String>,
+
+    /// What to do when an update of the block fails
+    #[serde(default)]
+    pub error_policy: ErrorPolicy,
+
+    /// Whether the text of the formats is Pango markup, rather than escaped
+    #[serde(default)]
+    pub markup: Markup,
+
+    /// Separator in front of the block, instead of the one of the theme
+    pub separator: Option<Separator>,
+
+    /// Minimum width of the block, in pixels or as the width of a text
+    pub min_width: Option<I3BlockMinWidth>,
+
+    /// Alignment of the text of the block within its `min_width`
+    pub align: Option<I3BlockAlign>,
+
+    /// Outputs on which the block is shown, any by default. Read by `shown_on_output`.
+    #[serde(default)]
+    pub output: Vec<String>,
+
+    /// Outputs on which the block is not shown. Read by `shown_on_output`.
+    #[serde(default)]
+    pub exclude_output: Vec<String>,
 }
 
 impl BaseBlockConfig {
-    const FIELDS: &'static [&'static str] = &["on_click", "theme_overrides", "icons_format"];
+    const FIELDS: &'static [&'static str] = &[
+        "on_click",
+        "theme_overrides",
+        "icons_overrides",
+        "icons_format",
+        "signal",
+        "format_alt",
+        "format_alt_button",
+        "short_format",
+        "error_policy",
+        "markup",
+        "separator",
+        "min_width",
+        "align",
+        "output",
+        "exclude_output",
+    ];
 
     // FIXME: this function is to paper over https://github.com/serde-rs/serde/issues/1957
     pub(super) fn extract(config: &mut Value) -> Value {
@@ -76,3 +285,258 @@ impl BaseBlockConfig {
         common_table.into()
     }
 }
+
+/// Whether the block of `block_config` is shown on the bar of `output`, according to its
+/// `output` and `exclude_output` options. A bar which doesn't know its output shows every block.
+pub fn shown_on_output(block_config: &Value, output: Option<&str>) -> Result<bool> {
+    let output = match output {
+        Some(output) => output,
+        None => return Ok(true),
+    };
+    let common_config: BaseBlockConfig = BaseBlockConfig::extract(&mut block_config.clone())
+        .try_into()
+        .configuration_error("Failed to deserialize common block config.")?;
+    let listed = |outputs: &[String]| outputs.iter().any(|name| name == output);
+    Ok(
+        (common_config.output.is_empty() || listed(&common_config.output))
+            && !listed(&common_config.exclude_output),
+    )
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum ErrorPolicy {
+    /// Replace the whole bar with the error
+    Fail,
+    /// Hide the block until it updates successfully again
+    Hide,
+    /// Show the error in place of the block until it updates successfully again
+    Show,
+    /// Show the error and retry the update, doubling the delay after each attempt
+    Retry {
+        #[serde(deserialize_with = "deserialize_duration")]
+        interval: Duration,
+        max: Option<u32>,
+    },
+}
+
+impl Default for ErrorPolicy {
+    fn default() -> Self {
+        ErrorPolicy::Fail
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Markup {
+    /// Escape the text of the formats, so that it is shown as written
+    None,
+    /// Keep the text of the formats as Pango markup, e.g. `<b>{title}</b>`
+    Pango,
+}
+
+/// Formats were always Pango markup before the option existed, so escaping has to be asked for
+impl Default for Markup {
+    fn default() -> Self {
+        Markup::Pango
+    }
+}
+
+/// The separator in front of a block, set by its `separator` option.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Separator {
+    /// `true` keeps the separator of the theme, `false` removes it
+    Enabled(bool),
+    /// Replaces the text of the separator of the theme
+    Custom(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shown_on_output, BaseBlock, BaseBlockConfig, ErrorPolicy, Separator};
+    use crate::blocks::{Block, Update};
+    use crate::config::SharedConfig;
+    use crate::errors::*;
+    use crate::input::MouseButton;
+    use crate::widgets::i3block_data::{I3BlockAlign, I3BlockMinWidth};
+    use crate::widgets::text::TextWidget;
+    use crate::widgets::I3BarWidget;
+    use std::time::Duration;
+
+    /// A block updated every minute, which fails until `failures` is down to zero
+    struct Flaky {
+        failures: u32,
+        text: TextWidget,
+    }
+
+    impl Block for Flaky {
+        fn id(&self) -> usize {
+            0
+        }
+
+        fn view(&self) -> Vec<&dyn I3BarWidget> {
+            vec![&self.text]
+        }
+
+        fn update(&mut self) -> Result<Option<Update>> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(BlockError("flaky".to_string(), "unavailable".to_string()));
+            }
+            Ok(Some(Duration::from_secs(60).into()))
+        }
+    }
+
+    fn flaky(failures: u32, error_policy: ErrorPolicy) -> BaseBlock<Flaky> {
+        BaseBlock {
+            name: "flaky".to_string(),
+            inner: Flaky {
+                failures,
+                text: TextWidget::new(0, 0, SharedConfig::default()),
+            },
+            on_click: None,
+            hidden: false,
+            signal: None,
+            format_alt: None,
+            format_alt_shown: false,
+            format_alt_button: MouseButton::Left,
+            short_text: None,
+            error_policy,
+            separator: None,
+            min_width: None,
+            align: None,
+            error: None,
+            retries: 0,
+            interval: None,
+            shared_config: SharedConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_recover_from_errors() {
+        let every = |secs| Some(Update::Every(Duration::from_secs(secs)));
+
+        // Updated again until it recovers, then at its own interval
+        let mut block = flaky(2, ErrorPolicy::Show);
+        assert_eq!(block.update().unwrap(), every(5));
+        assert_eq!(block.view().len(), 1);
+        assert_eq!(block.update().unwrap(), every(5));
+        assert_eq!(block.update().unwrap(), every(60));
+        assert!(block.error.is_none());
+
+        // Once the interval of the block is known, it is kept while failing
+        block.inner.failures = 1;
+        assert_eq!(block.update().unwrap(), every(60));
+
+        // Hidden until it recovers
+        let mut block = flaky(1, ErrorPolicy::Hide);
+        assert_eq!(block.update().unwrap(), every(5));
+        assert!(block.view().is_empty());
+        assert_eq!(block.update().unwrap(), every(60));
+        assert_eq!(block.view().len(), 1);
+
+        // Retried with a growing delay, then given up once out of attempts
+        let mut block = flaky(
+            4,
+            ErrorPolicy::Retry {
+                interval: Duration::from_secs(2),
+                max: Some(2),
+            },
+        );
+        assert_eq!(block.update().unwrap(), every(2));
+        assert_eq!(block.update().unwrap(), every(4));
+        assert_eq!(block.update().unwrap(), Some(Update::Once));
+        assert_eq!(block.view().len(), 1);
+        assert!(block.error.is_some());
+
+        // An update asked for by the block itself still lets it recover
+        block.inner.failures = 0;
+        assert_eq!(block.update().unwrap(), every(60));
+        assert!(block.error.is_none());
+
+        assert!(flaky(1, ErrorPolicy::Fail).update().is_err());
+    }
+
+    #[test]
+    fn test_restore_state() {
+        let mut block = flaky(0, ErrorPolicy::Fail);
+        block.toggle_hidden();
+        let state = block.state();
+        assert!(state.hidden);
+
+        let mut reloaded = flaky(0, ErrorPolicy::Fail);
+        reloaded.restore_state(state);
+        assert!(reloaded.view().is_empty());
+        assert_eq!(reloaded.state(), state);
+    }
+
+    #[test]
+    fn test_deserialize_error_policy() {
+        let config: BaseBlockConfig = toml::from_str(r#"error_policy = "hide""#).unwrap();
+        assert!(matches!(config.error_policy, ErrorPolicy::Hide));
+
+        let config: BaseBlockConfig =
+            toml::from_str(r#"error_policy = { retry = { interval = 5, max = 3 } }"#).unwrap();
+        match config.error_policy {
+            ErrorPolicy::Retry { interval, max } => {
+                assert_eq!(interval, Duration::from_secs(5));
+                assert_eq!(max, Some(3));
+            }
+            other => panic!("unexpected policy {:?}", other),
+        }
+
+        let config: BaseBlockConfig = toml::from_str("").unwrap();
+        assert!(matches!(config.error_policy, ErrorPolicy::Fail));
+    }
+
+    #[test]
+    fn test_deserialize_format_alt_button() {
+        let config: BaseBlockConfig = toml::from_str("").unwrap();
+        assert_eq!(config.format_alt_button, MouseButton::Left);
+
+        let config: BaseBlockConfig = toml::from_str(r#"format_alt_button = "wheel_up""#).unwrap();
+        assert_eq!(config.format_alt_button, MouseButton::WheelUp);
+    }
+
+    #[test]
+    fn test_deserialize_separator() {
+        let config: BaseBlockConfig = toml::from_str("separator = false").unwrap();
+        assert_eq!(config.separator, Some(Separator::Enabled(false)));
+
+        let config: BaseBlockConfig = toml::from_str(r#"separator = "|""#).unwrap();
+        assert_eq!(config.separator, Some(Separator::Custom("|".to_string())));
+    }
+
+    #[test]
+    fn test_deserialize_min_width() {
+        let config: BaseBlockConfig = toml::from_str("min_width = 100").unwrap();
+        assert_eq!(config.min_width, Some(I3BlockMinWidth::Pixels(100)));
+
+        let config: BaseBlockConfig = toml::from_str(
+            r#"min_width = "100.0%"
+align = "right""#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.min_width,
+            Some(I3BlockMinWidth::Text("100.0%".to_string()))
+        );
+        assert_eq!(config.align, Some(I3BlockAlign::Right));
+    }
+
+    #[test]
+    fn test_shown_on_output() {
+        let config: toml::Value = toml::from_str(r#"output = ["eDP-1"]"#).unwrap();
+        assert!(shown_on_output(&config, Some("eDP-1")).unwrap());
+        assert!(!shown_on_output(&config, Some("HDMI-A-1")).unwrap());
+        assert!(shown_on_output(&config, None).unwrap());
+
+        let config: toml::Value = toml::from_str(r#"exclude_output = ["HDMI-A-1"]"#).unwrap();
+        assert!(shown_on_output(&config, Some("eDP-1")).unwrap());
+        assert!(!shown_on_output(&config, Some("HDMI-A-1")).unwrap());
+
+        let config: toml::Value = toml::from_str("").unwrap();
+        assert!(shown_on_output(&config, Some("eDP-1")).unwrap());
+    }
+}
@@ -2,9 +2,11 @@
 //!
 //! This module contains the [`Battery`](./struct.Battery.html) block, which can
 //! display the status, capacity, and time remaining for (dis)charge for an
-//! internal power supply.
+//! internal power supply, and with UPower the batteries of wireless peripherals.
 
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -17,8 +19,9 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::{DurationFormat, TimeUnit, Value};
 use crate::scheduler::Task;
-use crate::util::{battery_level_to_icon, format_percent_bar, read_file, FormatTemplate};
+use crate::util::{battery_level_to_icon, format_percent_bar, read_file, severity, FormatTemplate};
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, Spacing, State};
 
@@ -303,9 +306,17 @@ impl BatteryDevice for PowerSupplyDevice {
     }
 }
 
+/// Object path of the device of UPower combining all the batteries
+const UPOWER_DISPLAY_DEVICE: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
 /// Represents a battery known to UPower.
 pub struct UpowerDevice {
-    device_path: String,
+    device: String,
+    /// Object path of the device, or `None` while it is missing
+    device_path: Option<String>,
+    /// Set by the monitor thread when UPower adds or removes a device, so that the device is
+    /// looked up again
+    devices_changed: Arc<AtomicBool>,
     con: dbus::ffidisp::Connection,
 }
 
@@ -315,89 +326,183 @@ impl UpowerDevice {
     /// `device` equals `"DisplayDevice"`, in which case it is converted to the
     /// path `"/org/freedesktop/UPower/devices/DisplayDevice"`. Raises an error
     /// if D-Bus cannot connect to this device, or if the device is not a
-    /// battery.
-    pub fn from_device(device: &str) -> Result<Self> {
-        let device_path;
+    /// battery. A missing device is only an error when `allow_missing` is
+    /// false, as it may be plugged in later.
+    pub fn from_device(device: &str, allow_missing: bool) -> Result<Self> {
         let con = dbus::ffidisp::Connection::get_private(dbus::ffidisp::BusType::System)
             .block_error("battery", "Failed to establish D-Bus connection.")?;
 
+        let device_path = Self::find_device_path(&con, device)?;
+        match device_path {
+            Some(ref device_path) => {
+                let upower_type: u32 = con
+                    .with_path("org.freedesktop.UPower", device_path, 1000)
+                    .get("org.freedesktop.UPower.Device", "Type")
+                    .block_error("battery", "Failed to read UPower Type property.")?;
+
+                // https://upower.freedesktop.org/docs/Device.html#Device:Type
+                // consider any peripheral, UPS and internal battery
+                if upower_type == 1 {
+                    return Err(BlockError(
+                        "battery".into(),
+                        "UPower device is not a battery.".into(),
+                    ));
+                }
+            }
+            None if !allow_missing => {
+                return Err(BlockError(
+                    "battery".into(),
+                    "UPower device could not be found.".into(),
+                ));
+            }
+            None => {}
+        }
+
+        Ok(UpowerDevice {
+            device: device.to_string(),
+            device_path,
+            devices_changed: Arc::new(AtomicBool::new(false)),
+            con,
+        })
+    }
+
+    /// The object paths of the devices UPower currently knows.
+    fn enumerate_devices(con: &dbus::ffidisp::Connection) -> Result<Vec<String>> {
+        let msg = dbus::Message::new_method_call(
+            "org.freedesktop.UPower",
+            "/org/freedesktop/UPower",
+            "org.freedesktop.UPower",
+            "EnumerateDevices",
+        )
+        .block_error("battery", "Failed to create DBus message")?;
+
+        let dbus_reply = con
+            .send_with_reply_and_block(msg, 2000)
+            .block_error("battery", "Failed to retrieve DBus reply")?;
+
+        // EnumerateDevices returns one argument, which is an array of ObjectPaths (not dbus::tree:ObjectPath).
+        let paths: Array<dbus::Path, _> = dbus_reply
+            .get1()
+            .block_error("battery", "Failed to read DBus reply")?;
+
+        Ok(paths
+            .map(|path| path.as_cstr().to_string_lossy().into_owned())
+            .collect())
+    }
+
+    /// The object path of the UPower device whose path ends with `device`, if
+    /// UPower currently knows one.
+    fn find_device_path(con: &dbus::ffidisp::Connection, device: &str) -> Result<Option<String>> {
         if device == "DisplayDevice" {
-            device_path = String::from("/org/freedesktop/UPower/devices/DisplayDevice");
-        } else {
-            let msg = dbus::Message::new_method_call(
-                "org.freedesktop.UPower",
-                "/org/freedesktop/UPower",
-                "org.freedesktop.UPower",
-                "EnumerateDevices",
-            )
-            .block_error("battery", "Failed to create DBus message")?;
-
-            let dbus_reply = con
-                .send_with_reply_and_block(msg, 2000)
-                .block_error("battery", "Failed to retrieve DBus reply")?;
-
-            // EnumerateDevices returns one argument, which is an array of ObjectPaths (not dbus::tree:ObjectPath).
-            let mut paths: Array<dbus::Path, _> = dbus_reply
-                .get1()
-                .block_error("battery", "Failed to read DBus reply")?;
-
-            device_path = paths
-                .find(|entry| entry.ends_with(device))
-                .block_error("battery", "UPower device could not be found.")?
-                .as_cstr()
-                .to_string_lossy()
-                .into_owned();
+            return Ok(Some(UPOWER_DISPLAY_DEVICE.to_string()));
         }
-        let upower_type: u32 = con
-            .with_path("org.freedesktop.UPower", &device_path, 1000)
-            .get("org.freedesktop.UPower.Device", "Type")
-            .block_error("battery", "Failed to read UPower Type property.")?;
-
-        // https://upower.freedesktop.org/docs/Device.html#Device:Type
-        // consider any peripheral, UPS and internal battery
-        if upower_type == 1 {
-            return Err(BlockError(
-                "battery".into(),
-                "UPower device is not a battery.".into(),
-            ));
+        Ok(Self::enumerate_devices(con)?
+            .into_iter()
+            .find(|path| path.ends_with(device)))
+    }
+
+    /// The devices with a battery which don't power the system, such as wireless mice,
+    /// keyboards and headsets, ordered by object path.
+    pub fn peripherals(&self) -> Result<Vec<Peripheral>> {
+        let mut peripherals = Vec::new();
+        let mut paths = Self::enumerate_devices(&self.con)?;
+        paths.sort();
+        for path in paths {
+            let device = self.con.with_path("org.freedesktop.UPower", &path, 1000);
+            // The device may have been removed meanwhile
+            let power_supply: bool =
+                match device.get("org.freedesktop.UPower.Device", "PowerSupply") {
+                    Ok(power_supply) => power_supply,
+                    Err(_) => continue,
+                };
+            let upower_type: u32 = device
+                .get("org.freedesktop.UPower.Device", "Type")
+                .unwrap_or(0);
+            // Unknown devices and AC adapters have no battery
+            if power_supply || upower_type <= 1 {
+                continue;
+            }
+            let percentage: f64 = device
+                .get("org.freedesktop.UPower.Device", "Percentage")
+                .block_error("battery", "Failed to read UPower Percentage property.")?;
+            let model: String = device
+                .get("org.freedesktop.UPower.Device", "Model")
+                .unwrap_or_default();
+            peripherals.push(Peripheral {
+                model,
+                kind: peripheral_kind(upower_type),
+                percentage: percentage.min(100.0) as u64,
+            });
         }
-        Ok(UpowerDevice { device_path, con })
+        Ok(peripherals)
+    }
+
+    fn device_path(&self) -> Result<&str> {
+        self.device_path
+            .as_deref()
+            .block_error("battery", "UPower device could not be found.")
     }
 
-    /// Monitor UPower property changes in a separate thread and send updates
-    /// via the `update_request` channel.
-    pub fn monitor(&self, id: usize, update_request: Sender<Task>) {
-        let path = self.device_path.clone();
+    /// Monitor UPower property changes, and batteries being plugged in or
+    /// out, in a separate thread and send updates via the `update_request`
+    /// channel. Changes to any device count when `peripherals` are shown too.
+    pub fn monitor(&self, id: usize, peripherals: bool, update_request: Sender<Task>) {
+        let device = self.device.clone();
+        let devices_changed = self.devices_changed.clone();
         thread::Builder::new()
             .name("battery".into())
             .spawn(move || {
                 let con = dbus::ffidisp::Connection::get_private(dbus::ffidisp::BusType::System)
                     .expect("Failed to establish D-Bus connection.");
-                let rule = format!(
-                    "type='signal',\
-                 path='{}',\
-                 interface='org.freedesktop.DBus.Properties',\
-                 member='PropertiesChanged'",
-                    path
-                );
-
-                // First we're going to get an (irrelevant) NameAcquired event.
-                con.incoming(10_000).next();
-
-                con.add_match(&rule)
+                // Any device, as the battery may be replaced by another one with the same name
+                let properties_rule = "type='signal',\
+                    sender='org.freedesktop.UPower',\
+                    path_namespace='/org/freedesktop/UPower/devices',\
+                    interface='org.freedesktop.DBus.Properties',\
+                    member='PropertiesChanged'";
+                let devices_rule = "type='signal',\
+                    sender='org.freedesktop.UPower',\
+                    path='/org/freedesktop/UPower',\
+                    interface='org.freedesktop.UPower'";
+                con.add_match(properties_rule)
                     .expect("Failed to add D-Bus match rule.");
+                con.add_match(devices_rule)
+                    .expect("Failed to add D-Bus match rule.");
+
+                let relevant = |msg: &dbus::Message| match msg.member().as_deref() {
+                    Some("PropertiesChanged") => {
+                        peripherals
+                            || msg.path().map_or(false, |path| {
+                                if device == "DisplayDevice" {
+                                    &*path == UPOWER_DISPLAY_DEVICE
+                                } else {
+                                    path.ends_with(&device)
+                                }
+                            })
+                    }
+                    Some("DeviceAdded") | Some("DeviceRemoved") => {
+                        devices_changed.store(true, Ordering::SeqCst);
+                        true
+                    }
+                    _ => false,
+                };
 
                 loop {
-                    if con.incoming(10_000).next().is_some() {
-                        update_request
+                    if con.incoming(60_000).any(|msg| relevant(&msg)) {
+                        // UPower changes several properties at once, so wait for the end of the
+                        // burst of signals to update once
+                        for msg in con.incoming(100) {
+                            relevant(&msg);
+                        }
+                        if update_request
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
-                        // Avoid update spam.
-                        // TODO: Is this necessary?
-                        thread::sleep(Duration::from_millis(1000))
+                            .is_err()
+                        {
+                            return;
+                        }
                     }
                 }
             })
@@ -407,17 +512,21 @@ impl UpowerDevice {
 
 impl BatteryDevice for UpowerDevice {
     fn is_available(&self) -> bool {
-        true // TODO: has to be implemented for UPower
+        self.device_path.is_some()
     }
 
     fn refresh_device_info(&mut self) -> Result<()> {
+        // The battery may have been replaced by another one with the same name
+        if self.devices_changed.swap(false, Ordering::SeqCst) {
+            self.device_path = Self::find_device_path(&self.con, &self.device)?;
+        }
         Ok(())
     }
 
     fn status(&self) -> Result<String> {
         let status: u32 = self
             .con
-            .with_path("org.freedesktop.UPower", &self.device_path, 1000)
+            .with_path("org.freedesktop.UPower", self.device_path()?, 1000)
             .get("org.freedesktop.UPower.Device", "State")
             .block_error("battery", "Failed to read UPower State property.")?;
 
@@ -436,7 +545,7 @@ impl BatteryDevice for UpowerDevice {
     fn capacity(&self) -> Result<u64> {
         let capacity: f64 = self
             .con
-            .with_path("org.freedesktop.UPower", &self.device_path, 1000)
+            .with_path("org.freedesktop.UPower", self.device_path()?, 1000)
             .get("org.freedesktop.UPower.Device", "Percentage")
             .block_error("battery", "Failed to read UPower Percentage property.")?;
 
@@ -455,7 +564,7 @@ impl BatteryDevice for UpowerDevice {
         };
         let time_to_empty: i64 = self
             .con
-            .with_path("org.freedesktop.UPower", &self.device_path, 1000)
+            .with_path("org.freedesktop.UPower", self.device_path()?, 1000)
             .get("org.freedesktop.UPower.Device", property)
             .block_error(
                 "battery",
@@ -467,7 +576,7 @@ impl BatteryDevice for UpowerDevice {
     fn power_consumption(&self) -> Result<u64> {
         let energy_rate: f64 = self
             .con
-            .with_path("org.freedesktop.UPower", &self.device_path, 1000)
+            .with_path("org.freedesktop.UPower", self.device_path()?, 1000)
             .get("org.freedesktop.UPower.Device", "EnergyRate")
             .block_error("battery", "Failed to read UPower EnergyRate property.")?;
         // FIXME: Might want to make the interface send Watts instead.
@@ -475,6 +584,54 @@ impl BatteryDevice for UpowerDevice {
     }
 }
 
+/// A wireless device whose battery is reported by UPower.
+pub struct Peripheral {
+    model: String,
+    kind: &'static str,
+    percentage: u64,
+}
+
+/// The kind of device of a UPower `Type`.
+///
+/// https://upower.freedesktop.org/docs/Device.html#Device:Type
+fn peripheral_kind(upower_type: u32) -> &'static str {
+    match upower_type {
+        5 => "mouse",
+        6 => "keyboard",
+        7 => "pda",
+        8 => "phone",
+        9 => "media player",
+        10 => "tablet",
+        12 => "gaming input",
+        13 => "pen",
+        14 => "touchpad",
+        17 => "headset",
+        18 => "speakers",
+        19 => "headphones",
+        22 => "remote control",
+        26 => "wearable",
+        27 => "toy",
+        _ => "device",
+    }
+}
+
+/// Moves an exponentially weighted moving average towards `sample`, taken `elapsed` after the
+/// previous one, so that samples older than `window` weigh little.
+fn ewma(average: f64, sample: f64, elapsed: Duration, window: Duration) -> f64 {
+    if window.as_secs_f64() == 0.0 {
+        return sample;
+    }
+    let weight = 1.0 - (-elapsed.as_secs_f64() / window.as_secs_f64()).exp();
+    average + weight * (sample - average)
+}
+
+/// Average power draw, in µW, while the battery keeps charging or discharging.
+struct PowerAverage {
+    power: f64,
+    updated: Instant,
+    status: String,
+}
+
 /// A block for displaying information about an internal power supply.
 pub struct Battery {
     id: usize,
@@ -487,10 +644,19 @@ pub struct Battery {
     allow_missing: bool,
     hide_missing: bool,
     driver: BatteryDriver,
+    smoothing_window: Duration,
+    power_average: Option<PowerAverage>,
     good: u64,
     info: u64,
     warning: u64,
     critical: u64,
+    /// Charge of the battery, unless it is missing
+    capacity: Option<u64>,
+    /// Only set when peripherals are shown, which needs the UPower driver
+    peripherals: Option<UpowerDevice>,
+    peripheral_format: FormatTemplate,
+    peripheral_outputs: Vec<TextWidget>,
+    shared_config: SharedConfig,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -571,6 +737,22 @@ pub struct BatteryConfig {
     /// If the battery device cannot be found, completely hide this block.
     #[serde(default = "BatteryConfig::default_hide_missing")]
     pub hide_missing: bool,
+
+    /// Period in seconds over which the power draw is averaged to estimate the time remaining
+    #[serde(
+        default = "BatteryConfig::default_smoothing_window",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub smoothing_window: Duration,
+
+    /// Also show the batteries of wireless devices known to UPower, such as mice and keyboards
+    #[serde(default = "BatteryConfig::default_peripherals")]
+    pub peripherals: bool,
+
+    /// Format string for displaying the battery of each peripheral.
+    /// placeholders: {model}, {type} and {percentage}
+    #[serde(default = "BatteryConfig::default_peripheral_format")]
+    pub peripheral_format: String,
 }
 
 impl BatteryConfig {
@@ -621,11 +803,39 @@ impl BatteryConfig {
     fn default_hide_missing() -> bool {
         false
     }
+
+    fn default_smoothing_window() -> Duration {
+        Duration::from_secs(120)
+    }
+
+    fn default_peripherals() -> bool {
+        false
+    }
+
+    fn default_peripheral_format() -> String {
+        "{model} {percentage}%".into()
+    }
 }
 
 impl ConfigBlock for Battery {
     type Config = BatteryConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        (
+            "format",
+            &["{percentage}", "{bar}", "{time}", "{time_raw}", "{power}"],
+        ),
+        (
+            "full_format",
+            &["{percentage}", "{bar}", "{time}", "{time_raw}", "{power}"],
+        ),
+        (
+            "missing_format",
+            &["{percentage}", "{bar}", "{time}", "{time_raw}", "{power}"],
+        ),
+        ("peripheral_format", &["{model}", "{type}", "{percentage}"]),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -652,12 +862,24 @@ impl ConfigBlock for Battery {
             _ => BatteryDriver::Sysfs,
         };
 
+        let mut peripherals = None;
         let device: Box<dyn BatteryDevice> = match driver {
             BatteryDriver::Upower => {
-                let out = UpowerDevice::from_device(&block_config.device)?;
-                out.monitor(id, update_request);
+                let out =
+                    UpowerDevice::from_device(&block_config.device, block_config.allow_missing)?;
+                out.monitor(id, block_config.peripherals, update_request);
+                if block_config.peripherals {
+                    // The block's device may be missing, so peripherals get their own connection
+                    peripherals = Some(UpowerDevice::from_device("DisplayDevice", false)?);
+                }
                 Box::new(out)
             }
+            BatteryDriver::Sysfs if block_config.peripherals => {
+                return Err(BlockError(
+                    "battery".into(),
+                    "Showing peripherals needs the upower driver.".into(),
+                ));
+            }
             BatteryDriver::Sysfs => Box::new(PowerSupplyDevice::from_device(
                 &block_config.device,
                 block_config.allow_missing,
@@ -667,26 +889,84 @@ impl ConfigBlock for Battery {
         Ok(Battery {
             id,
             update_interval: block_config.interval,
-            output: TextWidget::new(id, 0, shared_config),
+            output: TextWidget::new(id, 0, shared_config.clone()),
             device,
-            format: FormatTemplate::from_string(&format)?,
-            full_format: FormatTemplate::from_string(&block_config.full_format)?,
-            missing_format: FormatTemplate::from_string(&block_config.missing_format)?,
+            format: FormatTemplate::new(&format, &shared_config)?,
+            full_format: FormatTemplate::new(&block_config.full_format, &shared_config)?,
+            missing_format: FormatTemplate::new(&block_config.missing_format, &shared_config)?,
             allow_missing: block_config.allow_missing,
             hide_missing: block_config.hide_missing,
             driver,
+            smoothing_window: block_config.smoothing_window,
+            power_average: None,
             good: block_config.good,
             info: block_config.info,
             warning: block_config.warning,
             critical: block_config.critical,
+            capacity: None,
+            peripherals,
+            peripheral_format: FormatTemplate::new(
+                &block_config.peripheral_format,
+                &shared_config,
+            )?,
+            peripheral_outputs: Vec::new(),
+            shared_config,
         })
     }
 }
 
+impl Battery {
+    /// The state of a battery which is discharging, from its remaining capacity.
+    fn capacity_state(&self, capacity: u64) -> State {
+        if capacity <= self.critical {
+            State::Critical
+        } else if capacity <= self.warning {
+            State::Warning
+        } else if capacity <= self.info {
+            State::Info
+        } else if capacity > self.good {
+            State::Good
+        } else {
+            State::Idle
+        }
+    }
+
+    fn update_peripherals(&mut self) -> Result<()> {
+        let peripherals = match self.peripherals {
+            Some(ref upower) => upower.peripherals()?,
+            None => return Ok(()),
+        };
+        let mut outputs = Vec::with_capacity(peripherals.len());
+        for (i, peripheral) in peripherals.into_iter().enumerate() {
+            let values = map!(
+                "{model}" => Value::text(peripheral.model),
+                "{type}" => Value::text(peripheral.kind.to_string()),
+                "{percentage}" => Value::text(peripheral.percentage.to_string())
+            );
+            let mut output = TextWidget::new(self.id, i + 1, self.shared_config.clone())
+                .with_icon(battery_level_to_icon(Ok(peripheral.percentage)))
+                .with_text(&self.peripheral_format.render(&values)?);
+            output.set_state(self.capacity_state(peripheral.percentage));
+            outputs.push(output);
+        }
+        self.peripheral_outputs = outputs;
+        Ok(())
+    }
+}
+
 impl Block for Battery {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         // TODO: Maybe use dbus to immediately signal when the battery state changes.
 
+        // The device may have gone missing or come back
+        // It may be a different battery now, thereby refresh the device specs.
+        self.device.refresh_device_info()?;
+        self.update_peripherals()?;
+
         // Exit early, if the battery device went missing, but the user
         // allows this device to go missing.
         if !self.device.is_available() && self.allow_missing {
@@ -694,16 +974,18 @@ impl Block for Battery {
             // cannot be found right now.
             let empty_percent_bar = format_percent_bar(0.0);
             let values = map!(
-                "{percentage}" => "X",
-                "{bar}" => &empty_percent_bar,
-                "{time}" => "xx:xx",
-                "{power}" => "N/A"
+                "{percentage}" => Value::text("X".to_string()),
+                "{bar}" => Value::text(empty_percent_bar),
+                "{time}" => Value::text("xx:xx".to_string()),
+                "{time_raw}" => Value::text("xx:xx".to_string()),
+                "{power}" => Value::text("N/A".to_string())
             );
 
+            self.capacity = None;
             self.output.set_icon("bat_not_available");
-            self.output
-                .set_text(self.missing_format.render_static_str(&values)?);
+            self.output.set_text(self.missing_format.render(&values)?);
             self.output.set_state(State::Warning);
+            self.output.set_severity(None);
 
             return match self.driver {
                 BatteryDriver::Sysfs => Ok(Some(Update::Every(self.update_interval))),
@@ -711,12 +993,9 @@ impl Block for Battery {
             };
         }
 
-        // The device may have gone missing
-        // It may be a different battery now, thereby refresh the device specs.
-        self.device.refresh_device_info()?;
-
         let status = self.device.status()?;
         let capacity = self.device.capacity();
+        self.capacity = capacity.as_ref().ok().copied();
         let percentage = match capacity {
             Ok(capacity) => format!("{}", capacity),
             Err(_) => "×".into(),
@@ -725,54 +1004,78 @@ impl Block for Battery {
             Ok(capacity) => format_percent_bar(capacity as f32),
             Err(_) => "×".into(),
         };
-        let time = match self.device.time_remaining() {
-            Ok(time) => match time {
-                0 => "".into(),
-                _ => format!("{}:{:02}", std::cmp::min(time / 60, 99), time % 60),
-            },
-            Err(_) => "×".into(),
+        let time_raw = self.device.time_remaining().ok();
+        let power_now = self.device.power_consumption().ok();
+
+        // Short spikes of load barely move the average power draw, which the time remaining is
+        // scaled by. It starts over when the battery switches between charging and discharging.
+        let now = Instant::now();
+        let window = self.smoothing_window;
+        let previous = self
+            .power_average
+            .take()
+            .filter(|average| average.status == status);
+        self.power_average = power_now
+            .filter(|&power| power > 0)
+            .map(|power| PowerAverage {
+                power: match previous {
+                    Some(average) => {
+                        ewma(average.power, power as f64, now - average.updated, window)
+                    }
+                    None => power as f64,
+                },
+                updated: now,
+                status: status.clone(),
+            });
+        let time_smoothed = match (time_raw, power_now, &self.power_average) {
+            // The time remaining is the energy left divided by the power draw
+            (Some(time), Some(power), Some(average)) => {
+                Some((time as f64 * power as f64 / average.power).round() as u64)
+            }
+            (time, _, _) => time,
+        };
+
+        let time_value = |time: Option<u64>| match time {
+            Some(0) => Value::text(String::new()),
+            Some(time) => Value::duration(Duration::from_secs(time * 60)).duration_format(
+                DurationFormat::Clock(vec![TimeUnit::Hours, TimeUnit::Minutes]),
+            ),
+            None => Value::text("×".into()),
         };
         // convert µW to W for display
-        let power = match self.device.power_consumption() {
-            Ok(power) => format!("{:.2}", power as f64 / 1000.0 / 1000.0),
-            Err(_) => "×".into(),
+        let power = match power_now {
+            Some(power) => format!("{:.2}", power as f64 / 1000.0 / 1000.0),
+            None => "×".into(),
         };
-        let values = map!("{percentage}" => percentage,
-                            "{bar}" => bar,
-                            "{time}" => time,
-                            "{power}" => power);
+        let values = map!("{percentage}" => Value::text(percentage),
+                            "{bar}" => Value::text(bar),
+                            "{time}" => time_value(time_smoothed),
+                            "{time_raw}" => time_value(time_raw),
+                            "{power}" => Value::text(power));
 
         if status == "Full" || status == "Not charging" {
             self.output.set_icon("bat_full");
-            self.output
-                .set_text(self.full_format.render_static_str(&values)?);
+            self.output.set_text(self.full_format.render(&values)?);
             self.output.set_state(State::Good);
+            self.output.set_severity(None);
             self.output.set_spacing(Spacing::Hidden);
         } else {
-            self.output
-                .set_text(self.format.render_static_str(&values)?);
+            self.output.set_text(self.format.render(&values)?);
 
             // Check if the battery is in charging mode and change the state to Good.
             // Otherwise, adjust the state depeding the power percentance.
             match status.as_str() {
                 "Charging" => {
                     self.output.set_state(State::Good);
+                    self.output.set_severity(None);
                 }
                 _ => {
+                    self.output
+                        .set_severity(capacity.as_ref().ok().map(|&capacity| {
+                            severity(capacity as f64, self.good as f64, self.critical as f64)
+                        }));
                     self.output.set_state(match capacity {
-                        Ok(capacity) => {
-                            if capacity <= self.critical {
-                                State::Critical
-                            } else if capacity <= self.warning {
-                                State::Warning
-                            } else if capacity <= self.info {
-                                State::Info
-                            } else if capacity > self.good {
-                                State::Good
-                            } else {
-                                State::Idle
-                            }
-                        }
+                        Ok(capacity) => self.capacity_state(capacity),
                         Err(_) => State::Warning,
                     });
                 }
@@ -792,16 +1095,58 @@ impl Block for Battery {
         }
     }
 
+    fn percentage(&self) -> Option<u64> {
+        self.capacity
+    }
+
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        // Don't display the block at all, if it's configured to be hidden on missing batteries
+        let peripherals = self
+            .peripheral_outputs
+            .iter()
+            .map(|output| output as &dyn I3BarWidget);
+
+        // Don't display the battery at all, if it's configured to be hidden on missing batteries
         if !self.device.is_available() && self.hide_missing {
-            return Vec::new();
+            return peripherals.collect();
         }
 
-        vec![&self.output]
+        std::iter::once(&self.output as &dyn I3BarWidget)
+            .chain(peripherals)
+            .collect()
     }
 
     fn id(&self) -> usize {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ewma, peripheral_kind};
+
+    #[test]
+    fn test_ewma() {
+        let window = Duration::from_secs(120);
+        // A spike only moves the average a little when it is short
+        let average = ewma(10.0, 30.0, Duration::from_secs(10), window);
+        assert!(average > 11.5 && average < 11.7);
+        // Old samples weigh nothing after several windows
+        assert!((ewma(10.0, 30.0, Duration::from_secs(1200), window) - 30.0).abs() < 0.01);
+        assert_eq!(ewma(10.0, 30.0, Duration::from_secs(0), window), 10.0);
+        // Without a window, there's no smoothing
+        assert_eq!(
+            ewma(10.0, 30.0, Duration::from_secs(10), Duration::from_secs(0)),
+            30.0
+        );
+    }
+
+    #[test]
+    fn test_peripheral_kind() {
+        assert_eq!(peripheral_kind(5), "mouse");
+        assert_eq!(peripheral_kind(6), "keyboard");
+        assert_eq!(peripheral_kind(17), "headset");
+        assert_eq!(peripheral_kind(28), "device");
+    }
+}
@@ -1,5 +1,6 @@
 use serde_derive::Deserialize;
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -157,6 +158,11 @@ impl BluetoothDevice {
         let update_request_copy1 = update_request.clone();
         let update_request_copy2 = update_request.clone();
         let update_request_copy3 = update_request;
+        // Set once the bar dropped the block, e.g. after reloading the config
+        let dropped = Arc::new(AtomicBool::new(false));
+        let dropped_copy1 = dropped.clone();
+        let dropped_copy2 = dropped.clone();
+        let dropped_copy3 = dropped.clone();
 
         thread::Builder::new().name("bluetooth".into()).spawn(move || {
             let c = dbus::blocking::Connection::new_system().unwrap();
@@ -166,12 +172,15 @@ impl BluetoothDevice {
                 if ia.object == path_copy1.clone().into() {
                     let mut avail = avail_copy1.lock().unwrap();
                     *avail = true;
-                    update_request_copy1
+                    if update_request_copy1
                         .send(Task {
                             id,
                             update_time: Instant::now(),
                         })
-                        .unwrap();
+                        .is_err()
+                    {
+                        dropped_copy1.store(true, Ordering::Relaxed);
+                    }
                 }
                 true
             })
@@ -183,12 +192,15 @@ impl BluetoothDevice {
                 if ir.object == path_copy2.clone().into() {
                     let mut avail = avail_copy2.lock().unwrap();
                     *avail = false;
-                    update_request_copy2
+                    if update_request_copy2
                         .send(Task {
                             id,
                             update_time: Instant::now(),
                         })
-                        .unwrap();
+                        .is_err()
+                    {
+                        dropped_copy2.store(true, Ordering::Relaxed);
+                    }
                 }
                 true
             })
@@ -198,17 +210,20 @@ impl BluetoothDevice {
             let mr = PPC::match_rule(Some(&"org.bluez".into()), None).static_clone();
             // TODO: get updated values from the signal message
             c.add_match(mr, move |_ppc: PPC, _, _| {
-                update_request_copy3
+                if update_request_copy3
                     .send(Task {
                         id,
                         update_time: Instant::now(),
                     })
-                    .unwrap();
+                    .is_err()
+                {
+                    dropped_copy3.store(true, Ordering::Relaxed);
+                }
                 true
             })
             .unwrap();
 
-            loop {
+            while !dropped.load(Ordering::Relaxed) {
                 c.process(Duration::from_millis(1000)).unwrap();
             }
         }).unwrap();
@@ -247,6 +262,9 @@ impl BluetoothConfig {
 impl ConfigBlock for Bluetooth {
     type Config = BluetoothConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format_unavailable", &["{label}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -258,7 +276,7 @@ impl ConfigBlock for Bluetooth {
 
         Ok(Bluetooth {
             id,
-            output: TextWidget::new(id, 0, shared_config).with_icon(match device.icon {
+            output: TextWidget::new(id, 0, shared_config.clone()).with_icon(match device.icon {
                 Some(ref icon) if icon == "audio-card" => "headphones",
                 Some(ref icon) if icon == "input-gaming" => "joystick",
                 Some(ref icon) if icon == "input-keyboard" => "keyboard",
@@ -267,7 +285,10 @@ impl ConfigBlock for Bluetooth {
             }),
             device,
             hide_disconnected: block_config.hide_disconnected,
-            format_unavailable: FormatTemplate::from_string(&block_config.format_unavailable)?,
+            format_unavailable: FormatTemplate::new(
+                &block_config.format_unavailable,
+                &shared_config,
+            )?,
         })
     }
 }
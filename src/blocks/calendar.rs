@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::{
+    DateTime, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::{read_file, FormatTemplate};
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// A daily or weekly repetition of an event, from its `RRULE`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Recurrence {
+    /// Time between two occurrences
+    period: ChronoDuration,
+    /// Number of occurrences, including the first one
+    count: Option<u32>,
+    /// Start of the last occurrence, at the latest
+    until: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Event {
+    summary: String,
+    location: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    recurrence: Option<Recurrence>,
+}
+
+impl Event {
+    /// The start and end of the first occurrence which hasn't ended at `now`, if any.
+    fn next_occurrence(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let duration = self.end - self.start;
+        let mut start = self.start;
+        if let Some(recurrence) = self.recurrence {
+            let first_end = start + duration;
+            if first_end <= now {
+                // Skip the past occurrences at once
+                let mut skipped =
+                    (now - first_end).num_seconds() / recurrence.period.num_seconds().max(1) + 1;
+                if let Some(count) = recurrence.count {
+                    skipped = skipped.min(i64::from(count) - 1).max(0);
+                }
+                start = start + recurrence.period * skipped as i32;
+            }
+            if recurrence.until.map_or(false, |until| start > until) {
+                return None;
+            }
+        }
+        Some((start, start + duration)).filter(|&(_, end)| end > now)
+    }
+}
+
+pub struct Calendar {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    refresh_interval: Duration,
+    files: Vec<PathBuf>,
+    url: Option<String>,
+    warning: ChronoDuration,
+    format: FormatTemplate,
+    events: Vec<Event>,
+    last_refresh: Option<Instant>,
+    has_event: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// Paths of iCalendar files
+    #[serde(default)]
+    pub files: Vec<String>,
+
+    /// URL of an iCalendar file, e.g. the export of a CalDAV calendar
+    pub url: Option<String>,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "CalendarConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Time between two readings of the calendars, in seconds
+    #[serde(
+        default = "CalendarConfig::default_refresh_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub refresh_interval: Duration,
+
+    /// Minutes before an event from which the block is in warning state
+    #[serde(default = "CalendarConfig::default_warning_minutes")]
+    pub warning_minutes: i64,
+
+    /// Format override
+    #[serde(default = "CalendarConfig::default_format")]
+    pub format: String,
+}
+
+impl CalendarConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_refresh_interval() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_warning_minutes() -> i64 {
+        15
+    }
+
+    fn default_format() -> String {
+        "{countdown} {summary}".to_owned()
+    }
+}
+
+/// Joins the lines folded with a leading space or tab.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')) {
+            Some(rest) if !lines.is_empty() => lines.last_mut().unwrap().push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        // Line breaks are shown as spaces in the bar
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push(' '),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Parses a `DATE-TIME` in UTC, in the time zone of its `TZID` parameter or in local time, or
+/// a `DATE`, which starts at local midnight.
+fn parse_time(value: &str, params: &HashMap<&str, &str>) -> Option<DateTime<Utc>> {
+    if value.len() == 8 || params.get("VALUE") == Some(&"DATE") {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Local
+            .from_local_datetime(&date.and_hms(0, 0, 0))
+            .earliest()
+            .map(|time| time.with_timezone(&Utc));
+    }
+    if let Some(value) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&time));
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    // Time zones may have names which aren't in the tz database, e.g. on Windows
+    match params
+        .get("TZID")
+        .and_then(|tz| tz.trim_matches('"').parse::<Tz>().ok())
+    {
+        Some(tz) => tz
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+        None => Local
+            .from_local_datetime(&time)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc)),
+    }
+}
+
+/// Parses an `RRULE`. Only daily and weekly rules are supported, without `BY...` parts except
+/// the day of weekly ones, which is assumed to be the day of the event.
+fn parse_rrule(value: &str) -> Option<Recurrence> {
+    let parts: HashMap<&str, &str> = value
+        .split(';')
+        .filter_map(|part| {
+            let equal = part.find('=')?;
+            Some((&part[..equal], &part[equal + 1..]))
+        })
+        .collect();
+    let period = match parts.get("FREQ") {
+        Some(&"DAILY") => ChronoDuration::days(1),
+        Some(&"WEEKLY") => ChronoDuration::weeks(1),
+        _ => return None,
+    };
+    let weekly = period == ChronoDuration::weeks(1);
+    if parts.iter().any(|(key, value)| {
+        key.starts_with("BY") && !(weekly && *key == "BYDAY" && !value.contains(','))
+    }) {
+        return None;
+    }
+    let interval: i32 = parts
+        .get("INTERVAL")
+        .and_then(|interval| interval.parse().ok())
+        .unwrap_or(1);
+    Some(Recurrence {
+        period: period * interval.max(1),
+        count: parts.get("COUNT").and_then(|count| count.parse().ok()),
+        until: parts
+            .get("UNTIL")
+            .and_then(|until| parse_time(until, &HashMap::new())),
+    })
+}
+
+/// The events of an iCalendar file, except the cancelled ones.
+fn parse_events(ics: &str) -> Vec<Event> {
+    let lines = unfold(ics);
+    let mut events = Vec::new();
+    let mut event: Option<HashMap<String, (HashMap<&str, &str>, String)>> = None;
+    for line in &lines {
+        let colon = match line.find(':') {
+            Some(colon) => colon,
+            None => continue,
+        };
+        let (name, value) = (&line[..colon], &line[colon + 1..]);
+        let mut params = name.split(';');
+        let name = params.next().unwrap_or_default().to_ascii_uppercase();
+        let params: HashMap<&str, &str> = params
+            .filter_map(|param| {
+                let equal = param.find('=')?;
+                Some((&param[..equal], &param[equal + 1..]))
+            })
+            .collect();
+
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => event = Some(HashMap::new()),
+            ("END", "VEVENT") => {
+                let properties = match event.take() {
+                    Some(properties) => properties,
+                    None => continue,
+                };
+                let time = |name: &str| {
+                    let (params, value) = properties.get(name)?;
+                    parse_time(value, params)
+                };
+                let text = |name: &str| {
+                    properties
+                        .get(name)
+                        .map(|(_, value)| unescape(value))
+                        .unwrap_or_default()
+                };
+                let start = match time("DTSTART") {
+                    Some(start) => start,
+                    None => continue,
+                };
+                if text("STATUS") == "CANCELLED" {
+                    continue;
+                }
+                // Without an end, an event lasts a day if it starts at a date, and no time
+                // otherwise
+                let all_day = properties
+                    .get("DTSTART")
+                    .map_or(false, |(_, value)| value.len() == 8);
+                let end = time("DTEND")
+                    .unwrap_or_else(|| start + ChronoDuration::days(if all_day { 1 } else { 0 }));
+                events.push(Event {
+                    summary: text("SUMMARY"),
+                    location: text("LOCATION"),
+                    start,
+                    end,
+                    recurrence: properties
+                        .get("RRULE")
+                        .and_then(|(_, value)| parse_rrule(value)),
+                });
+            }
+            (_, _) => {
+                if let Some(ref mut properties) = event {
+                    properties.insert(name, (params, value.to_string()));
+                }
+            }
+        }
+    }
+    events
+}
+
+/// The time left until an event, e.g. `5m`, `1h05m` or `2d`.
+fn countdown(left: ChronoDuration) -> String {
+    let minutes = (left.num_seconds() + 59) / 60;
+    if minutes <= 0 {
+        "now".to_string()
+    } else if minutes < 60 {
+        format!("{}m", minutes)
+    } else if minutes < 24 * 60 {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}d", minutes / (24 * 60))
+    }
+}
+
+impl Calendar {
+    /// Reads the events of all calendars again.
+    fn refresh(&mut self) -> Result<()> {
+        let mut events = Vec::new();
+        for file in &self.files {
+            events.extend(parse_events(&read_file("calendar", file)?));
+        }
+        if let Some(ref url) = self.url {
+            let response = http::http_get_bytes(url, Some(Duration::from_secs(10)), vec![])?;
+            if !(200..300).contains(&response.code) {
+                return Err(BlockError(
+                    "calendar".to_string(),
+                    format!("{} replied with status {}", url, response.code),
+                ));
+            }
+            events.extend(parse_events(&String::from_utf8_lossy(&response.content)));
+        }
+        self.events = events;
+        self.last_refresh = Some(Instant::now());
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Calendar {
+    type Config = CalendarConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &["{countdown}", "{location}", "{start}", "{summary}"],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.files.is_empty() && block_config.url.is_none() {
+            return Err(BlockError(
+                "calendar".to_string(),
+                "either `files` or `url` must be set".to_string(),
+            ));
+        }
+        let files = block_config
+            .files
+            .iter()
+            .map(|file| match file.strip_prefix("~/") {
+                Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+                None => PathBuf::from(file),
+            })
+            .collect();
+
+        Ok(Calendar {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("calendar"),
+            update_interval: block_config.interval,
+            refresh_interval: block_config.refresh_interval,
+            files,
+            url: block_config.url,
+            warning: ChronoDuration::minutes(block_config.warning_minutes),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("calendar", "Invalid format specified")?,
+            events: Vec::new(),
+            last_refresh: None,
+            has_event: false,
+        })
+    }
+}
+
+impl Block for Calendar {
+    fn update(&mut self) -> Result<Option<Update>> {
+        if self
+            .last_refresh
+            .map_or(true, |last| last.elapsed() >= self.refresh_interval)
+        {
+            self.refresh()?;
+        }
+
+        let now = Utc::now();
+        let next = self
+            .events
+            .iter()
+            .filter_map(|event| Some((event, event.next_occurrence(now)?)))
+            .min_by_key(|&(_, (start, _))| start);
+        self.has_event = next.is_some();
+        let (event, (start, _)) = match next {
+            Some(next) => next,
+            None => return Ok(Some(self.update_interval.into())),
+        };
+
+        let local_start = start.with_timezone(&Local);
+        let start_text = if local_start.date() == Local::today() {
+            local_start.format("%H:%M").to_string()
+        } else {
+            local_start.format("%a %H:%M").to_string()
+        };
+        let values = map!(
+            "{summary}" => event.summary.clone(),
+            "{location}" => event.location.clone(),
+            "{countdown}" => countdown(start - now),
+            "{start}" => start_text
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(if start <= now {
+            State::Info
+        } else if start - now <= self.warning {
+            State::Warning
+        } else {
+            State::Idle
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.has_event {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{countdown, parse_events, Event, Recurrence};
+
+    #[test]
+    fn test_parse_events() {
+        let ics = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+SUMMARY:Weekly sync\\, with the\r
+  whole team\r
+LOCATION:Room 4\r
+DTSTART;TZID=Europe/Paris:20240105T093000\r
+DTEND;TZID=Europe/Paris:20240105T100000\r
+RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=3;BYDAY=FR\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Cancelled\r
+STATUS:CANCELLED\r
+DTSTART:20240105T120000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Lunch\r
+DTSTART:20240105T120000Z\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+        assert_eq!(
+            parse_events(ics),
+            vec![
+                Event {
+                    summary: "Weekly sync, with the whole team".to_string(),
+                    location: "Room 4".to_string(),
+                    start: Utc.ymd(2024, 1, 5).and_hms(8, 30, 0),
+                    end: Utc.ymd(2024, 1, 5).and_hms(9, 0, 0),
+                    recurrence: Some(Recurrence {
+                        period: Duration::weeks(2),
+                        count: Some(3),
+                        until: None,
+                    }),
+                },
+                Event {
+                    summary: "Lunch".to_string(),
+                    location: String::new(),
+                    start: Utc.ymd(2024, 1, 5).and_hms(12, 0, 0),
+                    end: Utc.ymd(2024, 1, 5).and_hms(12, 0, 0),
+                    recurrence: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let start = Utc.ymd(2024, 1, 5).and_hms(8, 30, 0);
+        let mut event = Event {
+            summary: String::new(),
+            location: String::new(),
+            start,
+            end: start + Duration::minutes(30),
+            recurrence: Some(Recurrence {
+                period: Duration::weeks(1),
+                count: Some(3),
+                until: None,
+            }),
+        };
+        let next = |event: &Event, now| event.next_occurrence(now).map(|(start, _)| start);
+
+        assert_eq!(next(&event, start - Duration::days(1)), Some(start));
+        // Ongoing
+        assert_eq!(next(&event, start + Duration::minutes(10)), Some(start));
+        assert_eq!(
+            next(&event, start + Duration::days(1)),
+            Some(start + Duration::weeks(1))
+        );
+        assert_eq!(
+            next(&event, start + Duration::days(10)),
+            Some(start + Duration::weeks(2))
+        );
+        assert_eq!(next(&event, start + Duration::days(20)), None);
+
+        event.recurrence = None;
+        assert_eq!(next(&event, start + Duration::days(1)), None);
+    }
+
+    #[test]
+    fn test_countdown() {
+        assert_eq!(countdown(Duration::seconds(-30)), "now");
+        assert_eq!(countdown(Duration::seconds(30)), "1m");
+        assert_eq!(countdown(Duration::minutes(65)), "1h05m");
+        assert_eq!(countdown(Duration::days(2)), "2d");
+    }
+}
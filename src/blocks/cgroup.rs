@@ -0,0 +1,188 @@
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Mount point of the cgroup v2 hierarchy
+const CGROUP_PATH: &str = "/sys/fs/cgroup";
+
+pub struct Cgroup {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    path: PathBuf,
+    name: String,
+    format: FormatTemplate,
+    /// Time of the last update and CPU time used by then, in microseconds
+    last_cpu: Option<(Instant, u64)>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CgroupConfig {
+    /// Path of the cgroup, relative to `/sys/fs/cgroup`, e.g. `user.slice`
+    pub path: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "CgroupConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "CgroupConfig::default_format")]
+    pub format: String,
+}
+
+impl CgroupConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{cpu} {memory}".to_owned()
+    }
+}
+
+/// The CPU time used by a cgroup, in microseconds, from its `cpu.stat`.
+fn parse_cpu_usage(cpu_stat: &str) -> Option<u64> {
+    cpu_stat.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        if words.next()? != "usage_usec" {
+            return None;
+        }
+        words.next()?.parse().ok()
+    })
+}
+
+impl Cgroup {
+    fn read(&self, file: &str) -> Result<String> {
+        read_to_string(self.path.join(file)).block_error(
+            "cgroup",
+            &format!("failed to read {} of cgroup {}", file, self.name),
+        )
+    }
+}
+
+impl ConfigBlock for Cgroup {
+    type Config = CgroupConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{cpu}",
+            "{memory_max}",
+            "{memory_percentage}",
+            "{memory}",
+            "{name}",
+        ],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let name = block_config.path.trim_matches('/').to_string();
+        Ok(Cgroup {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            update_interval: block_config.interval,
+            path: PathBuf::from(CGROUP_PATH).join(&name),
+            name,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("cgroup", "Invalid format specified")?,
+            last_cpu: None,
+        })
+    }
+}
+
+impl Block for Cgroup {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let now = Instant::now();
+        let usage = parse_cpu_usage(&self.read("cpu.stat")?)
+            .block_error("cgroup", "failed to parse cpu.stat")?;
+        // Share of one CPU used since the last update, so up to 100% per core
+        let cpu = match self.last_cpu {
+            Some((last_time, last_usage)) => {
+                let elapsed = now.duration_since(last_time).as_micros() as f64;
+                if elapsed > 0. {
+                    usage.saturating_sub(last_usage) as f64 / elapsed * 100.
+                } else {
+                    0.
+                }
+            }
+            None => 0.,
+        };
+        self.last_cpu = Some((now, usage));
+
+        let memory: f64 = self
+            .read("memory.current")?
+            .trim()
+            .parse()
+            .block_error("cgroup", "failed to parse memory.current")?;
+        // `max` when the memory of the cgroup is not limited
+        let memory_max = self.read("memory.max")?.trim().parse::<f64>().ok();
+
+        let values = map!(
+            "{name}" => Value::text(self.name.clone()),
+            "{cpu}" => Value::text(format!("{:.0}%", cpu)),
+            "{memory}" => Value::bytes(memory),
+            "{memory_max}" => memory_max.map(Value::bytes).unwrap_or_else(|| Value::text(String::new())),
+            "{memory_percentage}" => Value::text(
+                memory_max
+                    .map(|max| format!("{:.0}%", memory / max * 100.))
+                    .unwrap_or_default()
+            )
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text
+            .set_state(self.format.state(&values).unwrap_or(State::Idle));
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_cpu_usage;
+
+    #[test]
+    fn test_parse_cpu_usage() {
+        let cpu_stat = "usage_usec 8216390000
+user_usec 6022320000
+system_usec 2194070000
+nr_periods 0
+nr_throttled 0
+throttled_usec 0
+";
+        assert_eq!(parse_cpu_usage(cpu_stat), Some(8_216_390_000));
+        assert_eq!(parse_cpu_usage("user_usec 6022320000\n"), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! A block for displaying and toggling the charge limit of a battery.
+//!
+//! Laptops whose firmware supports it expose the charge level at which the battery stops charging
+//! in `/sys/class/power_supply/BAT*/charge_control_end_threshold`. The file is only writable by
+//! root, so unless a udev rule makes it writable, changes go through a helper such as `pkexec`.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::{read_file, write_sysfs, FormatTemplate};
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+const THRESHOLD_FILE: &str = "charge_control_end_threshold";
+
+/// The threshold to switch to from `current`: charging fully when the charge is capped, and
+/// capping it at `limit` otherwise.
+fn toggled_threshold(current: u8, limit: u8) -> u8 {
+    if current < 100 {
+        100
+    } else {
+        limit
+    }
+}
+
+/// The threshold file of the first battery which has one.
+fn find_threshold_file() -> Option<PathBuf> {
+    let mut batteries: Vec<PathBuf> = Path::new(POWER_SUPPLY_DIR)
+        .read_dir()
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("BAT"))
+        .map(|entry| entry.path().join(THRESHOLD_FILE))
+        .collect();
+    batteries.sort();
+    batteries.into_iter().find(|path| path.exists())
+}
+
+pub struct ChargeLimit {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    path: PathBuf,
+    limit: u8,
+    helper: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ChargeLimitConfig {
+    /// The battery in `/sys/class/power_supply/`, by default the first one with a charge limit
+    #[serde(default = "ChargeLimitConfig::default_device")]
+    pub device: Option<String>,
+
+    /// Charge level in percent to cap the charge at when toggling
+    #[serde(default = "ChargeLimitConfig::default_limit")]
+    pub limit: u8,
+
+    /// Program running `tee` as root to write the threshold when the block can't
+    #[serde(default = "ChargeLimitConfig::default_helper")]
+    pub helper: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "ChargeLimitConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "ChargeLimitConfig::default_format")]
+    pub format: String,
+}
+
+impl ChargeLimitConfig {
+    fn default_device() -> Option<String> {
+        None
+    }
+
+    fn default_limit() -> u8 {
+        80
+    }
+
+    fn default_helper() -> String {
+        "pkexec".to_owned()
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{threshold}%".to_owned()
+    }
+}
+
+impl ChargeLimit {
+    fn threshold(&self) -> Result<u8> {
+        read_file("charge_limit", &self.path)?
+            .trim()
+            .parse()
+            .block_error("charge_limit", "failed to parse the charge threshold")
+    }
+
+    fn set_threshold(&self, threshold: u8) -> Result<()> {
+        write_sysfs(
+            "charge_limit",
+            &[self.path.clone()],
+            &threshold.to_string(),
+            &self.helper,
+        )
+    }
+}
+
+impl ConfigBlock for ChargeLimit {
+    type Config = ChargeLimitConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{threshold}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let path = match block_config.device {
+            Some(device) => Path::new(POWER_SUPPLY_DIR)
+                .join(device)
+                .join(THRESHOLD_FILE),
+            None => find_threshold_file()
+                .block_error("charge_limit", "no battery with a charge limit found")?,
+        };
+        if !path.exists() {
+            return Err(BlockError(
+                "charge_limit".to_string(),
+                format!("{} does not exist", path.display()),
+            ));
+        }
+        if block_config.limit == 0 || block_config.limit > 100 {
+            return Err(BlockError(
+                "charge_limit".to_string(),
+                "limit must be between 1 and 100".to_string(),
+            ));
+        }
+
+        Ok(ChargeLimit {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("charge_limit"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("charge_limit", "Invalid format specified")?,
+            path,
+            limit: block_config.limit,
+            helper: block_config.helper,
+        })
+    }
+}
+
+impl Block for ChargeLimit {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let threshold = self.threshold()?;
+        let values = map!("{threshold}" => threshold.to_string());
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(if threshold < 100 {
+            State::Good
+        } else {
+            State::Idle
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button == MouseButton::Left {
+            self.set_threshold(toggled_threshold(self.threshold()?, self.limit))?;
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::toggled_threshold;
+
+    #[test]
+    fn test_toggled_threshold() {
+        assert_eq!(toggled_threshold(100, 80), 80);
+        assert_eq!(toggled_threshold(80, 80), 100);
+        // Capped at another level, e.g. by the firmware's own tool
+        assert_eq!(toggled_threshold(60, 80), 100);
+    }
+}
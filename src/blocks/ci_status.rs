@@ -0,0 +1,359 @@
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CiProvider {
+    /// GitHub Actions
+    Github,
+    /// GitLab CI
+    Gitlab,
+}
+
+impl CiProvider {
+    fn default_server(self) -> &'static str {
+        match self {
+            CiProvider::Github => "https://api.github.com",
+            CiProvider::Gitlab => "https://gitlab.com",
+        }
+    }
+
+    /// The environment variable holding the token, which is only needed for private projects.
+    fn token_env(self) -> &'static str {
+        match self {
+            CiProvider::Github => "I3RS_GITHUB_TOKEN",
+            CiProvider::Gitlab => "I3RS_GITLAB_TOKEN",
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            CiProvider::Github => "github",
+            CiProvider::Gitlab => "gitlab",
+        }
+    }
+}
+
+/// The outcome of the latest pipeline of a branch.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum PipelineStatus {
+    Passed,
+    Failed,
+    Running,
+    /// Canceled or skipped, or without any pipeline
+    Unknown,
+}
+
+impl PipelineStatus {
+    /// The status of a workflow run of GitHub Actions.
+    fn from_github(status: &str, conclusion: Option<&str>) -> Self {
+        match (status, conclusion) {
+            ("completed", Some("success")) => PipelineStatus::Passed,
+            ("completed", Some("failure")) | ("completed", Some("timed_out")) => {
+                PipelineStatus::Failed
+            }
+            ("completed", _) => PipelineStatus::Unknown,
+            _ => PipelineStatus::Running,
+        }
+    }
+
+    /// The status of a pipeline of GitLab CI.
+    fn from_gitlab(status: &str) -> Self {
+        match status {
+            "success" => PipelineStatus::Passed,
+            "failed" => PipelineStatus::Failed,
+            "created" | "waiting_for_resource" | "preparing" | "pending" | "running" => {
+                PipelineStatus::Running
+            }
+            _ => PipelineStatus::Unknown,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PipelineStatus::Passed => "passed",
+            PipelineStatus::Failed => "failed",
+            PipelineStatus::Running => "running",
+            PipelineStatus::Unknown => "unknown",
+        }
+    }
+
+    fn state(self) -> State {
+        match self {
+            PipelineStatus::Passed => State::Good,
+            PipelineStatus::Failed => State::Critical,
+            PipelineStatus::Running => State::Info,
+            PipelineStatus::Unknown => State::Idle,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    pub provider: CiProvider,
+
+    /// Full name of the repository, e.g. `greshake/i3status-rust`
+    pub repository: String,
+
+    /// Branch whose pipelines are shown, all if unset
+    pub branch: Option<String>,
+
+    /// API server for GitHub, or instance for GitLab
+    pub server: Option<String>,
+}
+
+struct Pipeline {
+    config: PipelineConfig,
+    token: Option<String>,
+    text: TextWidget,
+}
+
+impl Pipeline {
+    fn url(&self) -> String {
+        let server = self
+            .config
+            .server
+            .as_deref()
+            .unwrap_or_else(|| self.config.provider.default_server())
+            .trim_end_matches('/');
+        let mut url = match self.config.provider {
+            CiProvider::Github => format!(
+                "{}/repos/{}/actions/runs?per_page=1",
+                server, self.config.repository
+            ),
+            CiProvider::Gitlab => format!(
+                "{}/api/v4/projects/{}/pipelines?per_page=1",
+                server,
+                self.config.repository.replace('/', "%2F")
+            ),
+        };
+        if let Some(ref branch) = self.config.branch {
+            let parameter = match self.config.provider {
+                CiProvider::Github => "branch",
+                CiProvider::Gitlab => "ref",
+            };
+            url.push_str(&format!("&{}={}", parameter, branch));
+        }
+        url
+    }
+
+    fn status(&self) -> Result<PipelineStatus> {
+        let authorization;
+        let mut headers = Vec::new();
+        if let Some(ref token) = self.token {
+            match self.config.provider {
+                CiProvider::Github => {
+                    authorization = format!("Bearer {}", token);
+                    headers.push(("Authorization", authorization.as_str()));
+                }
+                CiProvider::Gitlab => headers.push(("PRIVATE-TOKEN", token.as_str())),
+            }
+        }
+        let url = self.url();
+        let response = http::http_get_json(&url, Some(Duration::from_secs(10)), headers)?;
+        if !(200..300).contains(&response.code) {
+            return Err(BlockError(
+                "ci_status".to_string(),
+                format!("{} replied with status {}", url, response.code),
+            ));
+        }
+        Ok(latest_status(self.config.provider, &response.content))
+    }
+}
+
+/// The status of the latest pipeline in a reply listing them.
+fn latest_status(provider: CiProvider, reply: &Value) -> PipelineStatus {
+    match provider {
+        CiProvider::Github => {
+            let run = &reply["workflow_runs"][0];
+            match run["status"].as_str() {
+                Some(status) => PipelineStatus::from_github(status, run["conclusion"].as_str()),
+                None => PipelineStatus::Unknown,
+            }
+        }
+        CiProvider::Gitlab => reply[0]["status"]
+            .as_str()
+            .map_or(PipelineStatus::Unknown, PipelineStatus::from_gitlab),
+    }
+}
+
+pub struct CiStatus {
+    id: usize,
+    pipelines: Vec<Pipeline>,
+    update_interval: Duration,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CiStatusConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "CiStatusConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "CiStatusConfig::default_format")]
+    pub format: String,
+
+    pub pipelines: Vec<PipelineConfig>,
+}
+
+impl CiStatusConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(120)
+    }
+
+    fn default_format() -> String {
+        "{repository} {status}".to_owned()
+    }
+}
+
+impl ConfigBlock for CiStatus {
+    type Config = CiStatusConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{branch}", "{repository}", "{status}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let pipelines = block_config
+            .pipelines
+            .into_iter()
+            .enumerate()
+            .map(|(i, config)| Pipeline {
+                token: std::env::var(config.provider.token_env()).ok(),
+                text: TextWidget::new(id, i, shared_config.clone())
+                    .with_icon(config.provider.icon()),
+                config,
+            })
+            .collect();
+
+        Ok(CiStatus {
+            id,
+            pipelines,
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("ci_status", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for CiStatus {
+    fn update(&mut self) -> Result<Option<Update>> {
+        for pipeline in &mut self.pipelines {
+            let status = pipeline.status()?;
+            let values = map!(
+                "{repository}" => pipeline.config.repository.clone(),
+                "{branch}" => pipeline.config.branch.clone().unwrap_or_default(),
+                "{status}" => status.name().to_string()
+            );
+            pipeline
+                .text
+                .set_text(self.format.render_static_str(&values)?);
+            pipeline.text.set_state(status.state());
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.pipelines
+            .iter()
+            .map(|pipeline| &pipeline.text as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{latest_status, CiProvider, PipelineStatus};
+
+    #[test]
+    fn test_github_status() {
+        let reply = |status, conclusion| {
+            json!({
+                "total_count": 1,
+                "workflow_runs": [{"status": status, "conclusion": conclusion}]
+            })
+        };
+        assert_eq!(
+            latest_status(CiProvider::Github, &reply("completed", json!("success"))),
+            PipelineStatus::Passed
+        );
+        assert_eq!(
+            latest_status(CiProvider::Github, &reply("completed", json!("failure"))),
+            PipelineStatus::Failed
+        );
+        assert_eq!(
+            latest_status(CiProvider::Github, &reply("in_progress", json!(null))),
+            PipelineStatus::Running
+        );
+        assert_eq!(
+            latest_status(CiProvider::Github, &reply("completed", json!("cancelled"))),
+            PipelineStatus::Unknown
+        );
+        assert_eq!(
+            latest_status(
+                CiProvider::Github,
+                &json!({"total_count": 0, "workflow_runs": []})
+            ),
+            PipelineStatus::Unknown
+        );
+    }
+
+    #[test]
+    fn test_gitlab_status() {
+        let reply = |status| json!([{"id": 1, "status": status}]);
+        assert_eq!(
+            latest_status(CiProvider::Gitlab, &reply("success")),
+            PipelineStatus::Passed
+        );
+        assert_eq!(
+            latest_status(CiProvider::Gitlab, &reply("failed")),
+            PipelineStatus::Failed
+        );
+        assert_eq!(
+            latest_status(CiProvider::Gitlab, &reply("pending")),
+            PipelineStatus::Running
+        );
+        assert_eq!(
+            latest_status(CiProvider::Gitlab, &reply("manual")),
+            PipelineStatus::Unknown
+        );
+        assert_eq!(
+            latest_status(CiProvider::Gitlab, &json!([])),
+            PipelineStatus::Unknown
+        );
+    }
+}
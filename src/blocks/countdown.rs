@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use crossbeam_channel::Sender;
+use dbus::arg::Variant;
+use dbus::blocking::Connection;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_opt_duration;
+use crate::errors::*;
+use crate::formatting::{DurationFormat, TimeUnit, Value};
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Where the countdown is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Timer {
+    /// Not started, or reset
+    Stopped,
+    Running {
+        end: Instant,
+    },
+    Paused {
+        remaining: Duration,
+    },
+    /// Reached zero, until it is reset
+    Finished,
+}
+
+pub struct Countdown {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    timer: Timer,
+    /// Length of the timer, or `None` when counting down to `until`
+    duration: Option<Duration>,
+    message: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CountdownConfig {
+    /// Length of the timer in seconds, started by a click
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub duration: Option<Duration>,
+
+    /// Local date and time to count down to, e.g. `2021-12-31 18:00`
+    pub until: Option<String>,
+
+    /// Format override
+    #[serde(default = "CountdownConfig::default_format")]
+    pub format: String,
+
+    /// Text of the notification sent when the countdown reaches zero
+    #[serde(default = "CountdownConfig::default_message")]
+    pub message: String,
+}
+
+impl CountdownConfig {
+    fn default_format() -> String {
+        "{remaining}".to_owned()
+    }
+
+    fn default_message() -> String {
+        "Time is up!".to_owned()
+    }
+}
+
+/// Parses a local date and time such as `2021-12-31 18:00`, or a time with its offset in the
+/// format of RFC 3339.
+fn parse_until(until: &str) -> Option<DateTime<Local>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(until) {
+        return Some(time.with_timezone(&Local));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(until, format).ok())
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+}
+
+/// Shows a desktop notification through the notification daemon.
+fn notify(message: &str) -> Result<()> {
+    let c = Connection::new_session()
+        .block_error("countdown", "Failed to establish D-Bus connection")?;
+    let p = c.with_proxy(
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        Duration::from_millis(2000),
+    );
+    let hints: HashMap<&str, Variant<u8>> = HashMap::new();
+    let _: (u32,) = p
+        .method_call(
+            "org.freedesktop.Notifications",
+            "Notify",
+            (
+                "i3status-rs",
+                0u32,
+                "",
+                "Countdown",
+                message,
+                Vec::<&str>::new(),
+                hints,
+                -1i32,
+            ),
+        )
+        .block_error("countdown", "Failed to send the notification")?;
+    Ok(())
+}
+
+impl Countdown {
+    fn remaining(&self) -> Duration {
+        match self.timer {
+            Timer::Stopped => self.duration.unwrap_or_default(),
+            Timer::Running { end } => end.saturating_duration_since(Instant::now()),
+            Timer::Paused { remaining } => remaining,
+            Timer::Finished => Duration::from_secs(0),
+        }
+    }
+
+    fn set_text(&mut self) -> Result<()> {
+        let remaining = self.remaining();
+        let values = map!(
+            "{remaining}" => Value::duration(remaining).duration_format(DurationFormat::Clock(vec![
+                TimeUnit::Hours,
+                TimeUnit::Minutes,
+                TimeUnit::Seconds,
+            ]))
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(match self.timer {
+            Timer::Finished => State::Critical,
+            Timer::Running { .. } => State::Info,
+            Timer::Stopped | Timer::Paused { .. } => State::Idle,
+        });
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Countdown {
+    type Config = CountdownConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{remaining}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let until = match block_config.until {
+            Some(ref until) => Some(
+                parse_until(until)
+                    .block_error("countdown", &format!("invalid date and time '{}'", until))?,
+            ),
+            None => None,
+        };
+        let timer = match (block_config.duration, until) {
+            (Some(_), None) => Timer::Stopped,
+            // The countdown to a date runs from the start
+            (None, Some(until)) => Timer::Running {
+                end: Instant::now()
+                    + (until - Local::now())
+                        .to_std()
+                        .unwrap_or_else(|_| Duration::from_secs(0)),
+            },
+            _ => {
+                return Err(BlockError(
+                    "countdown".to_owned(),
+                    "exactly one of 'duration' and 'until' must be set".to_owned(),
+                ))
+            }
+        };
+
+        Ok(Countdown {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("countdown"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("countdown", "Invalid format specified")?,
+            timer,
+            duration: block_config.duration,
+            message: block_config.message,
+        })
+    }
+}
+
+impl Block for Countdown {
+    fn update(&mut self) -> Result<Option<Update>> {
+        if let Timer::Running { end } = self.timer {
+            if Instant::now() >= end {
+                self.timer = Timer::Finished;
+                // Without a notification daemon, the critical state still tells it
+                let _ = notify(&self.message);
+            }
+        }
+        self.set_text()?;
+
+        Ok(Some(Duration::from_secs(1).into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        // A countdown to a date can't be paused, as the date doesn't move
+        let duration = match self.duration {
+            Some(duration) => duration,
+            None => return Ok(()),
+        };
+        self.timer = match (event.button, self.timer) {
+            (MouseButton::Right, _) | (_, Timer::Finished) => Timer::Stopped,
+            (MouseButton::Left, Timer::Stopped) => Timer::Running {
+                end: Instant::now() + duration,
+            },
+            (MouseButton::Left, Timer::Running { .. }) => Timer::Paused {
+                remaining: self.remaining(),
+            },
+            (MouseButton::Left, Timer::Paused { remaining }) => Timer::Running {
+                end: Instant::now() + remaining,
+            },
+            (_, timer) => timer,
+        };
+        self.set_text()
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, Local, TimeZone};
+
+    use super::parse_until;
+
+    #[test]
+    fn test_parse_until() {
+        assert_eq!(
+            parse_until("2021-12-31 18:00"),
+            Some(Local.ymd(2021, 12, 31).and_hms(18, 0, 0))
+        );
+        assert_eq!(
+            parse_until("2021-12-31 18:00:30"),
+            Some(Local.ymd(2021, 12, 31).and_hms(18, 0, 30))
+        );
+        assert_eq!(
+            parse_until("2021-12-31T18:00:00+02:00"),
+            Some(
+                FixedOffset::east(2 * 3600)
+                    .ymd(2021, 12, 31)
+                    .and_hms(18, 0, 0)
+                    .with_timezone(&Local)
+            )
+        );
+        assert_eq!(parse_until("tomorrow"), None);
+    }
+}
@@ -11,7 +11,7 @@ use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::scheduler::Task;
-use crate::util::{format_percent_bar, FormatTemplate};
+use crate::util::{format_percent_bar, severity, FormatTemplate};
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
@@ -97,6 +97,16 @@ impl CpuConfig {
 impl ConfigBlock for Cpu {
     type Config = CpuConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{barchart}",
+            "{frequency}",
+            "{utilizationbar}",
+            "{utilization}",
+        ],
+    )];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -112,13 +122,13 @@ impl ConfigBlock for Cpu {
         Ok(Cpu {
             id,
             update_interval: block_config.interval,
-            output: TextWidget::new(id, 0, shared_config).with_icon("cpu"),
+            output: TextWidget::new(id, 0, shared_config.clone()).with_icon("cpu"),
             prev_idles: [0; MAX_CPUS],
             prev_non_idles: [0; MAX_CPUS],
             minimum_info: block_config.info,
             minimum_warning: block_config.warning,
             minimum_critical: block_config.critical,
-            format: FormatTemplate::from_string(&format)
+            format: FormatTemplate::new(&format, &shared_config)
                 .block_error("cpu", "Invalid format specified for cpu")?,
             has_frequency: format.contains("{frequency}"),
             has_barchart: format.contains("{barchart}"),
@@ -128,6 +138,10 @@ impl ConfigBlock for Cpu {
 }
 
 impl Block for Cpu {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         let f = File::open("/proc/stat")
             .block_error("cpu", "Your system doesn't support /proc/stat")?;
@@ -227,6 +241,17 @@ impl Block for Cpu {
 
         self.output
             .set_text(self.format.render_static_str(&values)?);
+        match self.format.state_static_str(&values) {
+            Some(state) => {
+                self.output.set_state(state);
+                self.output.set_severity(None);
+            }
+            None => self.output.set_severity(Some(severity(
+                avg_utilization as f64,
+                self.minimum_info as f64,
+                self.minimum_critical as f64,
+            ))),
+        }
 
         Ok(Some(self.update_interval.into()))
     }
@@ -0,0 +1,242 @@
+//! A block for displaying the frequency and the scaling governor of the CPUs.
+//!
+//! Both are read from the policies in `/sys/devices/system/cpu/cpufreq/`, each of which covers
+//! the CPUs sharing a clock. Changing the governor needs root, so it goes through a helper such
+//! as `pkexec` unless a udev rule makes the files writable.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::{read_file, write_sysfs, FormatTemplate};
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const CPUFREQ_DIR: &str = "/sys/devices/system/cpu/cpufreq";
+
+/// Average of the frequencies in kHz, as read from `scaling_cur_freq`, in GHz.
+fn average_frequency(frequencies: &[u64]) -> f64 {
+    if frequencies.is_empty() {
+        return 0.;
+    }
+    frequencies.iter().sum::<u64>() as f64 / frequencies.len() as f64 / 1_000_000.
+}
+
+/// The governor after `current` in `available`, or before it when going `backward`, wrapping
+/// around. A governor which isn't available, e.g. because policies differ, goes to the first one.
+fn next_governor<'a>(available: &'a [String], current: &str, backward: bool) -> Option<&'a str> {
+    let len = available.len();
+    let next = match available.iter().position(|governor| governor == current) {
+        Some(position) if backward => (position + len - 1) % len,
+        Some(position) => (position + 1) % len,
+        None => 0,
+    };
+    available.get(next).map(String::as_str)
+}
+
+/// The directories of the cpufreq policies.
+fn policies() -> Result<Vec<PathBuf>> {
+    let mut policies: Vec<PathBuf> = Path::new(CPUFREQ_DIR)
+        .read_dir()
+        .block_error("cpufreq", &format!("failed to read {}", CPUFREQ_DIR))?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("policy"))
+        .map(|entry| entry.path())
+        .collect();
+    policies.sort();
+    Ok(policies)
+}
+
+pub struct Cpufreq {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    policies: Vec<PathBuf>,
+    helper: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CpufreqConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "CpufreqConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "CpufreqConfig::default_format")]
+    pub format: String,
+
+    /// Program running `tee` as root to write the governor when the block can't
+    #[serde(default = "CpufreqConfig::default_helper")]
+    pub helper: String,
+}
+
+impl CpufreqConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{frequency} {governor}".to_owned()
+    }
+
+    fn default_helper() -> String {
+        "pkexec".to_owned()
+    }
+}
+
+impl Cpufreq {
+    /// The governor of each policy.
+    fn governors(&self) -> Result<Vec<String>> {
+        self.policies
+            .iter()
+            .map(|policy| read_file("cpufreq", &policy.join("scaling_governor")))
+            .collect()
+    }
+
+    fn set_governor(&self, governor: &str) -> Result<()> {
+        let paths: Vec<PathBuf> = self
+            .policies
+            .iter()
+            .map(|policy| policy.join("scaling_governor"))
+            .collect();
+        write_sysfs("cpufreq", &paths, governor, &self.helper)
+    }
+}
+
+impl ConfigBlock for Cpufreq {
+    type Config = CpufreqConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{frequency}", "{governor}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let policies = policies()?;
+        if policies.is_empty() {
+            return Err(BlockError(
+                "cpufreq".to_string(),
+                "no cpufreq policy found, is CPU frequency scaling supported?".to_string(),
+            ));
+        }
+
+        Ok(Cpufreq {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("cpu"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("cpufreq", "Invalid format specified")?,
+            policies,
+            helper: block_config.helper,
+        })
+    }
+}
+
+impl Block for Cpufreq {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let frequencies = self
+            .policies
+            .iter()
+            .map(|policy| {
+                read_file("cpufreq", &policy.join("scaling_cur_freq"))?
+                    .trim()
+                    .parse::<u64>()
+                    .block_error("cpufreq", "failed to parse the frequency")
+            })
+            .collect::<Result<Vec<u64>>>()?;
+        let mut governors = self.governors()?;
+        governors.sort();
+        governors.dedup();
+
+        let values = map!(
+            "{frequency}" => format!("{:.1}GHz", average_frequency(&frequencies)),
+            "{governor}" => governors.join("/")
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        let backward = match event.button {
+            MouseButton::Left => false,
+            MouseButton::Right => true,
+            _ => return Ok(()),
+        };
+        let available: Vec<String> = read_file(
+            "cpufreq",
+            &self.policies[0].join("scaling_available_governors"),
+        )?
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+        let current = self.governors()?.remove(0);
+        if let Some(next) = next_governor(&available, &current, backward) {
+            self.set_governor(next)?;
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{average_frequency, next_governor};
+
+    #[test]
+    fn test_average_frequency() {
+        assert!((average_frequency(&[1_200_000, 2_800_000]) - 2.0).abs() < 1e-9);
+        assert!((average_frequency(&[3_400_000]) - 3.4).abs() < 1e-9);
+        assert!(average_frequency(&[]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_next_governor() {
+        let available: Vec<String> = vec!["performance".to_string(), "powersave".to_string()];
+        assert_eq!(
+            next_governor(&available, "performance", false),
+            Some("powersave")
+        );
+        assert_eq!(
+            next_governor(&available, "powersave", false),
+            Some("performance")
+        );
+        assert_eq!(
+            next_governor(&available, "performance", true),
+            Some("powersave")
+        );
+        assert_eq!(
+            next_governor(&available, "schedutil", false),
+            Some("performance")
+        );
+        assert_eq!(next_governor(&[], "performance", false), None);
+    }
+}
@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{Duration as ChronoDuration, Local};
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RatesProvider {
+    /// Reference rates of the European Central Bank
+    Ecb,
+    /// exchangerate.host
+    ExchangerateHost,
+}
+
+impl Default for RatesProvider {
+    fn default() -> Self {
+        RatesProvider::Ecb
+    }
+}
+
+/// Rates of currencies for one euro, as on each day, newest first.
+type DailyRates = Vec<HashMap<String, f64>>;
+
+impl RatesProvider {
+    /// The rates of the last two days on which `currencies` were quoted.
+    fn daily_rates(self, currencies: &[&str]) -> Result<DailyRates> {
+        let rates = match self {
+            RatesProvider::Ecb => {
+                let content =
+                    get("https://www.ecb.europa.eu/stats/eurofxref/eurofxref-hist-90d.xml")?;
+                parse_ecb(&String::from_utf8_lossy(&content))
+            }
+            RatesProvider::ExchangerateHost => {
+                // Rates are only published on working days, so a week surely has two of them
+                let today = Local::today().naive_local();
+                let url = format!(
+                    "https://api.exchangerate.host/timeseries?base=EUR&start_date={}&end_date={}&symbols={}",
+                    today - ChronoDuration::days(7),
+                    today,
+                    currencies.join(",")
+                );
+                let json: Value = serde_json::from_slice(&get(&url)?)
+                    .block_error("currency", "unexpected reply from exchangerate.host")?;
+                parse_exchangerate_host(&json)
+            }
+        };
+        if rates.is_empty() {
+            return Err(BlockError(
+                "currency".to_owned(),
+                "no exchange rates were found".to_owned(),
+            ));
+        }
+        Ok(rates)
+    }
+}
+
+fn get(url: &str) -> Result<Vec<u8>> {
+    let response = http::http_get_bytes(url, Some(Duration::from_secs(10)), vec![])?;
+    if !(200..300).contains(&response.code) {
+        return Err(BlockError(
+            "currency".to_owned(),
+            format!("{} replied with status {}", url, response.code),
+        ));
+    }
+    Ok(response.content)
+}
+
+/// The value of the attribute `name` in the tag `tag`, e.g. `<Cube currency='USD' rate='1.17'/>`.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=", name))? + name.len() + 2;
+    let quote = tag[start..].chars().next()?;
+    let value = &tag[start + 1..];
+    Some(&value[..value.find(quote)?])
+}
+
+/// The rates of the two newest days of the ECB's reference rates, which nest the rates of each
+/// day in `<Cube time='2021-03-31'>`.
+fn parse_ecb(xml: &str) -> DailyRates {
+    let mut days = DailyRates::new();
+    for tag in xml.split("<Cube").skip(1) {
+        let tag = &tag[..tag.find('>').unwrap_or_else(|| tag.len())];
+        if attribute(tag, "time").is_some() {
+            if days.len() == 2 {
+                break;
+            }
+            days.push(HashMap::new());
+        } else if let (Some(day), Some(currency), Some(rate)) = (
+            days.last_mut(),
+            attribute(tag, "currency"),
+            attribute(tag, "rate").and_then(|rate| rate.parse().ok()),
+        ) {
+            day.insert(currency.to_owned(), rate);
+        }
+    }
+    days
+}
+
+/// The rates of the two newest days of a time series of exchangerate.host.
+fn parse_exchangerate_host(json: &Value) -> DailyRates {
+    let mut days: Vec<(&String, HashMap<String, f64>)> = json["rates"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(date, day)| {
+            let day: HashMap<String, f64> = day
+                .as_object()?
+                .iter()
+                .filter_map(|(currency, rate)| Some((currency.clone(), rate.as_f64()?)))
+                .collect();
+            // Days without rates are left out, such as today before the rates are published
+            if day.is_empty() {
+                None
+            } else {
+                Some((date, day))
+            }
+        })
+        .collect();
+    // Dates are ISO 8601, which sort as text
+    days.sort_by(|a, b| b.0.cmp(a.0));
+    days.into_iter().take(2).map(|(_, day)| day).collect()
+}
+
+/// How many `to` one `from` is worth, from rates in euros.
+fn cross_rate(rates: &HashMap<String, f64>, from: &str, to: &str) -> Option<f64> {
+    let in_euros = |currency: &str| {
+        if currency == "EUR" {
+            Some(1.0)
+        } else {
+            rates.get(currency).copied()
+        }
+    };
+    Some(in_euros(to)? / in_euros(from)?)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CurrencyPair {
+    /// Currency which is exchanged, e.g. `EUR`
+    pub from: String,
+    /// Currency it is exchanged for, e.g. `USD`
+    pub to: String,
+}
+
+struct Pair {
+    config: CurrencyPair,
+    text: TextWidget,
+}
+
+pub struct Currency {
+    id: usize,
+    pairs: Vec<Pair>,
+    provider: RatesProvider,
+    update_interval: Duration,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CurrencyConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "CurrencyConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Service giving the rates
+    #[serde(default)]
+    pub provider: RatesProvider,
+
+    pub pairs: Vec<CurrencyPair>,
+
+    /// Format override
+    #[serde(default = "CurrencyConfig::default_format")]
+    pub format: String,
+}
+
+impl CurrencyConfig {
+    fn default_interval() -> Duration {
+        // The rates change once per working day
+        Duration::from_secs(6 * 60 * 60)
+    }
+
+    fn default_format() -> String {
+        "{from}→{to} {rate}".to_owned()
+    }
+}
+
+impl ConfigBlock for Currency {
+    type Config = CurrencyConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{change_pct}", "{from}", "{rate}", "{to}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let pairs = block_config
+            .pairs
+            .into_iter()
+            .enumerate()
+            .map(|(i, config)| Pair {
+                text: TextWidget::new(id, i, shared_config.clone()),
+                config: CurrencyPair {
+                    from: config.from.to_uppercase(),
+                    to: config.to.to_uppercase(),
+                },
+            })
+            .collect();
+
+        Ok(Currency {
+            id,
+            pairs,
+            provider: block_config.provider,
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("currency", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for Currency {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mut currencies: Vec<&str> = self
+            .pairs
+            .iter()
+            .flat_map(|pair| vec![pair.config.from.as_str(), pair.config.to.as_str()])
+            .filter(|&currency| currency != "EUR")
+            .collect();
+        currencies.sort_unstable();
+        currencies.dedup();
+        let rates = self.provider.daily_rates(&currencies)?;
+
+        for pair in &mut self.pairs {
+            let (from, to) = (pair.config.from.as_str(), pair.config.to.as_str());
+            let rate = cross_rate(&rates[0], from, to);
+            let previous = rates.get(1).and_then(|day| cross_rate(day, from, to));
+            let change_pct = match (rate, previous) {
+                (Some(rate), Some(previous)) => Some((rate / previous - 1.0) * 100.0),
+                _ => None,
+            };
+            let values = map!(
+                "{from}" => from.to_owned(),
+                "{to}" => to.to_owned(),
+                "{rate}" => rate.map_or_else(|| "-".to_owned(), |rate| format!("{:.4}", rate)),
+                "{change_pct}" => change_pct
+                    .map_or_else(|| "-".to_owned(), |change| format!("{:+.2}", change))
+            );
+            pair.text.set_text(self.format.render_static_str(&values)?);
+            pair.text.set_state(if rate.is_some() {
+                State::Idle
+            } else {
+                State::Warning
+            });
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.pairs
+            .iter()
+            .map(|pair| &pair.text as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{cross_rate, parse_ecb, parse_exchangerate_host};
+
+    #[test]
+    fn test_parse_ecb() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+	<gesmes:subject>Reference rates</gesmes:subject>
+	<Cube>
+		<Cube time='2021-03-31'>
+			<Cube currency='USD' rate='1.1725'/>
+			<Cube currency='GBP' rate='0.85208'/>
+		</Cube>
+		<Cube time='2021-03-30'>
+			<Cube currency='USD' rate='1.1741'/>
+			<Cube currency='GBP' rate='0.85335'/>
+		</Cube>
+		<Cube time='2021-03-29'>
+			<Cube currency='USD' rate='1.1784'/>
+		</Cube>
+	</Cube>
+</gesmes:Envelope>"#;
+        let days = parse_ecb(xml);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0]["USD"], 1.1725);
+        assert_eq!(days[0]["GBP"], 0.85208);
+        assert_eq!(days[1]["USD"], 1.1741);
+    }
+
+    #[test]
+    fn test_parse_exchangerate_host() {
+        let json = json!({
+            "success": true,
+            "timeseries": true,
+            "base": "EUR",
+            "rates": {
+                "2021-03-29": {"USD": 1.1784},
+                "2021-03-31": {"USD": 1.1725},
+                "2021-03-30": {"USD": 1.1741},
+                "2021-04-01": {}
+            }
+        });
+        let days = parse_exchangerate_host(&json);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0]["USD"], 1.1725);
+        assert_eq!(days[1]["USD"], 1.1741);
+    }
+
+    #[test]
+    fn test_cross_rate() {
+        let rates = [("USD".to_owned(), 1.25), ("GBP".to_owned(), 0.5)]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(cross_rate(&rates, "EUR", "USD"), Some(1.25));
+        assert_eq!(cross_rate(&rates, "USD", "EUR"), Some(0.8));
+        assert_eq!(cross_rate(&rates, "GBP", "USD"), Some(2.5));
+        assert_eq!(cross_rate(&rates, "EUR", "JPY"), None);
+    }
+}
@@ -13,7 +13,6 @@ use crate::de::deserialize_update;
 use crate::errors::*;
 use crate::input::I3BarEvent;
 use crate::scheduler::Task;
-use crate::signals::convert_to_valid_signal;
 use crate::subprocess::spawn_child_async;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
@@ -25,7 +24,6 @@ pub struct Custom {
     command: Option<String>,
     on_click: Option<String>,
     cycle: Option<Peekable<Cycle<vec::IntoIter<String>>>>,
-    signal: Option<i32>,
     tx_update_request: Sender<Task>,
     pub json: bool,
     hide_when_empty: bool,
@@ -49,9 +47,6 @@ pub struct CustomConfig {
     /// Commands to execute and change when the button is clicked
     pub cycle: Option<Vec<String>>,
 
-    /// Signal to update upon reception
-    pub signal: Option<i32>,
-
     /// Parse command output if it contains valid bar JSON
     #[serde(default = "CustomConfig::default_json")]
     pub json: bool,
@@ -92,7 +87,6 @@ impl ConfigBlock for Custom {
             command: None,
             on_click: None,
             cycle: None,
-            signal: None,
             tx_update_request: tx,
             json: block_config.json,
             hide_when_empty: block_config.hide_when_empty,
@@ -104,11 +98,6 @@ impl ConfigBlock for Custom {
             },
         };
 
-        if let Some(signal) = block_config.signal {
-            // If the signal is not in the valid range we return an error
-            custom.signal = Some(convert_to_valid_signal(signal)?);
-        };
-
         if block_config.cycle.is_some() && block_config.command.is_some() {
             return Err(BlockError(
                 "custom".to_string(),
@@ -189,18 +178,6 @@ impl Block for Custom {
         }
     }
 
-    fn signal(&mut self, signal: i32) -> Result<()> {
-        if let Some(sig) = self.signal {
-            if sig == signal {
-                self.tx_update_request.send(Task {
-                    id: self.id,
-                    update_time: Instant::now(),
-                })?;
-            }
-        }
-        Ok(())
-    }
-
     fn click(&mut self, _e: &I3BarEvent) -> Result<()> {
         let mut update = false;
 
@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -51,6 +52,9 @@ impl ConfigBlock for CustomDBus {
         }));
         let status = status_original.clone();
         let name = block_config.name;
+        // Set once the bar dropped the block, e.g. after reloading the config
+        let dropped = Arc::new(AtomicBool::new(false));
+        let dropped_copy = dropped.clone();
         thread::Builder::new()
             .name("custom_dbus".into())
             .spawn(move || {
@@ -90,11 +94,15 @@ impl ConfigBlock for CustomDBus {
                                         }
 
                                         // Tell block to update now.
-                                        send.send(Task {
-                                            id,
-                                            update_time: Instant::now(),
-                                        })
-                                        .unwrap();
+                                        if send
+                                            .send(Task {
+                                                id,
+                                                update_time: Instant::now(),
+                                            })
+                                            .is_err()
+                                        {
+                                            dropped_copy.store(true, Ordering::Relaxed);
+                                        }
 
                                         Ok(vec![m.msg.method_return()])
                                     })
@@ -112,8 +120,8 @@ impl ConfigBlock for CustomDBus {
                 // We add the tree to the connection so that incoming method calls will be handled.
                 tree.start_receive(&c);
 
-                // Serve clients forever.
-                loop {
+                // Serve clients until the bar drops the block.
+                while !dropped.load(Ordering::Relaxed) {
                     c.process(Duration::from_millis(1000)).unwrap();
                 }
             })
@@ -0,0 +1,242 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixDatagram, UnixListener};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use nix::sys::stat::Mode;
+use nix::unistd::mkfifo;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomSocketKind {
+    /// A Unix stream socket, e.g. `socat - UNIX-CONNECT:<path>`
+    Stream,
+    /// A Unix datagram socket, e.g. `socat - UNIX-SENDTO:<path>`
+    Datagram,
+    /// A named pipe, e.g. `echo text > <path>`
+    Fifo,
+}
+
+impl Default for CustomSocketKind {
+    fn default() -> Self {
+        Self::Stream
+    }
+}
+
+pub struct CustomSocket {
+    id: usize,
+    output: TextWidget,
+    json: bool,
+    hide_when_empty: bool,
+    is_empty: bool,
+    /// The last line written to the socket, if any yet
+    line: Arc<Mutex<Option<String>>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CustomSocketConfig {
+    /// Path of the socket or FIFO to create
+    pub path: PathBuf,
+
+    /// Kind of file to create at `path`
+    #[serde(default)]
+    pub kind: CustomSocketKind,
+
+    /// Parse lines as bar JSON, like the `custom` block does
+    #[serde(default)]
+    pub json: bool,
+
+    #[serde(default)]
+    pub hide_when_empty: bool,
+}
+
+#[derive(Deserialize)]
+struct Output {
+    #[serde(default)]
+    icon: String,
+    state: Option<State>,
+    text: String,
+}
+
+/// Removes a socket left behind by a previous instance, but nothing else in case of a typo.
+fn remove_stale_socket(path: &Path) {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        if metadata.file_type().is_socket() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Stores `line` as the content of the block and asks for an update. Returns false once the bar
+/// dropped the block, e.g. after reloading the config.
+fn push(line: &Arc<Mutex<Option<String>>>, text: &str, id: usize, send: &Sender<Task>) -> bool {
+    *line.lock().unwrap() = Some(text.trim().to_string());
+    send.send(Task {
+        id,
+        update_time: Instant::now(),
+    })
+    .is_ok()
+}
+
+impl ConfigBlock for CustomSocket {
+    type Config = CustomSocketConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let line = Arc::new(Mutex::new(None));
+        let line_copy = line.clone();
+        let path = block_config.path;
+
+        match block_config.kind {
+            CustomSocketKind::Stream => {
+                remove_stale_socket(&path);
+                let listener = UnixListener::bind(&path).block_error(
+                    "custom_socket",
+                    &format!("failed to bind socket {}", path.display()),
+                )?;
+                thread::Builder::new()
+                    .name("custom_socket".into())
+                    .spawn(move || {
+                        for stream in listener.incoming() {
+                            let stream = match stream {
+                                Ok(stream) => stream,
+                                Err(_) => continue,
+                            };
+                            for text in BufReader::new(stream).lines() {
+                                match text {
+                                    Ok(text) => {
+                                        if !push(&line_copy, &text, id, &send) {
+                                            return;
+                                        }
+                                    }
+                                    Err(_) => break,
+                                }
+                            }
+                        }
+                    })
+                    .unwrap();
+            }
+            CustomSocketKind::Datagram => {
+                remove_stale_socket(&path);
+                let socket = UnixDatagram::bind(&path).block_error(
+                    "custom_socket",
+                    &format!("failed to bind socket {}", path.display()),
+                )?;
+                thread::Builder::new()
+                    .name("custom_socket".into())
+                    .spawn(move || {
+                        let mut buf = vec![0; 65536];
+                        loop {
+                            let len = match socket.recv(&mut buf) {
+                                Ok(len) => len,
+                                Err(_) => continue,
+                            };
+                            let datagram = String::from_utf8_lossy(&buf[..len]);
+                            // A datagram holds a whole message, possibly with a trailing newline
+                            let text = datagram.trim_end().lines().last().unwrap_or_default();
+                            if !push(&line_copy, text, id, &send) {
+                                return;
+                            }
+                        }
+                    })
+                    .unwrap();
+            }
+            CustomSocketKind::Fifo => {
+                if !path.exists() {
+                    mkfifo(path.as_path(), Mode::S_IRUSR | Mode::S_IWUSR).block_error(
+                        "custom_socket",
+                        &format!("failed to create FIFO {}", path.display()),
+                    )?;
+                }
+                thread::Builder::new()
+                    .name("custom_socket".into())
+                    .spawn(move || loop {
+                        // Opening blocks until there is a writer, and reading ends once all
+                        // writers closed the FIFO, so reopen it for the next ones
+                        let fifo = match File::open(&path) {
+                            Ok(fifo) => fifo,
+                            Err(_) => return,
+                        };
+                        for text in BufReader::new(fifo).lines() {
+                            match text {
+                                Ok(text) => {
+                                    if !push(&line_copy, &text, id, &send) {
+                                        return;
+                                    }
+                                }
+                                Err(_) => break,
+                            }
+                        }
+                    })
+                    .unwrap();
+            }
+        }
+
+        Ok(CustomSocket {
+            id,
+            output: TextWidget::new(id, 0, shared_config),
+            json: block_config.json,
+            hide_when_empty: block_config.hide_when_empty,
+            is_empty: true,
+            line,
+        })
+    }
+}
+
+impl Block for CustomSocket {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let line = match *self.line.lock().unwrap() {
+            Some(ref line) => line.clone(),
+            None => return Ok(None),
+        };
+
+        if self.json {
+            let output: Output = serde_json::from_str(&line).map_err(|e| {
+                BlockError(
+                    "custom_socket".to_string(),
+                    format!("Error parsing JSON: {}", e),
+                )
+            })?;
+            self.output.set_icon(&output.icon);
+            self.output.set_state(output.state.unwrap_or(State::Idle));
+            self.is_empty = output.text.is_empty();
+            self.output.set_text(output.text);
+        } else {
+            self.is_empty = line.is_empty();
+            self.output.set_text(line);
+        }
+
+        // The socket thread asks for updates
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.is_empty && self.hide_when_empty {
+            vec![]
+        } else {
+            vec![&self.output]
+        }
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -61,8 +61,11 @@ pub struct DiskSpace {
     show_percentage: bool,
     show_bar: bool,
     alert_absolute: bool,
+    inodes_warning: Option<f64>,
+    inodes_alert: Option<f64>,
     format: FormatTemplate,
     icon: String,
+    shared_config: SharedConfig,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -84,7 +87,7 @@ pub struct DiskSpaceConfig {
 
     /// Format string for output
     /// placeholders: {percentage}, {bar}, {path}, {alias}, {available}, {free}, {total}, {used},
-    ///               {unit}
+    ///               {unit}, {inodes_used_percentage}, {inodes_free}, {inodes_total}
     #[serde(default = "DiskSpaceConfig::default_format")]
     pub format: String,
 
@@ -118,6 +121,14 @@ pub struct DiskSpaceConfig {
     /// use absolute (unit) values for disk space alerts
     #[serde(default = "DiskSpaceConfig::default_alert_absolute")]
     pub alert_absolute: bool,
+
+    /// Percentage of inodes used at which the state is set to warning
+    #[serde(default)]
+    pub inodes_warning: Option<f64>,
+
+    /// Percentage of inodes used at which the state is set to critical
+    #[serde(default)]
+    pub inodes_alert: Option<f64>,
 }
 
 impl DiskSpaceConfig {
@@ -201,6 +212,26 @@ impl DiskSpace {
 impl ConfigBlock for DiskSpace {
     type Config = DiskSpaceConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{alias}",
+            "{available}",
+            "{bar}",
+            "{free}",
+            "{icon}",
+            "{inodes_free}",
+            "{inodes_total}",
+            "{inodes_used_percentage}",
+            "{path}",
+            "{percentage}",
+            "{result}",
+            "{total}",
+            "{unit}",
+            "{used}",
+        ],
+    )];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -212,10 +243,10 @@ impl ConfigBlock for DiskSpace {
         Ok(DiskSpace {
             id,
             update_interval: block_config.interval,
-            disk_space: TextWidget::new(id, 0, shared_config),
+            disk_space: TextWidget::new(id, 0, shared_config.clone()),
             alias: block_config.alias,
             path: block_config.path,
-            format: FormatTemplate::from_string(&block_config.format)?,
+            format: FormatTemplate::new(&block_config.format, &shared_config)?,
             info_type: block_config.info_type,
             unit: block_config.unit,
             warning: block_config.warning,
@@ -223,12 +254,19 @@ impl ConfigBlock for DiskSpace {
             show_percentage: block_config.show_percentage,
             show_bar: block_config.show_bar,
             alert_absolute: block_config.alert_absolute,
+            inodes_warning: block_config.inodes_warning,
+            inodes_alert: block_config.inodes_alert,
             icon,
+            shared_config,
         })
     }
 }
 
 impl Block for DiskSpace {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         let statvfs = statvfs(Path::new(self.path.as_str()))
             .block_error("disk_space", "failed to retrieve statvfs")?;
@@ -239,6 +277,14 @@ impl Block for DiskSpace {
             * (statvfs.fragment_size() as u64);
         let available = (statvfs.blocks_available() as u64) * (statvfs.block_size() as u64);
         let free = (statvfs.blocks_free() as u64) * (statvfs.block_size() as u64);
+        let inodes_total = statvfs.files() as u64;
+        let inodes_free = statvfs.files_free() as u64;
+        // Some filesystems, e.g. btrfs, allocate inodes dynamically and report none
+        let inodes_used_percentage = if inodes_total > 0 {
+            (inodes_total - inodes_free) as f64 / inodes_total as f64 * 100.
+        } else {
+            0.
+        };
 
         let alert_type;
         match self.info_type {
@@ -256,7 +302,7 @@ impl Block for DiskSpace {
                 // Use format: "{used}/{total} {unit}" for previous format
                 result = used;
                 alert_type = AlertType::Above;
-                self.format = FormatTemplate::from_string("{used}/{total} {unit}")?;
+                self.format = FormatTemplate::new("{used}/{total} {unit}", &self.shared_config)?;
             }
             InfoType::Used => {
                 result = used;
@@ -266,9 +312,13 @@ impl Block for DiskSpace {
 
         let percentage = (result as f32) / (total as f32) * 100f32;
         if self.show_percentage {
-            self.format = FormatTemplate::from_string("{alias} {result} ({percentage}) {unit}")?;
+            self.format = FormatTemplate::new(
+                "{alias} {result} ({percentage}) {unit}",
+                &self.shared_config,
+            )?;
         } else if self.show_bar {
-            self.format = FormatTemplate::from_string("{alias} {result} {unit} {bar}")?;
+            self.format =
+                FormatTemplate::new("{alias} {result} {unit} {bar}", &self.shared_config)?;
         }
 
         let values = map!("{percentage}" => format!("{:.2}%", percentage),
@@ -280,6 +330,9 @@ impl Block for DiskSpace {
         "{used}" => format!("{:.2}", Unit::bytes_in_unit(self.unit, used)),
         "{available}" => format!("{:.2}", Unit::bytes_in_unit(self.unit, available)),
         "{free}" => format!("{:.2}", Unit::bytes_in_unit(self.unit, free)),
+        "{inodes_used_percentage}" => format!("{:.2}%", inodes_used_percentage),
+        "{inodes_free}" => inodes_free.to_string(),
+        "{inodes_total}" => inodes_total.to_string(),
         "{icon}" => self.icon.to_string(),
         "{result}" => format!("{:.2}", result)
         );
@@ -293,7 +346,20 @@ impl Block for DiskSpace {
             Unit::bytes_in_unit(self.unit, result)
         };
 
-        let state = self.compute_state(alert_val, self.warning, self.alert, alert_type);
+        let mut state = self.compute_state(alert_val, self.warning, self.alert, alert_type);
+        if self.inodes_warning.is_some() || self.inodes_alert.is_some() {
+            let inodes_state = self.compute_state(
+                inodes_used_percentage,
+                self.inodes_warning.unwrap_or(100.),
+                self.inodes_alert.unwrap_or(100.),
+                AlertType::Above,
+            );
+            state = match (state, inodes_state) {
+                (State::Critical, _) | (_, State::Critical) => State::Critical,
+                (State::Warning, _) | (_, State::Warning) => State::Warning,
+                (state, _) => state,
+            };
+        }
         self.disk_space.set_state(state);
 
         Ok(Some(self.update_interval.into()))
@@ -0,0 +1,277 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use nix::errno::Errno;
+use nix::sys::socket::{
+    bind, recv, socket, AddressFamily, MsgFlags, SockAddr, SockFlag, SockProtocol, SockType,
+};
+use serde_derive::Deserialize;
+use swayipc::Connection;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::subprocess::spawn_child_async;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const DRM_PATH: &str = "/sys/class/drm";
+
+/// The netlink multicast group of the uevents sent by the kernel
+const KERNEL_UEVENTS: u32 = 1;
+
+/// How long sway takes to set up an output after the kernel reports it
+const SWAY_SETTLE_TIME: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Copy, Clone, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplaysDriver {
+    Sway,
+    Drm,
+}
+
+impl Default for DisplaysDriver {
+    fn default() -> Self {
+        DisplaysDriver::Drm
+    }
+}
+
+struct Output {
+    name: String,
+    resolution: Option<String>,
+}
+
+impl DisplaysDriver {
+    fn outputs(self) -> Result<Vec<Output>> {
+        match self {
+            DisplaysDriver::Sway => Connection::new()
+                .block_error("displays", "failed to acquire connect to IPC")?
+                .get_outputs()
+                .block_error("displays", "failed to get outputs")?
+                .into_iter()
+                .filter(|o| o.active)
+                .map(|o| {
+                    Ok(Output {
+                        resolution: o.current_mode.map(|m| format!("{}x{}", m.width, m.height)),
+                        name: o.name,
+                    })
+                })
+                .collect(),
+            DisplaysDriver::Drm => {
+                let mut outputs = Vec::new();
+                let entries =
+                    read_dir(DRM_PATH).block_error("displays", "failed to read DRM devices")?;
+                for entry in entries {
+                    let path = entry
+                        .block_error("displays", "failed to read DRM device")?
+                        .path();
+                    let status = match read_to_string(path.join("status")) {
+                        Ok(status) => status,
+                        // Not a connector (e.g. the card itself)
+                        Err(_) => continue,
+                    };
+                    if status.trim() != "connected" {
+                        continue;
+                    }
+                    outputs.push(Output {
+                        name: drm_connector_name(&path),
+                        // The first listed mode is the preferred one
+                        resolution: read_to_string(path.join("modes"))
+                            .ok()
+                            .and_then(|modes| modes.lines().next().map(String::from)),
+                    });
+                }
+                outputs.sort_by(|a, b| a.name.cmp(&b.name));
+                Ok(outputs)
+            }
+        }
+    }
+
+    /// Updates the block whenever the kernel reports a change of a DRM device, such as an output
+    /// being plugged in. swayipc doesn't expose the output events of sway, but sway reacts to
+    /// the same changes.
+    fn monitor(self, id: usize, update_request: Sender<Task>) -> Result<()> {
+        let socket = socket(
+            AddressFamily::Netlink,
+            SockType::Datagram,
+            SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkKObjectUEvent,
+        )
+        .block_error("displays", "failed to open a uevent socket")?;
+        bind(socket, &SockAddr::new_netlink(0, KERNEL_UEVENTS))
+            .block_error("displays", "failed to listen to uevents")?;
+
+        thread::Builder::new()
+            .name("displays".into())
+            .spawn(move || {
+                let mut buf = [0; 8192];
+                loop {
+                    let drm_event = match recv(socket, &mut buf, MsgFlags::empty()) {
+                        Ok(len) => is_drm_event(&buf[..len]),
+                        Err(nix::Error::Sys(Errno::EINTR)) => continue,
+                        // Some events were dropped, any of them may have been a DRM one
+                        Err(nix::Error::Sys(Errno::ENOBUFS)) => true,
+                        Err(_) => return,
+                    };
+                    if !drm_event {
+                        continue;
+                    }
+
+                    let now = Instant::now();
+                    let mut updates = vec![now];
+                    if let DisplaysDriver::Sway = self {
+                        updates.push(now + SWAY_SETTLE_TIME);
+                    }
+                    for update_time in updates {
+                        if update_request.send(Task { id, update_time }).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })
+            .block_error("displays", "failed to start watching thread")?;
+        Ok(())
+    }
+}
+
+/// Whether a kernel uevent, made of NUL-separated `KEY=value` fields, is about a DRM device.
+fn is_drm_event(event: &[u8]) -> bool {
+    event
+        .split(|&b| b == 0)
+        .any(|field| field == b"SUBSYSTEM=drm")
+}
+
+/// Strips the `cardN-` prefix from a DRM connector name (e.g. `card0-HDMI-A-1`).
+fn drm_connector_name(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match name.find('-') {
+        Some(idx) if name.starts_with("card") => name[idx + 1..].to_string(),
+        _ => name,
+    }
+}
+
+pub struct Displays {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    driver: DisplaysDriver,
+    on_click: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DisplaysConfig {
+    /// Which backend to query for connected outputs
+    #[serde(default)]
+    pub driver: DisplaysDriver,
+
+    /// Format override
+    #[serde(default = "DisplaysConfig::default_format")]
+    pub format: String,
+}
+
+impl DisplaysConfig {
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+}
+
+impl ConfigBlock for Displays {
+    type Config = DisplaysConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{count}", "{outputs}", "{resolutions}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        block_config.driver.monitor(id, tx_update_request)?;
+
+        Ok(Displays {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("xrandr"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("displays", "Invalid format specified")?,
+            driver: block_config.driver,
+            on_click: None,
+        })
+    }
+
+    fn override_on_click(&mut self) -> Option<&mut Option<String>> {
+        Some(&mut self.on_click)
+    }
+}
+
+impl Block for Displays {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let outputs = self.driver.outputs()?;
+
+        let values = map!(
+            "{count}" => outputs.len().to_string(),
+            "{outputs}" => outputs
+                .iter()
+                .map(|o| o.name.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            "{resolutions}" => outputs
+                .iter()
+                .map(|o| o.resolution.as_deref().unwrap_or("?"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            if let Some(ref cmd) = self.on_click {
+                spawn_child_async("sh", &["-c", cmd])
+                    .block_error("displays", "could not spawn child")?;
+            }
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_drm_event;
+
+    #[test]
+    fn test_is_drm_event() {
+        assert!(is_drm_event(
+            b"change@/devices/pci0000:00/0000:00:02.0/drm/card0\0ACTION=change\0\
+              DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card0\0SUBSYSTEM=drm\0HOTPLUG=1\0"
+        ));
+        assert!(!is_drm_event(
+            b"add@/devices/virtual/net/wg0\0ACTION=add\0SUBSYSTEM=net\0INTERFACE=wg0\0"
+        ));
+    }
+}
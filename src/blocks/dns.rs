@@ -0,0 +1,297 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Times of the last lookups, in milliseconds, `None` for those which failed.
+#[derive(Clone, Copy, Debug)]
+struct Lookups {
+    system: Option<f64>,
+    /// Always `None` without a `server`
+    server: Option<f64>,
+}
+
+pub struct Dns {
+    id: usize,
+    text: TextWidget,
+    host: String,
+    server: Option<SocketAddr>,
+    format: FormatTemplate,
+    lookups: Arc<Mutex<Option<Lookups>>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DnsConfig {
+    /// Host name to resolve
+    #[serde(default = "DnsConfig::default_host")]
+    pub host: String,
+
+    /// Address of a DNS server to query as well, with an optional port, e.g. `9.9.9.9:53`
+    pub server: Option<String>,
+
+    /// Time between two lookups, in seconds
+    #[serde(
+        default = "DnsConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Time to wait for a reply of `server`, in seconds
+    #[serde(
+        default = "DnsConfig::default_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub timeout: Duration,
+
+    /// Format override
+    #[serde(default = "DnsConfig::default_format")]
+    pub format: String,
+}
+
+impl DnsConfig {
+    fn default_host() -> String {
+        "example.com".to_owned()
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(30)
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn default_format() -> String {
+        "{?{time}|failed}".to_owned()
+    }
+}
+
+/// Parses the address of a DNS server, which defaults to port 53.
+fn parse_server(server: &str) -> Option<SocketAddr> {
+    server.parse().ok().or_else(|| {
+        server
+            .parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, 53))
+    })
+}
+
+/// Builds a DNS query for the A records of `host`, asking for recursion.
+fn build_query(id: u16, host: &str) -> Vec<u8> {
+    let mut query = Vec::with_capacity(host.len() + 18);
+    query.extend_from_slice(&id.to_be_bytes());
+    // Flags with only "recursion desired", then one question and no other records
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in host.trim_end_matches('.').split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    // Type A, class IN
+    query.extend_from_slice(&[0, 1, 0, 1]);
+    query
+}
+
+/// Whether `reply` is a successful reply to the query `id`, whether or not it has records.
+fn is_success(id: u16, reply: &[u8]) -> bool {
+    reply.len() >= 12
+        && reply.starts_with(&id.to_be_bytes())
+        // A reply rather than a query
+        && reply[2] & 0x80 != 0
+        // No error code
+        && reply[3] & 0x0f == 0
+}
+
+/// Resolves `host` through the system resolver, as other programs do.
+fn lookup_system(host: &str) -> Option<f64> {
+    let start = Instant::now();
+    let mut addresses = (host, 0).to_socket_addrs().ok()?;
+    addresses.next()?;
+    Some(start.elapsed().as_secs_f64() * 1000.)
+}
+
+/// Resolves `host` by querying `server` directly.
+fn lookup_server(host: &str, server: SocketAddr, timeout: Duration) -> Option<f64> {
+    let local: IpAddr = if server.is_ipv4() {
+        Ipv4Addr::UNSPECIFIED.into()
+    } else {
+        Ipv6Addr::UNSPECIFIED.into()
+    };
+    let socket = UdpSocket::bind((local, 0)).ok()?;
+    socket.set_read_timeout(Some(timeout)).ok()?;
+    let id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos() as u16;
+
+    let start = Instant::now();
+    socket.send_to(&build_query(id, host), server).ok()?;
+    let mut reply = [0; 512];
+    loop {
+        let (len, from) = socket.recv_from(&mut reply).ok()?;
+        // Ignore stray packets, the read timeout still bounds the wait
+        if from == server && reply[..len].starts_with(&id.to_be_bytes()) {
+            let time = start.elapsed().as_secs_f64() * 1000.;
+            return Some(time).filter(|_| is_success(id, &reply[..len]));
+        }
+    }
+}
+
+impl ConfigBlock for Dns {
+    type Config = DnsConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{host}", "{server_time}", "{time}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let server = match block_config.server {
+            Some(ref server) => {
+                Some(parse_server(server).block_error("dns", "Invalid server specified")?)
+            }
+            None => None,
+        };
+        let lookups = Arc::new(Mutex::new(None));
+
+        // A lookup may take as long as the resolver's timeout, so don't block the other blocks
+        // meanwhile
+        let host = block_config.host.clone();
+        let timeout = block_config.timeout;
+        let interval = block_config.interval;
+        let lookups_copy = lookups.clone();
+        thread::Builder::new()
+            .name("dns".into())
+            .spawn(move || loop {
+                let system = lookup_system(&host);
+                let server = server.and_then(|server| lookup_server(&host, server, timeout));
+                *lookups_copy.lock().unwrap() = Some(Lookups { system, server });
+                if send
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .is_err()
+                {
+                    return;
+                }
+                thread::sleep(interval);
+            })
+            .unwrap();
+
+        Ok(Dns {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("dns"),
+            host: block_config.host,
+            server,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("dns", "Invalid format specified")?,
+            lookups,
+        })
+    }
+}
+
+impl Block for Dns {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let lookups = match *self.lookups.lock().unwrap() {
+            Some(lookups) => lookups,
+            None => return Ok(None),
+        };
+        let failed =
+            lookups.system.is_none() || (self.server.is_some() && lookups.server.is_none());
+
+        let time = |time: Option<f64>| {
+            Value::text(
+                time.map(|time| format!("{:.0}ms", time))
+                    .unwrap_or_default(),
+            )
+        };
+        let values = map!(
+            "{host}" => Value::text(self.host.clone()),
+            "{time}" => time(lookups.system),
+            "{server_time}" => time(lookups.server)
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(if failed {
+            State::Critical
+        } else {
+            self.format.state(&values).unwrap_or(State::Idle)
+        });
+
+        // The resolving thread asks for updates
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_query, is_success, parse_server};
+
+    #[test]
+    fn test_build_query() {
+        assert_eq!(
+            build_query(0x1234, "example.com."),
+            vec![
+                0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 7, b'e', b'x', b'a', b'm', b'p',
+                b'l', b'e', 3, b'c', b'o', b'm', 0, 0, 1, 0, 1
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_success() {
+        assert!(is_success(
+            0x1234,
+            &[0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]
+        ));
+        // NXDOMAIN
+        assert!(!is_success(
+            0x1234,
+            &[0x12, 0x34, 0x81, 0x83, 0, 1, 0, 0, 0, 0, 0, 0]
+        ));
+        assert!(!is_success(
+            0x4321,
+            &[0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]
+        ));
+        assert!(!is_success(0x1234, &[0x12, 0x34]));
+    }
+
+    #[test]
+    fn test_parse_server() {
+        let address = |address: &str| address.parse().ok();
+        assert_eq!(parse_server("9.9.9.9"), address("9.9.9.9:53"));
+        assert_eq!(parse_server("9.9.9.9:5353"), address("9.9.9.9:5353"));
+        assert_eq!(parse_server("::1"), address("[::1]:53"));
+        assert_eq!(parse_server("[::1]:5353"), address("[::1]:5353"));
+        assert_eq!(parse_server("dns.quad9.net"), None);
+    }
+}
@@ -1,23 +1,151 @@
+use std::collections::HashMap;
+use std::env;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crossbeam_channel::Sender;
 use serde_derive::Deserialize;
+use serde_json::{json, Value as JsonValue};
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::Value;
 use crate::http;
 use crate::scheduler::Task;
 use crate::util::FormatTemplate;
 use crate::widgets::text::TextWidget;
-use crate::widgets::I3BarWidget;
+use crate::widgets::{I3BarWidget, State};
+
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+/// Socket of the Podman service run as root
+const PODMAN_SOCKET: &str = "/run/podman/podman.sock";
 
 pub struct Docker {
     id: usize,
     text: TextWidget,
     format: FormatTemplate,
     update_interval: Duration,
+    /// Socket of the API, or `None` to find one at each update
+    socket_path: Option<PathBuf>,
+    /// Whether a widget is shown for each container matching the filters
+    containers: bool,
+    container_filters: String,
+    container_format: FormatTemplate,
+    container_texts: Vec<TextWidget>,
+    /// CPU time used by each container and by the system at the last update, by container ID
+    last_cpu: HashMap<String, (u64, u64)>,
+    shared_config: SharedConfig,
+}
+
+/// A container listed by the Docker API.
+#[derive(Deserialize, Debug, Clone)]
+struct Container {
+    #[serde(rename = "Id")]
+    id: String,
+
+    #[serde(rename = "Names")]
+    names: Vec<String>,
+
+    /// e.g. `running` or `paused`
+    #[serde(rename = "State")]
+    state: String,
+
+    /// e.g. `Up 2 hours (unhealthy)`
+    #[serde(rename = "Status")]
+    status: String,
+}
+
+/// Resource usage of a container, from the stats endpoint of the Docker API.
+#[derive(Debug, PartialEq)]
+struct Stats {
+    /// CPU time used by the container, in nanoseconds
+    cpu: u64,
+    /// CPU time used by the whole system, in nanoseconds
+    system_cpu: u64,
+    online_cpus: u64,
+    /// Memory used, leaving out the page cache
+    memory: f64,
+    memory_limit: f64,
+}
+
+impl Stats {
+    fn parse(json: &JsonValue) -> Option<Self> {
+        let memory_stats = &json["memory_stats"];
+        // Cgroups v1 count the page cache as `cache`, v2 as `inactive_file`
+        let cache = memory_stats["stats"]["inactive_file"]
+            .as_u64()
+            .or_else(|| memory_stats["stats"]["cache"].as_u64())
+            .unwrap_or(0);
+        Some(Stats {
+            cpu: json["cpu_stats"]["cpu_usage"]["total_usage"].as_u64()?,
+            system_cpu: json["cpu_stats"]["system_cpu_usage"].as_u64()?,
+            online_cpus: json["cpu_stats"]["online_cpus"].as_u64().unwrap_or(1),
+            memory: memory_stats["usage"].as_u64()?.saturating_sub(cache) as f64,
+            memory_limit: memory_stats["limit"].as_u64()? as f64,
+        })
+    }
+
+    /// Share of one CPU used since the previous `(cpu, system_cpu)` sample, so up to 100% per
+    /// core.
+    fn cpu_percentage(&self, previous: Option<(u64, u64)>) -> f64 {
+        match previous {
+            Some((cpu, system_cpu)) if self.system_cpu > system_cpu => {
+                self.cpu.saturating_sub(cpu) as f64 / (self.system_cpu - system_cpu) as f64
+                    * self.online_cpus as f64
+                    * 100.
+            }
+            _ => 0.,
+        }
+    }
+}
+
+/// The `filters` parameter listing the running and paused containers whose name contains `name`
+/// and which have the `label`, given as `key` or `key=value`.
+fn container_filters(name: Option<&str>, label: Option<&str>) -> String {
+    let mut filters = json!({ "status": ["running", "paused"] });
+    if let Some(name) = name {
+        filters["name"] = json!([name]);
+    }
+    if let Some(label) = label {
+        filters["label"] = json!([label]);
+    }
+    http::url_encode(&filters.to_string())
+}
+
+/// The sockets tried in turn: Docker's, then the one of Podman run by the user or by root.
+fn socket_candidates(runtime_dir: Option<&str>) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::from(DOCKER_SOCKET)];
+    if let Some(runtime_dir) = runtime_dir {
+        candidates.push(Path::new(runtime_dir).join("podman/podman.sock"));
+    }
+    candidates.push(PathBuf::from(PODMAN_SOCKET));
+    candidates
+}
+
+/// The first socket which a service listens to, as the socket of Docker may be left behind when
+/// it is stopped.
+fn find_socket() -> Option<PathBuf> {
+    socket_candidates(env::var("XDG_RUNTIME_DIR").ok().as_deref())
+        .into_iter()
+        .find(|path| UnixStream::connect(path).is_ok())
+}
+
+fn get_json(socket_path: &Path, path: &str) -> Result<JsonValue> {
+    let output =
+        http::http_get_socket_json(socket_path.to_path_buf(), &format!("http:/api/{}", path))?;
+    if !(200..300).contains(&output.code) {
+        return Err(BlockError(
+            "docker".to_string(),
+            format!(
+                "the Docker API replied to {} with status {}",
+                path, output.code
+            ),
+        ));
+    }
+    Ok(output.content)
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -51,6 +179,23 @@ pub struct DockerConfig {
     /// Format override
     #[serde(default = "DockerConfig::default_format")]
     pub format: String,
+
+    /// Socket of the Docker API, or of Podman's compatible API. Found automatically when unset
+    pub socket_path: Option<PathBuf>,
+
+    /// Show a widget for each running or paused container matching the filters
+    #[serde(default)]
+    pub containers: bool,
+
+    /// Only show the containers whose name contains this text
+    pub container_name: Option<String>,
+
+    /// Only show the containers with this label, as `key` or `key=value`
+    pub container_label: Option<String>,
+
+    /// Format of the widget of each container
+    #[serde(default = "DockerConfig::default_container_format")]
+    pub container_format: String,
 }
 
 impl DockerConfig {
@@ -61,34 +206,132 @@ impl DockerConfig {
     fn default_format() -> String {
         "{running}%".to_owned()
     }
+
+    fn default_container_format() -> String {
+        "{name} {cpu} {memory}".to_owned()
+    }
 }
 
 impl ConfigBlock for Docker {
     type Config = DockerConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        (
+            "format",
+            &[
+                "{total}",
+                "{running}",
+                "{paused}",
+                "{stopped}",
+                "{unhealthy}",
+                "{images}",
+            ],
+        ),
+        (
+            "container_format",
+            &["{name}", "{cpu}", "{memory}", "{memory_percentage}"],
+        ),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _: Sender<Task>,
     ) -> Result<Self> {
-        let text = TextWidget::new(id, 0, shared_config)
+        let text = TextWidget::new(id, 0, shared_config.clone())
             .with_text("N/A")
             .with_icon("docker");
         Ok(Docker {
             id,
             text,
-            format: FormatTemplate::from_string(&block_config.format)
+            format: FormatTemplate::new(&block_config.format, &shared_config)
                 .block_error("docker", "Invalid format specified")?,
             update_interval: block_config.interval,
+            socket_path: block_config.socket_path,
+            containers: block_config.containers,
+            container_filters: container_filters(
+                block_config.container_name.as_deref(),
+                block_config.container_label.as_deref(),
+            ),
+            container_format: FormatTemplate::new(&block_config.container_format, &shared_config)
+                .block_error("docker", "Invalid container_format specified")?,
+            container_texts: Vec::new(),
+            last_cpu: HashMap::new(),
+            shared_config,
         })
     }
 }
 
+impl Docker {
+    /// Shows a widget with the resource usage of each container matching the filters.
+    fn update_containers(&mut self, socket_path: &Path) -> Result<()> {
+        let containers: Vec<Container> = serde_json::from_value(get_json(
+            socket_path,
+            &format!("containers/json?filters={}", self.container_filters),
+        )?)
+        .block_error("docker", "Failed to parse JSON response.")?;
+
+        let mut texts = Vec::new();
+        let mut last_cpu = HashMap::new();
+        for (i, container) in containers.iter().enumerate() {
+            // The one-shot stats don't wait for a second sample, which is taken from the last update
+            let stats = Stats::parse(&get_json(
+                socket_path,
+                &format!(
+                    "containers/{}/stats?stream=false&one-shot=true",
+                    container.id
+                ),
+            )?)
+            .block_error("docker", "Failed to parse the stats of a container.")?;
+            let cpu = stats.cpu_percentage(self.last_cpu.get(&container.id).copied());
+            last_cpu.insert(container.id.clone(), (stats.cpu, stats.system_cpu));
+
+            let name = container
+                .names
+                .first()
+                .map_or(container.id.as_str(), |name| name.trim_start_matches('/'));
+            let values = map!(
+                "{name}" => Value::text(name.to_string()),
+                "{cpu}" => Value::text(format!("{:.0}%", cpu)),
+                "{memory}" => Value::bytes(stats.memory),
+                "{memory_percentage}" => Value::text(if stats.memory_limit > 0. {
+                    format!("{:.0}%", stats.memory / stats.memory_limit * 100.)
+                } else {
+                    String::new()
+                })
+            );
+            let mut text = TextWidget::new(self.id, i + 1, self.shared_config.clone());
+            text.set_text(self.container_format.render(&values)?);
+            text.set_state(if container.status.contains("(unhealthy)") {
+                State::Critical
+            } else if container.state == "paused" {
+                State::Warning
+            } else {
+                State::Idle
+            });
+            texts.push(text);
+        }
+        self.container_texts = texts;
+        self.last_cpu = last_cpu;
+        Ok(())
+    }
+}
+
 impl Block for Docker {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
-        let socket_path = std::path::PathBuf::from("/var/run/docker.sock");
-        let output = http::http_get_socket_json(socket_path, "http:/api/info");
+        let socket_path = match self.socket_path.clone().or_else(find_socket) {
+            Some(socket_path) => socket_path,
+            None => {
+                self.text.set_text("N/A".to_string());
+                return Ok(Some(self.update_interval.into()));
+            }
+        };
+        let output = http::http_get_socket_json(socket_path.clone(), "http:/api/info");
 
         if output.is_err() {
             self.text.set_text("N/A".to_string());
@@ -98,24 +341,116 @@ impl Block for Docker {
         let status: Status = serde_json::from_value(output.unwrap().content)
             .block_error("docker", "Failed to parse JSON response.")?;
 
+        // Only the health filter of the container list tells the unhealthy containers
+        let unhealthy = if self.format.has_placeholder("{unhealthy}") {
+            get_json(
+                &socket_path,
+                &format!(
+                    "containers/json?filters={}",
+                    http::url_encode(r#"{"health":["unhealthy"]}"#)
+                ),
+            )?
+            .as_array()
+            .map_or(0, |containers| containers.len())
+        } else {
+            0
+        };
+
         let values = map!(
             "{total}" => format!("{}", status.total),
             "{running}" => format!("{}", status.running),
             "{paused}" => format!("{}", status.paused),
             "{stopped}" => format!("{}", status.stopped),
+            "{unhealthy}" => format!("{}", unhealthy),
             "{images}" => format!("{}", status.images)
         );
 
         self.text.set_text(self.format.render_static_str(&values)?);
 
+        if self.containers {
+            self.update_containers(&socket_path)?;
+        }
+
         Ok(Some(self.update_interval.into()))
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        vec![&self.text]
+        let mut widgets: Vec<&dyn I3BarWidget> = vec![&self.text];
+        widgets.extend(
+            self.container_texts
+                .iter()
+                .map(|text| text as &dyn I3BarWidget),
+        );
+        widgets
     }
 
     fn id(&self) -> usize {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use std::path::PathBuf;
+
+    use super::{container_filters, socket_candidates, Stats};
+
+    #[test]
+    fn test_stats() {
+        let json = json!({
+            "cpu_stats": {
+                "cpu_usage": {"total_usage": 2_000_000_000u64},
+                "system_cpu_usage": 104_000_000_000u64,
+                "online_cpus": 4
+            },
+            "memory_stats": {
+                "usage": 300_000_000u64,
+                "limit": 1_000_000_000u64,
+                "stats": {"inactive_file": 100_000_000u64}
+            }
+        });
+        let stats = Stats::parse(&json).unwrap();
+        assert_eq!(
+            stats,
+            Stats {
+                cpu: 2_000_000_000,
+                system_cpu: 104_000_000_000,
+                online_cpus: 4,
+                memory: 200_000_000.,
+                memory_limit: 1_000_000_000.,
+            }
+        );
+        // A quarter of the system's time is a whole CPU out of 4
+        assert_eq!(
+            stats.cpu_percentage(Some((1_000_000_000, 100_000_000_000))),
+            100.
+        );
+        assert_eq!(stats.cpu_percentage(None), 0.);
+        assert_eq!(Stats::parse(&json!({})), None);
+    }
+
+    #[test]
+    fn test_socket_candidates() {
+        assert_eq!(
+            socket_candidates(Some("/run/user/1000")),
+            vec![
+                PathBuf::from("/var/run/docker.sock"),
+                PathBuf::from("/run/user/1000/podman/podman.sock"),
+                PathBuf::from("/run/podman/podman.sock"),
+            ]
+        );
+        assert_eq!(socket_candidates(None).len(), 2);
+    }
+
+    #[test]
+    fn test_container_filters() {
+        assert_eq!(
+            container_filters(None, None),
+            "%7B%22status%22%3A%5B%22running%22%2C%22paused%22%5D%7D"
+        );
+        assert!(container_filters(Some("web"), Some("env=prod"))
+            .contains("%22label%22%3A%5B%22env%3Dprod%22%5D"));
+    }
+}
@@ -0,0 +1,213 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use dbus::ffidisp::{BusType, Connection};
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// NetworkManager connecting or disconnecting
+const NM_STATE_CHANGED_RULE: &str = "type='signal',\
+    path='/org/freedesktop/NetworkManager',\
+    interface='org.freedesktop.NetworkManager',\
+    member='StateChanged'";
+
+/// NetworkManager switching to another primary connection, e.g. a VPN
+const NM_PROPERTIES_CHANGED_RULE: &str = "type='signal',\
+    path='/org/freedesktop/NetworkManager',\
+    interface='org.freedesktop.DBus.Properties',\
+    member='PropertiesChanged',\
+    arg0='org.freedesktop.NetworkManager'";
+
+pub struct ExternalIp {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    url: String,
+    ipv6_url: Option<String>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalIpConfig {
+    /// Update interval in seconds, besides updating when the network changes
+    #[serde(
+        default = "ExternalIpConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Endpoint replying a JSON object with the address in `ip`, and optionally `country` and
+    /// `city`
+    #[serde(default = "ExternalIpConfig::default_url")]
+    pub url: String,
+
+    /// Endpoint only reachable over IPv6, replying a JSON object with the address in `ip`
+    pub ipv6_url: Option<String>,
+
+    /// Format override
+    #[serde(default = "ExternalIpConfig::default_format")]
+    pub format: String,
+}
+
+impl ExternalIpConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(300)
+    }
+
+    fn default_url() -> String {
+        "https://ifconfig.co/json".to_owned()
+    }
+
+    fn default_format() -> String {
+        "{?{ip}|offline}".to_owned()
+    }
+}
+
+/// A string field of the reply, trying several names as services don't agree on them.
+fn field(reply: &JsonValue, names: &[&str]) -> String {
+    names
+        .iter()
+        .find_map(|name| reply[name].as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn fetch(url: &str) -> Result<JsonValue> {
+    let response = http::http_get_json(url, Some(Duration::from_secs(5)), vec![])?;
+    if response.code != 200 {
+        return Err(BlockError(
+            "external_ip".to_string(),
+            format!("{} replied with status {}", url, response.code),
+        ));
+    }
+    Ok(response.content)
+}
+
+impl ConfigBlock for ExternalIp {
+    type Config = ExternalIpConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &["{city}", "{country_code}", "{country}", "{ipv6}", "{ip}"],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        thread::Builder::new()
+            .name("external_ip".into())
+            .spawn(move || {
+                // Without NetworkManager, the block just updates on its interval
+                let c = match Connection::get_private(BusType::System) {
+                    Ok(c) => c,
+                    Err(_) => return,
+                };
+                if c.add_match(NM_STATE_CHANGED_RULE).is_err()
+                    || c.add_match(NM_PROPERTIES_CHANGED_RULE).is_err()
+                {
+                    return;
+                }
+
+                loop {
+                    if c.incoming(300_000).next().is_some() {
+                        // Connecting sends a burst of signals, and routes take a moment to
+                        // settle, so wait for a quiet second before asking for the address
+                        while c.incoming(1000).next().is_some() {}
+                        if send
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(ExternalIp {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("net_wired"),
+            update_interval: block_config.interval,
+            url: block_config.url,
+            ipv6_url: block_config.ipv6_url,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("external_ip", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for ExternalIp {
+    fn update(&mut self) -> Result<Option<Update>> {
+        // Being offline is shown rather than reported as an error
+        let reply = fetch(&self.url).ok();
+        let ipv6 = match self.ipv6_url {
+            Some(ref url) => fetch(url).ok(),
+            None => None,
+        };
+        let online = reply.is_some() || ipv6.is_some();
+        // Fields of `null` are `null` too, so they are shown as empty
+        let reply = reply.unwrap_or_default();
+        let ipv6 = ipv6.unwrap_or_default();
+
+        let values = map!(
+            "{ip}" => field(&reply, &["ip"]),
+            "{country}" => field(&reply, &["country", "country_name"]),
+            "{country_code}" => field(&reply, &["country_iso", "country_code"]),
+            "{city}" => field(&reply, &["city"]),
+            "{ipv6}" => field(&ipv6, &["ip"])
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text
+            .set_state(if online { State::Idle } else { State::Warning });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::field;
+
+    #[test]
+    fn test_field() {
+        let reply = serde_json::json!({
+            "ip": "203.0.113.7",
+            "country_name": "Netherlands",
+            "country_code": "NL",
+            "city": "Amsterdam",
+        });
+        assert_eq!(field(&reply, &["ip"]), "203.0.113.7");
+        assert_eq!(field(&reply, &["country", "country_name"]), "Netherlands");
+        assert_eq!(field(&reply, &["region"]), "");
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use regex::Regex;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+const HWMON_PATH: &str = "/sys/class/hwmon";
+
+pub struct Fan {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    chip: Option<Regex>,
+    label: Option<Regex>,
+    stopped: u32,
+    max: Option<u32>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FanConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "FanConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Regex matching the name of the hwmon chips to read the fans of
+    pub chip: Option<String>,
+
+    /// Regex matching the labels of the fans, or names like `fan1` if they have none
+    pub label: Option<String>,
+
+    /// Speed in RPM at or below which a fan is considered stopped
+    #[serde(default)]
+    pub stopped: u32,
+
+    /// Speed in RPM at or above which a fan is considered at its maximum, instead of the maximum
+    /// reported by the chip
+    pub max: Option<u32>,
+
+    /// Format override
+    #[serde(default = "FanConfig::default_format")]
+    pub format: String,
+}
+
+impl FanConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{rpm}".to_owned()
+    }
+}
+
+/// A fan, as exposed by `/sys/class/hwmon/*/fan*_input`.
+struct FanInput {
+    label: String,
+    rpm: u32,
+    max: Option<u32>,
+}
+
+fn read_number(path: &Path) -> Option<u32> {
+    read_to_string(path).ok()?.trim().parse().ok()
+}
+
+impl Fan {
+    /// Reads the fans of the chips and with the labels matching the config.
+    fn read_fans(&self) -> Result<Vec<FanInput>> {
+        let mut fans = Vec::new();
+        let chips = read_dir(HWMON_PATH).block_error("fan", "failed to read hwmon devices")?;
+        for chip in chips.flatten() {
+            let chip = chip.path();
+            let name = read_to_string(chip.join("name")).unwrap_or_default();
+            if let Some(ref regex) = self.chip {
+                if !regex.is_match(name.trim()) {
+                    continue;
+                }
+            }
+
+            let mut inputs: Vec<String> = read_dir(&chip)
+                .block_error("fan", "failed to read hwmon device")?
+                .flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter_map(|file| {
+                    file.strip_suffix("_input")
+                        .filter(|input| input.starts_with("fan"))
+                        .map(String::from)
+                })
+                .collect();
+            inputs.sort();
+
+            for input in inputs {
+                let label = read_to_string(chip.join(format!("{}_label", input)))
+                    .map(|label| label.trim().to_string())
+                    .unwrap_or_else(|_| input.clone());
+                if let Some(ref regex) = self.label {
+                    if !regex.is_match(&label) {
+                        continue;
+                    }
+                }
+                if let Some(rpm) = read_number(&chip.join(format!("{}_input", input))) {
+                    fans.push(FanInput {
+                        label,
+                        rpm,
+                        max: read_number(&chip.join(format!("{}_max", input))),
+                    });
+                }
+            }
+        }
+        Ok(fans)
+    }
+}
+
+impl ConfigBlock for Fan {
+    type Config = FanConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{label}", "{rpm}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let regex = |pattern: Option<String>, option: &str| match pattern {
+            Some(pattern) => Regex::new(&pattern)
+                .map(Some)
+                .block_error("fan", &format!("invalid regex for `{}`", option)),
+            None => Ok(None),
+        };
+
+        Ok(Fan {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            update_interval: block_config.interval,
+            chip: regex(block_config.chip, "chip")?,
+            label: regex(block_config.label, "label")?,
+            stopped: block_config.stopped,
+            max: block_config.max,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("fan", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for Fan {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let fans = self.read_fans()?;
+        if fans.is_empty() {
+            return Err(BlockError("fan".to_string(), "no fan found".to_string()));
+        }
+
+        let mut texts = Vec::new();
+        let mut state = State::Idle;
+        for fan in fans {
+            let values = map!(
+                "{label}" => fan.label,
+                "{rpm}" => fan.rpm.to_string()
+            );
+            texts.push(self.format.render_static_str(&values)?);
+
+            if fan.rpm <= self.stopped {
+                state = State::Critical;
+            } else if matches!(self.max.or(fan.max), Some(max) if fan.rpm >= max) {
+                if let State::Idle = state {
+                    state = State::Warning;
+                }
+            }
+        }
+        self.text.set_text(texts.join(" "));
+        self.text.set_state(state);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -0,0 +1,546 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, TimeZone, Utc};
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::{deserialize_duration, deserialize_opt_duration};
+use crate::errors::*;
+use crate::http;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::subprocess::spawn_child_async;
+use crate::util::{xdg_data_home, FormatTemplate};
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// An item of an RSS feed or an entry of an Atom feed.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Entry {
+    pub(crate) title: String,
+    pub(crate) link: String,
+    pub(crate) date: Option<DateTime<Utc>>,
+}
+
+/// The elements named `name` in `xml`, as their attributes and their content, which is `None`
+/// for empty elements such as `<link href="..."/>`.
+fn elements<'a>(xml: &'a str, name: &str) -> Vec<(&'a str, Option<&'a str>)> {
+    let open = format!("<{}", name);
+    let close = format!("</{}>", name);
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        // Skip elements whose name only starts with `name`, e.g. `<itemCount>`
+        if !rest.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let attributes = &rest[..end];
+        rest = &rest[end + 1..];
+        if let Some(attributes) = attributes.strip_suffix('/') {
+            found.push((attributes, None));
+            continue;
+        }
+        match rest.find(&close) {
+            Some(end) => {
+                found.push((attributes, Some(&rest[..end])));
+                rest = &rest[end + close.len()..];
+            }
+            None => break,
+        }
+    }
+    found
+}
+
+/// The value of the attribute `name` among `attributes`.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(start) = rest.find(name) {
+        let preceded_by_space = rest[..start].ends_with(char::is_whitespace);
+        rest = &rest[start + name.len()..];
+        let value = match rest.trim_start().strip_prefix('=') {
+            Some(value) if preceded_by_space => value.trim_start(),
+            _ => continue,
+        };
+        let quote = value.chars().next().filter(|&c| c == '"' || c == '\'')?;
+        let value = &value[1..];
+        return value.find(quote).map(|end| unescape(&value[..end]));
+    }
+    None
+}
+
+/// Replaces the entities of XML text, and of HTML for the few ones which are common in feeds.
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ => match entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+            }
+            .and_then(std::char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                unescaped.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// The text of an element's content, which may be a CDATA section, on a single line.
+fn text(content: &str) -> String {
+    let content = content.trim();
+    let text = match content
+        .strip_prefix("<![CDATA[")
+        .and_then(|cdata| cdata.strip_suffix("]]>"))
+    {
+        Some(cdata) => cdata.to_string(),
+        None => unescape(content),
+    };
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The text of the first child element of `parent` named `name`.
+fn child_text(parent: &str, name: &str) -> Option<String> {
+    elements(parent, name)
+        .into_iter()
+        .find_map(|(_, content)| content)
+        .map(text)
+}
+
+/// The entries of an RSS or Atom feed, as far as they can be understood.
+pub(crate) fn parse_feed(xml: &str) -> Vec<Entry> {
+    let items = elements(xml, "item");
+    let (items, is_atom) = if items.is_empty() {
+        (elements(xml, "entry"), true)
+    } else {
+        (items, false)
+    };
+    items
+        .into_iter()
+        .filter_map(|(_, content)| content)
+        .map(|item| {
+            let link = if is_atom {
+                // The link to the web page has no relation, or the `alternate` one
+                elements(item, "link")
+                    .into_iter()
+                    .find(|(attributes, _)| {
+                        attribute(attributes, "rel").map_or(true, |rel| rel == "alternate")
+                    })
+                    .and_then(|(attributes, _)| attribute(attributes, "href"))
+            } else {
+                child_text(item, "link").or_else(|| child_text(item, "guid"))
+            };
+            let date = if is_atom {
+                child_text(item, "published")
+                    .or_else(|| child_text(item, "updated"))
+                    .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+            } else {
+                child_text(item, "pubDate")
+                    .and_then(|date| DateTime::parse_from_rfc2822(&date).ok())
+                    .or_else(|| {
+                        child_text(item, "dc:date")
+                            .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+                    })
+            };
+            Entry {
+                title: child_text(item, "title").unwrap_or_default(),
+                link: link.unwrap_or_default(),
+                date: date.map(|date| date.with_timezone(&Utc)),
+            }
+        })
+        .collect()
+}
+
+/// The file keeping when the feeds were last read, so that this survives restarts.
+fn last_read_path() -> PathBuf {
+    xdg_data_home().join("i3status-rust/feeds")
+}
+
+/// The times the feeds were last read from lines such as `1617184800 https://example.org/feed`.
+fn parse_last_read(content: &str) -> HashMap<String, DateTime<Utc>> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(2, ' ');
+            let time = fields.next()?.parse().ok()?;
+            let url = fields.next()?.trim();
+            Some((url.to_string(), Utc.timestamp(time, 0)))
+        })
+        .collect()
+}
+
+/// Records that the feeds at `urls` are read at `time`, keeping the times of other feeds, which
+/// may belong to other blocks.
+fn save_last_read(urls: &[String], time: DateTime<Utc>) -> Result<()> {
+    let path = last_read_path();
+    let mut last_read = parse_last_read(&fs::read_to_string(&path).unwrap_or_default());
+    for url in urls {
+        last_read.insert(url.clone(), time);
+    }
+    let content: String = last_read
+        .iter()
+        .map(|(url, time)| format!("{} {}\n", time.timestamp(), url))
+        .collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).block_error("feeds", "failed to create the data directory")?;
+    }
+    fs::write(&path, content).block_error("feeds", "failed to save the read feeds")
+}
+
+pub struct Feeds {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    update_interval: Duration,
+    cycle_interval: Option<Duration>,
+    hide_when_empty: bool,
+    urls: Vec<String>,
+    /// Entries of each feed, in the order of `urls`
+    entries: Vec<Vec<Entry>>,
+    /// When each feed was last read, by URL
+    last_read: HashMap<String, DateTime<Utc>>,
+    last_refresh: Option<Instant>,
+    start: Instant,
+    unread: Vec<Entry>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FeedsConfig {
+    /// URLs of the RSS or Atom feeds
+    pub feeds: Vec<String>,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "FeedsConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Time each unread headline is shown for, in seconds
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub cycle_interval: Option<Duration>,
+
+    /// Format override
+    #[serde(default = "FeedsConfig::default_format")]
+    pub format: String,
+
+    #[serde(default)]
+    pub hide_when_empty: bool,
+}
+
+impl FeedsConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_format() -> String {
+        "{unread}".to_owned()
+    }
+}
+
+impl Feeds {
+    /// Downloads all feeds again.
+    fn refresh(&mut self) -> Result<()> {
+        let mut entries = Vec::new();
+        for url in &self.urls {
+            let response = http::http_get_bytes(url, Some(Duration::from_secs(10)), vec![])?;
+            if !(200..300).contains(&response.code) {
+                return Err(BlockError(
+                    "feeds".to_string(),
+                    format!("{} replied with status {}", url, response.code),
+                ));
+            }
+            entries.push(parse_feed(&String::from_utf8_lossy(&response.content)));
+        }
+        self.entries = entries;
+        self.last_refresh = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Finds the entries published since their feed was last read, newest first.
+    fn find_unread(&mut self) {
+        let mut unread: Vec<Entry> = self
+            .urls
+            .iter()
+            .zip(&self.entries)
+            .flat_map(|(url, entries)| {
+                let last_read = self.last_read.get(url).copied();
+                entries
+                    .iter()
+                    .filter(move |entry| match (entry.date, last_read) {
+                        (Some(date), Some(last_read)) => date > last_read,
+                        // Without a date, entries are only new if the feed was never read
+                        (_, last_read) => last_read.is_none(),
+                    })
+            })
+            .cloned()
+            .collect();
+        unread.sort_by(|a, b| b.date.cmp(&a.date));
+        self.unread = unread;
+    }
+
+    /// Marks all feeds as read.
+    fn mark_read(&mut self) -> Result<()> {
+        let now = Utc::now();
+        for url in &self.urls {
+            self.last_read.insert(url.clone(), now);
+        }
+        self.unread.clear();
+        save_last_read(&self.urls, now)
+    }
+
+    fn render(&mut self) -> Result<()> {
+        // Show each headline in turn for `cycle_interval`, or the newest one
+        let headline = match self.cycle_interval {
+            Some(cycle) if !self.unread.is_empty() => {
+                let turn = self.start.elapsed().as_secs() / cycle.as_secs().max(1);
+                self.unread.get(turn as usize % self.unread.len())
+            }
+            _ => self.unread.first(),
+        };
+        let values = map!(
+            "{unread}" => self.unread.len().to_string(),
+            "{title}" => headline.map(|entry| entry.title.clone()).unwrap_or_default()
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(if self.unread.is_empty() {
+            State::Idle
+        } else {
+            State::Info
+        });
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Feeds {
+    type Config = FeedsConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{title}", "{unread}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        if block_config.feeds.is_empty() {
+            return Err(BlockError(
+                "feeds".to_string(),
+                "`feeds` must list at least one URL".to_string(),
+            ));
+        }
+
+        Ok(Feeds {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("rss"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("feeds", "Invalid format specified")?,
+            update_interval: block_config.interval,
+            cycle_interval: block_config.cycle_interval,
+            hide_when_empty: block_config.hide_when_empty,
+            urls: block_config.feeds,
+            entries: Vec::new(),
+            last_read: parse_last_read(&fs::read_to_string(last_read_path()).unwrap_or_default()),
+            last_refresh: None,
+            start: Instant::now(),
+            unread: Vec::new(),
+        })
+    }
+}
+
+impl Block for Feeds {
+    fn update(&mut self) -> Result<Option<Update>> {
+        if self
+            .last_refresh
+            .map_or(true, |last| last.elapsed() >= self.update_interval)
+        {
+            self.refresh()?;
+            self.find_unread();
+        }
+        self.render()?;
+
+        Ok(Some(
+            self.cycle_interval
+                .unwrap_or(self.update_interval)
+                .min(self.update_interval)
+                .into(),
+        ))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.unread.is_empty() && self.hide_when_empty {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        match e.button {
+            MouseButton::Left => {
+                let newest = self
+                    .unread
+                    .first()
+                    .or_else(|| self.entries.iter().flatten().max_by_key(|entry| entry.date));
+                if let Some(entry) = newest {
+                    if !entry.link.is_empty() {
+                        spawn_child_async("xdg-open", &[entry.link.as_str()])
+                            .block_error("feeds", "could not run xdg-open")?;
+                    }
+                }
+                self.mark_read()?;
+                self.render()
+            }
+            MouseButton::Right => {
+                self.mark_read()?;
+                self.render()
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{attribute, parse_feed, parse_last_read, text, unescape, Entry};
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(unescape("&lt;b&gt; &#233;t&#xE9;"), "<b> été");
+        assert_eq!(unescape("a & b &unknown; c"), "a & b &unknown; c");
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(text("<![CDATA[Rust & <i>you</i>]]>"), "Rust & <i>you</i>");
+        assert_eq!(text("\n  Release\n  notes  "), "Release notes");
+    }
+
+    #[test]
+    fn test_attribute() {
+        let attributes = r#" rel="alternate" href='https://example.org/?a=1&amp;b=2'"#;
+        assert_eq!(attribute(attributes, "rel"), Some("alternate".to_string()));
+        assert_eq!(
+            attribute(attributes, "href"),
+            Some("https://example.org/?a=1&b=2".to_string())
+        );
+        assert_eq!(attribute(r#" hreflang="en""#, "href"), None);
+    }
+
+    #[test]
+    fn test_parse_rss() {
+        let xml = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel>
+  <title>Blog</title>
+  <link>https://example.org/</link>
+  <item>
+    <title>First &amp; foremost</title>
+    <link>https://example.org/first</link>
+    <pubDate>Wed, 31 Mar 2021 10:00:00 +0000</pubDate>
+  </item>
+  <item>
+    <title><![CDATA[Second]]></title>
+    <guid>https://example.org/second</guid>
+  </item>
+</channel></rss>"#;
+        assert_eq!(
+            parse_feed(xml),
+            vec![
+                Entry {
+                    title: "First & foremost".to_string(),
+                    link: "https://example.org/first".to_string(),
+                    date: Some(Utc.ymd(2021, 3, 31).and_hms(10, 0, 0)),
+                },
+                Entry {
+                    title: "Second".to_string(),
+                    link: "https://example.org/second".to_string(),
+                    date: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let xml = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Blog</title>
+  <link href="https://example.org/"/>
+  <entry>
+    <title type="text">Release</title>
+    <link rel="edit" href="https://example.org/edit/1"/>
+    <link rel="alternate" href="https://example.org/release"/>
+    <updated>2021-03-31T12:00:00+02:00</updated>
+  </entry>
+</feed>"#;
+        assert_eq!(
+            parse_feed(xml),
+            vec![Entry {
+                title: "Release".to_string(),
+                link: "https://example.org/release".to_string(),
+                date: Some(Utc.ymd(2021, 3, 31).and_hms(10, 0, 0)),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_last_read() {
+        let last_read = parse_last_read("1617184800 https://example.org/feed\ngarbage\n");
+        assert_eq!(last_read.len(), 1);
+        assert_eq!(
+            last_read["https://example.org/feed"],
+            Utc.ymd(2021, 3, 31).and_hms(10, 0, 0)
+        );
+    }
+}
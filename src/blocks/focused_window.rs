@@ -10,7 +10,9 @@ use swayipc::{Connection, EventType};
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::Value;
 use crate::scheduler::Task;
+use crate::util::FormatTemplate;
 use crate::widgets::text::TextWidget;
 use crate::widgets::I3BarWidget;
 
@@ -26,9 +28,11 @@ pub struct FocusedWindow {
     id: usize,
     text: TextWidget,
     title: Arc<Mutex<String>>,
+    app_id: Arc<Mutex<String>>,
     marks: Arc<Mutex<String>>,
     show_marks: MarksType,
     max_width: usize,
+    format: Option<FormatTemplate>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -41,6 +45,10 @@ pub struct FocusedWindowConfig {
     /// Show marks in place of title (if exist)
     #[serde(default = "FocusedWindowConfig::default_show_marks")]
     pub show_marks: MarksType,
+
+    /// Format override, in place of the title or the marks
+    #[serde(default)]
+    pub format: Option<String>,
 }
 
 impl FocusedWindowConfig {
@@ -53,9 +61,24 @@ impl FocusedWindowConfig {
     }
 }
 
+/// The Wayland app_id of a window, or the class of an X11 window running under Xwayland.
+fn window_app_id(node: &Node) -> String {
+    node.app_id
+        .clone()
+        .or_else(|| {
+            node.window_properties
+                .as_ref()
+                .and_then(|properties| properties.class.clone())
+        })
+        .unwrap_or_default()
+}
+
 impl ConfigBlock for FocusedWindow {
     type Config = FocusedWindowConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{app_id}", "{marks}", "{title}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -63,25 +86,32 @@ impl ConfigBlock for FocusedWindow {
         tx: Sender<Task>,
     ) -> Result<Self> {
         let title = Arc::new(Mutex::new(String::from("")));
+        let app_id = Arc::new(Mutex::new(String::from("")));
         let marks = Arc::new(Mutex::new(String::from("")));
         let marks_type = block_config.show_marks;
 
         let update_window = {
             let title = title.clone();
+            let app_id = app_id.clone();
 
-            move |new_title| {
+            move |new_title, new_app_id| {
                 let mut title = title
                     .lock()
                     .expect("lock has been poisoned in `window` block");
+                let mut app_id = app_id
+                    .lock()
+                    .expect("lock has been poisoned in `window` block");
 
-                let changed = *title != new_title;
+                let changed = *title != new_title || *app_id != new_app_id;
                 *title = new_title;
+                *app_id = new_app_id;
                 changed
             }
         };
 
         let close_window = {
             let title = title.clone();
+            let app_id = app_id.clone();
 
             move |closed_title: String| {
                 let mut title = title
@@ -90,6 +120,9 @@ impl ConfigBlock for FocusedWindow {
 
                 if *title == closed_title {
                     *title = "".to_string();
+                    *app_id
+                        .lock()
+                        .expect("lock has been poisoned in `window` block") = "".to_string();
                     true
                 } else {
                     false
@@ -141,19 +174,26 @@ impl ConfigBlock for FocusedWindow {
                     let updated = match event.expect("could not read event in `window` block") {
                         Event::Window(e) => match (e.change, e.container) {
                             (WindowChange::Mark, Node { marks, .. }) => update_marks(marks),
-                            (WindowChange::Focus, Node { name, marks, .. }) => {
-                                let updated_for_window = name.map(&update_window).unwrap_or(false);
-                                let updated_for_marks = update_marks(marks);
+                            (WindowChange::Focus, node) => {
+                                let app_id = window_app_id(&node);
+                                let updated_for_window = node
+                                    .name
+                                    .map(|name| update_window(name, app_id))
+                                    .unwrap_or(false);
+                                let updated_for_marks = update_marks(node.marks);
                                 updated_for_window || updated_for_marks
                             }
                             (
                                 WindowChange::Title,
-                                Node {
+                                node @ Node {
                                     focused: true,
-                                    name: Some(name),
+                                    name: Some(_),
                                     ..
                                 },
-                            ) => update_window(name),
+                            ) => {
+                                let app_id = window_app_id(&node);
+                                update_window(node.name.unwrap_or_default(), app_id)
+                            }
                             (
                                 WindowChange::Close,
                                 Node {
@@ -163,29 +203,42 @@ impl ConfigBlock for FocusedWindow {
                             _ => false,
                         },
                         Event::Workspace(e) if e.change == WorkspaceChange::Init => {
-                            update_window("".to_string())
+                            update_window("".to_string(), "".to_string())
                         }
                         _ => false,
                     };
 
-                    if updated {
-                        tx.send(Task {
-                            id,
-                            update_time: Instant::now(),
-                        })
-                        .expect("could not communicate with channel in `window` block");
+                    if updated
+                        && tx
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                    {
+                        return;
                     }
                 }
             })
             .expect("failed to start watching thread for `window` block");
 
+        let format = match block_config.format {
+            Some(ref format) => Some(
+                FormatTemplate::new(format, &shared_config)
+                    .block_error("focused_window", "Invalid format specified")?,
+            ),
+            None => None,
+        };
+
         let text = TextWidget::new(id, 0, shared_config);
         Ok(FocusedWindow {
             id,
             text,
             max_width: block_config.max_width,
             show_marks: block_config.show_marks,
+            format,
             title,
+            app_id,
             marks,
         })
     }
@@ -205,6 +258,22 @@ impl Block for FocusedWindow {
             .block_error("focused_window", "failed to acquire lock")?)
         .clone();
         title_string = title_string.chars().take(self.max_width).collect();
+
+        if let Some(ref format) = self.format {
+            let app_id = (*self
+                .app_id
+                .lock()
+                .block_error("focused_window", "failed to acquire lock")?)
+            .clone();
+            let values = map!(
+                "{title}" => Value::text(title_string),
+                "{app_id}" => Value::text(app_id),
+                "{marks}" => Value::text(marks_string)
+            );
+            self.text.set_text(format.render(&values)?);
+            return Ok(None);
+        }
+
         let out_str = match self.show_marks {
             MarksType::None => title_string,
             _ => {
@@ -233,6 +302,10 @@ impl Block for FocusedWindow {
         }
     }
 
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        self.format.as_mut()
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -0,0 +1,324 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use inotify::{Inotify, WatchMask};
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+struct RepositoryStatus {
+    /// Current branch, or `HEAD` when it is detached
+    branch: String,
+    dirty: usize,
+    untracked: usize,
+    /// Commits of the branch which its upstream doesn't have, and the other way around
+    ahead: usize,
+    behind: usize,
+}
+
+/// Parses the output of `git status --porcelain=v2 --branch`. Changed entries start with `1`,
+/// renamed or copied ones with `2` and unmerged ones with `u`, whether staged or not, and
+/// untracked files with `?`.
+fn parse_status(output: &str) -> RepositoryStatus {
+    let mut status = RepositoryStatus {
+        branch: String::new(),
+        dirty: 0,
+        untracked: 0,
+        ahead: 0,
+        behind: 0,
+    };
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            status.branch = match head {
+                "(detached)" => "HEAD".to_string(),
+                branch => branch.to_string(),
+            };
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            // Such as `+2 -1`, only when the branch has an upstream
+            for count in ab.split_whitespace() {
+                if let Some(ahead) = count.strip_prefix('+') {
+                    status.ahead = ahead.parse().unwrap_or(0);
+                } else if let Some(behind) = count.strip_prefix('-') {
+                    status.behind = behind.parse().unwrap_or(0);
+                }
+            }
+        } else if line.starts_with("1 ") || line.starts_with("2 ") || line.starts_with("u ") {
+            status.dirty += 1;
+        } else if line.starts_with("? ") {
+            status.untracked += 1;
+        }
+    }
+    status
+}
+
+/// Runs git in the repository at `path` and returns what it prints.
+fn run_git(path: &Path, args: &[&str]) -> Result<String> {
+    // `git status` refreshes `.git/index` otherwise, which is watched, so every update would
+    // trigger another one
+    let output = Command::new("git")
+        .arg("--no-optional-locks")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .block_error("git", "failed to run git")?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "git".to_string(),
+            format!(
+                "git failed in {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+    String::from_utf8(output.stdout).block_error("git", "git printed invalid UTF-8")
+}
+
+fn repository_status(path: &Path) -> Result<RepositoryStatus> {
+    run_git(
+        path,
+        &[
+            "status",
+            "--porcelain=v2",
+            "--branch",
+            "--untracked-files=all",
+            "--ignore-submodules",
+        ],
+    )
+    .map(|output| parse_status(&output))
+}
+
+/// Requests an update of the block whenever git changes the index or a reference, such as after
+/// a commit, a checkout or a fetch.
+fn watch(id: usize, git_dirs: Vec<PathBuf>, tx_update_request: Sender<Task>) -> Result<()> {
+    let mut notify = Inotify::init().block_error("git", "failed to start inotify")?;
+    let mask = WatchMask::CREATE | WatchMask::MODIFY | WatchMask::MOVED_TO | WatchMask::DELETE;
+    for git_dir in git_dirs {
+        // References live in files under `.git/refs`, unless they are packed in `.git`
+        let refs = [git_dir.join("refs/heads"), git_dir.join("refs/remotes")];
+        let remotes = git_dir
+            .join("refs/remotes")
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path());
+        for dir in std::iter::once(git_dir.clone())
+            .chain(refs.iter().cloned())
+            .chain(remotes)
+            .filter(|dir| dir.is_dir())
+        {
+            notify
+                .add_watch(&dir, mask)
+                .block_error("git", &format!("failed to watch {}", dir.display()))?;
+        }
+    }
+
+    thread::spawn(move || {
+        let mut buffer = [0; 1024];
+        loop {
+            let events = notify
+                .read_events_blocking(&mut buffer)
+                .expect("error while reading inotify events");
+            // Git writes each file to a `.lock` file first, then renames it
+            let changed = events.into_iter().any(|event| {
+                event.name.map_or(true, |name| {
+                    Path::new(name).extension() != Some(OsStr::new("lock"))
+                })
+            });
+            if changed
+                && tx_update_request
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .is_err()
+            {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+struct Repo {
+    path: PathBuf,
+    /// Name of the directory of the repository
+    name: String,
+    text: TextWidget,
+}
+
+pub struct Git {
+    id: usize,
+    repositories: Vec<Repo>,
+    update_interval: Duration,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GitConfig {
+    /// Paths of the repositories to show
+    pub repositories: Vec<String>,
+
+    /// Update interval in seconds, to notice changes to the files of the working tree
+    #[serde(
+        default = "GitConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "GitConfig::default_format")]
+    pub format: String,
+}
+
+impl GitConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{name} {branch} ~{dirty} ?{untracked} ↑{ahead} ↓{behind}".to_owned()
+    }
+}
+
+impl ConfigBlock for Git {
+    type Config = GitConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{ahead}",
+            "{behind}",
+            "{branch}",
+            "{dirty}",
+            "{name}",
+            "{untracked}",
+        ],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let mut repositories = Vec::new();
+        let mut git_dirs = Vec::new();
+        for (i, path) in block_config.repositories.iter().enumerate() {
+            let path = match path.strip_prefix("~/") {
+                Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+                None => PathBuf::from(path),
+            };
+            // The git directory isn't `.git` in worktrees and submodules
+            let git_dir = run_git(&path, &["rev-parse", "--absolute-git-dir"])?;
+            git_dirs.push(PathBuf::from(git_dir.trim_end()));
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            repositories.push(Repo {
+                path,
+                name,
+                text: TextWidget::new(id, i, shared_config.clone()).with_icon("git"),
+            });
+        }
+        watch(id, git_dirs, tx_update_request)?;
+
+        Ok(Git {
+            id,
+            repositories,
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("git", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for Git {
+    fn update(&mut self) -> Result<Option<Update>> {
+        for repo in &mut self.repositories {
+            let status = repository_status(&repo.path)?;
+            let values = map!(
+                "{name}" => repo.name.clone(),
+                "{branch}" => status.branch,
+                "{dirty}" => status.dirty.to_string(),
+                "{untracked}" => status.untracked.to_string(),
+                "{ahead}" => status.ahead.to_string(),
+                "{behind}" => status.behind.to_string()
+            );
+            repo.text.set_text(self.format.render_static_str(&values)?);
+            repo.text
+                .set_state(if status.dirty > 0 || status.untracked > 0 {
+                    State::Warning
+                } else if status.ahead > 0 || status.behind > 0 {
+                    State::Info
+                } else {
+                    State::Idle
+                });
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.repositories
+            .iter()
+            .map(|repo| &repo.text as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_status;
+
+    #[test]
+    fn test_parse_status() {
+        let output = "# branch.oid 8a3c1e0f6b2d4c5e7f9a1b3c5d7e9f0a2b4c6d8e
+# branch.head master
+# branch.upstream origin/master
+# branch.ab +2 -1
+1 .M N... 100644 100644 100644 3b18e51 3b18e51 src/main.rs
+1 A. N... 000000 100644 100644 0000000 e69de29 src/new.rs
+1 MM N... 100644 100644 100644 a1b2c3d d4e5f6a README.md
+2 R. N... 100644 100644 100644 9f8e7d6 9f8e7d6 R100 src/moved.rs\tsrc/old.rs
+? notes.txt
+? target/debug/build.log
+";
+        let status = parse_status(output);
+        assert_eq!(status.branch, "master");
+        assert_eq!((status.dirty, status.untracked), (4, 2));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+
+        // A detached HEAD has no upstream
+        let status = parse_status("# branch.oid 8a3c1e0\n# branch.head (detached)\n");
+        assert_eq!(status.branch, "HEAD");
+        assert_eq!((status.ahead, status.behind), (0, 0));
+
+        // A new repository without commits
+        let status = parse_status("# branch.oid (initial)\n# branch.head master\n? a.txt\n");
+        assert_eq!((status.dirty, status.untracked), (0, 1));
+    }
+}
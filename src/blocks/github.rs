@@ -18,6 +18,22 @@ use crate::widgets::I3BarWidget;
 
 const GITHUB_TOKEN_ENV: &str = "I3RS_GITHUB_TOKEN";
 
+/// Reasons of notifications, each of which has a placeholder, as specified by:
+/// https://developer.github.com/v3/activity/notifications/#notification-reasons
+const REASONS: &[&str] = &[
+    "assign",
+    "author",
+    "comment",
+    "invitation",
+    "manual",
+    "mention",
+    "review_requested",
+    "security_alert",
+    "state_change",
+    "subscribed",
+    "team_mention",
+];
+
 pub struct Github {
     id: usize,
     text: TextWidget,
@@ -25,10 +41,40 @@ pub struct Github {
     api_server: String,
     token: String,
     format: FormatTemplate,
+    filters: HashMap<String, NotificationFilter>,
+    notifications: Vec<Notification>,
+    /// `Last-Modified` header of the last reply, to only get the notifications again if they
+    /// changed
+    last_modified: Option<String>,
+    /// Minimum time between requests asked by the server
+    poll_interval: Duration,
     total_notifications: u64,
     hide_if_total_is_zero: bool,
 }
 
+/// Notifications counted by a placeholder named after the filter.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationFilter {
+    /// Reasons of the notifications, all if empty
+    #[serde(default)]
+    pub reasons: Vec<String>,
+
+    /// Full names of the repositories of the notifications, e.g. `rust-lang/rust`, all if empty
+    #[serde(default)]
+    pub repositories: Vec<String>,
+}
+
+impl NotificationFilter {
+    fn matches(&self, notification: &Notification) -> bool {
+        (self.reasons.is_empty() || self.reasons.contains(&notification.reason))
+            && (self.repositories.is_empty()
+                || self
+                    .repositories
+                    .contains(&notification.repository.full_name))
+    }
+}
+
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct GithubConfig {
@@ -46,6 +92,10 @@ pub struct GithubConfig {
     #[serde(default = "GithubConfig::default_format")]
     pub format: String,
 
+    /// Filters by name, each of which is a placeholder
+    #[serde(default)]
+    pub filters: HashMap<String, NotificationFilter>,
+
     #[serde(default = "GithubConfig::default_hide_if_total_is_zero")]
     pub hide_if_total_is_zero: bool,
 }
@@ -80,7 +130,18 @@ impl ConfigBlock for Github {
         let token = std::env::var(GITHUB_TOKEN_ENV)
             .block_error("github", "missing I3RS_GITHUB_TOKEN environment variable")?;
 
-        let text = TextWidget::new(id, 0, shared_config)
+        if let Some(name) = block_config
+            .filters
+            .keys()
+            .find(|name| *name == "total" || REASONS.contains(&name.as_str()))
+        {
+            return Err(BlockError(
+                "github".to_string(),
+                format!("the filter name `{}` is already a placeholder", name),
+            ));
+        }
+
+        let text = TextWidget::new(id, 0, shared_config.clone())
             .with_text("x")
             .with_icon("github");
         Ok(Github {
@@ -89,57 +150,106 @@ impl ConfigBlock for Github {
             text,
             api_server: block_config.api_server,
             token,
-            format: FormatTemplate::from_string(&block_config.format)
+            format: FormatTemplate::new(&block_config.format, &shared_config)
                 .block_error("github", "Invalid format specified")?,
+            filters: block_config.filters,
+            notifications: Vec::new(),
+            last_modified: None,
+            poll_interval: Duration::from_secs(0),
             total_notifications: 0,
             hide_if_total_is_zero: block_config.hide_if_total_is_zero,
         })
     }
 }
 
+impl Github {
+    /// Gets the notifications again unless they didn't change since the last request. They are
+    /// only available from the REST API, not from the GraphQL one.
+    fn refresh(&mut self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        let mut url = format!("{}/notifications", self.api_server);
+        let mut notifications = Vec::new();
+        let mut first_page = true;
+        let authorization = format!("Bearer {}", self.token);
+        loop {
+            let mut headers = vec![("Authorization", authorization.as_str())];
+            // Only the first page is requested conditionally, so that the others are consistent
+            // with it. Replies without changes don't count in the rate limit.
+            if first_page {
+                if let Some(ref last_modified) = self.last_modified {
+                    headers.push(("If-Modified-Since", last_modified.as_str()));
+                }
+            }
+            let response = http::http_get_bytes(&url, Some(Duration::from_secs(3)), headers)?;
+            if first_page {
+                if let Some(poll_interval) = response
+                    .header("X-Poll-Interval")
+                    .and_then(|value| value.parse().ok())
+                {
+                    self.poll_interval = Duration::from_secs(poll_interval);
+                }
+                if response.code == 304 {
+                    return Ok(());
+                }
+                self.last_modified = response.header("Last-Modified").map(str::to_string);
+            }
+            if !(200..300).contains(&response.code) {
+                return Err(format!("{} replied with status {}", url, response.code).into());
+            }
+
+            let page: Vec<Notification> = serde_json::from_slice(&response.content)?;
+            notifications.extend(page);
+            first_page = false;
+
+            url = match response
+                .header("Link")
+                .and_then(|links| parse_links_header(links).get("next").cloned())
+            {
+                Some(next) => next.to_string(),
+                None => break,
+            };
+        }
+        self.notifications = notifications;
+        Ok(())
+    }
+}
+
 impl Block for Github {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
-        let aggregations = match Notifications::new(&self.api_server, &self.token).try_fold(
-            map!("total".to_owned() => 0),
-            |mut acc,
-             notif|
-             -> std::result::Result<HashMap<String, u64>, Box<dyn std::error::Error>> {
-                let n = notif?;
-                acc.entry(n.reason).and_modify(|v| *v += 1).or_insert(1);
-                acc.entry("total".to_owned()).and_modify(|v| *v += 1);
-                Ok(acc)
-            },
-        ) {
-            Ok(v) => v,
-            Err(_) => {
-                // If there is a error reported, set the value to x
-                self.text.set_text("x".to_owned());
-                return Ok(Some(self.update_interval.into()));
-            }
-        };
+        let update_interval = self.update_interval.max(self.poll_interval);
+        if self.refresh().is_err() {
+            // If there is a error reported, set the value to x
+            self.text.set_text("x".to_owned());
+            self.last_modified = None;
+            return Ok(Some(update_interval.into()));
+        }
 
-        let default: u64 = 0;
-        self.total_notifications = *aggregations.get("total").unwrap_or(&default);
-        let values = map!(
-            "{total}" => format!("{}", self.total_notifications),
-            // As specified by:
-            // https://developer.github.com/v3/activity/notifications/#notification-reasons
-            "{assign}" => format!("{}", aggregations.get("assign").unwrap_or(&default)),
-            "{author}" => format!("{}", aggregations.get("author").unwrap_or(&default)),
-            "{comment}" => format!("{}", aggregations.get("comment").unwrap_or(&default)),
-            "{invitation}" => format!("{}", aggregations.get("invitation").unwrap_or(&default)),
-            "{manual}" => format!("{}", aggregations.get("manual").unwrap_or(&default)),
-            "{mention}" => format!("{}", aggregations.get("mention").unwrap_or(&default)),
-            "{review_requested}" => format!("{}", aggregations.get("review_requested").unwrap_or(&default)),
-            "{security_alert}" => format!("{}", aggregations.get("security_alert").unwrap_or(&default)),
-            "{state_change}" => format!("{}", aggregations.get("state_change").unwrap_or(&default)),
-            "{subscribed}" => format!("{}", aggregations.get("subscribed").unwrap_or(&default)),
-            "{team_mention}" => format!("{}", aggregations.get("team_mention").unwrap_or(&default))
-        );
+        self.total_notifications = self.notifications.len() as u64;
+        let notifications = &self.notifications;
+        let count = |matches: &dyn Fn(&Notification) -> bool| {
+            notifications.iter().filter(|n| matches(n)).count()
+        };
+        let mut counts = Vec::new();
+        for reason in REASONS {
+            let matches = |n: &Notification| n.reason == *reason;
+            counts.push((format!("{{{}}}", reason), count(&matches)));
+        }
+        for (name, filter) in &self.filters {
+            let matches = |n: &Notification| filter.matches(n);
+            counts.push((format!("{{{}}}", name), count(&matches)));
+        }
+        let mut values: HashMap<&str, usize> = counts
+            .iter()
+            .map(|(key, count)| (key.as_str(), *count))
+            .collect();
+        values.insert("{total}", notifications.len());
 
         self.text.set_text(self.format.render_static_str(&values)?);
 
-        Ok(Some(self.update_interval.into()))
+        Ok(Some(update_interval.into()))
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
@@ -155,72 +265,15 @@ impl Block for Github {
     }
 }
 
-#[derive(Deserialize)]
-struct Notification {
-    reason: String,
-}
-
-struct Notifications<'a> {
-    notifications: <Vec<Notification> as IntoIterator>::IntoIter,
-    token: &'a str,
-    next_page_url: String,
-}
-
-impl<'a> Iterator for Notifications<'a> {
-    type Item = std::result::Result<Notification, Box<dyn std::error::Error>>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.try_next() {
-            Ok(Some(notif)) => Some(Ok(notif)),
-            Ok(None) => None,
-            Err(err) => Some(Err(err)),
-        }
-    }
+#[derive(Deserialize, Debug, Clone)]
+struct Repository {
+    full_name: String,
 }
 
-impl<'a> Notifications<'a> {
-    fn new(api_server: &'a str, token: &'a str) -> Notifications<'a> {
-        Notifications {
-            next_page_url: format!("{}/notifications", api_server),
-            token,
-            notifications: vec![].into_iter(),
-        }
-    }
-
-    fn try_next(
-        &mut self,
-    ) -> std::result::Result<Option<Notification>, Box<dyn std::error::Error>> {
-        if let Some(notif) = self.notifications.next() {
-            return Ok(Some(notif));
-        }
-
-        if self.next_page_url.is_empty() {
-            return Ok(None);
-        }
-
-        let header_value = format!("Bearer {}", self.token);
-        let headers = vec![("Authorization", header_value.as_str())];
-        let result =
-            http::http_get_json(&self.next_page_url, Some(Duration::from_secs(3)), headers)?;
-
-        self.next_page_url = result
-            .headers
-            .iter()
-            .find_map(|header| {
-                if header.starts_with("Link:") {
-                    parse_links_header(header).get("next").cloned()
-                } else {
-                    None
-                }
-            })
-            .unwrap_or(&"")
-            .to_string();
-
-        let notifications: Vec<Notification> = serde_json::from_value(result.content)?;
-        self.notifications = notifications.into_iter();
-
-        Ok(self.notifications.next())
-    }
+#[derive(Deserialize, Debug, Clone)]
+struct Notification {
+    reason: String,
+    repository: Repository,
 }
 
 fn parse_links_header(raw_links: &str) -> HashMap<&str, &str> {
@@ -261,4 +314,23 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn it_filters_notifications() {
+        let notification = Notification {
+            reason: "mention".to_string(),
+            repository: Repository {
+                full_name: "rust-lang/rust".to_string(),
+            },
+        };
+        let filter = |reasons: &[&str], repositories: &[&str]| NotificationFilter {
+            reasons: reasons.iter().map(|r| r.to_string()).collect(),
+            repositories: repositories.iter().map(|r| r.to_string()).collect(),
+        };
+        assert!(filter(&[], &[]).matches(&notification));
+        assert!(filter(&["mention", "review_requested"], &[]).matches(&notification));
+        assert!(filter(&["mention"], &["rust-lang/rust"]).matches(&notification));
+        assert!(!filter(&["review_requested"], &[]).matches(&notification));
+        assert!(!filter(&["mention"], &["rust-lang/cargo"]).matches(&notification));
+    }
 }
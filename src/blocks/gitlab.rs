@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const GITLAB_TOKEN_ENV: &str = "I3RS_GITLAB_TOKEN";
+
+pub struct Gitlab {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    instance: String,
+    token: String,
+    format: FormatTemplate,
+    /// User name of the token's owner, once known
+    username: Option<String>,
+    total: u64,
+    hide_if_total_is_zero: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GitlabConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "GitlabConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// URL of the GitLab instance
+    #[serde(default = "GitlabConfig::default_instance")]
+    pub instance: String,
+
+    /// Format override
+    #[serde(default = "GitlabConfig::default_format")]
+    pub format: String,
+
+    #[serde(default)]
+    pub hide_if_total_is_zero: bool,
+}
+
+impl GitlabConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_instance() -> String {
+        "https://gitlab.com".to_owned()
+    }
+
+    fn default_format() -> String {
+        "{todos}".to_owned()
+    }
+}
+
+#[derive(Deserialize)]
+struct User {
+    username: String,
+}
+
+impl Gitlab {
+    fn get(&self, path: &str) -> Result<http::HttpResponse<serde_json::Value>> {
+        let url = format!("{}/api/v4/{}", self.instance.trim_end_matches('/'), path);
+        let response = http::http_get_json(
+            &url,
+            Some(Duration::from_secs(3)),
+            vec![("PRIVATE-TOKEN", self.token.as_str())],
+        )?;
+        if !(200..300).contains(&response.code) {
+            return Err(BlockError(
+                "gitlab".to_string(),
+                format!("{} replied with status {}", url, response.code),
+            ));
+        }
+        Ok(response)
+    }
+
+    /// The number of items listed at `path`, from the `X-Total` header of its first page.
+    fn count(&self, path: &str) -> Result<u64> {
+        let response = self.get(&format!("{}&per_page=1", path))?;
+        // Instances leave the header out when counting is too slow, so count the page instead
+        let total = response
+            .header("X-Total")
+            .and_then(|total| total.parse().ok());
+        Ok(total.unwrap_or_else(|| {
+            response
+                .content
+                .as_array()
+                .map_or(0, |items| items.len() as u64)
+        }))
+    }
+
+    fn username(&mut self) -> Result<String> {
+        if let Some(ref username) = self.username {
+            return Ok(username.clone());
+        }
+        let user: User = serde_json::from_value(self.get("user")?.content)
+            .block_error("gitlab", "unexpected reply about the user")?;
+        self.username = Some(user.username.clone());
+        Ok(user.username)
+    }
+
+    /// Counts the items shown by the format, which are `None` otherwise.
+    fn counts(&mut self) -> Result<(Option<u64>, Option<u64>, Option<u64>)> {
+        let shown =
+            |key| self.format.has_placeholder(key) || self.format.has_placeholder("{total}");
+        let (todos_shown, assigned_shown, reviews_shown) =
+            (shown("{todos}"), shown("{assigned}"), shown("{reviews}"));
+
+        let todos = if todos_shown {
+            Some(self.count("todos?state=pending")?)
+        } else {
+            None
+        };
+        let assigned = if assigned_shown {
+            Some(self.count("merge_requests?state=opened&scope=assigned_to_me")?)
+        } else {
+            None
+        };
+        let reviews = if reviews_shown {
+            let username = self.username()?;
+            Some(self.count(&format!(
+                "merge_requests?state=opened&scope=all&reviewer_username={}",
+                username
+            ))?)
+        } else {
+            None
+        };
+        Ok((todos, assigned, reviews))
+    }
+}
+
+impl ConfigBlock for Gitlab {
+    type Config = GitlabConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{assigned}", "{reviews}", "{todos}", "{total}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _: Sender<Task>,
+    ) -> Result<Self> {
+        let token = std::env::var(GITLAB_TOKEN_ENV)
+            .block_error("gitlab", "missing I3RS_GITLAB_TOKEN environment variable")?;
+
+        let text = TextWidget::new(id, 0, shared_config.clone())
+            .with_text("x")
+            .with_icon("gitlab");
+        Ok(Gitlab {
+            id,
+            update_interval: block_config.interval,
+            text,
+            instance: block_config.instance,
+            token,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("gitlab", "Invalid format specified")?,
+            username: None,
+            total: 0,
+            hide_if_total_is_zero: block_config.hide_if_total_is_zero,
+        })
+    }
+}
+
+impl Block for Gitlab {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let (todos, assigned, reviews) = match self.counts() {
+            Ok(counts) => counts,
+            Err(_) => {
+                // If there is a error reported, set the value to x
+                self.text.set_text("x".to_owned());
+                return Ok(Some(self.update_interval.into()));
+            }
+        };
+
+        self.total = todos.unwrap_or(0) + assigned.unwrap_or(0) + reviews.unwrap_or(0);
+        let values = map!(
+            "{todos}" => todos.unwrap_or(0),
+            "{assigned}" => assigned.unwrap_or(0),
+            "{reviews}" => reviews.unwrap_or(0),
+            "{total}" => self.total
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.hide_if_total_is_zero && self.total == 0 {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use regex::Regex;
+use serde_derive::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Http {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    url: String,
+    headers: BTreeMap<String, String>,
+    json: BTreeMap<String, String>,
+    regex: Option<Regex>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// URL to get
+    pub url: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "HttpConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Headers sent with the request, e.g. for authentication
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Placeholders set to the values at these JSON pointers of the reply, e.g. `/data/0/count`
+    #[serde(default)]
+    pub json: BTreeMap<String, String>,
+
+    /// Regex whose named capture groups are set as placeholders
+    pub regex: Option<String>,
+
+    /// Format override
+    #[serde(default = "HttpConfig::default_format")]
+    pub format: String,
+}
+
+impl HttpConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{body}".to_owned()
+    }
+}
+
+/// A JSON value as shown in the bar: strings without quotes, and `null` as nothing.
+fn json_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// The placeholders extracted from `body`, without braces. Those which are not found in the
+/// reply are empty, so that they can be left out with conditional sections.
+fn extract(
+    body: &str,
+    json: &BTreeMap<String, String>,
+    regex: Option<&Regex>,
+) -> Result<Vec<(String, String)>> {
+    let mut values = Vec::new();
+
+    if !json.is_empty() {
+        let reply: JsonValue =
+            serde_json::from_str(body).block_error("http", "the reply is not valid JSON")?;
+        for (name, pointer) in json {
+            let value = reply.pointer(pointer).map(json_text).unwrap_or_default();
+            values.push((name.clone(), value));
+        }
+    }
+
+    if let Some(regex) = regex {
+        let captures = regex.captures(body);
+        for name in regex.capture_names().flatten() {
+            let value = captures
+                .as_ref()
+                .and_then(|captures| captures.name(name))
+                .map(|capture| capture.as_str().to_string())
+                .unwrap_or_default();
+            values.push((name.to_string(), value));
+        }
+    }
+
+    Ok(values)
+}
+
+impl ConfigBlock for Http {
+    type Config = HttpConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let regex = match block_config.regex {
+            Some(ref regex) => {
+                Some(Regex::new(regex).block_error("http", "Invalid regex specified")?)
+            }
+            None => None,
+        };
+
+        Ok(Http {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            update_interval: block_config.interval,
+            url: block_config.url,
+            headers: block_config.headers,
+            json: block_config.json,
+            regex,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("http", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for Http {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let response = http::http_get_bytes(&self.url, Some(Duration::from_secs(10)), headers)?;
+        if !(200..300).contains(&response.code) {
+            return Err(BlockError(
+                "http".to_string(),
+                format!("{} replied with status {}", self.url, response.code),
+            ));
+        }
+        let body = String::from_utf8_lossy(&response.content);
+
+        let extracted = extract(&body, &self.json, self.regex.as_ref())?;
+        let extracted: Vec<(String, String)> = extracted
+            .into_iter()
+            .map(|(name, value)| (format!("{{{}}}", name), value))
+            .collect();
+        let status = response.code.to_string();
+        let mut values: HashMap<&str, &str> = map!(
+            "{body}" => body.trim(),
+            "{status}" => status.as_str()
+        );
+        for (name, value) in &extracted {
+            values.insert(name, value);
+        }
+
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text
+            .set_state(self.format.state_static_str(&values).unwrap_or(State::Idle));
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use regex::Regex;
+
+    use super::extract;
+
+    #[test]
+    fn test_extract() {
+        let body = r#"{"data": [{"count": 42, "name": "queue", "paused": false, "owner": null}]}"#;
+        let mut json = BTreeMap::new();
+        json.insert("count".to_string(), "/data/0/count".to_string());
+        json.insert("name".to_string(), "/data/0/name".to_string());
+        json.insert("owner".to_string(), "/data/0/owner".to_string());
+        json.insert("missing".to_string(), "/data/1/count".to_string());
+        let regex = Regex::new(r#""paused": (?P<paused>\w+)"#).unwrap();
+        assert_eq!(
+            extract(body, &json, Some(&regex)).unwrap(),
+            vec![
+                ("count".to_string(), "42".to_string()),
+                ("missing".to_string(), String::new()),
+                ("name".to_string(), "queue".to_string()),
+                ("owner".to_string(), String::new()),
+                ("paused".to_string(), "false".to_string()),
+            ]
+        );
+
+        assert!(extract("<html>", &json, None).is_err());
+        assert_eq!(extract("<html>", &BTreeMap::new(), None).unwrap(), vec![]);
+    }
+}
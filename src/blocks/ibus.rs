@@ -58,6 +58,9 @@ impl IBusConfig {
 impl ConfigBlock for IBus {
     type Config = IBusConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{engine}"])];
+
     #[allow(clippy::many_single_char_names)]
     fn new(
         id: usize,
@@ -111,20 +114,24 @@ impl ConfigBlock for IBus {
                             let mut engine = engine_copy.lock().unwrap();
                             // see comment on L167
                             *engine = "Reload the bar!".to_string();
-							send2.send(Task {
+							if send2.send(Task {
 								id,
 								update_time: Instant::now(),
-							}).unwrap();
+							}).is_err() {
+							    return;
+							}
 						} else if name.contains("IBus") && old_owner.is_empty() && !new_owner.is_empty() {
 							let (lock, cvar) = &*available_copy;
 							let mut available = lock.lock().unwrap();
 							*available = true;
 							cvar.notify_one();
 
-							send2.send(Task {
+							if send2.send(Task {
 						   		id,
 						   		update_time: Instant::now(),
-							}).unwrap();
+							}).is_err() {
+							    return;
+							}
 						}
                     }
                 }
@@ -198,29 +205,37 @@ impl ConfigBlock for IBus {
                             let mut engine = engine_copy3.lock().unwrap();
                             *engine = engine_name.to_string();
                             // Tell block to update now.
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            if send
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                            {
+                                return;
+                            }
                         };
                     }
                 }
             })
             .unwrap();
 
-        let text = TextWidget::new(id, 0, shared_config).with_text("IBus");
+        let text = TextWidget::new(id, 0, shared_config.clone()).with_text("IBus");
         Ok(IBus {
             id,
             text,
             engine: engine_original,
             mappings: block_config.mappings,
-            format: FormatTemplate::from_string(&block_config.format)?,
+            format: FormatTemplate::new(&block_config.format, &shared_config)?,
         })
     }
 }
 
 impl Block for IBus {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
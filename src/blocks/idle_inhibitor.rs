@@ -0,0 +1,217 @@
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use dbus::arg::OwnedFd;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_opt_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleInhibitorDriver {
+    /// Take an inhibitor lock from systemd-logind
+    Logind,
+    /// Pause the `swayidle` processes
+    Swayidle,
+}
+
+impl Default for IdleInhibitorDriver {
+    fn default() -> Self {
+        Self::Logind
+    }
+}
+
+/// An active inhibition.
+struct Inhibition {
+    since: Instant,
+    /// The logind lock, which is released when the descriptor is closed
+    _lock: Option<OwnedFd>,
+}
+
+pub struct IdleInhibitor {
+    id: usize,
+    text: TextWidget,
+    driver: IdleInhibitorDriver,
+    what: String,
+    timeout: Option<Duration>,
+    inhibition: Option<Inhibition>,
+    send: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IdleInhibitorConfig {
+    /// How to inhibit idleness
+    #[serde(default)]
+    pub driver: IdleInhibitorDriver,
+
+    /// Colon-separated list of what logind inhibits, e.g. `idle:sleep`
+    #[serde(default = "IdleInhibitorConfig::default_what")]
+    pub what: String,
+
+    /// Time after which the inhibition ends by itself, in seconds
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub timeout: Option<Duration>,
+
+    /// Text to display in i3bar for this block
+    pub text: Option<String>,
+}
+
+impl IdleInhibitorConfig {
+    fn default_what() -> String {
+        "idle".to_owned()
+    }
+}
+
+/// Takes a `block` inhibitor lock from systemd-logind, held as long as the returned descriptor.
+fn logind_inhibit(what: &str) -> Result<OwnedFd> {
+    let con = dbus::ffidisp::Connection::get_private(dbus::ffidisp::BusType::System)
+        .block_error("idle_inhibitor", "Failed to establish D-Bus connection.")?;
+    let msg = dbus::Message::new_method_call(
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+        "Inhibit",
+    )
+    .block_error("idle_inhibitor", "Failed to create D-Bus message")?
+    .append2(what, "i3status-rs")
+    .append2("Inhibited from the bar", "block");
+
+    con.send_with_reply_and_block(msg, 2000)
+        .block_error("idle_inhibitor", "Failed to take an inhibitor lock")?
+        .read1()
+        .block_error("idle_inhibitor", "Unexpected reply to Inhibit")
+}
+
+/// Sends `signal` to the `swayidle` processes, stopping or resuming their timers.
+fn signal_swayidle(signal: &str) -> Result<()> {
+    let status = Command::new("pkill")
+        .args(&[signal, "-x", "swayidle"])
+        .status()
+        .block_error("idle_inhibitor", "Failed to run pkill")?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(BlockError(
+            "idle_inhibitor".to_string(),
+            "swayidle is not running".to_string(),
+        ))
+    }
+}
+
+impl IdleInhibitor {
+    fn inhibit(&mut self) -> Result<()> {
+        let lock = match self.driver {
+            IdleInhibitorDriver::Logind => Some(logind_inhibit(&self.what)?),
+            IdleInhibitorDriver::Swayidle => {
+                signal_swayidle("-STOP")?;
+                None
+            }
+        };
+        let since = Instant::now();
+        self.inhibition = Some(Inhibition { since, _lock: lock });
+
+        // Clicks don't reschedule blocks, so ask for the update which ends the inhibition
+        if let Some(timeout) = self.timeout {
+            let id = self.id;
+            let send = self.send.clone();
+            thread::Builder::new()
+                .name("idle_inhibitor".into())
+                .spawn(move || {
+                    thread::sleep(timeout);
+                    send.send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .ok();
+                })
+                .unwrap();
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<()> {
+        // Dropping the inhibition closes the logind lock
+        if self.inhibition.take().is_some() && self.driver == IdleInhibitorDriver::Swayidle {
+            signal_swayidle("-CONT")?;
+        }
+        Ok(())
+    }
+
+    fn display(&mut self) {
+        if self.inhibition.is_some() {
+            self.text.set_icon("caffeine_on");
+            self.text.set_state(State::Good);
+        } else {
+            self.text.set_icon("caffeine_off");
+            self.text.set_state(State::Idle);
+        }
+    }
+}
+
+impl ConfigBlock for IdleInhibitor {
+    type Config = IdleInhibitorConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(IdleInhibitor {
+            id,
+            text: TextWidget::new(id, 0, shared_config)
+                .with_icon("caffeine_off")
+                .with_text(&block_config.text.unwrap_or_default()),
+            driver: block_config.driver,
+            what: block_config.what,
+            timeout: block_config.timeout,
+            inhibition: None,
+            send,
+        })
+    }
+}
+
+impl Block for IdleInhibitor {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let expired = match (&self.inhibition, self.timeout) {
+            (Some(inhibition), Some(timeout)) => inhibition.since.elapsed() >= timeout,
+            _ => false,
+        };
+        if expired {
+            self.release()?;
+        }
+        self.display();
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.button == MouseButton::Left {
+            if self.inhibition.is_some() {
+                self.release()?;
+            } else {
+                self.inhibit()?;
+            }
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -0,0 +1,457 @@
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use curl::easy::Easy;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Servers may end an IDLE command after 30 minutes, so it is renewed before
+const IDLE_RENEWAL: Duration = Duration::from_secs(25 * 60);
+
+/// Time to wait for the reply to a command
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How to reach an IMAP account.
+#[derive(Clone)]
+struct Account {
+    url: String,
+    username: String,
+    password: String,
+}
+
+impl Account {
+    fn easy(&self) -> Result<Easy> {
+        let mut easy = Easy::new();
+        easy.url(&self.url)?;
+        easy.username(&self.username)?;
+        easy.password(&self.password)?;
+        easy.connect_timeout(Duration::from_secs(10))?;
+        Ok(easy)
+    }
+
+    /// The number of unread messages of `folder`.
+    fn unread(&self, folder: &str) -> Result<u64> {
+        let mut easy = self.easy()?;
+        easy.timeout(COMMAND_TIMEOUT)?;
+        easy.custom_request(&format!("STATUS {} (UNSEEN)", quote(folder)))?;
+        let mut reply = Vec::new();
+        {
+            let mut transfer = easy.transfer();
+            transfer.write_function(|data| {
+                reply.extend_from_slice(data);
+                Ok(data.len())
+            })?;
+            transfer.perform()?;
+        }
+        parse_unseen(&String::from_utf8_lossy(&reply))
+            .block_error("imap", &format!("unexpected reply to STATUS of {}", folder))
+    }
+
+    /// Waits for changes of `folder` with the IDLE command, calling `changed` for each one, until
+    /// the connection fails.
+    fn idle(&self, folder: &str, changed: &dyn Fn()) -> Result<()> {
+        let mut easy = self.easy()?;
+        // Curl connects and logs in, then lets us talk to the server
+        easy.connect_only(true)?;
+        easy.perform()?;
+        let mut session = Session {
+            easy,
+            buffer: Vec::new(),
+            tag: 0,
+        };
+
+        let tag = session.command(&format!("EXAMINE {}", quote(folder)))?;
+        session.wait_for(&tag, COMMAND_TIMEOUT, |_| {})?;
+        loop {
+            let tag = session.command("IDLE")?;
+            let started = Instant::now();
+            // The server replies with the tag once the IDLE command is done
+            let done = session.wait_for(&tag, IDLE_RENEWAL, |line| {
+                if is_change(line) {
+                    changed();
+                }
+            });
+            match done {
+                Ok(()) => continue,
+                Err(_) if started.elapsed() >= IDLE_RENEWAL => {
+                    session.send("DONE")?;
+                    session.wait_for(&tag, COMMAND_TIMEOUT, |_| {})?;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+/// A connection on which we send commands ourselves.
+struct Session {
+    easy: Easy,
+    /// Received data which doesn't make a whole line yet
+    buffer: Vec<u8>,
+    tag: u32,
+}
+
+impl Session {
+    fn send(&mut self, line: &str) -> Result<()> {
+        let data = format!("{}\r\n", line);
+        let mut sent = 0;
+        while sent < data.len() {
+            match self.easy.send(&data.as_bytes()[sent..]) {
+                Ok(len) => sent += len,
+                Err(error) if error.is_again() => thread::sleep(Duration::from_millis(100)),
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends a command with a new tag, which is returned.
+    fn command(&mut self, command: &str) -> Result<String> {
+        self.tag += 1;
+        let tag = format!("i{}", self.tag);
+        self.send(&format!("{} {}", tag, command))?;
+        Ok(tag)
+    }
+
+    /// Passes the received lines to `untagged` until the reply of the command `tag`, which is an
+    /// error unless it is `OK`, or until `timeout`.
+    fn wait_for(&mut self, tag: &str, timeout: Duration, untagged: impl Fn(&str)) -> Result<()> {
+        let start = Instant::now();
+        let mut data = [0; 4096];
+        while start.elapsed() < timeout {
+            let len = match self.easy.recv(&mut data) {
+                Ok(0) => return Err(BlockError("imap".into(), "connection closed".into())),
+                Ok(len) => len,
+                // The socket doesn't block, so check again a bit later
+                Err(error) if error.is_again() => {
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
+                Err(error) => return Err(error.into()),
+            };
+            self.buffer.extend_from_slice(&data[..len]);
+            while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end();
+                match line
+                    .strip_prefix(tag)
+                    .and_then(|rest| rest.strip_prefix(' '))
+                {
+                    Some(status) if status.starts_with("OK") => return Ok(()),
+                    Some(status) => {
+                        return Err(BlockError(
+                            "imap".into(),
+                            format!("server replied {}", status),
+                        ))
+                    }
+                    None => untagged(line),
+                }
+            }
+        }
+        Err(BlockError("imap".into(), "timed out".into()))
+    }
+}
+
+/// Quotes a folder name for IMAP commands.
+fn quote(folder: &str) -> String {
+    format!("\"{}\"", folder.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The number of unseen messages of a reply to `STATUS`, e.g. `* STATUS INBOX (UNSEEN 3)`.
+fn parse_unseen(reply: &str) -> Option<u64> {
+    let line = reply.lines().find(|line| line.starts_with("* STATUS"))?;
+    let start = line.find("UNSEEN ")? + "UNSEEN ".len();
+    line[start..]
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Whether an untagged response received while idling tells that messages were added, removed
+/// or had their flags changed.
+fn is_change(line: &str) -> bool {
+    line.starts_with("* ")
+        && ["EXISTS", "EXPUNGE", "FETCH"]
+            .iter()
+            .any(|response| line.split_whitespace().nth(2) == Some(response))
+}
+
+pub struct Imap {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    hide_when_empty: bool,
+    /// Total unread count of the folders, or the error of the last check
+    unread: Arc<Mutex<Option<std::result::Result<u64, String>>>>,
+    is_empty: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ImapConfig {
+    /// Host name of the IMAP server
+    pub server: String,
+
+    /// Port of the server, by default 993 with TLS and 143 without
+    pub port: Option<u16>,
+
+    /// Connect with TLS, rather than in plain text, e.g. to a local bridge
+    #[serde(default = "ImapConfig::default_tls")]
+    pub tls: bool,
+
+    pub username: String,
+
+    pub password: Option<String>,
+
+    /// Shell command printing the password, e.g. `pass show mail`
+    pub password_cmd: Option<String>,
+
+    /// Folders whose unread messages are counted
+    #[serde(default = "ImapConfig::default_folders")]
+    pub folders: Vec<String>,
+
+    /// Wait for changes with the IDLE command, rather than only checking every `interval`
+    #[serde(default = "ImapConfig::default_idle")]
+    pub idle: bool,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "ImapConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "ImapConfig::default_format")]
+    pub format: String,
+
+    #[serde(default)]
+    pub hide_when_empty: bool,
+}
+
+impl ImapConfig {
+    fn default_tls() -> bool {
+        true
+    }
+
+    fn default_folders() -> Vec<String> {
+        vec!["INBOX".to_owned()]
+    }
+
+    fn default_idle() -> bool {
+        true
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(300)
+    }
+
+    fn default_format() -> String {
+        "{unread}".to_owned()
+    }
+}
+
+impl ConfigBlock for Imap {
+    type Config = ImapConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{unread}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let password = match (block_config.password, block_config.password_cmd) {
+            (Some(password), None) => password,
+            (None, Some(command)) => {
+                let output = Command::new("sh")
+                    .args(&["-c", command.as_str()])
+                    .output()
+                    .block_error("imap", "failed to run password_cmd")?;
+                if !output.status.success() {
+                    return Err(BlockError(
+                        "imap".to_string(),
+                        "password_cmd failed".to_string(),
+                    ));
+                }
+                // Tools such as `pass` print more lines after the password
+                String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            }
+            _ => {
+                return Err(BlockError(
+                    "imap".to_string(),
+                    "exactly one of `password` and `password_cmd` must be set".to_string(),
+                ))
+            }
+        };
+        let (scheme, default_port) = if block_config.tls {
+            ("imaps", 993)
+        } else {
+            ("imap", 143)
+        };
+        let account = Account {
+            url: format!(
+                "{}://{}:{}/",
+                scheme,
+                block_config.server,
+                block_config.port.unwrap_or(default_port)
+            ),
+            username: block_config.username,
+            password,
+        };
+
+        let unread = Arc::new(Mutex::new(None));
+        let (tx_changed, rx_changed) = crossbeam_channel::unbounded();
+
+        if block_config.idle {
+            for folder in block_config.folders.clone() {
+                let account = account.clone();
+                let tx_changed = tx_changed.clone();
+                let interval = block_config.interval;
+                thread::Builder::new()
+                    .name("imap".into())
+                    .spawn(move || loop {
+                        let changed = || {
+                            let _ = tx_changed.send(());
+                        };
+                        // Servers may not support IDLE, or drop the connection, in which case
+                        // the folder is still checked every `interval`
+                        let _ = account.idle(&folder, &changed);
+                        thread::sleep(interval);
+                    })
+                    .unwrap();
+            }
+        }
+
+        let unread_copy = unread.clone();
+        let folders = block_config.folders;
+        let interval = block_config.interval;
+        thread::Builder::new()
+            .name("imap".into())
+            .spawn(move || loop {
+                let total = folders
+                    .iter()
+                    .map(|folder| account.unread(folder))
+                    .sum::<Result<u64>>()
+                    .map_err(|error| error.to_string());
+                *unread_copy.lock().unwrap() = Some(total);
+                if send
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .is_err()
+                {
+                    return;
+                }
+
+                let _ = rx_changed.recv_timeout(interval);
+                // Changes often come in bursts, e.g. a new message is added and then flagged
+                thread::sleep(Duration::from_secs(1));
+                while rx_changed.try_recv().is_ok() {}
+            })
+            .unwrap();
+
+        Ok(Imap {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("mail"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("imap", "Invalid format specified")?,
+            hide_when_empty: block_config.hide_when_empty,
+            unread,
+            is_empty: true,
+        })
+    }
+}
+
+impl Block for Imap {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let unread = match *self.unread.lock().unwrap() {
+            Some(Ok(unread)) => unread,
+            Some(Err(ref error)) => return Err(BlockError("imap".to_string(), error.clone())),
+            None => return Ok(None),
+        };
+        self.is_empty = unread == 0;
+
+        let values = map!(
+            "{unread}" => Value::text(unread.to_string())
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text
+            .set_state(self.format.state(&values).unwrap_or(if unread > 0 {
+                State::Info
+            } else {
+                State::Idle
+            }));
+
+        // The checking thread asks for updates
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.is_empty && self.hide_when_empty {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_change, parse_unseen, quote};
+
+    #[test]
+    fn test_parse_unseen() {
+        assert_eq!(parse_unseen("* STATUS INBOX (UNSEEN 3)\r\n"), Some(3));
+        assert_eq!(
+            parse_unseen("* STATUS \"Lists/Rust\" (MESSAGES 12 UNSEEN 0)\r\n"),
+            Some(0)
+        );
+        assert_eq!(parse_unseen("* STATUS INBOX (MESSAGES 12)\r\n"), None);
+        assert_eq!(parse_unseen(""), None);
+    }
+
+    #[test]
+    fn test_is_change() {
+        assert!(is_change("* 23 EXISTS"));
+        assert!(is_change("* 5 EXPUNGE"));
+        assert!(is_change("* 14 FETCH (FLAGS (\\Seen))"));
+        assert!(!is_change("+ idling"));
+        assert!(!is_change("* OK Still here"));
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("INBOX"), "\"INBOX\"");
+        assert_eq!(quote("A \"B\""), "\"A \\\"B\\\"\"");
+    }
+}
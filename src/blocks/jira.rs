@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::subprocess::spawn_child_async;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const JIRA_TOKEN_ENV: &str = "I3RS_JIRA_TOKEN";
+
+pub struct Jira {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    server: String,
+    jql: String,
+    authorization: String,
+    format: FormatTemplate,
+    total: u64,
+    hide_if_total_is_zero: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct JiraConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "JiraConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// URL of the Jira site, e.g. `https://example.atlassian.net`
+    pub server: String,
+
+    /// Query whose issues are counted
+    #[serde(default = "JiraConfig::default_jql")]
+    pub jql: String,
+
+    /// User for basic authentication with an API token, as on Jira Cloud. The token is a
+    /// personal access token otherwise, as on Jira Server.
+    pub username: Option<String>,
+
+    /// Format override
+    #[serde(default = "JiraConfig::default_format")]
+    pub format: String,
+
+    #[serde(default)]
+    pub hide_if_total_is_zero: bool,
+}
+
+impl JiraConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(300)
+    }
+
+    fn default_jql() -> String {
+        "assignee = currentUser() AND resolution = Unresolved".to_owned()
+    }
+
+    fn default_format() -> String {
+        "{total}".to_owned()
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    total: u64,
+}
+
+impl Jira {
+    fn search(&self) -> Result<u64> {
+        // No issue is needed, only their number
+        let url = format!(
+            "{}/rest/api/2/search?maxResults=0&fields=none&jql={}",
+            self.server,
+            http::url_encode(&self.jql)
+        );
+        let response = http::http_get_json(
+            &url,
+            Some(Duration::from_secs(10)),
+            vec![("Authorization", self.authorization.as_str())],
+        )?;
+        if !(200..300).contains(&response.code) {
+            return Err(BlockError(
+                "jira".to_string(),
+                format!("the search failed with status {}", response.code),
+            ));
+        }
+        let results: SearchResults = serde_json::from_value(response.content)
+            .block_error("jira", "unexpected reply to the search")?;
+        Ok(results.total)
+    }
+}
+
+impl ConfigBlock for Jira {
+    type Config = JiraConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[("format", &["{total}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _: Sender<Task>,
+    ) -> Result<Self> {
+        let token = std::env::var(JIRA_TOKEN_ENV)
+            .block_error("jira", "missing I3RS_JIRA_TOKEN environment variable")?;
+        let authorization = match block_config.username {
+            Some(ref username) => http::basic_auth(username, &token),
+            None => format!("Bearer {}", token),
+        };
+
+        Ok(Jira {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone())
+                .with_text("x")
+                .with_icon("tasks"),
+            update_interval: block_config.interval,
+            server: block_config.server.trim_end_matches('/').to_string(),
+            jql: block_config.jql,
+            authorization,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("jira", "Invalid format specified")?,
+            total: 0,
+            hide_if_total_is_zero: block_config.hide_if_total_is_zero,
+        })
+    }
+}
+
+impl Block for Jira {
+    fn update(&mut self) -> Result<Option<Update>> {
+        self.total = self.search()?;
+        let values = map!(
+            "{total}" => self.total
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.hide_if_total_is_zero && self.total == 0 {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.button == MouseButton::Left {
+            let url = format!(
+                "{}/issues/?jql={}",
+                self.server,
+                http::url_encode(&self.jql)
+            );
+            spawn_child_async("xdg-open", &[url.as_str()])
+                .block_error("jira", "could not run xdg-open")?;
+        }
+        Ok(())
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -0,0 +1,220 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_opt_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Journal {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    window: Option<Duration>,
+    warning: usize,
+    critical: usize,
+    /// Timestamps of the entries logged since boot or the last reset, in microseconds
+    entries: Arc<Mutex<Vec<u64>>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct JournalConfig {
+    /// Format override
+    #[serde(default = "JournalConfig::default_format")]
+    pub format: String,
+
+    /// Lowest priority of the entries to count, as understood by `journalctl --priority`
+    #[serde(default = "JournalConfig::default_priority")]
+    pub priority: String,
+
+    /// Only count the entries of the user's units rather than the whole system
+    #[serde(default)]
+    pub user: bool,
+
+    /// Only count the entries logged during this many last seconds, instead of since boot
+    #[serde(default, deserialize_with = "deserialize_opt_duration")]
+    pub window: Option<Duration>,
+
+    /// Minimum number of entries, where state is set to warning
+    #[serde(default = "JournalConfig::default_warning")]
+    pub warning: usize,
+
+    /// Minimum number of entries, where state is set to critical
+    #[serde(default = "JournalConfig::default_critical")]
+    pub critical: usize,
+}
+
+impl JournalConfig {
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+
+    fn default_priority() -> String {
+        "err".to_owned()
+    }
+
+    fn default_warning() -> usize {
+        1
+    }
+
+    fn default_critical() -> usize {
+        10
+    }
+}
+
+/// The time an entry printed by `journalctl --output=json` was logged at, in microseconds since
+/// the epoch.
+fn entry_timestamp(line: &str) -> Option<u64> {
+    let entry: serde_json::Value = serde_json::from_str(line).ok()?;
+    entry.get("__REALTIME_TIMESTAMP")?.as_str()?.parse().ok()
+}
+
+fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+impl ConfigBlock for Journal {
+    type Config = JournalConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[("format", &["{count}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let mut args = vec![
+            "--follow",
+            "--boot",
+            "--lines=all",
+            "--output=json",
+            "--no-pager",
+            "--priority",
+        ];
+        args.push(&block_config.priority);
+        if block_config.user {
+            args.push("--user");
+        }
+        let stdout = Command::new("journalctl")
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .block_error("journal", "failed to run journalctl")?
+            .stdout
+            .block_error("journal", "failed to pipe the output of journalctl")?;
+
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let entries_copy = entries.clone();
+        thread::Builder::new()
+            .name("journal".into())
+            .spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    if let Some(timestamp) = entry_timestamp(&line) {
+                        entries_copy.lock().unwrap().push(timestamp);
+                        if send
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(Journal {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("journal", "Invalid format specified")?,
+            window: block_config.window,
+            warning: block_config.warning,
+            critical: block_config.critical,
+            entries,
+        })
+    }
+}
+
+impl Block for Journal {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let count = {
+            let mut entries = self.entries.lock().unwrap();
+            if let Some(window) = self.window {
+                let since = now_micros().saturating_sub(window.as_micros() as u64);
+                entries.retain(|&timestamp| timestamp >= since);
+            }
+            entries.len()
+        };
+
+        let values = map!("{count}" => count);
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(match count {
+            x if x >= self.critical => State::Critical,
+            x if x >= self.warning => State::Warning,
+            _ => State::Idle,
+        });
+
+        // Entries leave the window without the journal telling about it
+        Ok(self
+            .window
+            .map(|window| (window / 60).max(Duration::from_secs(1)).into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button == MouseButton::Left {
+            self.entries.lock().unwrap().clear();
+            self.update()?;
+        }
+
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entry_timestamp;
+
+    #[test]
+    fn test_entry_timestamp() {
+        let line = r#"{"__REALTIME_TIMESTAMP":"1700000000123456","PRIORITY":"3","MESSAGE":"Failed to start foo.service."}"#;
+        assert_eq!(entry_timestamp(line), Some(1_700_000_000_123_456));
+        assert_eq!(entry_timestamp(r#"{"MESSAGE":"no timestamp"}"#), None);
+        assert_eq!(entry_timestamp("not json"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -101,6 +102,33 @@ impl KDEConnectConfig {
 impl ConfigBlock for KDEConnect {
     type Config = KDEConnectConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        (
+            "format",
+            &[
+                "{bat_charge}",
+                "{bat_icon}",
+                "{bat_state}",
+                "{id}",
+                "{name}",
+                "{notif_count}",
+                "{notif_icon}",
+            ],
+        ),
+        (
+            "format_disconnected",
+            &[
+                "{bat_charge}",
+                "{bat_icon}",
+                "{bat_state}",
+                "{id}",
+                "{name}",
+                "{notif_count}",
+                "{notif_icon}",
+            ],
+        ),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -113,6 +141,15 @@ impl ConfigBlock for KDEConnect {
         let send5 = send.clone();
         let send6 = send.clone();
         let send7 = send.clone();
+        // Set once the bar dropped the block, e.g. after reloading the config
+        let dropped = Arc::new(AtomicBool::new(false));
+        let dropped1 = dropped.clone();
+        let dropped2 = dropped.clone();
+        let dropped3 = dropped.clone();
+        let dropped4 = dropped.clone();
+        let dropped5 = dropped.clone();
+        let dropped6 = dropped.clone();
+        let dropped7 = dropped.clone();
 
         let c = Connection::new_session().block_error(
             "kdeconnect",
@@ -282,12 +319,15 @@ impl ConfigBlock for KDEConnect {
                         *name = s.name;
 
                         // Tell block to update now.
-                        send2
+                        if send2
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                        {
+                            dropped2.store(true, Ordering::Relaxed);
+                        }
 
                         true
                     },
@@ -306,12 +346,15 @@ impl ConfigBlock for KDEConnect {
                         // not they both changed. So we only need to send updates
                         // in one of the two battery signal handlers. Hopefully
                         // one day they add proper PropertiesChanged signals.
-                        send6
+                        if send6
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                        {
+                            dropped6.store(true, Ordering::Relaxed);
+                        }
 
                         true
                     },
@@ -331,11 +374,15 @@ impl ConfigBlock for KDEConnect {
                             // not they both changed. So we only need to send updates
                             // in one of the two battery signal handlers. Hopefully
                             // one day they add proper PropertiesChanged signals.
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            if send
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                            {
+                                dropped1.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -367,11 +414,15 @@ impl ConfigBlock for KDEConnect {
                             let mut charge = charge_copy.lock().unwrap();
                             *charge = s.charge;
 
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            if send
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                            {
+                                dropped1.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -387,12 +438,15 @@ impl ConfigBlock for KDEConnect {
                             *notif_count += 1;
 
                             // Tell block to update now.
-                            send3
+                            if send3
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                dropped3.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -410,12 +464,15 @@ impl ConfigBlock for KDEConnect {
                             };
 
                             // Tell block to update now.
-                            send4
+                            if send4
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                dropped4.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -429,12 +486,15 @@ impl ConfigBlock for KDEConnect {
                             *notif_count = 0;
 
                             // Tell block to update now.
-                            send5
+                            if send5
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                dropped5.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -457,12 +517,15 @@ impl ConfigBlock for KDEConnect {
                             *notif_count += 1;
 
                             // Tell block to update now.
-                            send3
+                            if send3
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                dropped3.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -480,12 +543,15 @@ impl ConfigBlock for KDEConnect {
                             };
 
                             // Tell block to update now.
-                            send4
+                            if send4
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                dropped4.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -499,12 +565,15 @@ impl ConfigBlock for KDEConnect {
                             *notif_count = 0;
 
                             // Tell block to update now.
-                            send5
+                            if send5
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                dropped5.store(true, Ordering::Relaxed);
+                            }
 
                             true
                         },
@@ -529,18 +598,21 @@ impl ConfigBlock for KDEConnect {
                         *reachable = s.is_visible;
 
                         // Tell block to update now.
-                        send7
+                        if send7
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                        {
+                            dropped7.store(true, Ordering::Relaxed);
+                        }
 
                         true
                     },
                 );
 
-                loop {
+                while !dropped.load(Ordering::Relaxed) {
                     c.process(Duration::from_millis(1000)).unwrap();
                 }
             })
@@ -560,8 +632,11 @@ impl ConfigBlock for KDEConnect {
             bat_info: block_config.bat_info,
             bat_warning: block_config.bat_warning,
             bat_critical: block_config.bat_critical,
-            format: FormatTemplate::from_string(&block_config.format)?,
-            format_disconnected: FormatTemplate::from_string(&block_config.format_disconnected)?,
+            format: FormatTemplate::new(&block_config.format, &shared_config)?,
+            format_disconnected: FormatTemplate::new(
+                &block_config.format_disconnected,
+                &shared_config,
+            )?,
             output: TextWidget::new(id, 0, shared_config.clone()).with_icon("phone"),
             shared_config,
         })
@@ -569,6 +644,10 @@ impl ConfigBlock for KDEConnect {
 }
 
 impl Block for KDEConnect {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -0,0 +1,158 @@
+use std::fs::{read_dir, read_to_string};
+use std::path::Path;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Directories holding the modules of each installed kernel, named after its release
+const MODULES_PATHS: &[&str] = &["/usr/lib/modules", "/lib/modules"];
+
+/// Created by Debian and Ubuntu when an upgrade needs a reboot
+const REBOOT_REQUIRED_PATH: &str = "/var/run/reboot-required";
+
+pub struct Kernel {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    reboot_text: String,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KernelConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "KernelConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "KernelConfig::default_format")]
+    pub format: String,
+
+    /// Text of the `{reboot}` placeholder when a reboot is required
+    #[serde(default = "KernelConfig::default_reboot_text")]
+    pub reboot_text: String,
+}
+
+impl KernelConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_format() -> String {
+        "{running}{reboot}".to_owned()
+    }
+
+    fn default_reboot_text() -> String {
+        " reboot required".to_owned()
+    }
+}
+
+/// Splits a kernel release like `5.15.0-91-generic` into its numbers, to compare releases.
+fn release_numbers(release: &str) -> Vec<u64> {
+    release
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+/// The most recent release among the kernels whose modules are installed.
+fn latest_installed() -> Option<String> {
+    let modules = MODULES_PATHS.iter().find_map(|path| read_dir(path).ok())?;
+    modules
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .max_by_key(|release| release_numbers(release))
+}
+
+impl ConfigBlock for Kernel {
+    type Config = KernelConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{latest}", "{reboot}", "{running}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Kernel {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("kernel", "Invalid format specified")?,
+            reboot_text: block_config.reboot_text,
+        })
+    }
+}
+
+impl Block for Kernel {
+    fn update(&mut self) -> Result<Option<Update>> {
+        // Same as `uname -r`
+        let running = read_to_string("/proc/sys/kernel/osrelease")
+            .block_error("kernel", "failed to read the kernel release")?
+            .trim()
+            .to_string();
+        let latest = latest_installed().unwrap_or_else(|| running.clone());
+
+        // Some distributions, e.g. Arch Linux, replace the modules of the running kernel when
+        // upgrading it, without keeping the previous release around
+        let running_installed = MODULES_PATHS
+            .iter()
+            .any(|path| Path::new(path).join(&running).exists());
+        let reboot = Path::new(REBOOT_REQUIRED_PATH).exists()
+            || !running_installed
+            || release_numbers(&latest) > release_numbers(&running);
+
+        let values = map!(
+            "{running}" => running,
+            "{latest}" => latest,
+            "{reboot}" => if reboot { self.reboot_text.clone() } else { String::new() }
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text
+            .set_state(if reboot { State::Warning } else { State::Idle });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::release_numbers;
+
+    #[test]
+    fn test_release_numbers() {
+        assert_eq!(release_numbers("5.15.0-91-generic"), vec![5, 15, 0, 91]);
+        assert!(release_numbers("5.15.0-101-generic") > release_numbers("5.15.0-91-generic"));
+        assert!(release_numbers("6.6.10-arch1-1") > release_numbers("6.6.9-arch1-1"));
+        assert!(release_numbers("6.1.0-17-amd64") < release_numbers("6.5.0-0.deb12.4-amd64"));
+    }
+}
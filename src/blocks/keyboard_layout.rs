@@ -12,7 +12,7 @@ use dbus::{
 };
 use serde_derive::Deserialize;
 use swayipc::reply::Event;
-use swayipc::reply::InputChange;
+use swayipc::reply::{Input, InputChange, WindowChange};
 use swayipc::{Connection, EventType};
 
 use crate::blocks::{Block, ConfigBlock, Update};
@@ -160,13 +160,15 @@ impl KeyboardLayoutMonitor for LocaleBus {
                 loop {
                     // TODO: This actually seems to trigger twice for each localectl
                     // change.
-                    if con.incoming(10_000).next().is_some() {
-                        update_request
+                    if con.incoming(10_000).next().is_some()
+                        && update_request
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                    {
+                        return;
                     }
                 }
             })
@@ -277,12 +279,15 @@ impl KeyboardLayoutMonitor for KbdDaemonBus {
                 loop {
                     for ci in c.iter(100_000) {
                         if let dbus::ffidisp::ConnectionItem::Signal(_) = ci {
-                            update_request
+                            if update_request
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .unwrap();
+                                .is_err()
+                            {
+                                return;
+                            }
                         }
                     }
                 }
@@ -314,27 +319,34 @@ impl dbus::ffidisp::MsgHandler for KbddMessageHandler {
 }
 
 pub struct Sway {
+    sway_kb_identifier: String,
     sway_kb_layout: Arc<Mutex<String>>,
+    per_window: bool,
 }
 
 impl Sway {
-    pub fn new(sway_kb_identifier: String) -> Result<Self> {
+    pub fn new(sway_kb_identifier: String, per_window: bool) -> Result<Self> {
         let layout = swayipc::Connection::new()
             .unwrap()
             .get_inputs()
             .unwrap()
             .into_iter()
-            .find(|input| {
-                (sway_kb_identifier.is_empty() || input.identifier == sway_kb_identifier)
-                    && input.input_type == "keyboard"
-            })
+            .find(|input| Sway::is_monitored(&sway_kb_identifier, input))
             .and_then(|input| input.xkb_active_layout_name)
             .block_error("sway", "Failed to get xkb_active_layout_name.")?;
 
         Ok(Sway {
+            sway_kb_identifier,
             sway_kb_layout: Arc::new(Mutex::new(layout)),
+            per_window,
         })
     }
+
+    /// Whether `input` is the keyboard to monitor, any keyboard without an identifier.
+    fn is_monitored(sway_kb_identifier: &str, input: &Input) -> bool {
+        (sway_kb_identifier.is_empty() || input.identifier == sway_kb_identifier)
+            && input.input_type == "keyboard"
+    }
 }
 
 impl KeyboardLayoutMonitor for Sway {
@@ -368,42 +380,80 @@ impl KeyboardLayoutMonitor for Sway {
     }
 
     /// Monitor layout changes in a separate thread and send updates
-    /// via the `update_request` channel.
+    /// via the `update_request` channel. With `per_window`, the layout
+    /// of each window is restored when it gets the focus back.
     fn monitor(&self, id: usize, update_request: Sender<Task>) {
         let arc = Arc::clone(&self.sway_kb_layout);
+        let identifier = self.sway_kb_identifier.clone();
+        let per_window = self.per_window;
         thread::Builder::new()
             .name("keyboard_layout".into())
             .spawn(move || {
-                for event in Connection::new()
-                    .unwrap()
-                    .subscribe(&[EventType::Input])
-                    .unwrap()
-                {
+                let target = if identifier.is_empty() {
+                    "type:keyboard".to_string()
+                } else {
+                    format!("\"{}\"", identifier)
+                };
+                let mut commands = Connection::new().unwrap();
+                // Layout index of each window, and of the focused one
+                let mut window_layouts: HashMap<i64, u32> = HashMap::new();
+                let mut focused: Option<i64> = None;
+                let mut active_index: Option<u32> = None;
+
+                let events: &[EventType] = if per_window {
+                    &[EventType::Input, EventType::Window]
+                } else {
+                    &[EventType::Input]
+                };
+                for event in Connection::new().unwrap().subscribe(events).unwrap() {
                     match event.unwrap() {
                         Event::Input(e) => match e.change {
-                            InputChange::XkbLayout => {
+                            InputChange::XkbLayout | InputChange::XkbKeymap => {
+                                if !Sway::is_monitored(&identifier, &e.input) {
+                                    continue;
+                                }
                                 if let Some(name) = e.input.xkb_active_layout_name {
                                     let mut layout = arc.lock().unwrap();
                                     *layout = name;
                                 }
-                                update_request
+                                active_index = e.input.xkb_active_layout_name_index;
+                                if let (Some(window), Some(index)) = (focused, active_index) {
+                                    window_layouts.insert(window, index);
+                                }
+                                if update_request
                                     .send(Task {
                                         id,
                                         update_time: Instant::now(),
                                     })
-                                    .unwrap();
+                                    .is_err()
+                                {
+                                    return;
+                                }
                             }
-                            InputChange::XkbKeymap => {
-                                if let Some(name) = e.input.xkb_active_layout_name {
-                                    let mut layout = arc.lock().unwrap();
-                                    *layout = name;
+                            _ => {}
+                        },
+                        Event::Window(e) => match e.change {
+                            WindowChange::Focus => {
+                                focused = Some(e.container.id);
+                                match window_layouts.get(&e.container.id) {
+                                    // Switching sends an input event, which updates the block
+                                    Some(&index) if Some(index) != active_index => {
+                                        let _ = commands.run_command(format!(
+                                            "input {} xkb_switch_layout {}",
+                                            target, index
+                                        ));
+                                    }
+                                    Some(_) => {}
+                                    // New windows keep the current layout
+                                    None => {
+                                        if let Some(index) = active_index {
+                                            window_layouts.insert(e.container.id, index);
+                                        }
+                                    }
                                 }
-                                update_request
-                                    .send(Task {
-                                        id,
-                                        update_time: Instant::now(),
-                                    })
-                                    .unwrap();
+                            }
+                            WindowChange::Close => {
+                                window_layouts.remove(&e.container.id);
                             }
                             _ => {}
                         },
@@ -430,6 +480,9 @@ pub struct KeyboardLayoutConfig {
 
     sway_kb_identifier: String,
 
+    /// Restore the layout of each window when it gets the focus back
+    sway_per_window: bool,
+
     // Used to ovrreride long layout names: "German (dead acute)" => "DE"
     mappings: HashMap<String, String>,
 }
@@ -456,6 +509,9 @@ pub struct KeyboardLayout {
 impl ConfigBlock for KeyboardLayout {
     type Config = KeyboardLayoutConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{layout}", "{variant}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -475,7 +531,10 @@ impl ConfigBlock for KeyboardLayout {
                 Box::new(monitor)
             }
             KeyboardLayoutDriver::Sway => {
-                let monitor = Sway::new(block_config.sway_kb_identifier)?;
+                let monitor = Sway::new(
+                    block_config.sway_kb_identifier,
+                    block_config.sway_per_window,
+                )?;
                 monitor.monitor(id, send);
                 Box::new(monitor)
             }
@@ -485,13 +544,13 @@ impl ConfigBlock for KeyboardLayout {
         } else {
             None
         };
-        let output = TextWidget::new(id, 0, shared_config);
+        let output = TextWidget::new(id, 0, shared_config.clone());
         Ok(KeyboardLayout {
             id,
             output,
             monitor,
             update_interval,
-            format: FormatTemplate::from_string(&block_config.format).block_error(
+            format: FormatTemplate::new(&block_config.format, &shared_config).block_error(
                 "keyboard_layout",
                 "Invalid format specified for keyboard_layout",
             )?,
@@ -501,6 +560,10 @@ impl ConfigBlock for KeyboardLayout {
 }
 
 impl Block for KeyboardLayout {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -508,7 +571,11 @@ impl Block for KeyboardLayout {
     fn update(&mut self) -> Result<Option<Update>> {
         let mut layout = self.monitor.keyboard_layout()?;
         let variant = self.monitor.keyboard_variant()?;
-        if let Some(mapped) = self.mappings.get(&format!("{} ({})", layout, variant)) {
+        if let Some(mapped) = self
+            .mappings
+            .get(&format!("{} ({})", layout, variant))
+            .or_else(|| self.mappings.get(&layout))
+        {
             layout = mapped.to_string();
         }
         let values = map!(
@@ -0,0 +1,359 @@
+use std::env;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Kubernetes {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    kubeconfig: PathBuf,
+    namespace: Option<String>,
+    pods: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct KubernetesConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "KubernetesConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "KubernetesConfig::default_format")]
+    pub format: String,
+
+    /// Path of the kubeconfig, by default the first one of `$KUBECONFIG` or `~/.kube/config`
+    pub kubeconfig: Option<PathBuf>,
+
+    /// Namespace whose pods are checked, instead of the one of the current context
+    pub namespace: Option<String>,
+
+    /// Check the pods of the namespace with `kubectl`
+    #[serde(default)]
+    pub pods: bool,
+}
+
+impl KubernetesConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_format() -> String {
+        "{context}:{namespace}".to_owned()
+    }
+
+    fn default_kubeconfig() -> PathBuf {
+        match env::var("KUBECONFIG") {
+            // Like `kubectl`, only the first file may set the current context
+            Ok(paths) if !paths.is_empty() => {
+                PathBuf::from(paths.split(':').next().unwrap_or_default())
+            }
+            _ => PathBuf::from(env::var("HOME").unwrap_or_default()).join(".kube/config"),
+        }
+    }
+}
+
+/// A scalar value of YAML, without its quotes.
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+        .unwrap_or(value)
+}
+
+/// The current context of a kubeconfig and its namespace, if it sets one. Only YAML's block
+/// style is understood, which is what `kubectl config` writes.
+fn parse_kubeconfig(config: &str) -> Option<(String, Option<String>)> {
+    let current = config
+        .lines()
+        .find_map(|line| line.strip_prefix("current-context:"))
+        .map(unquote)
+        .filter(|current| !current.is_empty())?;
+
+    // Each context is an item of the list `contexts`, with its `name` and `context.namespace`
+    let mut contexts = Vec::new();
+    let mut in_contexts = false;
+    for line in config.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(|c: char| c.is_whitespace() || c == '-') {
+            in_contexts = line.trim_end() == "contexts:";
+            continue;
+        }
+        if !in_contexts {
+            continue;
+        }
+        let mut entry = line.trim_start();
+        if let Some(item) = entry.strip_prefix('-') {
+            contexts.push((None, None));
+            entry = item.trim_start();
+        }
+        if let Some((name, namespace)) = contexts.last_mut() {
+            if let Some(value) = entry.strip_prefix("name:") {
+                *name = Some(unquote(value));
+            } else if let Some(value) = entry.strip_prefix("namespace:") {
+                *namespace = Some(unquote(value).to_string());
+            }
+        }
+    }
+
+    let namespace = contexts
+        .into_iter()
+        .find(|(name, _)| *name == Some(current))
+        .and_then(|(_, namespace)| namespace);
+    Some((current.to_string(), namespace))
+}
+
+/// Pods which aren't ready, out of a list of pods of the Kubernetes API.
+#[derive(Debug, Default, PartialEq)]
+struct PodCounts {
+    not_ready: usize,
+    /// Pods restarting a failing container over and over
+    crashlooping: usize,
+}
+
+fn parse_pods(json: &Value) -> Option<PodCounts> {
+    let mut counts = PodCounts::default();
+    for pod in json["items"].as_array()? {
+        let phase = pod["status"]["phase"].as_str().unwrap_or_default();
+        // Completed jobs are done rather than not ready
+        if phase == "Succeeded" {
+            continue;
+        }
+        let containers = pod["status"]["containerStatuses"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if phase != "Running"
+            || containers
+                .iter()
+                .any(|container| container["ready"] != Value::Bool(true))
+        {
+            counts.not_ready += 1;
+        }
+        if containers.iter().any(|container| {
+            container["state"]["waiting"]["reason"].as_str() == Some("CrashLoopBackOff")
+        }) {
+            counts.crashlooping += 1;
+        }
+    }
+    Some(counts)
+}
+
+/// Lists the pods with `kubectl`, which knows every way to authenticate to the API server.
+fn get_pods(context: &str, namespace: &str) -> Result<PodCounts> {
+    let output = Command::new("kubectl")
+        .args(&[
+            "--context",
+            context,
+            "--namespace",
+            namespace,
+            "--request-timeout",
+            "10s",
+            "get",
+            "pods",
+            "--output",
+            "json",
+        ])
+        .output()
+        .block_error("kubernetes", "Failed to run `kubectl get pods`")?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "kubernetes".to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let json: Value = serde_json::from_slice(&output.stdout)
+        .block_error("kubernetes", "unexpected output of `kubectl get pods`")?;
+    parse_pods(&json).block_error("kubernetes", "unexpected output of `kubectl get pods`")
+}
+
+impl ConfigBlock for Kubernetes {
+    type Config = KubernetesConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &["{context}", "{crashlooping}", "{namespace}", "{not_ready}"],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Kubernetes {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("kubernetes"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("kubernetes", "Invalid format specified")?,
+            kubeconfig: block_config
+                .kubeconfig
+                .unwrap_or_else(KubernetesConfig::default_kubeconfig),
+            namespace: block_config.namespace,
+            pods: block_config.pods,
+        })
+    }
+}
+
+impl Block for Kubernetes {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let current = read_to_string(&self.kubeconfig)
+            .ok()
+            .and_then(|config| parse_kubeconfig(&config));
+        let (context, namespace) = match current {
+            Some((context, namespace)) => (context, namespace),
+            None => {
+                self.text.set_text("N/A".to_string());
+                self.text.set_state(State::Idle);
+                return Ok(Some(self.update_interval.into()));
+            }
+        };
+        let namespace = self
+            .namespace
+            .clone()
+            .or(namespace)
+            .unwrap_or_else(|| "default".to_string());
+
+        // An unreachable cluster, e.g. behind a VPN which is down, only shows as unknown counts
+        let pods = if self.pods {
+            Some(get_pods(&context, &namespace).ok())
+        } else {
+            None
+        };
+        let count = |count: fn(&PodCounts) -> usize| match &pods {
+            Some(Some(pods)) => count(pods).to_string(),
+            Some(None) => "?".to_string(),
+            None => String::new(),
+        };
+
+        let values = map!(
+            "{context}" => context.clone(),
+            "{namespace}" => namespace.clone(),
+            "{not_ready}" => count(|pods| pods.not_ready),
+            "{crashlooping}" => count(|pods| pods.crashlooping)
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(match pods {
+            Some(Some(pods)) if pods.crashlooping > 0 => State::Critical,
+            Some(Some(pods)) if pods.not_ready > 0 => State::Warning,
+            Some(None) => State::Warning,
+            _ => State::Idle,
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{parse_kubeconfig, parse_pods, PodCounts};
+
+    #[test]
+    fn test_parse_kubeconfig() {
+        let config = r#"apiVersion: v1
+clusters:
+- cluster:
+    server: https://prod.example.com:6443
+  name: prod
+contexts:
+- context:
+    cluster: prod
+    user: admin
+  name: prod-admin
+- context:
+    cluster: prod
+    namespace: web
+    user: dev
+  name: "prod-dev"
+current-context: prod-dev
+kind: Config
+users:
+- name: dev
+  user:
+    token: secret
+"#;
+        assert_eq!(
+            parse_kubeconfig(config),
+            Some(("prod-dev".to_string(), Some("web".to_string())))
+        );
+        assert_eq!(
+            parse_kubeconfig(
+                &config.replace("current-context: prod-dev", "current-context: prod-admin")
+            ),
+            Some(("prod-admin".to_string(), None))
+        );
+        assert_eq!(
+            parse_kubeconfig(&config.replace("current-context: prod-dev", "current-context: \"\"")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_pods() {
+        let json = json!({
+            "items": [
+                {"status": {"phase": "Running", "containerStatuses": [
+                    {"ready": true, "state": {"running": {}}}
+                ]}},
+                {"status": {"phase": "Running", "containerStatuses": [
+                    {"ready": true, "state": {"running": {}}},
+                    {"ready": false, "state": {"waiting": {"reason": "CrashLoopBackOff"}}}
+                ]}},
+                {"status": {"phase": "Pending"}},
+                {"status": {"phase": "Succeeded", "containerStatuses": [
+                    {"ready": false, "state": {"terminated": {"reason": "Completed"}}}
+                ]}}
+            ]
+        });
+        assert_eq!(
+            parse_pods(&json),
+            Some(PodCounts {
+                not_ready: 2,
+                crashlooping: 1
+            })
+        );
+        assert_eq!(parse_pods(&json!({})), None);
+    }
+}
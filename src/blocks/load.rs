@@ -74,13 +74,16 @@ impl LoadConfig {
 impl ConfigBlock for Load {
     type Config = LoadConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{15m}", "{1m}", "{5m}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
-        let text = TextWidget::new(id, 0, shared_config)
+        let text = TextWidget::new(id, 0, shared_config.clone())
             .with_icon("cogs")
             .with_state(State::Info);
 
@@ -99,7 +102,7 @@ impl ConfigBlock for Load {
             minimum_info: block_config.info,
             minimum_warning: block_config.warning,
             minimum_critical: block_config.critical,
-            format: FormatTemplate::from_string(&block_config.format)
+            format: FormatTemplate::new(&block_config.format, &shared_config)
                 .block_error("load", "Invalid format specified for load")?,
             text,
         })
@@ -107,6 +110,10 @@ impl ConfigBlock for Load {
 }
 
 impl Block for Load {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         let mut f = OpenOptions::new()
             .read(true)
@@ -138,6 +145,9 @@ impl Block for Load {
         });
 
         self.text.set_text(self.format.render_static_str(&values)?);
+        if let Some(state) = self.format.state_static_str(&values) {
+            self.text.set_state(state);
+        }
 
         Ok(Some(self.update_interval.into()))
     }
@@ -0,0 +1,171 @@
+use std::fs::{read_dir, read_to_string};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+const LEDS_PATH: &str = "/sys/class/leds";
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LockKey {
+    CapsLock,
+    NumLock,
+    ScrollLock,
+}
+
+impl LockKey {
+    /// The function of the LEDs of this key, which ends their names, e.g. `input3::capslock`.
+    fn led_function(self) -> &'static str {
+        match self {
+            LockKey::CapsLock => "capslock",
+            LockKey::NumLock => "numlock",
+            LockKey::ScrollLock => "scrolllock",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LockKey::CapsLock => "CAPS",
+            LockKey::NumLock => "NUM",
+            LockKey::ScrollLock => "SCROLL",
+        }
+    }
+}
+
+struct Lock {
+    key: LockKey,
+    text: TextWidget,
+    active: bool,
+}
+
+pub struct LockKeys {
+    id: usize,
+    locks: Vec<Lock>,
+    update_interval: Duration,
+    hide_when_inactive: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LockKeysConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "LockKeysConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Keys to show, in this order
+    #[serde(default = "LockKeysConfig::default_keys")]
+    pub keys: Vec<LockKey>,
+
+    /// Only show the keys which are locked
+    #[serde(default)]
+    pub hide_when_inactive: bool,
+}
+
+impl LockKeysConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn default_keys() -> Vec<LockKey> {
+        vec![LockKey::CapsLock, LockKey::NumLock]
+    }
+}
+
+/// Whether the LED named `led` belongs to `key`.
+fn is_led_of(led: &str, key: LockKey) -> bool {
+    led.rsplit("::").next() == Some(key.led_function())
+}
+
+/// Whether any keyboard shows `key` as locked. Keyboards share the lock states, but a keyboard
+/// may have no LED for a key.
+fn is_locked(key: LockKey) -> Result<bool> {
+    let leds = read_dir(LEDS_PATH).block_error("lock_keys", "failed to read /sys/class/leds")?;
+    Ok(leds.flatten().any(|led| {
+        is_led_of(&led.file_name().to_string_lossy(), key)
+            && read_to_string(led.path().join("brightness"))
+                .map(|brightness| brightness.trim() != "0")
+                .unwrap_or(false)
+    }))
+}
+
+impl ConfigBlock for LockKeys {
+    type Config = LockKeysConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let locks = block_config
+            .keys
+            .iter()
+            .enumerate()
+            .map(|(i, &key)| Lock {
+                key,
+                text: TextWidget::new(id, i, shared_config.clone()).with_text(key.label()),
+                active: false,
+            })
+            .collect();
+
+        Ok(LockKeys {
+            id,
+            locks,
+            update_interval: block_config.interval,
+            hide_when_inactive: block_config.hide_when_inactive,
+        })
+    }
+}
+
+impl Block for LockKeys {
+    fn update(&mut self) -> Result<Option<Update>> {
+        for lock in &mut self.locks {
+            lock.active = is_locked(lock.key)?;
+            lock.text.set_state(if lock.active {
+                State::Warning
+            } else {
+                State::Idle
+            });
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.locks
+            .iter()
+            .filter(|lock| lock.active || !self.hide_when_inactive)
+            .map(|lock| &lock.text as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_led_of, LockKey};
+
+    #[test]
+    fn test_is_led_of() {
+        assert!(is_led_of("input3::capslock", LockKey::CapsLock));
+        assert!(is_led_of("input12::numlock", LockKey::NumLock));
+        assert!(!is_led_of("input3::capslock", LockKey::NumLock));
+        assert!(!is_led_of("input3::scrolllock", LockKey::CapsLock));
+        assert!(!is_led_of("tpacpi::power", LockKey::ScrollLock));
+    }
+}
@@ -0,0 +1,220 @@
+use std::fs::read_to_string;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Mdraid {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    device: String,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MdraidConfig {
+    /// Name of the array, e.g. `md0`
+    pub device: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "MdraidConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "MdraidConfig::default_format")]
+    pub format: String,
+}
+
+impl MdraidConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_format() -> String {
+        "{device} {status} {progress}".to_owned()
+    }
+}
+
+/// An array, as described in `/proc/mdstat`.
+#[derive(Debug, PartialEq)]
+struct MdArray {
+    /// Number of devices in the array
+    total: usize,
+    /// Number of working devices
+    working: usize,
+    /// State of each device, `U` if it is up and `_` if it is down
+    status: String,
+    /// The running resync, recovery, reshape or check, and its progress in percent
+    action: Option<(String, f64)>,
+}
+
+impl MdArray {
+    fn degraded(&self) -> bool {
+        self.working < self.total || self.status.contains('_')
+    }
+}
+
+/// Finds the array `device` in the contents of `/proc/mdstat`.
+fn parse_mdstat(mdstat: &str, device: &str) -> Option<MdArray> {
+    let mut lines = mdstat
+        .lines()
+        .skip_while(|line| line.split_whitespace().next() != Some(device))
+        .skip(1)
+        .take_while(|line| line.starts_with(char::is_whitespace));
+
+    // The line with the number of blocks ends with e.g. `[3/2] [UU_]`
+    let mut words = lines.next()?.split_whitespace().rev();
+    let status = words.next()?.trim_matches(|c| c == '[' || c == ']');
+    let mut counts = words
+        .next()?
+        .trim_matches(|c| c == '[' || c == ']')
+        .split('/');
+    let total = counts.next()?.parse().ok()?;
+    let working = counts.next()?.parse().ok()?;
+
+    // e.g. `[=>...................]  recovery =  8.4% (41164032/488254464) finish=106.9min`
+    let action = lines.find_map(|line| {
+        let mut words = line
+            .split_whitespace()
+            .skip_while(|word| word.starts_with('['));
+        let action = words.next()?;
+        if words.next()? != "=" {
+            return None;
+        }
+        let progress = words.next()?.strip_suffix('%')?.parse().ok()?;
+        Some((action.to_string(), progress))
+    });
+
+    Some(MdArray {
+        total,
+        working,
+        status: status.to_string(),
+        action,
+    })
+}
+
+impl ConfigBlock for Mdraid {
+    type Config = MdraidConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{action}",
+            "{devices}",
+            "{device}",
+            "{progress}",
+            "{status}",
+        ],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Mdraid {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            update_interval: block_config.interval,
+            device: block_config.device,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("mdraid", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for Mdraid {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mdstat =
+            read_to_string("/proc/mdstat").block_error("mdraid", "failed to read /proc/mdstat")?;
+        let array = parse_mdstat(&mdstat, &self.device).block_error(
+            "mdraid",
+            &format!("array {} not found in /proc/mdstat", self.device),
+        )?;
+
+        let (action, progress) = match array.action {
+            Some((ref action, progress)) => (action.clone(), format!("{:.1}%", progress)),
+            None => (String::new(), String::new()),
+        };
+        let values = map!(
+            "{device}" => self.device.clone(),
+            "{status}" => array.status.clone(),
+            "{devices}" => format!("{}/{}", array.working, array.total),
+            "{action}" => action,
+            "{progress}" => progress
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(if array.degraded() {
+            State::Critical
+        } else if array.action.is_some() {
+            State::Info
+        } else {
+            State::Idle
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_mdstat, MdArray};
+
+    const MDSTAT: &str = "Personalities : [raid1] [raid6] [raid5] [raid4]
+md0 : active raid1 sdb1[1] sda1[0]
+      1953382464 blocks super 1.2 [2/2] [UU]
+      bitmap: 0/15 pages [0KB], 65536KB chunk
+
+md1 : active raid5 sdc1[3] sdd1[1] sde1[0]
+      976508928 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/2] [UU_]
+      [=>...................]  recovery =  8.4% (41164032/488254464) finish=106.9min speed=69700K/sec
+
+unused devices: <none>
+";
+
+    #[test]
+    fn test_parse_mdstat() {
+        assert_eq!(
+            parse_mdstat(MDSTAT, "md0"),
+            Some(MdArray {
+                total: 2,
+                working: 2,
+                status: "UU".to_string(),
+                action: None,
+            })
+        );
+
+        let md1 = parse_mdstat(MDSTAT, "md1").unwrap();
+        assert!(md1.degraded());
+        assert_eq!(md1.action, Some(("recovery".to_string(), 8.4)));
+
+        assert_eq!(parse_mdstat(MDSTAT, "md2"), None);
+    }
+}
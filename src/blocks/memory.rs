@@ -1,5 +1,5 @@
 use std::fmt;
-use std::fs::File;
+use std::fs::{read_dir, read_to_string, File};
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 use std::time::{Duration, Instant};
@@ -153,6 +153,57 @@ impl Memstate {
     }
 }
 
+/// Sizes summed over all zram devices, in KiB
+#[derive(Clone, Copy, Debug, Default)]
+struct Zramstate {
+    /// Size of the data stored, before compression
+    orig_data_size: u64,
+    /// Size of the data stored, after compression
+    compr_data_size: u64,
+    /// Memory allocated by zram, including fragmentation and metadata
+    mem_used_total: u64,
+}
+
+impl Zramstate {
+    /// Reads `/sys/block/zram*/mm_stat`, whose first fields are the sizes in bytes. Without zram,
+    /// all sizes are zero.
+    fn read() -> Self {
+        let mut state = Zramstate::default();
+        let devices = match read_dir("/sys/block") {
+            Ok(devices) => devices,
+            Err(_) => return state,
+        };
+        for device in devices.flatten() {
+            if !device.file_name().to_string_lossy().starts_with("zram") {
+                continue;
+            }
+            let mm_stat = match read_to_string(device.path().join("mm_stat")) {
+                Ok(mm_stat) => mm_stat,
+                Err(_) => continue,
+            };
+            let sizes: Vec<u64> = mm_stat
+                .split_whitespace()
+                .take(3)
+                .filter_map(|size| size.parse().ok())
+                .collect();
+            if let [orig_data_size, compr_data_size, mem_used_total] = sizes[..] {
+                state.orig_data_size += orig_data_size / 1024;
+                state.compr_data_size += compr_data_size / 1024;
+                state.mem_used_total += mem_used_total / 1024;
+            }
+        }
+        state
+    }
+
+    fn ratio(&self) -> f32 {
+        if self.compr_data_size == 0 {
+            0.
+        } else {
+            self.orig_data_size as f32 / self.compr_data_size as f32
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Memory {
     id: usize,
@@ -255,7 +306,7 @@ impl MemoryConfig {
 }
 
 impl Memory {
-    fn format_insert_values(&mut self, mem_state: Memstate) -> Result<String> {
+    fn format_insert_values(&mut self, mem_state: Memstate, zram: Zramstate) -> Result<String> {
         let mem_total = Unit::KiB(mem_state.mem_total());
         let mem_free = Unit::KiB(mem_state.mem_free());
         let swap_total = Unit::KiB(mem_state.swap_total());
@@ -266,6 +317,9 @@ impl Memory {
         let cached = Unit::KiB(mem_state.cached() + mem_state.s_reclaimable() - mem_state.shmem());
         let mem_used = Unit::KiB(mem_total_used.n() - (buffers.n() + cached.n()));
         let mem_avail = Unit::KiB(mem_total.n() - mem_used.n());
+        let zram_orig = Unit::KiB(zram.orig_data_size);
+        let zram_compr = Unit::KiB(zram.compr_data_size);
+        let zram_used = Unit::KiB(zram.mem_used_total);
 
         let values = map!(
             "{MTg}" => format!("{:.1}", mem_total.gib()),
@@ -311,7 +365,14 @@ impl Memory {
             "{Cm}" => format!("{}", cached.mib()),
             "{Cp}" => format!("{:.2}", cached.percent(mem_total)),
             "{Cpi}" => format!("{:02}", cached.percent(mem_total) as i32),
-            "{Cpb}" => format_percent_bar(cached.percent(mem_total)));
+            "{Cpb}" => format_percent_bar(cached.percent(mem_total)),
+            "{ZOg}" => format!("{:.1}", zram_orig.gib()),
+            "{ZOm}" => format!("{}", zram_orig.mib()),
+            "{ZCg}" => format!("{:.1}", zram_compr.gib()),
+            "{ZCm}" => format!("{}", zram_compr.mib()),
+            "{ZUg}" => format!("{:.1}", zram_used.gib()),
+            "{ZUm}" => format!("{}", zram_used.mib()),
+            "{ZR}" => format!("{:.2}", zram.ratio()));
 
         match self.memtype {
             Memtype::Memory => self.output.0.set_state(match mem_used.percent(mem_total) {
@@ -347,6 +408,31 @@ impl Memory {
 impl ConfigBlock for Memory {
     type Config = MemoryConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        (
+            "format_mem",
+            &[
+                "{Bg}", "{Bm}", "{Bpb}", "{Bpi}", "{Bp}", "{Cg}", "{Cm}", "{Cpb}", "{Cpi}", "{Cp}",
+                "{MAg}", "{MAm}", "{MApb}", "{MApi}", "{MAp}", "{MFg}", "{MFm}", "{MFpb}",
+                "{MFpi}", "{MFp}", "{MTg}", "{MTm}", "{MUg}", "{MUm}", "{MUpb}", "{MUpi}", "{MUp}",
+                "{Mug}", "{Mum}", "{Mupb}", "{Mupi}", "{Mup}", "{SFg}", "{SFm}", "{SFpb}",
+                "{SFpi}", "{SFp}", "{STg}", "{STm}", "{SUg}", "{SUm}", "{SUpb}", "{SUpi}", "{SUp}",
+                "{ZCg}", "{ZCm}", "{ZOg}", "{ZOm}", "{ZR}", "{ZUg}", "{ZUm}",
+            ],
+        ),
+        (
+            "format_swap",
+            &[
+                "{Bg}", "{Bm}", "{Bpb}", "{Bpi}", "{Bp}", "{Cg}", "{Cm}", "{Cpb}", "{Cpi}", "{Cp}",
+                "{MAg}", "{MAm}", "{MApb}", "{MApi}", "{MAp}", "{MFg}", "{MFm}", "{MFpb}",
+                "{MFpi}", "{MFp}", "{MTg}", "{MTm}", "{MUg}", "{MUm}", "{MUpb}", "{MUpi}", "{MUp}",
+                "{Mug}", "{Mum}", "{Mupb}", "{Mupi}", "{Mup}", "{SFg}", "{SFm}", "{SFpb}",
+                "{SFpi}", "{SFp}", "{STg}", "{STm}", "{SUg}", "{SUm}", "{SUpb}", "{SUpi}", "{SUp}",
+                "{ZCg}", "{ZCm}", "{ZOg}", "{ZOm}", "{ZR}", "{ZUg}", "{ZUm}",
+            ],
+        ),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -354,7 +440,7 @@ impl ConfigBlock for Memory {
         tx: Sender<Task>,
     ) -> Result<Self> {
         let icons: bool = block_config.icons;
-        let widget = TextWidget::new(id, 0, shared_config).with_text("");
+        let widget = TextWidget::new(id, 0, shared_config.clone()).with_text("");
         Ok(Memory {
             id,
             memtype: block_config.display_type,
@@ -368,8 +454,8 @@ impl ConfigBlock for Memory {
             },
             clickable: block_config.clickable,
             format: (
-                FormatTemplate::from_string(&block_config.format_mem)?,
-                FormatTemplate::from_string(&block_config.format_swap)?,
+                FormatTemplate::new(&block_config.format_mem, &shared_config)?,
+                FormatTemplate::new(&block_config.format_swap, &shared_config)?,
             ),
             update_interval: block_config.interval,
             tx_update_request: tx,
@@ -471,7 +557,7 @@ impl Block for Memory {
         }
 
         // Now, create the string to be shown
-        let output_text = self.format_insert_values(mem_state)?;
+        let output_text = self.format_insert_values(mem_state, Zramstate::read())?;
 
         match self.memtype {
             Memtype::Memory => self.output.0.set_text(output_text),
@@ -19,6 +19,7 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::{LogicalDirection, Scrolling, SharedConfig};
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::{DurationFormat, TimeUnit, Value};
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
@@ -34,6 +35,7 @@ struct Player {
     playback_status: PlaybackStatus,
     artist: Option<String>,
     title: Option<String>,
+    length: Option<Duration>,
     //TODO
     //volume: u32,
 }
@@ -52,6 +54,21 @@ impl Default for PlaybackStatus {
     }
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MusicScroll {
+    /// Seek forward or backward by `seek_step`
+    Seek,
+    /// Cycle through the players
+    Player,
+}
+
+impl Default for MusicScroll {
+    fn default() -> Self {
+        Self::Seek
+    }
+}
+
 pub struct Music {
     id: usize,
     play_id: usize,
@@ -78,6 +95,7 @@ pub struct Music {
     send: Sender<Task>,
     format: FormatTemplate,
     scrolling: Scrolling,
+    scroll: MusicScroll,
 }
 
 impl Music {
@@ -215,6 +233,15 @@ pub struct MusicConfig {
     #[serde(default = "MusicConfig::default_hide_when_empty")]
     pub hide_when_empty: bool,
 
+    /// Switch to a player when it starts playing, and away from the current one when it stops
+    /// while another is playing.
+    #[serde(default = "MusicConfig::default_prefer_playing")]
+    pub prefer_playing: bool,
+
+    /// What scrolling on the block does.
+    #[serde(default)]
+    pub scroll: MusicScroll,
+
     /// Format string for displaying music player info.
     #[serde(default = "MusicConfig::default_format")]
     pub format: String,
@@ -269,6 +296,10 @@ impl MusicConfig {
         false
     }
 
+    fn default_prefer_playing() -> bool {
+        true
+    }
+
     fn default_format() -> String {
         "{combo}".to_string()
     }
@@ -277,6 +308,19 @@ impl MusicConfig {
 impl ConfigBlock for Music {
     type Config = MusicConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{artist}",
+            "{avail}",
+            "{combo}",
+            "{length}",
+            "{player}",
+            "{position}",
+            "{title}",
+        ],
+    )];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -333,40 +377,18 @@ impl ConfigBlock for Music {
             let bn: &str = r.read1().ok().unwrap();
 
             if !initial_players.iter().any(|p| p.bus_name == bn) {
-                // Get current media info, if any
-                let p = c.with_path(name, "/org/mpris/MediaPlayer2", 500);
-                let data = p.get("org.mpris.MediaPlayer2.Player", "Metadata");
-                let (title, artist) = match data {
-                    Err(_) => (String::new(), String::new()),
-                    Ok(data) => {
-                        extract_from_metadata(&data).unwrap_or((String::new(), String::new()))
-                    }
-                };
-
-                // Get current playback status
-                let data = p.get("org.mpris.MediaPlayer2.Player", "PlaybackStatus");
-                let status = match data {
-                    Err(_) => PlaybackStatus::Unknown,
-                    Ok(data) => {
-                        let data: Box<dyn RefArg> = data;
-                        extract_playback_status(&data)
-                    }
-                };
-
-                initial_players.push(Player {
-                    bus_name: bn.to_string(),
-                    interface_name: name.to_string(),
-                    playback_status: status,
-                    artist: Some(artist),
-                    title: Some(title),
-                });
+                initial_players.push(query_player(&c, name, bn));
             }
         }
+        if block_config.prefer_playing {
+            select_playing(&mut initial_players);
+        }
 
         let players_original = Arc::new(Mutex::new(initial_players));
         let players_copy = players_original.clone();
         let players_copy2 = players_original.clone();
         let players_copy3 = players_original;
+        let prefer_playing = block_config.prefer_playing;
         thread::Builder::new().name("music".into()).spawn(move || {
             let c = Connection::get_private(BusType::Session).unwrap();
             c.add_match("interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path='/org/mpris/MediaPlayer2'").unwrap();
@@ -378,18 +400,19 @@ impl ConfigBlock for Music {
                     if msg.sender().is_some() {
                         if let Some(signal) = PropertiesPropertiesChanged::from_message(&msg) {
                             let mut players = players_copy2.lock().expect("failed to acquire lock for `players`");
-                            let player = players.iter_mut().find(|p| p.bus_name == msg.sender().unwrap().to_string());
-                            if player.is_none() {
+                            let pos = match players.iter().position(|p| p.bus_name == msg.sender().unwrap().to_string()) {
+                                Some(pos) => pos,
                                 // Ignoring update since could not find player in the array.
                                 // This shouldn't actually occur as long as the other thread updates the array in time.
-                                continue;
-                            }
-                            let p = player.unwrap();
+                                None => continue,
+                            };
+                            let p = &mut players[pos];
                             let mut updated = false;
+                            let mut status_changed = false;
                             let raw_metadata = signal.changed_properties.get("Metadata");
                             if let Some(data) = raw_metadata {
-                                let (title, artist) =
-                                    extract_from_metadata(&data.0).unwrap_or((String::new(), String::new()));
+                                let (title, artist, length) =
+                                    extract_from_metadata(&data.0).unwrap_or((String::new(), String::new(), None));
                                 if p.artist != Some(artist.clone()) {
                                     p.artist = Some(artist);
                                     updated = true;
@@ -398,6 +421,10 @@ impl ConfigBlock for Music {
                                     p.title = Some(title);
                                     updated = true;
                                 }
+                                if p.length != length {
+                                    p.length = length;
+                                    updated = true;
+                                }
                             };
                             let raw_metadata = signal.changed_properties.get("PlaybackStatus");
                             if let Some(data) = raw_metadata {
@@ -405,6 +432,7 @@ impl ConfigBlock for Music {
                                 if p.playback_status != new_status {
                                     p.playback_status = new_status;
                                     updated = true;
+                                    status_changed = true;
                                 }
                             };
                             // workaround for `playerctld`
@@ -422,15 +450,26 @@ impl ConfigBlock for Music {
                                 if playerctl_playerlist.peek().is_none() {
                                     p.artist = None;
                                     p.title = None;
+                                    p.length = None;
                                     updated = true;
                                 }
                             };
-                            if updated {
-                                send.send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap();
+                            if status_changed && prefer_playing {
+                                if players[pos].playback_status == PlaybackStatus::Playing {
+                                    move_to_front(&mut players, pos);
+                                } else if pos == 0 {
+                                    select_playing(&mut players);
+                                }
+                            }
+                            if updated
+                                && send
+                                    .send(Task {
+                                        id,
+                                        update_time: Instant::now(),
+                                    })
+                                    .is_err()
+                            {
+                                return;
                             }
                         }
                     }
@@ -456,11 +495,15 @@ impl ConfigBlock for Music {
                          if !old_owner.is_empty() && new_owner.is_empty() {
                              if let Some(pos) = players.iter().position(|p| p.bus_name == old_owner) {
                                  players.remove(pos);
-                                 send2.send(Task {
+                                 if prefer_playing && pos == 0 {
+                                     select_playing(&mut players);
+                                 }
+                                 if send2.send(Task {
                                      id,
                                      update_time: Instant::now(),
-                                 })
-                                 .unwrap();
+                                 }).is_err() {
+                                     return;
+                                 }
                              }
                          } else if old_owner.is_empty() && !new_owner.is_empty() && !ignored_player(name, &interface_name_exclude_regexps, preferred_player.clone()) && !players.iter().any(|p| p.bus_name == new_owner) {
                          players.push(Player {
@@ -469,12 +512,14 @@ impl ConfigBlock for Music {
                              playback_status: PlaybackStatus::Unknown,
                              artist: None,
                              title: None,
+                             length: None,
                          });
-                         send2.send(Task {
+                         if send2.send(Task {
                              id,
                              update_time: Instant::now(),
-                         })
-                         .unwrap();
+                         }).is_err() {
+                             return;
+                         }
                          }
                     }
                 }
@@ -561,8 +606,9 @@ impl ConfigBlock for Music {
             players: players_copy,
             hide_when_empty: block_config.hide_when_empty,
             send: send3,
-            format: FormatTemplate::from_string(&block_config.format)?,
+            format: FormatTemplate::new(&block_config.format, &shared_config)?,
             scrolling: shared_config.scrolling,
+            scroll: block_config.scroll,
         })
     }
 
@@ -572,6 +618,10 @@ impl ConfigBlock for Music {
 }
 
 impl Block for Music {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -610,14 +660,41 @@ impl Block for Music {
                 self.smart_trim(artist.clone(), title.clone())
             };
 
+        // The position isn't signaled by players, so it is only queried if it is shown
+        let show_position = self.format.has_placeholder("{position}");
+        let position = if show_position {
+            self.dbus_conn
+                .with_path(
+                    metadata.interface_name.clone(),
+                    "/org/mpris/MediaPlayer2",
+                    500,
+                )
+                .get::<i64>("org.mpris.MediaPlayer2.Player", "Position")
+                .ok()
+                .map(|position| Duration::from_micros(position.max(0) as u64))
+        } else {
+            None
+        };
+        let duration = |duration: Option<Duration>| match duration {
+            Some(duration) => {
+                Value::duration(duration).duration_format(DurationFormat::Clock(vec![
+                    TimeUnit::Minutes,
+                    TimeUnit::Seconds,
+                ]))
+            }
+            None => Value::text(String::new()),
+        };
+
         let values = map!(
-            "{artist}" => artist.clone(),
-            "{title}" => title.clone(),
-            "{combo}" => combo,
+            "{artist}" => Value::text(artist.clone()),
+            "{title}" => Value::text(title.clone()),
+            "{combo}" => Value::text(combo),
             //TODO
             //"{vol}" => volume,
-            "{player}" => player_name,
-            "{avail}" => players.len().to_string()
+            "{player}" => Value::text(player_name),
+            "{avail}" => Value::text(players.len().to_string()),
+            "{length}" => duration(metadata.length),
+            "{position}" => duration(position)
         );
 
         if !(rotation_in_progress) {
@@ -625,9 +702,10 @@ impl Block for Music {
                 self.current_song_widget.set_text(String::new());
             } else {
                 self.current_song_widget
-                    .set_text(self.format.render_static_str(&values)?);
+                    .set_text(self.format.render(&values)?);
             }
         }
+        let playing = metadata.playback_status == PlaybackStatus::Playing;
 
         if let Some(ref mut play) = self.play {
             play.set_icon(match metadata.playback_status {
@@ -640,7 +718,12 @@ impl Block for Music {
 
         // If `marquee` is enabled then we need to schedule an update for the text rotation.
         // (time_to_next_rotation is always None if marquee is disabled)
-        if let Some(t) = time_to_next_rotation {
+        if show_position && playing {
+            Ok(Some(Update::Every(
+                time_to_next_rotation
+                    .map_or(Duration::from_secs(1), |t| t.min(Duration::from_secs(1))),
+            )))
+        } else if let Some(t) = time_to_next_rotation {
             Ok(Some(Update::Every(t)))
         // We just finished a rotation so we wait before starting again
         } else if self.marquee {
@@ -710,6 +793,20 @@ impl Block for Music {
                         })?;
                     }
                 }
+                _ if self.scroll == MusicScroll::Player => {
+                    if (event_id == self.id || event_id == self.collapsed_id) && players.len() > 0 {
+                        use LogicalDirection::*;
+                        match self.scrolling.to_logical_direction(event.button) {
+                            Some(Up) => players.rotate_right(1),
+                            Some(Down) => players.rotate_left(1),
+                            None => return Ok(()),
+                        }
+                        self.send.send(Task {
+                            id: self.id,
+                            update_time: Instant::now(),
+                        })?;
+                    }
+                }
                 _ => {
                     if event_id == self.id && players.len() > 0 {
                         let metadata = players.first().unwrap();
@@ -770,6 +867,54 @@ impl Block for Music {
     }
 }
 
+/// Queries the status and current song of a player appearing on the bus as `name`.
+fn query_player(c: &Connection, name: &str, bus_name: &str) -> Player {
+    // Get current media info, if any
+    let p = c.with_path(name, "/org/mpris/MediaPlayer2", 500);
+    let data = p.get("org.mpris.MediaPlayer2.Player", "Metadata");
+    let (title, artist, length) = match data {
+        Err(_) => (String::new(), String::new(), None),
+        Ok(data) => extract_from_metadata(&data).unwrap_or((String::new(), String::new(), None)),
+    };
+
+    // Get current playback status
+    let data = p.get("org.mpris.MediaPlayer2.Player", "PlaybackStatus");
+    let status = match data {
+        Err(_) => PlaybackStatus::Unknown,
+        Ok(data) => {
+            let data: Box<dyn RefArg> = data;
+            extract_playback_status(&data)
+        }
+    };
+
+    Player {
+        bus_name: bus_name.to_string(),
+        interface_name: name.to_string(),
+        playback_status: status,
+        artist: Some(artist),
+        title: Some(title),
+        length,
+    }
+}
+
+/// Makes the player at `pos` the one shown by the block.
+fn move_to_front(players: &mut Vec<Player>, pos: usize) {
+    let player = players.remove(pos);
+    players.insert(0, player);
+}
+
+/// Shows a playing player, unless the one shown is playing already.
+fn select_playing(players: &mut Vec<Player>) {
+    let playing = players
+        .iter()
+        .position(|p| p.playback_status == PlaybackStatus::Playing);
+    if let Some(pos) = playing {
+        if pos > 0 {
+            move_to_front(players, pos);
+        }
+    }
+}
+
 fn extract_playback_status(value: &dyn RefArg) -> PlaybackStatus {
     if let Some(status) = value.as_str() {
         match status {
@@ -798,9 +943,10 @@ fn extract_artist_from_value(value: &dyn RefArg) -> Result<&str> {
 }
 
 #[allow(clippy::borrowed_box)] // TODO: remove clippy workaround
-fn extract_from_metadata(metadata: &Box<dyn RefArg>) -> Result<(String, String)> {
+fn extract_from_metadata(metadata: &Box<dyn RefArg>) -> Result<(String, String, Option<Duration>)> {
     let mut title = String::new();
     let mut artist = String::new();
+    let mut length = None;
 
     let mut iter = metadata
         .as_iter()
@@ -822,10 +968,16 @@ fn extract_from_metadata(metadata: &Box<dyn RefArg>) -> Result<(String, String)>
                         .block_error("music", "failed to extract metadata")?,
                 )
             }
+            // In microseconds
+            "mpris:length" => {
+                length = value
+                    .as_i64()
+                    .map(|length| Duration::from_micros(length.max(0) as u64))
+            }
             _ => {}
         };
     }
-    Ok((title, artist))
+    Ok((title, artist, length))
 }
 
 fn ignored_player(
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt;
 use std::fs::{read_to_string, OpenOptions};
@@ -7,6 +8,10 @@ use std::process::Command;
 use std::time::{Duration, Instant};
 
 use crossbeam_channel::Sender;
+use dbus::arg::{RefArg, Variant};
+use dbus::ffidisp::stdintf::org_freedesktop_dbus::ObjectManager;
+use dbus::ffidisp::{BusType, Connection};
+use dbus::Message;
 use lazy_static::lazy_static;
 use regex::bytes::Regex;
 use serde_derive::Deserialize;
@@ -15,12 +20,11 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
-use crate::input::{I3BarEvent, MouseButton};
+use crate::formatting::{Prefix, Value};
+use crate::http;
 use crate::scheduler::Task;
-use crate::util::{
-    escape_pango_text, format_number, format_percent_bar, format_vec_to_bar_graph, FormatTemplate,
-};
-use crate::widgets::{text::TextWidget, I3BarWidget, Spacing};
+use crate::util::{escape_pango_text, format_percent_bar, format_vec_to_bar_graph, FormatTemplate};
+use crate::widgets::{text::TextWidget, I3BarWidget, Spacing, State};
 
 lazy_static! {
     static ref DEFAULT_DEV_REGEX: Regex = Regex::new("default.*dev (\\w*).*").unwrap();
@@ -32,6 +36,38 @@ lazy_static! {
     static ref IW_BITRATE_REGEX: Regex =
         Regex::new("tx bitrate: (\\d+(?:\\.?\\d+) [[:alpha:]]+/s)").unwrap();
     static ref IW_SIGNAL_REGEX: Regex = Regex::new("signal: (-?\\d+) dBm").unwrap();
+    static ref IW_FREQ_REGEX: Regex = Regex::new("freq: (\\d+)").unwrap();
+}
+
+const IWD_SERVICE: &str = "net.connman.iwd";
+
+/// Whether the internet is reachable through a device, as found by a connectivity check.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Connectivity {
+    Online,
+    /// The probe is redirected or answered by a captive portal asking to log in
+    Portal,
+    Offline,
+}
+
+impl Connectivity {
+    /// Requests `url`, which should reply with an empty `204 No Content`. Captive portals
+    /// intercept the request and redirect it to their login page instead.
+    fn check(url: &str) -> Self {
+        match http::http_get_status(url, Some(Duration::from_secs(3))) {
+            Ok(204) => Connectivity::Online,
+            Ok(_) => Connectivity::Portal,
+            Err(_) => Connectivity::Offline,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Connectivity::Online => "online",
+            Connectivity::Portal => "portal",
+            Connectivity::Offline => "offline",
+        }
+    }
 }
 
 pub struct NetworkDevice {
@@ -158,6 +194,19 @@ impl NetworkDevice {
         self.tun || self.wg || self.ppp
     }
 
+    /// The icon of the kind of this device.
+    fn icon(&self) -> &'static str {
+        if self.wireless {
+            "net_wireless"
+        } else if self.is_vpn() {
+            "net_vpn"
+        } else if self.device == "lo" {
+            "net_loopback"
+        } else {
+            "net_wired"
+        }
+    }
+
     /// Queries the wireless SSID of this device, if it is connected to one.
     pub fn ssid(&self) -> Result<Option<String>> {
         if self.is_up()? && self.wireless {
@@ -172,11 +221,12 @@ impl NetworkDevice {
             return Ok(None);
         }
 
+        // iw is not necessarily installed along with iwd
         let iw_output = Command::new("iw")
             .args(&["dev", &self.device, "link"])
             .output()
-            .block_error("net", "Failed to execute signal strength query.")?
-            .stdout;
+            .map(|output| output.stdout)
+            .unwrap_or_default();
 
         if let Some(raw) = IW_SIGNAL_REGEX
             .captures_iter(&iw_output)
@@ -191,10 +241,32 @@ impl NetworkDevice {
                 })
                 .map(Some)
         } else {
-            Ok(None)
+            Ok(IwdStation::get(&self.device).and_then(|station| station.signal))
         }
     }
 
+    /// Queries the frequency of the wireless network this device is connected to, in MHz.
+    fn frequency(&self) -> Result<Option<u32>> {
+        if !self.is_up()? || !self.wireless {
+            return Ok(None);
+        }
+
+        let iw_output = Command::new("iw")
+            .args(&["dev", &self.device, "link"])
+            .output()
+            .map(|output| output.stdout)
+            .unwrap_or_default();
+
+        let frequency = IW_FREQ_REGEX
+            .captures_iter(&iw_output)
+            .next()
+            .and_then(|x| x.get(1))
+            .and_then(|raw| std::str::from_utf8(raw.as_bytes()).ok())
+            .and_then(|raw| raw.parse().ok());
+        Ok(frequency
+            .or_else(|| IwdStation::get(&self.device).and_then(|station| station.frequency)))
+    }
+
     fn relative_signal_strength(&self) -> Result<Option<u32>> {
         let xbm = if let Some(xbm) = self.absolute_signal_strength()? {
             xbm as f64
@@ -337,17 +409,21 @@ impl NetworkDevice {
 pub struct Net {
     id: usize,
     format: FormatTemplate,
-    format_alt: Option<FormatTemplate>,
     output: TextWidget,
     ssid: Option<String>,
     max_ssid_width: usize,
     signal_strength: Option<String>,
     signal_strength_bar: Option<String>,
+    frequency: Option<String>,
     ip_addr: Option<String>,
     ipv6_addr: Option<String>,
     bitrate: Option<String>,
-    output_tx: String,
-    output_rx: String,
+    connectivity_check: Option<String>,
+    connectivity: Option<Connectivity>,
+    /// Upload speed, in bytes per second
+    speed_tx: f64,
+    /// Download speed, in bytes per second
+    speed_rx: f64,
     graph_tx: String,
     graph_rx: String,
     update_interval: Duration,
@@ -402,9 +478,6 @@ pub struct NetConfig {
     #[serde(default = "NetConfig::default_format")]
     pub format: String,
 
-    #[serde(default = "NetConfig::default_format_alt")]
-    pub format_alt: Option<String>,
-
     /// Which interface in /sys/class/net/ to read from.
     pub device: Option<String>,
 
@@ -431,6 +504,13 @@ pub struct NetConfig {
     /// Minimum unit to display for throughput indicators.
     #[serde(default = "NetConfig::default_speed_min_unit")]
     pub speed_min_unit: Unit,
+
+    /// Number of past speeds drawn by the graphs.
+    #[serde(default = "NetConfig::default_graph_length")]
+    pub graph_length: usize,
+
+    /// URL replying `204 No Content` when the internet is reachable, to detect captive portals.
+    pub connectivity_check: Option<String>,
 }
 
 impl NetConfig {
@@ -442,10 +522,6 @@ impl NetConfig {
         "{speed_up} {speed_down}".to_owned()
     }
 
-    fn default_format_alt() -> Option<String> {
-        None
-    }
-
     fn default_hide_inactive() -> bool {
         false
     }
@@ -469,11 +545,33 @@ impl NetConfig {
     fn default_speed_digits() -> usize {
         3
     }
+
+    fn default_graph_length() -> usize {
+        10
+    }
 }
 
 impl ConfigBlock for Net {
     type Config = NetConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{bitrate}",
+            "{connectivity}",
+            "{frequency}",
+            "{graph_down}",
+            "{graph_up}",
+            "{ipv6}",
+            "{ip}",
+            "{signal_strength_bar}",
+            "{signal_strength}",
+            "{speed_down}",
+            "{speed_up}",
+            "{ssid}",
+        ],
+    )];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -490,34 +588,14 @@ impl ConfigBlock for Net {
         };
         let init_rx_bytes = device.rx_bytes().unwrap_or(0);
         let init_tx_bytes = device.tx_bytes().unwrap_or(0);
-        let wireless = device.is_wireless();
-        let vpn = device.is_vpn();
-
-        let format_alt = if let Some(f) = block_config.format_alt {
-            Some(
-                FormatTemplate::from_string(&f)
-                    .block_error("net", "Invalid format_alt specified")?,
-            )
-        } else {
-            None
-        };
 
         Ok(Net {
             id,
             update_interval: block_config.interval,
-            format: FormatTemplate::from_string(&block_config.format)
+            format: FormatTemplate::new(&block_config.format, &shared_config)
                 .block_error("net", "Invalid format specified")?,
-            format_alt,
             output: TextWidget::new(id, 0, shared_config.clone())
-                .with_icon(if wireless {
-                    "net_wireless"
-                } else if vpn {
-                    "net_vpn"
-                } else if device.device == "lo" {
-                    "net_loopback"
-                } else {
-                    "net_wired"
-                })
+                .with_icon(device.icon())
                 .with_text("")
                 .with_spacing(Spacing::Inline),
             use_bits: block_config.use_bits,
@@ -525,42 +603,22 @@ impl ConfigBlock for Net {
             speed_digits: block_config.speed_digits,
             ssid: None,
             max_ssid_width: block_config.max_ssid_width,
-            signal_strength: if wireless && block_config.format.contains("{signal_strength}") {
-                Some(0.to_string())
-            } else {
-                None
-            },
-            signal_strength_bar: if wireless
-                && block_config.format.contains("{signal_strength_bar}")
-            {
-                Some("".to_string())
-            } else {
-                None
-            },
-            // TODO: a better way to deal with this?
-            bitrate: if block_config.format.contains("{bitrate}") {
-                Some("".to_string())
-            } else {
-                None
-            },
-            ip_addr: if block_config.format.contains("{ip}") {
-                Some("".to_string())
-            } else {
-                None
-            },
-            ipv6_addr: if block_config.format.contains("{ipv6}") {
-                Some("".to_string())
-            } else {
-                None
-            },
-            output_tx: String::new(),
-            output_rx: String::new(),
+            signal_strength: None,
+            signal_strength_bar: None,
+            frequency: None,
+            bitrate: None,
+            connectivity_check: block_config.connectivity_check,
+            connectivity: None,
+            ip_addr: None,
+            ipv6_addr: None,
+            speed_tx: 0.,
+            speed_rx: 0.,
             graph_tx: String::new(),
             graph_rx: String::new(),
             device,
             auto_device: block_config.device.is_none(),
-            rx_buff: vec![0.; 10],
-            tx_buff: vec![0.; 10],
+            rx_buff: vec![0.; block_config.graph_length.max(1)],
+            tx_buff: vec![0.; block_config.graph_length.max(1)],
             rx_bytes: init_rx_bytes,
             tx_bytes: init_tx_bytes,
             active: true,
@@ -596,15 +654,15 @@ impl Net {
 
             if self.device.device() != dev {
                 self.device = NetworkDevice::from_device(dev);
-                self.output.set_icon(if self.device.is_wireless() {
-                    "net_wireless"
-                } else if self.device.is_vpn() {
-                    "net_vpn"
-                } else if self.device.device == "lo" {
-                    "net_loopback"
-                } else {
-                    "net_wired"
-                });
+                self.output.set_icon(self.device.icon());
+                self.connectivity = None;
+
+                // Start the speeds and graphs afresh rather than mixing both interfaces
+                self.tx_bytes = self.device.tx_bytes().unwrap_or(0);
+                self.rx_bytes = self.device.rx_bytes().unwrap_or(0);
+                for speed in self.tx_buff.iter_mut().chain(self.rx_buff.iter_mut()) {
+                    *speed = 0.;
+                }
             }
         }
     }
@@ -649,6 +707,30 @@ impl Net {
         Ok(())
     }
 
+    fn update_frequency(&mut self) -> Result<()> {
+        if let Some(ref mut frequency_string) = self.frequency {
+            if let Some(frequency) = self.device.frequency()? {
+                *frequency_string = format!("{:.1}GHz", frequency as f64 / 1000.);
+            }
+        }
+        Ok(())
+    }
+
+    fn update_connectivity(&mut self) {
+        if let Some(ref url) = self.connectivity_check {
+            let connectivity = Connectivity::check(url);
+            if self.connectivity != Some(connectivity) {
+                self.output
+                    .set_icon(if connectivity == Connectivity::Portal {
+                        "net_portal"
+                    } else {
+                        self.device.icon()
+                    });
+            }
+            self.connectivity = Some(connectivity);
+        }
+    }
+
     fn update_ip_addr(&mut self) -> Result<()> {
         if let Some(ref mut ip_addr_string) = self.ip_addr {
             let ip_addr = self.device.ip_addr()?;
@@ -665,6 +747,24 @@ impl Net {
         Ok(())
     }
 
+    /// Starts looking up the values used by the current format, which may change on click
+    fn enable_placeholders(&mut self) {
+        let format = &self.format;
+        let enable = |value: &mut Option<String>, key: &str, initial: &str| {
+            if value.is_none() && format.has_placeholder(key) {
+                *value = Some(initial.to_string());
+            }
+        };
+        if self.device.is_wireless() {
+            enable(&mut self.signal_strength, "{signal_strength}", "0");
+            enable(&mut self.signal_strength_bar, "{signal_strength_bar}", "");
+            enable(&mut self.frequency, "{frequency}", "");
+        }
+        enable(&mut self.bitrate, "{bitrate}", "");
+        enable(&mut self.ip_addr, "{ip}", "");
+        enable(&mut self.ipv6_addr, "{ipv6}", "");
+    }
+
     fn update_tx_rx(&mut self) -> Result<()> {
         // TODO: consider using `as_nanos`
         let update_interval = (self.update_interval.as_secs() as f64)
@@ -676,16 +776,7 @@ impl Net {
         let tx_bytes = (diff as f64 / update_interval) as u64;
         self.tx_bytes = current_tx;
 
-        self.output_tx = format_number(
-            if self.use_bits {
-                tx_bytes * 8
-            } else {
-                tx_bytes
-            } as f64,
-            self.speed_digits,
-            &self.speed_min_unit.to_string(),
-            if self.use_bits { "b" } else { "B" },
-        );
+        self.speed_tx = tx_bytes as f64;
 
         self.tx_buff.remove(0);
         self.tx_buff.push(tx_bytes as f64);
@@ -696,16 +787,7 @@ impl Net {
         let rx_bytes = (diff as f64 / update_interval) as u64;
         self.rx_bytes = current_rx;
 
-        self.output_rx = format_number(
-            if self.use_bits {
-                rx_bytes * 8
-            } else {
-                rx_bytes
-            } as f64,
-            self.speed_digits,
-            &self.speed_min_unit.to_string(),
-            if self.use_bits { "b" } else { "B" },
-        );
+        self.speed_rx = rx_bytes as f64;
 
         self.rx_buff.remove(0);
         self.rx_buff.push(rx_bytes as f64);
@@ -718,6 +800,7 @@ impl Net {
 impl Block for Net {
     fn update(&mut self) -> Result<Option<Update>> {
         self.update_device();
+        self.enable_placeholders();
 
         // skip updating if device is not up.
         self.exists = self.device.exists()?;
@@ -751,35 +834,56 @@ impl Block for Net {
         {
             self.update_ssid()?;
             self.update_signal_strength()?;
+            self.update_frequency()?;
             self.update_ip_addr()?;
+            self.update_connectivity();
             self.last_update = now;
         }
 
         self.update_tx_rx()?;
 
-        let mut s_up = self.shared_config.get_icon("net_up").unwrap_or_default();
-        s_up.push_str(&self.output_tx);
-        let mut s_dn = self.shared_config.get_icon("net_down").unwrap_or_default();
-        s_dn.push_str(&self.output_rx);
-
-        let empty_string = "".to_string();
-        let na_string = "N/A".to_string();
+        let min_prefix =
+            Prefix::from_symbol(&self.speed_min_unit.to_string()).unwrap_or(Prefix::One);
+        let speed = |bytes: f64, icon: &str| {
+            let speed = if self.use_bits {
+                Value::bits(bytes * 8.)
+            } else {
+                Value::bytes(bytes)
+            };
+            speed
+                .min_prefix(min_prefix)
+                .digits(self.speed_digits)
+                .icon(self.shared_config.get_icon(icon).unwrap_or_default())
+        };
+        let text = |text: &Option<String>, default: &str| {
+            Value::text(text.clone().unwrap_or_else(|| default.to_string()))
+        };
 
         let values = map!(
-            "{ssid}" => self.ssid.as_ref().unwrap_or(&na_string),
-            "{signal_strength}" => self.signal_strength.as_ref().unwrap_or(&na_string),
-            "{signal_strength_bar}" => self.signal_strength_bar.as_ref().unwrap_or(&empty_string),
-            "{bitrate}" => self.bitrate.as_ref().unwrap_or(&empty_string),
-            "{ip}" => self.ip_addr.as_ref().unwrap_or(&empty_string),
-            "{ipv6}" => self.ipv6_addr.as_ref().unwrap_or(&empty_string),
-            "{speed_up}" => &s_up,
-            "{speed_down}" => &s_dn,
-            "{graph_up}" => &self.graph_tx,
-            "{graph_down}" => &self.graph_rx
+            "{ssid}" => text(&self.ssid, "N/A"),
+            "{signal_strength}" => text(&self.signal_strength, "N/A"),
+            "{signal_strength_bar}" => text(&self.signal_strength_bar, ""),
+            "{frequency}" => text(&self.frequency, ""),
+            "{bitrate}" => text(&self.bitrate, ""),
+            "{ip}" => text(&self.ip_addr, ""),
+            "{ipv6}" => text(&self.ipv6_addr, ""),
+            "{speed_up}" => speed(self.speed_tx, "net_up"),
+            "{speed_down}" => speed(self.speed_rx, "net_down"),
+            "{graph_up}" => Value::text(self.graph_tx.clone()),
+            "{graph_down}" => Value::text(self.graph_rx.clone()),
+            "{connectivity}" => Value::text(self.connectivity.map(Connectivity::name).unwrap_or_default().to_string())
         );
 
-        self.output
-            .set_text(self.format.render_static_str(&values)?);
+        self.output.set_text(self.format.render(&values)?);
+        self.output.set_state(
+            self.format
+                .state(&values)
+                .unwrap_or(match self.connectivity {
+                    Some(Connectivity::Portal) => State::Warning,
+                    Some(Connectivity::Offline) => State::Critical,
+                    _ => State::Idle,
+                }),
+        );
 
         Ok(Some(self.update_interval.into()))
     }
@@ -792,14 +896,8 @@ impl Block for Net {
         }
     }
 
-    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
-        if event.button == MouseButton::Left {
-            if let Some(ref mut format) = self.format_alt {
-                std::mem::swap(format, &mut self.format);
-            }
-            self.update()?;
-        }
-        Ok(())
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
     }
 
     fn id(&self) -> usize {
@@ -830,6 +928,10 @@ fn get_ssid(dev: &NetworkDevice) -> Result<Option<String>> {
         return Ok(Some(res));
     }
 
+    if let Some(station) = IwdStation::get(&dev.device) {
+        return Ok(Some(station.ssid));
+    }
+
     if let Some(res) = get_iwctl_ssid(dev)? {
         return Ok(Some(res));
     }
@@ -837,6 +939,89 @@ fn get_ssid(dev: &NetworkDevice) -> Result<Option<String>> {
     Ok(None)
 }
 
+/// The network a device managed by iwd is connected to.
+struct IwdStation {
+    ssid: String,
+    /// Signal strength, in dBm
+    signal: Option<i32>,
+    /// Frequency, in MHz
+    frequency: Option<u32>,
+}
+
+impl IwdStation {
+    /// Asks iwd over D-Bus about the network `device` is connected to. Returns None if iwd is
+    /// not running, doesn't manage the device, or the device is not connected.
+    fn get(device: &str) -> Option<Self> {
+        let con = Connection::get_private(BusType::System).ok()?;
+        let objects = con
+            .with_path(IWD_SERVICE, "/", 1000)
+            .get_managed_objects()
+            .ok()?;
+
+        let (path, interfaces) = objects.iter().find(|(_, interfaces)| {
+            interfaces
+                .get("net.connman.iwd.Device")
+                .and_then(|props| props.get("Name"))
+                .and_then(|name| name.0.as_str())
+                == Some(device)
+        })?;
+        let station = interfaces.get("net.connman.iwd.Station")?;
+        if station.get("State")?.0.as_str()? != "connected" {
+            return None;
+        }
+        let network =
+            dbus::Path::new(station.get("ConnectedNetwork")?.0.as_str()?.to_string()).ok()?;
+        let ssid = objects
+            .get(&network)?
+            .get("net.connman.iwd.Network")?
+            .get("Name")?
+            .0
+            .as_str()?
+            .to_string();
+
+        // Diagnostics are not available with all drivers
+        let diagnostics: Option<HashMap<String, Variant<Box<dyn RefArg>>>> =
+            Message::new_method_call(
+                IWD_SERVICE,
+                path.clone(),
+                "net.connman.iwd.StationDiagnostic",
+                "GetDiagnostics",
+            )
+            .ok()
+            .and_then(|msg| con.send_with_reply_and_block(msg, 1000).ok())
+            .and_then(|reply| reply.get1());
+        let diagnostic = |key: &str| {
+            diagnostics
+                .as_ref()
+                .and_then(|diagnostics| diagnostics.get(key))
+                .and_then(|value| value.0.as_i64())
+        };
+
+        let signal = diagnostic("RSSI").map(|rssi| rssi as i32).or_else(|| {
+            // Scanned networks come with their signal strength, in 100 * dBm
+            let msg = Message::new_method_call(
+                IWD_SERVICE,
+                path.clone(),
+                "net.connman.iwd.Station",
+                "GetOrderedNetworks",
+            )
+            .ok()?;
+            let networks: Vec<(dbus::Path, i16)> =
+                con.send_with_reply_and_block(msg, 1000).ok()?.get1()?;
+            networks
+                .into_iter()
+                .find(|(path, _)| *path == network)
+                .map(|(_, signal)| i32::from(signal) / 100)
+        });
+
+        Some(IwdStation {
+            ssid,
+            signal,
+            frequency: diagnostic("Frequency").map(|frequency| frequency as u32),
+        })
+    }
+}
+
 #[inline]
 /// Attempt to get the SSID the given device is connected to from iw.
 /// Returns Err if:
@@ -536,6 +536,15 @@ impl NetworkManagerConfig {
 impl ConfigBlock for NetworkManager {
     type Config = NetworkManagerConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        ("ap_format", &["{ssid}", "{strength}", "{freq}"]),
+        (
+            "device_format",
+            &["{icon}", "{typename}", "{ap}", "{name}", "{ips}"],
+        ),
+        ("connection_format", &["{devices}", "{id}"]),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -563,12 +572,17 @@ impl ConfigBlock for NetworkManager {
                     for event in c.iter(timeout) {
                         match event {
                             ConnectionItem::Nothing => (),
-                            _ => send
-                                .send(Task {
-                                    id,
-                                    update_time: Instant::now(),
-                                })
-                                .unwrap(),
+                            _ => {
+                                if send
+                                    .send(Task {
+                                        id,
+                                        update_time: Instant::now(),
+                                    })
+                                    .is_err()
+                                {
+                                    return;
+                                }
+                            }
                         }
                     }
                 }
@@ -587,9 +601,12 @@ impl ConfigBlock for NetworkManager {
             manager,
             primary_only: block_config.primary_only,
             max_ssid_width: block_config.max_ssid_width,
-            ap_format: FormatTemplate::from_string(&block_config.ap_format)?,
-            device_format: FormatTemplate::from_string(&block_config.device_format)?,
-            connection_format: FormatTemplate::from_string(&block_config.connection_format)?,
+            ap_format: FormatTemplate::new(&block_config.ap_format, &shared_config)?,
+            device_format: FormatTemplate::new(&block_config.device_format, &shared_config)?,
+            connection_format: FormatTemplate::new(
+                &block_config.connection_format,
+                &shared_config,
+            )?,
             interface_name_exclude_regexps: compile_regexps(block_config.interface_name_exclude)
                 .block_error("networkmanager", "failed to parse exclude patterns")?,
             interface_name_include_regexps: compile_regexps(block_config.interface_name_include)
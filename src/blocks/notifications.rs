@@ -0,0 +1,190 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use dbus::ffidisp::{BusType, Connection};
+use dbus::message::MessageType;
+use dbus::Message;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Method calls sent to the notification daemon when a notification is shown
+const NOTIFY_RULE: &str =
+    "type='method_call',interface='org.freedesktop.Notifications',member='Notify'";
+
+/// Notifications received since the last clear.
+#[derive(Debug, Default)]
+struct Received {
+    count: usize,
+    app: String,
+    summary: String,
+}
+
+pub struct Notifications {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    hide_when_empty: bool,
+    received: Arc<Mutex<Received>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Format override
+    #[serde(default = "NotificationsConfig::default_format")]
+    pub format: String,
+
+    /// Names of applications whose notifications are not counted
+    #[serde(default)]
+    pub ignored_apps: Vec<String>,
+
+    #[serde(default)]
+    pub hide_when_empty: bool,
+}
+
+impl NotificationsConfig {
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+}
+
+/// The application name and summary of a new notification, `None` for anything else,
+/// including notifications which replace a previous one, e.g. volume or brightness popups.
+fn new_notification(msg: &Message) -> Option<(String, String)> {
+    if msg.msg_type() != MessageType::MethodCall || &*msg.member()? != "Notify" {
+        return None;
+    }
+    let (app, replaces_id, _icon, summary) = msg.read4::<&str, u32, &str, &str>().ok()?;
+    Some((app.to_string(), summary.to_string())).filter(|_| replaces_id == 0)
+}
+
+impl ConfigBlock for Notifications {
+    type Config = NotificationsConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{app}", "{count}", "{summary}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let received = Arc::new(Mutex::new(Received::default()));
+        let received_copy = received.clone();
+        let ignored_apps = block_config.ignored_apps;
+
+        thread::Builder::new()
+            .name("notifications".into())
+            .spawn(move || {
+                let c = Connection::get_private(BusType::Session)
+                    .expect("Failed to establish D-Bus connection in thread");
+
+                // Monitoring lets us see calls meant for the notification daemon, but the
+                // connection cannot send anything afterwards
+                let m = Message::new_method_call(
+                    "org.freedesktop.DBus",
+                    "/org/freedesktop/DBus",
+                    "org.freedesktop.DBus.Monitoring",
+                    "BecomeMonitor",
+                )
+                .unwrap()
+                .append2(vec![NOTIFY_RULE], 0u32);
+                c.send_with_reply_and_block(m, 1000)
+                    .expect("Failed to monitor the D-Bus session bus");
+
+                loop {
+                    for msg in c.incoming(1000) {
+                        let (app, summary) = match new_notification(&msg) {
+                            Some(notification) => notification,
+                            None => continue,
+                        };
+                        if ignored_apps.contains(&app) {
+                            continue;
+                        }
+                        let mut received = received_copy.lock().unwrap();
+                        received.count += 1;
+                        received.app = app;
+                        received.summary = summary;
+
+                        if send
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(Notifications {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("bell"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("notifications", "Invalid format specified")?,
+            hide_when_empty: block_config.hide_when_empty,
+            received,
+        })
+    }
+}
+
+impl Block for Notifications {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let received = self.received.lock().unwrap();
+
+        let values = map!(
+            "{count}" => Value::text(received.count.to_string()),
+            "{app}" => Value::text(received.app.clone()),
+            "{summary}" => Value::text(received.summary.clone())
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text
+            .set_state(self.format.state(&values).unwrap_or(if received.count > 0 {
+                State::Info
+            } else {
+                State::Idle
+            }));
+
+        // The monitoring thread asks for updates
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.hide_when_empty && self.received.lock().unwrap().count == 0 {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if e.button == MouseButton::Left {
+            *self.received.lock().unwrap() = Received::default();
+            self.update()?;
+        }
+        Ok(())
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -45,6 +45,8 @@ impl NotifyConfig {
 impl ConfigBlock for Notify {
     type Config = NotifyConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[("format", &["{state}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -95,11 +97,15 @@ impl ConfigBlock for Notify {
                             *paused = *status;
 
                             // Tell block to update now.
-                            send.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            if send
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                            {
+                                return;
+                            }
                         }
                     }
                 }
@@ -109,13 +115,17 @@ impl ConfigBlock for Notify {
         Ok(Notify {
             id,
             paused: state,
-            format: FormatTemplate::from_string(&block_config.format)?,
+            format: FormatTemplate::new(&block_config.format, &shared_config)?,
             output: TextWidget::new(notify_id, 0, shared_config).with_icon(icon),
         })
     }
 }
 
 impl Block for Notify {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -6,14 +6,17 @@ use std::path::Path;
 use std::process::Command;
 use std::time::Duration;
 
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use crossbeam_channel::Sender;
 use regex::Regex;
 use serde_derive::Deserialize;
 
+use crate::blocks::feeds::parse_feed;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::http;
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::util::{has_command, FormatTemplate};
@@ -30,6 +33,9 @@ pub struct Pacman {
     warning_updates_regex: Option<Regex>,
     critical_updates_regex: Option<Regex>,
     watched: Watched,
+    critical_pacman: Option<usize>,
+    critical_aur: Option<usize>,
+    news: bool,
     uptodate: bool,
     hide_when_uptodate: bool,
 }
@@ -43,6 +49,23 @@ pub enum Watched {
     Both(String),
 }
 
+/// AUR helpers whose command listing the available updates is known.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AurHelper {
+    Paru,
+    Yay,
+}
+
+impl AurHelper {
+    fn command(self) -> &'static str {
+        match self {
+            AurHelper::Paru => "paru -Qua",
+            AurHelper::Yay => "yay -Qua",
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct PacmanConfig {
@@ -80,6 +103,21 @@ pub struct PacmanConfig {
     #[serde()]
     pub aur_command: Option<String>,
 
+    /// AUR helper listing available updates, instead of `aur_command`
+    pub aur_helper: Option<AurHelper>,
+
+    /// Indicate a `critical` state for the block if at least this many pacman updates are
+    /// available
+    pub critical_pacman: Option<usize>,
+
+    /// Indicate a `critical` state for the block if at least this many AUR updates are available
+    pub critical_aur: Option<usize>,
+
+    /// Indicate a `warning` state for the block if Arch Linux published news since the last
+    /// system upgrade
+    #[serde(default)]
+    pub news: bool,
+
     #[serde(default = "PacmanConfig::default_hide_when_uptodate")]
     pub hide_when_uptodate: bool,
 }
@@ -119,7 +157,7 @@ impl PacmanConfig {
         if both || (pacman && aur) {
             let aur_command = aur_command.block_error(
                 "pacman",
-                "{aur} or {both} found in format string but no aur_command supplied",
+                "{aur} or {both} found in format string but no aur_command or aur_helper supplied",
             )?;
             Ok(Watched::Both(aur_command))
         } else if pacman && !aur {
@@ -127,7 +165,7 @@ impl PacmanConfig {
         } else if !pacman && aur {
             let aur_command = aur_command.block_error(
                 "pacman",
-                "{aur} found in format string but no aur_command supplied",
+                "{aur} found in format string but no aur_command or aur_helper supplied",
             )?;
             Ok(Watched::AUR(aur_command))
         } else {
@@ -146,30 +184,63 @@ impl PacmanConfig {
     fn default_hide_when_uptodate() -> bool {
         false
     }
+
+    /// The command listing the available AUR updates, either given or that of the AUR helper.
+    fn aur_command(
+        aur_command: Option<String>,
+        aur_helper: Option<AurHelper>,
+    ) -> Result<Option<String>> {
+        match (aur_command, aur_helper) {
+            (Some(_), Some(_)) => Err(ConfigurationError(
+                "pacman".to_string(),
+                (
+                    "aur_command and aur_helper can't be used together".to_string(),
+                    "invalid config".to_string(),
+                ),
+            )),
+            (Some(aur_command), None) => Ok(Some(aur_command)),
+            (None, aur_helper) => Ok(aur_helper.map(|helper| helper.command().to_string())),
+        }
+    }
 }
 
 impl ConfigBlock for Pacman {
     type Config = PacmanConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        (
+            "format",
+            &["{aur}", "{both}", "{count}", "{news}", "{pacman}"],
+        ),
+        (
+            "format_singular",
+            &["{aur}", "{both}", "{count}", "{news}", "{pacman}"],
+        ),
+        (
+            "format_up_to_date",
+            &["{aur}", "{both}", "{count}", "{news}", "{pacman}"],
+        ),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
-        let output = TextWidget::new(id, 0, shared_config).with_icon("update");
+        let output = TextWidget::new(id, 0, shared_config.clone()).with_icon("update");
 
         Ok(Pacman {
             id,
             update_interval: block_config.interval,
-            format: FormatTemplate::from_string(&block_config.format)
+            format: FormatTemplate::new(&block_config.format, &shared_config)
                 .block_error("pacman", "Invalid format specified for pacman::format")?,
-            format_singular: FormatTemplate::from_string(&block_config.format_singular)
+            format_singular: FormatTemplate::new(&block_config.format_singular, &shared_config)
                 .block_error(
                     "pacman",
                     "Invalid format specified for pacman::format_singular",
                 )?,
-            format_up_to_date: FormatTemplate::from_string(&block_config.format_up_to_date)
+            format_up_to_date: FormatTemplate::new(&block_config.format_up_to_date, &shared_config)
                 .block_error(
                     "pacman",
                     "Invalid format specified for pacman::format_up_to_date",
@@ -209,8 +280,11 @@ impl ConfigBlock for Pacman {
                 &block_config.format,
                 &block_config.format_singular,
                 &block_config.format_up_to_date,
-                block_config.aur_command,
+                PacmanConfig::aur_command(block_config.aur_command, block_config.aur_helper)?,
             )?,
+            critical_pacman: block_config.critical_pacman,
+            critical_aur: block_config.critical_aur,
+            news: block_config.news,
             uptodate: false,
             hide_when_uptodate: block_config.hide_when_uptodate,
         })
@@ -316,6 +390,52 @@ fn get_aur_available_updates(aur_command: &str) -> Result<String> {
     )
 }
 
+/// When the last full system upgrade started, from the log of pacman, whose lines start with
+/// `[2021-03-31T10:15:30+0200]`, or `[2021-03-31 10:15]` in older versions.
+fn parse_last_upgrade(log: &str) -> Option<DateTime<Utc>> {
+    let line = log
+        .lines()
+        .rev()
+        .find(|line| line.contains("[PACMAN] starting full system upgrade"))?;
+    let time = &line[line.find('[')? + 1..line.find(']')?];
+    DateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%z")
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").ok()?;
+            Local
+                .from_local_datetime(&time)
+                .earliest()
+                .map(|time| time.with_timezone(&Utc))
+        })
+}
+
+/// The number of Arch Linux news published since the last system upgrade, which often ask for
+/// manual intervention during the next one.
+fn get_news_count() -> Result<usize> {
+    let log = fs::read_to_string("/var/log/pacman.log")
+        .block_error("pacman", "Failed to read /var/log/pacman.log")?;
+    let last_upgrade = match parse_last_upgrade(&log) {
+        Some(last_upgrade) => last_upgrade,
+        None => return Ok(0),
+    };
+    let response = http::http_get_bytes(
+        "https://archlinux.org/feeds/news/",
+        Some(Duration::from_secs(10)),
+        vec![],
+    )?;
+    if !(200..300).contains(&response.code) {
+        return Err(BlockError(
+            "pacman".to_string(),
+            format!("the news feed replied with status {}", response.code),
+        ));
+    }
+    Ok(parse_feed(&String::from_utf8_lossy(&response.content))
+        .iter()
+        .filter(|entry| entry.date.map_or(false, |date| date > last_upgrade))
+        .count())
+}
+
 fn get_update_count(updates: &str) -> usize {
     updates
         .lines()
@@ -332,6 +452,10 @@ fn has_critical_update(updates: &str, regex: &Regex) -> bool {
 }
 
 impl Block for Pacman {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn id(&self) -> usize {
         self.id
     }
@@ -345,7 +469,7 @@ impl Block for Pacman {
     }
 
     fn update(&mut self) -> Result<Option<Update>> {
-        let (formatting_map, warning, critical, cum_count) = match &self.watched {
+        let (mut formatting_map, warning, critical, pacman_count, aur_count) = match &self.watched {
             Watched::Pacman => {
                 check_fakeroot_command_exists()?;
                 let pacman_available_updates = get_pacman_available_updates()?;
@@ -359,7 +483,7 @@ impl Block for Pacman {
                     has_critical_update(&pacman_available_updates, regex)
                 });
 
-                (formatting_map, warning, critical, pacman_count)
+                (formatting_map, warning, critical, pacman_count, 0)
             }
             Watched::AUR(aur_command) => {
                 let aur_available_updates = get_aur_available_updates(&aur_command)?;
@@ -373,7 +497,7 @@ impl Block for Pacman {
                     has_critical_update(&aur_available_updates, regex)
                 });
 
-                (formatting_map, warning, critical, aur_count)
+                (formatting_map, warning, critical, 0, aur_count)
             }
             Watched::Both(aur_command) => {
                 check_fakeroot_command_exists()?;
@@ -392,15 +516,27 @@ impl Block for Pacman {
                         || has_critical_update(&pacman_available_updates, regex)
                 });
 
-                (formatting_map, warning, critical, pacman_count + aur_count)
+                (formatting_map, warning, critical, pacman_count, aur_count)
             }
         };
+        let critical = critical
+            || self
+                .critical_pacman
+                .map_or(false, |threshold| pacman_count >= threshold)
+            || self
+                .critical_aur
+                .map_or(false, |threshold| aur_count >= threshold);
+        let cum_count = pacman_count + aur_count;
+        let news = if self.news { get_news_count()? } else { 0 };
+        formatting_map.insert("{news}", news);
+        let warning = warning || news > 0;
         self.output.set_text(match cum_count {
             0 => self.format_up_to_date.render_static_str(&formatting_map)?,
             1 => self.format_singular.render_static_str(&formatting_map)?,
             _ => self.format.render_static_str(&formatting_map)?,
         });
         self.output.set_state(match cum_count {
+            0 if news > 0 => State::Warning,
             0 => State::Idle,
             _ => {
                 if critical {
@@ -427,8 +563,11 @@ impl Block for Pacman {
 
 #[cfg(test)]
 mod tests {
+    use chrono::{TimeZone, Utc};
+
     use crate::blocks::pacman::{
-        get_aur_available_updates, get_update_count, PacmanConfig, Watched,
+        get_aur_available_updates, get_update_count, parse_last_upgrade, AurHelper, PacmanConfig,
+        Watched,
     };
 
     #[test]
@@ -485,6 +624,45 @@ mod tests {
         assert_eq!(watched.unwrap(), Watched::Both("aur cmd".to_string()));
     }
 
+    #[test]
+    fn test_parse_last_upgrade() {
+        let log = concat!(
+            "[2021-03-30T09:00:00+0200] [PACMAN] starting full system upgrade\n",
+            "[2021-03-30T09:00:05+0200] [ALPM] upgraded linux (5.11.10-1 -> 5.11.11-1)\n",
+            "[2021-03-31T10:15:30+0200] [PACMAN] starting full system upgrade\n",
+            "[2021-03-31T10:15:31+0200] [PACMAN] Running 'pacman -S vim'\n",
+        );
+        assert_eq!(
+            parse_last_upgrade(log),
+            Some(Utc.ymd(2021, 3, 31).and_hms(8, 15, 30))
+        );
+        assert_eq!(
+            parse_last_upgrade("[2021-03-31T10:15:31+0200] [PACMAN] Running 'pacman -S vim'\n"),
+            None
+        );
+        assert!(
+            parse_last_upgrade("[2018-01-02 10:15] [PACMAN] starting full system upgrade\n")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_aur_command() {
+        assert_eq!(PacmanConfig::aur_command(None, None).unwrap(), None);
+        assert_eq!(
+            PacmanConfig::aur_command(Some("pikaur -Qua".to_string()), None).unwrap(),
+            Some("pikaur -Qua".to_string())
+        );
+        assert_eq!(
+            PacmanConfig::aur_command(None, Some(AurHelper::Paru)).unwrap(),
+            Some("paru -Qua".to_string())
+        );
+        assert!(
+            PacmanConfig::aur_command(Some("pikaur -Qua".to_string()), Some(AurHelper::Yay))
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_get_aur_available_updates() {
         // aur_command should behave as echo -ne "foo x.x -> y.y\n"
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Ping {
+    id: usize,
+    text: TextWidget,
+    host: String,
+    format: FormatTemplate,
+    warning_loss: f64,
+    /// Round-trip times of the last pings, in milliseconds, `None` for those which got no reply
+    samples: Arc<Mutex<VecDeque<Option<f64>>>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PingConfig {
+    /// Host name or address to ping
+    pub host: String,
+
+    /// Time between two pings, in seconds
+    #[serde(
+        default = "PingConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Time to wait for a reply, in seconds
+    #[serde(
+        default = "PingConfig::default_timeout",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub timeout: Duration,
+
+    /// Number of last pings the packet loss is computed over
+    #[serde(default = "PingConfig::default_window")]
+    pub window: usize,
+
+    /// Minimum packet loss in percent, where state is set to warning
+    #[serde(default = "PingConfig::default_warning_loss")]
+    pub warning_loss: f64,
+
+    /// Format override
+    #[serde(default = "PingConfig::default_format")]
+    pub format: String,
+}
+
+impl PingConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_timeout() -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn default_window() -> usize {
+        10
+    }
+
+    fn default_warning_loss() -> f64 {
+        10.
+    }
+
+    fn default_format() -> String {
+        "{?{rtt}|unreachable}".to_owned()
+    }
+}
+
+/// The round-trip time in milliseconds, from the output of `ping`, e.g.
+/// `64 bytes from 9.9.9.9: icmp_seq=1 ttl=59 time=12.3 ms`.
+fn parse_rtt(output: &str) -> Option<f64> {
+    let time = output.find("time=")?;
+    output[time + 5..].split_whitespace().next()?.parse().ok()
+}
+
+fn ping(host: &str, timeout: Duration) -> Option<f64> {
+    let timeout = timeout.as_secs().max(1).to_string();
+    let output = Command::new("ping")
+        .args(&["-n", "-c", "1", "-W", &timeout, host])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_rtt(&String::from_utf8_lossy(&output.stdout))
+}
+
+impl ConfigBlock for Ping {
+    type Config = PingConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{host}", "{loss}", "{rtt}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let samples = Arc::new(Mutex::new(VecDeque::new()));
+
+        // A ping may take as long as the timeout, so don't block the other blocks meanwhile
+        let host = block_config.host.clone();
+        let window = block_config.window.max(1);
+        let timeout = block_config.timeout;
+        let interval = block_config.interval;
+        let samples_copy = samples.clone();
+        thread::Builder::new()
+            .name("ping".into())
+            .spawn(move || loop {
+                let rtt = ping(&host, timeout);
+                {
+                    let mut samples = samples_copy.lock().unwrap();
+                    samples.push_back(rtt);
+                    while samples.len() > window {
+                        samples.pop_front();
+                    }
+                }
+                if send
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .is_err()
+                {
+                    return;
+                }
+                thread::sleep(interval);
+            })
+            .unwrap();
+
+        Ok(Ping {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("ping"),
+            host: block_config.host,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("ping", "Invalid format specified")?,
+            warning_loss: block_config.warning_loss,
+            samples,
+        })
+    }
+}
+
+impl Block for Ping {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let (rtt, loss) = {
+            let samples = self.samples.lock().unwrap();
+            let lost = samples.iter().filter(|rtt| rtt.is_none()).count();
+            let loss = if samples.is_empty() {
+                0.
+            } else {
+                lost as f64 / samples.len() as f64 * 100.
+            };
+            (samples.back().copied(), loss)
+        };
+        let reachable = !matches!(rtt, Some(None));
+
+        let values = map!(
+            "{host}" => Value::text(self.host.clone()),
+            "{rtt}" => Value::text(rtt.flatten().map(|rtt| format!("{:.1}ms", rtt)).unwrap_or_default()),
+            "{loss}" => Value::text(format!("{:.0}%", loss))
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(if !reachable {
+            State::Critical
+        } else if let Some(state) = self.format.state(&values) {
+            state
+        } else if loss > 0. && loss >= self.warning_loss {
+            State::Warning
+        } else {
+            State::Idle
+        });
+
+        // The pinging thread asks for updates
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_rtt;
+
+    #[test]
+    fn test_parse_rtt() {
+        let output = "PING 9.9.9.9 (9.9.9.9) 56(84) bytes of data.
+64 bytes from 9.9.9.9: icmp_seq=1 ttl=59 time=12.3 ms
+
+--- 9.9.9.9 ping statistics ---
+1 packets transmitted, 1 received, 0% packet loss, time 0ms
+rtt min/avg/max/mdev = 12.345/12.345/12.345/0.000 ms
+";
+        assert_eq!(parse_rtt(output), Some(12.3));
+        assert_eq!(parse_rtt("1 packets transmitted, 0 received"), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Runs an external program as a block, talking to it over stdin/stdout.
+//!
+//! The protocol is line-based JSON, in the spirit of the i3bar protocol:
+//!
+//! 1. On startup the plugin receives a single handshake line on stdin with the protocol
+//!    `version`, its own `config` table, the `theme` colors and the `icons` map.
+//! 2. The plugin then prints one JSON object per line on stdout whenever it wants to update
+//!    the block: `{"text": "...", "icon": "...", "state": "..."}`.
+//! 3. Clicks on the block are forwarded to the plugin's stdin as
+//!    `{"event": "click", "button": "left"}`.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use toml::value::Table;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Version of the plugin protocol, sent in the handshake.
+const PROTOCOL_VERSION: u32 = 1;
+
+pub struct Plugin {
+    id: usize,
+    text: TextWidget,
+    command: String,
+    args: Vec<String>,
+    handshake: String,
+    hide_when_empty: bool,
+    is_empty: bool,
+    child: Option<Child>,
+    stdin: Option<ChildStdin>,
+    /// Latest message from the plugin, and whether its stdout has been closed
+    shared: Arc<Mutex<PluginState>>,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Default)]
+struct PluginState {
+    message: Option<PluginMessage>,
+    exited: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    /// Path to the plugin executable
+    pub command: String,
+
+    /// Arguments passed to the plugin executable
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Plugin specific configuration, forwarded as-is in the handshake
+    #[serde(default)]
+    pub config: Table,
+
+    #[serde(default)]
+    pub hide_when_empty: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct PluginMessage {
+    #[serde(default)]
+    icon: String,
+    #[serde(default = "default_state")]
+    state: State,
+    text: String,
+}
+
+fn default_state() -> State {
+    State::Idle
+}
+
+fn button_name(button: MouseButton) -> &'static str {
+    match button {
+        MouseButton::Left => "left",
+        MouseButton::Middle => "middle",
+        MouseButton::Right => "right",
+        MouseButton::WheelUp => "wheel_up",
+        MouseButton::WheelDown => "wheel_down",
+        MouseButton::Forward => "forward",
+        MouseButton::Back => "back",
+        MouseButton::Unknown => "unknown",
+    }
+}
+
+impl ConfigBlock for Plugin {
+    type Config = PluginConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let theme = &shared_config.theme;
+        let handshake = serde_json::json!({
+            "version": PROTOCOL_VERSION,
+            "config": block_config.config,
+            "theme": {
+                "idle_bg": theme.idle_bg,
+                "idle_fg": theme.idle_fg,
+                "info_bg": theme.info_bg,
+                "info_fg": theme.info_fg,
+                "good_bg": theme.good_bg,
+                "good_fg": theme.good_fg,
+                "warning_bg": theme.warning_bg,
+                "warning_fg": theme.warning_fg,
+                "critical_bg": theme.critical_bg,
+                "critical_fg": theme.critical_fg,
+            },
+            "icons": shared_config.icons_map(),
+            "icons_format": shared_config.icons_format(),
+        })
+        .to_string();
+
+        let mut plugin = Plugin {
+            id,
+            text: TextWidget::new(id, 0, shared_config),
+            command: block_config.command,
+            args: block_config.args,
+            handshake,
+            hide_when_empty: block_config.hide_when_empty,
+            is_empty: true,
+            child: None,
+            stdin: None,
+            shared: Arc::new(Mutex::new(PluginState::default())),
+            tx_update_request,
+        };
+        plugin.spawn()?;
+
+        Ok(plugin)
+    }
+}
+
+impl Plugin {
+    /// Starts the plugin process, sends the handshake and watches its output.
+    fn spawn(&mut self) -> Result<()> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .block_error("plugin", &format!("failed to start '{}'", self.command))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .block_error("plugin", "failed to open stdin of the plugin")?;
+        let stdout = child
+            .stdout
+            .take()
+            .block_error("plugin", "failed to open stdout of the plugin")?;
+        writeln!(stdin, "{}", self.handshake)
+            .block_error("plugin", "failed to send the handshake to the plugin")?;
+
+        *self
+            .shared
+            .lock()
+            .block_error("plugin", "failed to acquire lock")? = PluginState::default();
+
+        let id = self.id;
+        let shared = self.shared.clone();
+        let tx = self.tx_update_request.clone();
+        thread::Builder::new()
+            .name("plugin".into())
+            .spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    // Ignore anything that isn't a valid message, so plugins can't kill the bar
+                    if let Ok(message) = serde_json::from_str::<PluginMessage>(&line) {
+                        shared
+                            .lock()
+                            .expect("lock has been poisoned in `plugin` block")
+                            .message = Some(message);
+                        if tx
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                }
+                shared
+                    .lock()
+                    .expect("lock has been poisoned in `plugin` block")
+                    .exited = true;
+                tx.send(Task {
+                    id,
+                    update_time: Instant::now(),
+                })
+                .ok();
+            })
+            .block_error("plugin", "failed to start watching thread")?;
+
+        self.child = Some(child);
+        self.stdin = Some(stdin);
+        Ok(())
+    }
+
+    fn reap(&mut self) {
+        self.stdin = None;
+        if let Some(mut child) = self.child.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+impl Block for Plugin {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let (message, exited) = {
+            let shared = self
+                .shared
+                .lock()
+                .block_error("plugin", "failed to acquire lock")?;
+            (shared.message.clone(), shared.exited)
+        };
+
+        if exited {
+            // Restart the plugin on the next update, which the error policy may schedule
+            if self.child.is_some() {
+                self.reap();
+                return Err(BlockError(
+                    "plugin".to_string(),
+                    format!("'{}' exited", self.command),
+                ));
+            }
+            self.spawn()?;
+            return Ok(None);
+        }
+
+        if let Some(message) = message {
+            self.text.set_icon(&message.icon);
+            self.text.set_state(message.state);
+            self.is_empty = message.text.is_empty();
+            self.text.set_text(message.text);
+        }
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.is_empty && self.hide_when_empty {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let Some(ref mut stdin) = self.stdin {
+            let event = serde_json::json!({
+                "event": "click",
+                "button": button_name(e.button),
+            });
+            // A plugin that is gone is reported by the next update
+            writeln!(stdin, "{}", event).ok();
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        self.reap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PluginMessage;
+
+    #[test]
+    fn test_parse_plugin_message() {
+        let message: PluginMessage =
+            serde_json::from_str(r#"{"text": "hello", "state": "Good", "icon": "bell"}"#).unwrap();
+        assert_eq!(message.text, "hello");
+        assert_eq!(message.icon, "bell");
+
+        let message: PluginMessage = serde_json::from_str(r#"{"text": ""}"#).unwrap();
+        assert_eq!(message.icon, "");
+
+        assert!(serde_json::from_str::<PluginMessage>(r#"{"icon": "bell"}"#).is_err());
+    }
+}
@@ -7,6 +7,7 @@ use serde_derive::Deserialize;
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::formatting::{DurationFormat, TimeUnit};
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::subprocess::spawn_child_async;
@@ -33,27 +34,14 @@ impl State {
 
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            State::Stopped => write!(f, "\u{25a0} 0:00"),
-            State::Started(_) => write!(
-                f,
-                "\u{f04b} {}:{:02}",
-                self.elapsed().as_secs() / 60,
-                self.elapsed().as_secs() % 60
-            ),
-            State::OnBreak(_) => write!(
-                f,
-                "\u{2615} {}:{:02}",
-                self.elapsed().as_secs() / 60,
-                self.elapsed().as_secs() % 60
-            ),
-            State::Paused(duration) => write!(
-                f,
-                "\u{f04c} {}:{:02}",
-                duration.as_secs() / 60,
-                duration.as_secs() % 60
-            ),
-        }
+        let (symbol, elapsed) = match self {
+            State::Stopped => ("\u{25a0}", Duration::from_secs(0)),
+            State::Started(_) => ("\u{f04b}", self.elapsed()),
+            State::OnBreak(_) => ("\u{2615}", self.elapsed()),
+            State::Paused(duration) => ("\u{f04c}", duration.to_owned()),
+        };
+        let clock = DurationFormat::Clock(vec![TimeUnit::Minutes, TimeUnit::Seconds]);
+        write!(f, "{} {}", symbol, clock.render(elapsed))
     }
 }
 
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use dbus::arg::{RefArg, Variant};
+use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+use dbus::blocking::Connection;
+use dbus::ffidisp::{BusType, Connection as SignalConnection};
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const POWER_PROFILES_SERVICE: &str = "net.hadess.PowerProfiles";
+const POWER_PROFILES_PATH: &str = "/net/hadess/PowerProfiles";
+
+/// The active profile being changed, by this block or by anything else
+const PROFILE_CHANGED_RULE: &str = "type='signal',\
+    sender='net.hadess.PowerProfiles',\
+    path='/net/hadess/PowerProfiles',\
+    interface='org.freedesktop.DBus.Properties',\
+    member='PropertiesChanged'";
+
+/// The profile after `active` in `profiles`, or before it when going `backward`, wrapping around.
+fn cycle<'a>(profiles: &'a [String], active: &str, backward: bool) -> Option<&'a str> {
+    let len = profiles.len();
+    let position = profiles.iter().position(|profile| profile == active)?;
+    let next = if backward {
+        (position + len - 1) % len
+    } else {
+        (position + 1) % len
+    };
+    Some(profiles[next].as_str())
+}
+
+pub struct PowerProfiles {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    connection: Connection,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PowerProfilesConfig {
+    /// Format override
+    #[serde(default = "PowerProfilesConfig::default_format")]
+    pub format: String,
+}
+
+impl PowerProfilesConfig {
+    fn default_format() -> String {
+        "{profile}".to_owned()
+    }
+}
+
+impl PowerProfiles {
+    fn get<T: for<'b> dbus::arg::Get<'b>>(&self, property: &str) -> Result<T> {
+        self.connection
+            .with_proxy(
+                POWER_PROFILES_SERVICE,
+                POWER_PROFILES_PATH,
+                Duration::from_millis(1000),
+            )
+            .get(POWER_PROFILES_SERVICE, property)
+            .block_error(
+                "power_profiles",
+                &format!("failed to read the {} property", property),
+            )
+    }
+
+    /// Names of the profiles offered, from the most saving to the most powerful.
+    fn profiles(&self) -> Result<Vec<String>> {
+        let profiles: Vec<HashMap<String, Variant<Box<dyn RefArg>>>> = self.get("Profiles")?;
+        Ok(profiles
+            .iter()
+            .filter_map(|profile| Some(profile.get("Profile")?.0.as_str()?.to_string()))
+            .collect())
+    }
+
+    fn set_text(&mut self, profile: &str) -> Result<()> {
+        let values = map!("{profile}" => profile.to_string());
+        self.text.set_text(self.format.render_static_str(&values)?);
+        Ok(())
+    }
+}
+
+impl ConfigBlock for PowerProfiles {
+    type Config = PowerProfilesConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{profile}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let connection = Connection::new_system()
+            .block_error("power_profiles", "failed to establish D-Bus connection")?;
+
+        thread::Builder::new()
+            .name("power_profiles".into())
+            .spawn(move || {
+                let c = SignalConnection::get_private(BusType::System)
+                    .expect("Failed to establish D-Bus connection in thread");
+                c.add_match(PROFILE_CHANGED_RULE).unwrap();
+
+                loop {
+                    if c.incoming(60_000).next().is_some()
+                        && send
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                    {
+                        return;
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(PowerProfiles {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("power_profile"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("power_profiles", "Invalid format specified")?,
+            connection,
+        })
+    }
+}
+
+impl Block for PowerProfiles {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let active: String = self.get("ActiveProfile")?;
+        self.set_text(&active)?;
+
+        Ok(None)
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        let backward = match event.button {
+            MouseButton::Left | MouseButton::WheelUp => false,
+            MouseButton::Right | MouseButton::WheelDown => true,
+            _ => return Ok(()),
+        };
+        let active: String = self.get("ActiveProfile")?;
+        let profiles = self.profiles()?;
+        if let Some(next) = cycle(&profiles, &active, backward) {
+            self.connection
+                .with_proxy(
+                    POWER_PROFILES_SERVICE,
+                    POWER_PROFILES_PATH,
+                    Duration::from_millis(1000),
+                )
+                .set(POWER_PROFILES_SERVICE, "ActiveProfile", next.to_string())
+                .block_error("power_profiles", "failed to change the power profile")?;
+            self.set_text(next)?;
+        }
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cycle;
+
+    #[test]
+    fn test_cycle() {
+        let profiles: Vec<String> = vec![
+            "power-saver".to_string(),
+            "balanced".to_string(),
+            "performance".to_string(),
+        ];
+        assert_eq!(cycle(&profiles, "balanced", false), Some("performance"));
+        assert_eq!(cycle(&profiles, "performance", false), Some("power-saver"));
+        assert_eq!(cycle(&profiles, "power-saver", true), Some("performance"));
+        assert_eq!(cycle(&profiles, "balanced", true), Some("power-saver"));
+        assert_eq!(cycle(&profiles, "unknown", false), None);
+        assert_eq!(cycle(&[], "balanced", false), None);
+    }
+}
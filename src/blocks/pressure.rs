@@ -0,0 +1,263 @@
+use std::fs::read_to_string;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureResource {
+    Cpu,
+    Memory,
+    Io,
+}
+
+impl PressureResource {
+    fn name(self) -> &'static str {
+        match self {
+            PressureResource::Cpu => "cpu",
+            PressureResource::Memory => "memory",
+            PressureResource::Io => "io",
+        }
+    }
+}
+
+impl Default for PressureResource {
+    fn default() -> Self {
+        Self::Cpu
+    }
+}
+
+pub struct Pressure {
+    id: usize,
+    text: TextWidget,
+    resource: PressureResource,
+    format: FormatTemplate,
+    update_interval: Duration,
+    minimum_info: f64,
+    minimum_warning: f64,
+    minimum_critical: f64,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PressureConfig {
+    /// The resource to show the pressure of
+    #[serde(default)]
+    pub resource: PressureResource,
+
+    #[serde(default = "PressureConfig::default_format")]
+    pub format: String,
+
+    #[serde(
+        default = "PressureConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Minimum share of time stalled over the last 10 seconds, in percent, where state is set
+    /// to info
+    #[serde(default = "PressureConfig::default_info")]
+    pub info: f64,
+
+    /// Minimum share of time stalled over the last 10 seconds, in percent, where state is set
+    /// to warning
+    #[serde(default = "PressureConfig::default_warning")]
+    pub warning: f64,
+
+    /// Minimum share of time stalled over the last 10 seconds, in percent, where state is set
+    /// to critical
+    #[serde(default = "PressureConfig::default_critical")]
+    pub critical: f64,
+}
+
+impl PressureConfig {
+    fn default_format() -> String {
+        "{some10}%".to_owned()
+    }
+
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_info() -> f64 {
+        5.0
+    }
+
+    fn default_warning() -> f64 {
+        20.0
+    }
+
+    fn default_critical() -> f64 {
+        50.0
+    }
+}
+
+/// The averages of a line of a `/proc/pressure` file, in percent.
+#[derive(Debug, Default, PartialEq)]
+struct Stall {
+    avg10: f64,
+    avg60: f64,
+    avg300: f64,
+}
+
+/// Parses the `some` and `full` lines of a `/proc/pressure` file, e.g.
+/// `some avg10=0.31 avg60=0.12 avg300=0.04 total=9123456`.
+///
+/// The `full` line is missing for the CPU on kernels older than 5.13, in which case it is zero.
+fn parse_pressure(pressure: &str) -> Option<(Stall, Stall)> {
+    let mut some = None;
+    let mut full = Stall::default();
+    for line in pressure.lines() {
+        let mut words = line.split_whitespace();
+        let kind = words.next()?;
+        let mut stall = Stall::default();
+        for word in words {
+            let mut pair = word.splitn(2, '=');
+            let key = pair.next()?;
+            let value = pair.next()?;
+            match key {
+                "avg10" => stall.avg10 = value.parse().ok()?,
+                "avg60" => stall.avg60 = value.parse().ok()?,
+                "avg300" => stall.avg300 = value.parse().ok()?,
+                _ => {}
+            }
+        }
+        match kind {
+            "some" => some = Some(stall),
+            "full" => full = stall,
+            _ => {}
+        }
+    }
+    Some((some?, full))
+}
+
+impl ConfigBlock for Pressure {
+    type Config = PressureConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{full10}",
+            "{full300}",
+            "{full60}",
+            "{resource}",
+            "{some10}",
+            "{some300}",
+            "{some60}",
+        ],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Pressure {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            resource: block_config.resource,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("pressure", "Invalid format specified")?,
+            update_interval: block_config.interval,
+            minimum_info: block_config.info,
+            minimum_warning: block_config.warning,
+            minimum_critical: block_config.critical,
+        })
+    }
+}
+
+impl Block for Pressure {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let path = format!("/proc/pressure/{}", self.resource.name());
+        let pressure = read_to_string(&path).block_error(
+            "pressure",
+            &format!(
+                "failed to read {}, is the kernel built with CONFIG_PSI?",
+                path
+            ),
+        )?;
+        let (some, full) = parse_pressure(&pressure)
+            .block_error("pressure", &format!("failed to parse {}", path))?;
+
+        let values = map!(
+            "{resource}" => self.resource.name().to_string(),
+            "{some10}" => format!("{:.2}", some.avg10),
+            "{some60}" => format!("{:.2}", some.avg60),
+            "{some300}" => format!("{:.2}", some.avg300),
+            "{full10}" => format!("{:.2}", full.avg10),
+            "{full60}" => format!("{:.2}", full.avg60),
+            "{full300}" => format!("{:.2}", full.avg300)
+        );
+
+        self.text.set_state(match some.avg10 {
+            x if x >= self.minimum_critical => State::Critical,
+            x if x >= self.minimum_warning => State::Warning,
+            x if x >= self.minimum_info => State::Info,
+            _ => State::Idle,
+        });
+        self.text.set_text(self.format.render_static_str(&values)?);
+        if let Some(state) = self.format.state_static_str(&values) {
+            self.text.set_state(state);
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_pressure, Stall};
+
+    #[test]
+    fn test_parse_pressure() {
+        let pressure = "some avg10=1.53 avg60=0.87 avg300=0.22 total=47271426
+full avg10=0.41 avg60=0.20 avg300=0.05 total=18316397
+";
+        assert_eq!(
+            parse_pressure(pressure),
+            Some((
+                Stall {
+                    avg10: 1.53,
+                    avg60: 0.87,
+                    avg300: 0.22,
+                },
+                Stall {
+                    avg10: 0.41,
+                    avg60: 0.20,
+                    avg300: 0.05,
+                }
+            ))
+        );
+
+        let pressure = "some avg10=12.00 avg60=3.10 avg300=0.70 total=7612371\n";
+        assert_eq!(
+            parse_pressure(pressure).map(|(some, full)| (some.avg10, full)),
+            Some((12.0, Stall::default()))
+        );
+
+        assert_eq!(parse_pressure("full avg10=0.00 avg60=0.00"), None);
+    }
+}
@@ -0,0 +1,218 @@
+use std::fs::{read_dir, read_link, read_to_string};
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Privacy {
+    id: usize,
+    camera: TextWidget,
+    microphone: TextWidget,
+    update_interval: Duration,
+    show_apps: bool,
+    ignored_apps: Vec<String>,
+    camera_apps: Vec<String>,
+    microphone_apps: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PrivacyConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "PrivacyConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Show the names of the applications using the devices next to the icons
+    #[serde(default)]
+    pub show_apps: bool,
+
+    /// Names of applications which are not reported, e.g. peak meters of mixers
+    #[serde(default)]
+    pub ignored_apps: Vec<String>,
+}
+
+impl PrivacyConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+}
+
+/// The names of the processes which have a video device open, as far as we may look into them.
+fn camera_apps() -> Vec<String> {
+    let mut apps = Vec::new();
+    let processes = match read_dir("/proc") {
+        Ok(processes) => processes,
+        Err(_) => return apps,
+    };
+    for process in processes.flatten() {
+        // Other users' descriptors can't be read, which also skips non-process entries
+        let fds = match read_dir(process.path().join("fd")) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        let uses_camera = fds.flatten().any(|fd| {
+            read_link(fd.path())
+                .map(|target| target.to_string_lossy().starts_with("/dev/video"))
+                .unwrap_or(false)
+        });
+        if uses_camera {
+            if let Ok(name) = read_to_string(process.path().join("comm")) {
+                apps.push(name.trim().to_string());
+            }
+        }
+    }
+    apps
+}
+
+/// The names of the applications recording from the output of `pactl list source-outputs`,
+/// which PipeWire also provides through its PulseAudio server.
+fn parse_source_outputs(output: &str) -> Vec<String> {
+    let mut apps = Vec::new();
+    // The binary name is only a fallback for streams without an application name
+    let mut stream: Option<(Option<String>, Option<String>)> = None;
+    for line in output.lines().map(str::trim) {
+        if line.starts_with("Source Output #") {
+            if let Some((name, binary)) = stream.take() {
+                apps.push(name.or(binary).unwrap_or_else(|| "unknown".to_string()));
+            }
+            stream = Some((None, None));
+        } else if let Some((ref mut name, ref mut binary)) = stream {
+            let property = |key: &str| {
+                line.strip_prefix(key).map(|value| {
+                    value
+                        .trim_start_matches(" = ")
+                        .trim_matches('"')
+                        .to_string()
+                })
+            };
+            if let Some(value) = property("application.name") {
+                *name = Some(value);
+            } else if let Some(value) = property("application.process.binary") {
+                *binary = Some(value);
+            }
+        }
+    }
+    if let Some((name, binary)) = stream {
+        apps.push(name.or(binary).unwrap_or_else(|| "unknown".to_string()));
+    }
+    apps
+}
+
+fn microphone_apps() -> Vec<String> {
+    Command::new("pactl")
+        .args(&["list", "source-outputs"])
+        // Property names are translated otherwise
+        .env("LC_ALL", "C")
+        .output()
+        .map(|output| parse_source_outputs(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default()
+}
+
+impl Privacy {
+    /// Sorts `apps` and removes the ignored ones and duplicates.
+    fn filter(&self, mut apps: Vec<String>) -> Vec<String> {
+        apps.retain(|app| !self.ignored_apps.contains(app));
+        apps.sort();
+        apps.dedup();
+        apps
+    }
+}
+
+impl ConfigBlock for Privacy {
+    type Config = PrivacyConfig;
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Privacy {
+            id,
+            camera: TextWidget::new(id, 0, shared_config.clone())
+                .with_icon("webcam")
+                .with_state(State::Warning),
+            microphone: TextWidget::new(id, 1, shared_config)
+                .with_icon("microphone_full")
+                .with_state(State::Warning),
+            update_interval: block_config.interval,
+            show_apps: block_config.show_apps,
+            ignored_apps: block_config.ignored_apps,
+            camera_apps: Vec::new(),
+            microphone_apps: Vec::new(),
+        })
+    }
+}
+
+impl Block for Privacy {
+    fn update(&mut self) -> Result<Option<Update>> {
+        self.camera_apps = self.filter(camera_apps());
+        self.microphone_apps = self.filter(microphone_apps());
+
+        if self.show_apps {
+            self.camera.set_text(self.camera_apps.join(", "));
+            self.microphone.set_text(self.microphone_apps.join(", "));
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        let mut widgets: Vec<&dyn I3BarWidget> = Vec::new();
+        if !self.camera_apps.is_empty() {
+            widgets.push(&self.camera);
+        }
+        if !self.microphone_apps.is_empty() {
+            widgets.push(&self.microphone);
+        }
+        widgets
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_source_outputs;
+
+    #[test]
+    fn test_parse_source_outputs() {
+        let output = r#"Source Output #42
+	Driver: protocol-native.c
+	Owner Module: 10
+	Client: 35
+	Source: 1
+	Properties:
+		media.name = "RecordStream"
+		application.name = "Firefox"
+		application.process.binary = "firefox"
+Source Output #43
+	Driver: PipeWire
+	Source: 1
+	Properties:
+		application.process.binary = "arecord"
+Source Output #44
+	Driver: PipeWire
+	Source: 1
+"#;
+        assert_eq!(
+            parse_source_outputs(output),
+            vec!["Firefox", "arecord", "unknown"]
+        );
+        assert!(parse_source_outputs("").is_empty());
+    }
+}
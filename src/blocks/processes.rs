@@ -0,0 +1,326 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessSort {
+    Cpu,
+    Memory,
+}
+
+impl Default for ProcessSort {
+    fn default() -> Self {
+        Self::Cpu
+    }
+}
+
+/// Parses `/proc/<pid>/stat` into the name of the process and the clock ticks it has been
+/// scheduled for, in user and kernel mode. The name is in parentheses and may contain anything,
+/// including spaces and parentheses.
+fn parse_stat(stat: &str) -> Option<(String, u64)> {
+    let start = stat.find('(')?;
+    let end = stat.rfind(')')?;
+    let name = stat.get(start + 1..end)?.to_string();
+    // The fields after the name start with the third one, the state
+    let fields: Vec<&str> = stat[end + 1..].split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some((name, utime + stime))
+}
+
+/// Parses the resident memory of a process, in bytes, from `/proc/<pid>/status`. Kernel threads
+/// have none.
+fn parse_resident_memory(status: &str) -> u64 {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.split_whitespace().next()?.parse::<u64>().ok())
+        .map_or(0, |kib| kib * 1024)
+}
+
+/// Parses `/proc/stat` into the clock ticks elapsed on all the CPUs together, and the number
+/// of CPUs.
+fn parse_total_ticks(stat: &str) -> Option<(u64, usize)> {
+    let total = stat
+        .lines()
+        .find(|line| line.starts_with("cpu "))?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|ticks| ticks.parse::<u64>().ok())
+        .sum();
+    let cpus = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu") && !line.starts_with("cpu "))
+        .count();
+    Some((total, cpus.max(1)))
+}
+
+struct Process {
+    pid: u32,
+    name: String,
+    ticks: u64,
+    memory: u64,
+}
+
+/// The processes currently running, and the clock ticks elapsed on all the CPUs with the number
+/// of CPUs.
+fn sample() -> Result<(Vec<Process>, u64, usize)> {
+    let (total, cpus) = parse_total_ticks(
+        &read_to_string("/proc/stat").block_error("processes", "failed to read /proc/stat")?,
+    )
+    .block_error("processes", "failed to parse /proc/stat")?;
+
+    let mut processes = Vec::new();
+    let entries = std::fs::read_dir("/proc").block_error("processes", "failed to read /proc")?;
+    for entry in entries.flatten() {
+        let pid = match entry.file_name().to_string_lossy().parse::<u32>() {
+            Ok(pid) => pid,
+            Err(_) => continue,
+        };
+        // The process may have exited meanwhile
+        let (name, ticks) = match read_to_string(entry.path().join("stat"))
+            .ok()
+            .and_then(|stat| parse_stat(&stat))
+        {
+            Some(stat) => stat,
+            None => continue,
+        };
+        let memory = read_to_string(entry.path().join("status"))
+            .map_or(0, |status| parse_resident_memory(&status));
+        processes.push(Process {
+            pid,
+            name,
+            ticks,
+            memory,
+        });
+    }
+    Ok((processes, total, cpus))
+}
+
+pub struct Processes {
+    id: usize,
+    texts: Vec<TextWidget>,
+    update_interval: Duration,
+    format: FormatTemplate,
+    sort_by: ProcessSort,
+    count: usize,
+    memory_total: u64,
+    /// Ticks of each process and elapsed in total at the previous update
+    previous_ticks: HashMap<u32, u64>,
+    previous_total: u64,
+    shared_config: SharedConfig,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessesConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "ProcessesConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "ProcessesConfig::default_format")]
+    pub format: String,
+
+    /// Whether to show the processes using the most CPU or the most memory
+    #[serde(default)]
+    pub sort_by: ProcessSort,
+
+    /// Number of processes to show
+    #[serde(default = "ProcessesConfig::default_count")]
+    pub count: usize,
+}
+
+impl ProcessesConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(5)
+    }
+
+    fn default_format() -> String {
+        "{name} {cpu}".to_owned()
+    }
+
+    fn default_count() -> usize {
+        1
+    }
+}
+
+impl ConfigBlock for Processes {
+    type Config = ProcessesConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{cpu}",
+            "{memory_percentage}",
+            "{memory}",
+            "{name}",
+            "{pid}",
+        ],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let memory_total = read_to_string("/proc/meminfo")
+            .ok()
+            .and_then(|meminfo| {
+                meminfo
+                    .lines()
+                    .find_map(|line| line.strip_prefix("MemTotal:"))?
+                    .split_whitespace()
+                    .next()?
+                    .parse::<u64>()
+                    .ok()
+            })
+            .block_error(
+                "processes",
+                "failed to read the total memory from /proc/meminfo",
+            )?
+            * 1024;
+        // The CPU usage is measured between two updates, so the first one needs a sample
+        let (processes, previous_total, _) = sample()?;
+
+        Ok(Processes {
+            id,
+            texts: Vec::new(),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("processes", "Invalid format specified")?,
+            sort_by: block_config.sort_by,
+            count: block_config.count,
+            memory_total,
+            previous_ticks: processes
+                .iter()
+                .map(|process| (process.pid, process.ticks))
+                .collect(),
+            previous_total,
+            shared_config,
+        })
+    }
+}
+
+impl Block for Processes {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let (processes, total, cpus) = sample()?;
+        // Usage in percent of one CPU, as shown by top
+        let elapsed = total.saturating_sub(self.previous_total) as f64 / cpus as f64;
+        let mut usages: Vec<(&Process, f64)> = processes
+            .iter()
+            .map(|process| {
+                let previous = self.previous_ticks.get(&process.pid).copied().unwrap_or(0);
+                let cpu = if elapsed > 0. {
+                    process.ticks.saturating_sub(previous) as f64 / elapsed * 100.
+                } else {
+                    0.
+                };
+                (process, cpu)
+            })
+            .collect();
+        match self.sort_by {
+            ProcessSort::Cpu => {
+                usages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal))
+            }
+            ProcessSort::Memory => usages.sort_by(|a, b| b.0.memory.cmp(&a.0.memory)),
+        }
+
+        let mut texts = Vec::new();
+        for (i, (process, cpu)) in usages.iter().take(self.count).enumerate() {
+            let values = map!(
+                "{name}" => Value::text(process.name.clone()),
+                "{pid}" => Value::text(process.pid.to_string()),
+                "{cpu}" => Value::text(format!("{:.0}%", cpu)),
+                "{memory}" => Value::bytes(process.memory as f64),
+                "{memory_percentage}" => Value::text(format!(
+                    "{:.0}%",
+                    process.memory as f64 / self.memory_total as f64 * 100.
+                ))
+            );
+            let mut text = TextWidget::new(self.id, i, self.shared_config.clone());
+            if i == 0 {
+                text = text.with_icon("cogs");
+            }
+            text.set_text(self.format.render(&values)?);
+            texts.push(text);
+        }
+        self.texts = texts;
+
+        self.previous_ticks = processes
+            .iter()
+            .map(|process| (process.pid, process.ticks))
+            .collect();
+        self.previous_total = total;
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.texts
+            .iter()
+            .map(|text| text as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_resident_memory, parse_stat, parse_total_ticks};
+
+    #[test]
+    fn test_parse_stat() {
+        let stat = "4242 (Web Content (1)) S 1 4242 4242 0 -1 4194560 1234 0 0 0 150 25 0 0 \
+                    20 0 30 0 12345 1000000 5000 18446744073709551615";
+        assert_eq!(parse_stat(stat), Some(("Web Content (1)".to_string(), 175)));
+        assert_eq!(parse_stat("4242 (truncated) S 1"), None);
+    }
+
+    #[test]
+    fn test_parse_resident_memory() {
+        let status = "Name:\tfirefox\nVmPeak:\t  300000 kB\nVmRSS:\t  204800 kB\nThreads:\t80\n";
+        assert_eq!(parse_resident_memory(status), 204800 * 1024);
+        // A kernel thread
+        assert_eq!(
+            parse_resident_memory("Name:\tkworker/0:1\nThreads:\t1\n"),
+            0
+        );
+    }
+
+    #[test]
+    fn test_parse_total_ticks() {
+        let stat = "cpu  100 0 50 800 10 0 5 0 0 0\n\
+                    cpu0 50 0 25 400 5 0 3 0 0 0\n\
+                    cpu1 50 0 25 400 5 0 2 0 0 0\n\
+                    intr 12345\n";
+        assert_eq!(parse_total_ticks(stat), Some((965, 2)));
+        assert_eq!(parse_total_ticks("intr 12345\n"), None);
+    }
+}
@@ -0,0 +1,183 @@
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::{Node, NodeType};
+use swayipc::{Connection, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+const SCRATCHPAD_WORKSPACE: &str = "__i3_scratch";
+
+pub struct Scratchpad {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    hide_when_empty: bool,
+    scratchpad_count: usize,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScratchpadConfig {
+    /// Format override
+    #[serde(default = "ScratchpadConfig::default_format")]
+    pub format: String,
+
+    /// Hide the block if the scratchpad is empty
+    #[serde(default = "ScratchpadConfig::default_hide_when_empty")]
+    pub hide_when_empty: bool,
+}
+
+impl ScratchpadConfig {
+    fn default_format() -> String {
+        "{scratchpad}".to_owned()
+    }
+
+    fn default_hide_when_empty() -> bool {
+        false
+    }
+}
+
+/// Counts the windows (leaf containers) below `node`.
+fn count_windows(node: &Node) -> usize {
+    if node.nodes.is_empty()
+        && node.floating_nodes.is_empty()
+        && (node.node_type == NodeType::Con || node.node_type == NodeType::FloatingCon)
+    {
+        return 1;
+    }
+    node.nodes
+        .iter()
+        .chain(node.floating_nodes.iter())
+        .map(count_windows)
+        .sum()
+}
+
+fn has_focus(node: &Node) -> bool {
+    node.focused
+        || node
+            .nodes
+            .iter()
+            .chain(node.floating_nodes.iter())
+            .any(has_focus)
+}
+
+/// Returns the workspace nodes of the tree.
+fn workspaces(node: &Node) -> Vec<&Node> {
+    if node.node_type == NodeType::Workspace {
+        return vec![node];
+    }
+    node.nodes.iter().flat_map(workspaces).collect()
+}
+
+impl ConfigBlock for Scratchpad {
+    type Config = ScratchpadConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{scratchpad}", "{windows}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let _test_conn =
+            Connection::new().block_error("scratchpad", "failed to acquire connect to IPC")?;
+
+        thread::Builder::new()
+            .name("scratchpad".into())
+            .spawn(move || {
+                let conn = Connection::new().expect("failed to open connection with swayipc");
+                let events = conn
+                    .subscribe(&[EventType::Window, EventType::Workspace])
+                    .expect("could not subscribe to window events");
+
+                for _event in events {
+                    if tx_update_request
+                        .send(Task {
+                            id,
+                            update_time: Instant::now(),
+                        })
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            })
+            .block_error("scratchpad", "failed to start watching thread")?;
+
+        Ok(Scratchpad {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("scratchpad"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("scratchpad", "Invalid format specified")?,
+            hide_when_empty: block_config.hide_when_empty,
+            scratchpad_count: 0,
+        })
+    }
+}
+
+impl Block for Scratchpad {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let tree = Connection::new()
+            .block_error("scratchpad", "failed to acquire connect to IPC")?
+            .get_tree()
+            .block_error("scratchpad", "failed to get tree")?;
+
+        let workspaces = workspaces(&tree);
+        self.scratchpad_count = workspaces
+            .iter()
+            .find(|w| w.name.as_deref() == Some(SCRATCHPAD_WORKSPACE))
+            .map(|w| count_windows(w))
+            .unwrap_or(0);
+        let focused_count = workspaces
+            .iter()
+            .find(|w| has_focus(w))
+            .map(|w| count_windows(w))
+            .unwrap_or(0);
+
+        let values = map!(
+            "{scratchpad}" => self.scratchpad_count.to_string(),
+            "{windows}" => focused_count.to_string()
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.hide_when_empty && self.scratchpad_count == 0 {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        if let MouseButton::Left = e.button {
+            Connection::new()
+                .block_error("scratchpad", "failed to acquire connect to IPC")?
+                .run_command("scratchpad show")
+                .block_error("scratchpad", "failed to run IPC command")?;
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use dbus::ffidisp::{BusType, Connection};
+use dbus::message::MessageType;
+use dbus::strings::Path;
+use dbus::Message;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Messages telling when screencast sessions of xdg-desktop-portal start and end
+const RULES: &[&str] = &[
+    "type='method_call',interface='org.freedesktop.portal.ScreenCast',member='Start'",
+    "type='method_call',interface='org.freedesktop.portal.Session',member='Close'",
+    "type='signal',interface='org.freedesktop.portal.Session',member='Closed'",
+    // The portal closes the sessions of applications which leave the bus
+    "type='signal',interface='org.freedesktop.DBus',member='NameOwnerChanged'",
+];
+
+pub struct Screencast {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    /// Object paths of the active sessions
+    sessions: Arc<Mutex<HashSet<String>>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScreencastConfig {
+    /// Format override
+    #[serde(default = "ScreencastConfig::default_format")]
+    pub format: String,
+}
+
+impl ScreencastConfig {
+    fn default_format() -> String {
+        "".to_owned()
+    }
+}
+
+/// The prefix of the object paths of the sessions created by the client with the unique bus
+/// name `sender`, e.g. `:1.42`, as specified by xdg-desktop-portal.
+fn session_prefix(sender: &str) -> String {
+    format!(
+        "/org/freedesktop/portal/desktop/session/{}/",
+        sender.trim_start_matches(':').replace('.', "_")
+    )
+}
+
+/// Updates the active `sessions` from a monitored message, returning whether they changed.
+fn handle_message(msg: &Message, sessions: &mut HashSet<String>) -> bool {
+    let member = match msg.member() {
+        Some(member) => member,
+        None => return false,
+    };
+    match (msg.msg_type(), &*member) {
+        (MessageType::MethodCall, "Start") => match msg.read1::<Path>() {
+            Ok(session) => sessions.insert(session.to_string()),
+            Err(_) => false,
+        },
+        (MessageType::MethodCall, "Close") | (MessageType::Signal, "Closed") => match msg.path() {
+            Some(session) => sessions.remove(&*session),
+            None => false,
+        },
+        (MessageType::Signal, "NameOwnerChanged") => match msg.read3::<&str, &str, &str>() {
+            Ok((name, _, "")) if name.starts_with(':') => {
+                let prefix = session_prefix(name);
+                let count = sessions.len();
+                sessions.retain(|session| !session.starts_with(&prefix));
+                sessions.len() != count
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+impl ConfigBlock for Screencast {
+    type Config = ScreencastConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[("format", &["{count}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let sessions = Arc::new(Mutex::new(HashSet::new()));
+        let sessions_copy = sessions.clone();
+
+        thread::Builder::new()
+            .name("screencast".into())
+            .spawn(move || {
+                let c = Connection::get_private(BusType::Session)
+                    .expect("Failed to establish D-Bus connection in thread");
+
+                // Monitoring lets us see calls meant for the portal, but the connection cannot
+                // send anything afterwards
+                let m = Message::new_method_call(
+                    "org.freedesktop.DBus",
+                    "/org/freedesktop/DBus",
+                    "org.freedesktop.DBus.Monitoring",
+                    "BecomeMonitor",
+                )
+                .unwrap()
+                .append2(RULES.to_vec(), 0u32);
+                c.send_with_reply_and_block(m, 1000)
+                    .expect("Failed to monitor the D-Bus session bus");
+
+                loop {
+                    for msg in c.incoming(1000) {
+                        if handle_message(&msg, &mut sessions_copy.lock().unwrap())
+                            && send
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(Screencast {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone())
+                .with_icon("screencast")
+                .with_state(State::Warning),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("screencast", "Invalid format specified")?,
+            sessions,
+        })
+    }
+}
+
+impl Block for Screencast {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let count = self.sessions.lock().unwrap().len();
+        let values = map!(
+            "{count}" => Value::text(count.to_string())
+        );
+        self.text.set_text(self.format.render(&values)?);
+
+        // The monitoring thread asks for updates
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.sessions.lock().unwrap().is_empty() {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use dbus::strings::Path;
+    use dbus::Message;
+
+    use super::{handle_message, session_prefix};
+
+    const SESSION: &str = "/org/freedesktop/portal/desktop/session/1_42/obs";
+
+    #[test]
+    fn test_session_prefix() {
+        assert_eq!(
+            session_prefix(":1.42"),
+            "/org/freedesktop/portal/desktop/session/1_42/"
+        );
+    }
+
+    #[test]
+    fn test_handle_message() {
+        let mut sessions = HashSet::new();
+        let start = Message::new_method_call(
+            "org.freedesktop.portal.Desktop",
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.ScreenCast",
+            "Start",
+        )
+        .unwrap()
+        .append2(Path::new(SESSION).unwrap(), "");
+        assert!(handle_message(&start, &mut sessions));
+        assert!(sessions.contains(SESSION));
+
+        let unrelated = Message::new_signal(
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameOwnerChanged",
+        )
+        .unwrap()
+        .append3(":1.43", ":1.43", "");
+        assert!(!handle_message(&unrelated, &mut sessions));
+
+        let left = Message::new_signal(
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameOwnerChanged",
+        )
+        .unwrap()
+        .append3(":1.42", ":1.42", "");
+        assert!(handle_message(&left, &mut sessions));
+        assert!(sessions.is_empty());
+
+        assert!(handle_message(&start, &mut sessions));
+        let close = Message::new_method_call(
+            "org.freedesktop.portal.Desktop",
+            SESSION,
+            "org.freedesktop.portal.Session",
+            "Close",
+        )
+        .unwrap();
+        assert!(handle_message(&close, &mut sessions));
+        assert!(sessions.is_empty());
+    }
+}
@@ -181,13 +181,15 @@ impl SoundDevice for AlsaSoundDevice {
                     // Block until we get some output. Doesn't really matter what
                     // the output actually is -- these are events -- we just update
                     // the sound information if *something* happens.
-                    if monitor.read(&mut buffer).is_ok() {
-                        tx_update_request
+                    if monitor.read(&mut buffer).is_ok()
+                        && tx_update_request
                             .send(Task {
                                 id,
                                 update_time: Instant::now(),
                             })
-                            .unwrap();
+                            .is_err()
+                    {
+                        return;
                     }
                     // Don't update too often. Wait 1/4 second, fast enough for
                     // volume button mashing but slow enough to skip event spam.
@@ -550,14 +552,18 @@ impl PulseAudioClient {
     }
 
     fn send_update_event() {
-        for (id, tx_update_request) in &*PULSEAUDIO_EVENT_LISTENER.lock().unwrap() {
-            tx_update_request
-                .send(Task {
-                    id: *id,
-                    update_time: Instant::now(),
-                })
-                .unwrap();
-        }
+        // Forget the blocks which the bar dropped, e.g. after reloading the config
+        PULSEAUDIO_EVENT_LISTENER
+            .lock()
+            .unwrap()
+            .retain(|id, tx_update_request| {
+                tx_update_request
+                    .send(Task {
+                        id: *id,
+                        update_time: Instant::now(),
+                    })
+                    .is_ok()
+            });
     }
 }
 
@@ -674,6 +680,159 @@ impl SoundDevice for PulseAudioSoundDevice {
     }
 }
 
+struct PipeWireSoundDevice {
+    /// Node ID or name, as accepted by `wpctl`
+    target: String,
+    name: String,
+    volume: u32,
+    muted: bool,
+}
+
+impl PipeWireSoundDevice {
+    fn new(device_kind: DeviceKind, name: Option<String>) -> Result<Self> {
+        let target = name.unwrap_or_else(|| {
+            match device_kind {
+                DeviceKind::Sink => "@DEFAULT_AUDIO_SINK@",
+                DeviceKind::Source => "@DEFAULT_AUDIO_SOURCE@",
+            }
+            .into()
+        });
+        let mut sd = PipeWireSoundDevice {
+            target,
+            name: String::new(),
+            volume: 0,
+            muted: false,
+        };
+        sd.get_info()?;
+
+        Ok(sd)
+    }
+
+    fn wpctl(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("wpctl")
+            .args(args)
+            .output()
+            .block_error("sound", "could not run wpctl")?;
+        if !output.status.success() {
+            return Err(BlockError(
+                "sound".into(),
+                format!("wpctl failed for {}", self.target),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Parses the output of `wpctl get-volume`, e.g. `Volume: 0.40 [MUTED]`, into the volume in
+/// percent and whether the node is muted.
+fn parse_wpctl_volume(output: &str) -> Option<(u32, bool)> {
+    let mut words = output.trim().strip_prefix("Volume:")?.split_whitespace();
+    let volume = words.next()?.parse::<f64>().ok()?;
+    let muted = words.any(|word| word == "[MUTED]");
+    Some(((volume * 100.).round() as u32, muted))
+}
+
+/// Finds the `node.name` property in the output of `wpctl inspect`, whose lines look like
+/// `  * node.name = "alsa_output.pci-0000_00_1f.3.analog-stereo"`.
+fn parse_wpctl_node_name(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut parts = line
+            .trim_start_matches(|c| c == ' ' || c == '*')
+            .splitn(2, " = ");
+        if parts.next()? != "node.name" {
+            return None;
+        }
+        Some(parts.next()?.trim().trim_matches('"').to_string())
+    })
+}
+
+impl SoundDevice for PipeWireSoundDevice {
+    fn volume(&self) -> u32 {
+        self.volume
+    }
+    fn muted(&self) -> bool {
+        self.muted
+    }
+    fn output_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_info(&mut self) -> Result<()> {
+        let (volume, muted) = parse_wpctl_volume(&self.wpctl(&["get-volume", &self.target])?)
+            .block_error("sound", "could not parse the volume from wpctl")?;
+        self.volume = volume;
+        self.muted = muted;
+        // The default node may change, so its name is looked up each time
+        self.name =
+            parse_wpctl_node_name(&self.wpctl(&["inspect", &self.target])?).unwrap_or_default();
+
+        Ok(())
+    }
+
+    fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()> {
+        let new_vol = max(0, self.volume as i32 + step) as u32;
+        let capped_volume = if let Some(vol_cap) = max_vol {
+            min(new_vol, vol_cap)
+        } else {
+            new_vol
+        };
+        self.wpctl(&["set-volume", &self.target, &format!("{}%", capped_volume)])?;
+
+        self.volume = capped_volume;
+
+        Ok(())
+    }
+
+    fn toggle(&mut self) -> Result<()> {
+        self.wpctl(&["set-mute", &self.target, "toggle"])?;
+
+        self.muted = !self.muted;
+
+        Ok(())
+    }
+
+    fn monitor(&mut self, id: usize, tx_update_request: Sender<Task>) -> Result<()> {
+        // `pw-dump --monitor` prints the changed objects of the graph whenever any changes,
+        // including the volume of nodes and the default nodes.
+        thread::Builder::new()
+            .name("sound_pipewire".into())
+            .spawn(move || {
+                // Line-buffer to reduce noise.
+                let mut monitor = Command::new("stdbuf")
+                    .args(&["-oL", "pw-dump", "--monitor"])
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .expect("Failed to start pw-dump")
+                    .stdout
+                    .expect("Failed to pipe pw-dump output");
+
+                let mut buffer = [0; 4096];
+                loop {
+                    match monitor.read(&mut buffer) {
+                        // pw-dump exited, e.g. because PipeWire was stopped
+                        Ok(0) | Err(_) => return,
+                        Ok(_) => {
+                            if tx_update_request
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                            {
+                                return;
+                            }
+                        }
+                    }
+                    // Each change is printed as a burst of JSON, so wait a bit to update once
+                    thread::sleep(Duration::new(0, 250_000_000))
+                }
+            })
+            .unwrap();
+
+        Ok(())
+    }
+}
+
 // TODO: Use the alsa control bindings to implement push updates
 pub struct Sound {
     text: TextWidget,
@@ -767,6 +926,7 @@ pub enum SoundDriver {
     Alsa,
     #[cfg(feature = "pulseaudio")]
     PulseAudio,
+    PipeWire,
 }
 
 impl Default for SoundDriver {
@@ -880,6 +1040,9 @@ impl Sound {
 impl ConfigBlock for Sound {
     type Config = SoundConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{output_name}", "{volume}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -914,6 +1077,9 @@ impl ConfigBlock for Sound {
         // prefer PulseAudio if available and selected, fallback to ALSA
         let device: Box<dyn SoundDevice> = match pulseaudio_device {
             Ok(dev) => Box::new(dev),
+            Err(_) if matches!(block_config.driver, SoundDriver::PipeWire) => Box::new(
+                PipeWireSoundDevice::new(block_config.device_kind, block_config.name)?,
+            ),
             Err(_) => Box::new(AlsaSoundDevice::new(
                 block_config.name.unwrap_or_else(|| "Master".into()),
                 block_config.device.unwrap_or_else(|| "default".into()),
@@ -925,7 +1091,7 @@ impl ConfigBlock for Sound {
             id,
             device,
             device_kind: block_config.device_kind,
-            format: FormatTemplate::from_string(&block_config.format)?,
+            format: FormatTemplate::new(&block_config.format, &shared_config)?,
             step_width,
             on_click: None,
             show_volume_when_muted: block_config.show_volume_when_muted,
@@ -950,6 +1116,10 @@ impl ConfigBlock for Sound {
 const FILTER: &[char] = &['[', ']', '%'];
 
 impl Block for Sound {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         self.display()?;
         Ok(None)
@@ -959,6 +1129,10 @@ impl Block for Sound {
         vec![&self.text]
     }
 
+    fn percentage(&self) -> Option<u64> {
+        Some(self.device.volume() as u64)
+    }
+
     fn click(&mut self, e: &I3BarEvent) -> Result<()> {
         match e.button {
             MouseButton::Right => self.device.toggle()?,
@@ -990,3 +1164,34 @@ impl Block for Sound {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_wpctl_node_name, parse_wpctl_volume};
+
+    #[test]
+    fn test_parse_wpctl_volume() {
+        assert_eq!(parse_wpctl_volume("Volume: 0.40\n"), Some((40, false)));
+        assert_eq!(
+            parse_wpctl_volume("Volume: 1.25 [MUTED]\n"),
+            Some((125, true))
+        );
+        assert_eq!(parse_wpctl_volume("Translate ID failed\n"), None);
+    }
+
+    #[test]
+    fn test_parse_wpctl_node_name() {
+        let output = r#"id 52, type PipeWire:Interface:Node
+    audio.channels = "2"
+  * media.class = "Audio/Sink"
+  * node.description = "Built-in Audio Analog Stereo"
+  * node.name = "alsa_output.pci-0000_00_1f.3.analog-stereo"
+    node.nick = "ALC257 Analog"
+"#;
+        assert_eq!(
+            parse_wpctl_node_name(output),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo".to_string())
+        );
+        assert_eq!(parse_wpctl_node_name("id 52\n"), None);
+    }
+}
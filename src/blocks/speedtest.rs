@@ -11,18 +11,21 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::{Prefix, Value};
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
-use crate::util::format_number;
+use crate::util::FormatTemplate;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
 pub struct SpeedTest {
     id: usize,
     vals: Arc<Mutex<(bool, Vec<f32>)>>,
-    text: Vec<TextWidget>,
+    text: TextWidget,
+    format: FormatTemplate,
     config: SpeedTestConfig,
     send: Sender<()>,
+    shared_config: SharedConfig,
 }
 
 #[derive(Copy, Clone, Debug, Deserialize)]
@@ -56,6 +59,10 @@ pub struct SpeedTestConfig {
     )]
     pub interval: Duration,
 
+    /// Format override
+    #[serde(default = "SpeedTestConfig::default_format")]
+    pub format: String,
+
     /// Mode of speed display, true => MB/s, false => Mb/s
     #[serde(default = "SpeedTestConfig::default_bytes")]
     pub bytes: bool,
@@ -74,6 +81,10 @@ impl SpeedTestConfig {
         Duration::from_secs(1800)
     }
 
+    fn default_format() -> String {
+        "{ping} {speed_down} {speed_up}".to_owned()
+    }
+
     fn default_bytes() -> bool {
         false
     }
@@ -87,14 +98,12 @@ impl SpeedTestConfig {
     }
 }
 
-fn get_values(bytes: bool) -> Result<String> {
-    let mut cmd = Command::new("speedtest-cli");
-    cmd.arg("--simple");
-    if bytes {
-        cmd.arg("--bytes");
-    }
+/// Runs a test, whose results are the ping in ms and the speeds in Mbit/s.
+fn get_values() -> Result<String> {
     String::from_utf8(
-        cmd.output()
+        Command::new("speedtest-cli")
+            .arg("--simple")
+            .output()
             .block_error("speedtest", "could not get speedtest-cli output")?
             .stdout,
     )
@@ -122,14 +131,13 @@ fn make_thread(
     recv: Receiver<()>,
     done: Sender<Task>,
     values: Arc<Mutex<(bool, Vec<f32>)>>,
-    config: SpeedTestConfig,
     id: usize,
 ) {
     thread::Builder::new()
         .name("speedtest".into())
         .spawn(move || loop {
             if recv.recv().is_ok() {
-                if let Ok(output) = get_values(config.bytes) {
+                if let Ok(output) = get_values() {
                     if let Ok(vals) = parse_values(&output) {
                         if vals.len() == 3 {
                             let (ref mut update, ref mut values) = *values
@@ -139,11 +147,15 @@ fn make_thread(
 
                             *update = true;
 
-                            done.send(Task {
-                                id,
-                                update_time: Instant::now(),
-                            })
-                            .unwrap();
+                            if done
+                                .send(Task {
+                                    id,
+                                    update_time: Instant::now(),
+                                })
+                                .is_err()
+                            {
+                                return;
+                            }
                         }
                     }
                 }
@@ -155,6 +167,9 @@ fn make_thread(
 impl ConfigBlock for SpeedTest {
     type Config = SpeedTestConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{ping}", "{speed_down}", "{speed_up}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -166,59 +181,81 @@ impl ConfigBlock for SpeedTest {
         let vals = Arc::new(Mutex::new((false, vec![])));
 
         // Make the update thread
-        make_thread(recv, done, vals.clone(), block_config.clone(), id);
+        make_thread(recv, done, vals.clone(), id);
 
-        let ty = if block_config.bytes { "MB/s" } else { "Mb/s" };
-        Ok(SpeedTest {
+        let mut block = SpeedTest {
             vals,
-            text: vec![
-                TextWidget::new(id, 0, shared_config.clone())
-                    .with_icon("ping")
-                    .with_text("0ms"),
-                TextWidget::new(id, 1, shared_config.clone())
-                    .with_icon("net_down")
-                    .with_text(&format!("0{}", ty)),
-                TextWidget::new(id, 2, shared_config)
-                    .with_icon("net_up")
-                    .with_text(&format!("0{}", ty)),
-            ],
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("speedtest", "Invalid format specified")?,
             id,
             send,
             config: block_config,
-        })
+            shared_config,
+        };
+        block.show(0., 0., 0.)?;
+        Ok(block)
+    }
+}
+
+impl SpeedTest {
+    /// Shows the results of a test, with the ping in seconds and the speeds in bits per second.
+    fn show(&mut self, ping: f64, down: f64, up: f64) -> Result<()> {
+        let min_prefix =
+            Prefix::from_symbol(&self.config.speed_min_unit.to_string()).unwrap_or(Prefix::One);
+        let speed = |bits: f64, icon: &str| {
+            if self.config.bytes {
+                Value::bytes(bits / 8.)
+            } else {
+                Value::bits(bits)
+            }
+            .min_prefix(min_prefix)
+            .digits(self.config.speed_digits)
+            .icon(self.shared_config.get_icon(icon).unwrap_or_default())
+        };
+        let values = map!(
+            "{ping}" => Value::seconds(ping)
+                .min_prefix(Prefix::Milli)
+                .digits(self.config.speed_digits)
+                .icon(self.shared_config.get_icon("ping").unwrap_or_default()),
+            "{speed_down}" => speed(down, "net_down"),
+            "{speed_up}" => speed(up, "net_up")
+        );
+        self.text.set_text(self.format.render(&values)?);
+        Ok(())
     }
 }
 
 impl Block for SpeedTest {
-    fn update(&mut self) -> Result<Option<Update>> {
-        let (ref mut updated, ref vals) = *self
-            .vals
-            .lock()
-            .block_error("speedtest", "mutext poisoned")?;
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
 
-        if *updated {
-            *updated = false;
+    fn update(&mut self) -> Result<Option<Update>> {
+        let results = {
+            let (ref mut updated, ref vals) = *self
+                .vals
+                .lock()
+                .block_error("speedtest", "mutext poisoned")?;
+            if *updated {
+                *updated = false;
+                Some(vals.clone())
+            } else {
+                None
+            }
+        };
 
+        if let Some(vals) = results {
             if vals.len() == 3 {
                 let ping = vals[0] as f64 / 1_000.0;
-                let down = vals[1] as f64 * 1_000_000.0;
-                let up = vals[2] as f64 * 1_000_000.0;
-                self.text[0].set_text(format_number(ping, self.config.speed_digits, "", "s"));
-                self.text[1].set_text(format_number(
-                    down,
-                    self.config.speed_digits,
-                    &self.config.speed_min_unit.to_string(),
-                    if self.config.bytes { "B/s" } else { "b/s" },
-                ));
-                self.text[2].set_text(format_number(
-                    up,
-                    self.config.speed_digits,
-                    &self.config.speed_min_unit.to_string(),
-                    if self.config.bytes { "B/s" } else { "b/s" },
-                ));
+                self.show(
+                    ping,
+                    vals[1] as f64 * 1_000_000.0,
+                    vals[2] as f64 * 1_000_000.0,
+                )?;
 
                 // ping is in seconds
-                self.text[0].set_state(match (ping * 1000.) as i32 {
+                self.text.set_state(match (ping * 1000.) as i32 {
                     0..=25 => State::Good,
                     26..=60 => State::Info,
                     61..=100 => State::Warning,
@@ -241,11 +278,7 @@ impl Block for SpeedTest {
     }
 
     fn view(&self) -> Vec<&dyn I3BarWidget> {
-        let mut new: Vec<&dyn I3BarWidget> = Vec::with_capacity(self.text.len());
-        for w in &self.text {
-            new.push(w);
-        }
-        new
+        vec![&self.text]
     }
 
     fn id(&self) -> usize {
@@ -0,0 +1,218 @@
+use std::fs::read_to_string;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// State of established connections in `/proc/net/tcp`
+const TCP_ESTABLISHED: &str = "01";
+
+pub struct Ssh {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    port: u16,
+    format: FormatTemplate,
+    hide_when_empty: bool,
+    is_empty: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SshConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "SshConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Port the SSH server listens on
+    #[serde(default = "SshConfig::default_port")]
+    pub port: u16,
+
+    /// Format override
+    #[serde(default = "SshConfig::default_format")]
+    pub format: String,
+
+    #[serde(default)]
+    pub hide_when_empty: bool,
+}
+
+impl SshConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_port() -> u16 {
+        22
+    }
+
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+}
+
+/// Parses an address of `/proc/net/tcp` or `/proc/net/tcp6`, e.g. `0100007F:0016`. The IP
+/// address is made of 32-bit words in host byte order.
+fn parse_address(address: &str) -> Option<(IpAddr, u16)> {
+    let colon = address.find(':')?;
+    let (ip, port) = (&address[..colon], &address[colon + 1..]);
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let mut words = Vec::new();
+    for i in (0..ip.len()).step_by(8) {
+        let word = u32::from_str_radix(ip.get(i..i + 8)?, 16).ok()?;
+        words.push(word.to_ne_bytes());
+    }
+    let ip = match words.len() {
+        1 => IpAddr::V4(Ipv4Addr::from(words[0])),
+        4 => {
+            let mut octets = [0; 16];
+            for (i, word) in words.iter().enumerate() {
+                octets[i * 4..i * 4 + 4].copy_from_slice(word);
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some((ip, port))
+}
+
+/// The remote addresses of established connections to the local `port`, from the content of
+/// `/proc/net/tcp` or `/proc/net/tcp6`.
+fn inbound_connections(table: &str, port: u16) -> Vec<IpAddr> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 || fields[3] != TCP_ESTABLISHED {
+                return None;
+            }
+            let (_, local_port) = parse_address(fields[1])?;
+            let (remote, _) = parse_address(fields[2])?;
+            Some(remote).filter(|_| local_port == port)
+        })
+        .collect()
+}
+
+impl ConfigBlock for Ssh {
+    type Config = SshConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{count}", "{remotes}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Ssh {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("ssh"),
+            update_interval: block_config.interval,
+            port: block_config.port,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("ssh", "Invalid format specified")?,
+            hide_when_empty: block_config.hide_when_empty,
+            is_empty: true,
+        })
+    }
+}
+
+impl Block for Ssh {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mut remotes = Vec::new();
+        // Without IPv6, there is no tcp6 table
+        for table in &["/proc/net/tcp", "/proc/net/tcp6"] {
+            if let Ok(table) = read_to_string(table) {
+                remotes.extend(inbound_connections(&table, self.port));
+            }
+        }
+        let count = remotes.len();
+        remotes.sort();
+        remotes.dedup();
+        self.is_empty = count == 0;
+
+        let values = map!(
+            "{count}" => Value::text(count.to_string()),
+            "{remotes}" => Value::text(
+                remotes
+                    .iter()
+                    .map(|remote| remote.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text
+            .set_state(self.format.state(&values).unwrap_or(if count > 0 {
+                State::Warning
+            } else {
+                State::Idle
+            }));
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if self.is_empty && self.hide_when_empty {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inbound_connections, parse_address};
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("0100007F:0016"),
+            Some(("127.0.0.1".parse().unwrap(), 22))
+        );
+        assert_eq!(
+            parse_address("B80D0120000000000000000001000000:C350"),
+            Some(("2001:db8::1".parse().unwrap(), 50000))
+        );
+        assert_eq!(parse_address("0100007F"), None);
+    }
+
+    #[test]
+    fn test_inbound_connections() {
+        let table = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 21312 1 0000000000000000 100 0 0 10 0
+   1: 0201A8C0:0016 0501A8C0:D431 01 00000000:00000000 02:0009E8D6 00000000     0        0 81232 2 0000000000000000 20 4 1 10 -1
+   2: 0201A8C0:9C40 0501A8C0:0016 01 00000000:00000000 02:0009E8D6 00000000  1000        0 81254 2 0000000000000000 20 4 1 10 -1
+";
+        assert_eq!(
+            inbound_connections(table, 22),
+            vec!["192.168.1.5".parse::<std::net::IpAddr>().unwrap()]
+        );
+        assert!(inbound_connections(table, 2222).is_empty());
+    }
+}
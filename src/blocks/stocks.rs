@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::http;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+const ALPHA_VANTAGE_API_KEY_ENV: &str = "I3RS_ALPHA_VANTAGE_API_KEY";
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub enum StockService {
+    Yahoo,
+    AlphaVantage(AlphaVantage),
+}
+
+impl Default for StockService {
+    fn default() -> Self {
+        StockService::Yahoo
+    }
+}
+
+impl StockService {
+    fn provider(&self) -> &dyn QuoteProvider {
+        match self {
+            StockService::Yahoo => &Yahoo,
+            StockService::AlphaVantage(provider) => provider,
+        }
+    }
+}
+
+/// The latest price of a symbol.
+#[derive(Debug, PartialEq)]
+struct Quote {
+    price: f64,
+    /// Change since the previous close, in percent
+    change: f64,
+}
+
+/// A service telling the prices of stocks.
+trait QuoteProvider {
+    /// Gets the quotes of `symbols`, leaving out those the service doesn't know.
+    fn quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>>;
+}
+
+struct Yahoo;
+
+impl QuoteProvider for Yahoo {
+    fn quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        let url = format!(
+            "https://query1.finance.yahoo.com/v7/finance/quote?symbols={}",
+            http::url_encode(&symbols.join(","))
+        );
+        parse_yahoo(&get_json(&url)?).block_error("stocks", "unexpected reply from Yahoo Finance")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlphaVantage {
+    #[serde(default = "AlphaVantage::getenv_api_key")]
+    api_key: Option<String>,
+}
+
+impl AlphaVantage {
+    fn getenv_api_key() -> Option<String> {
+        env::var(ALPHA_VANTAGE_API_KEY_ENV).ok()
+    }
+}
+
+impl QuoteProvider for AlphaVantage {
+    fn quotes(&self, symbols: &[String]) -> Result<HashMap<String, Quote>> {
+        let api_key = self.api_key.as_ref().block_error(
+            "stocks",
+            "missing 'provider.api_key' or I3RS_ALPHA_VANTAGE_API_KEY environment variable",
+        )?;
+        // The service only quotes one symbol at a time
+        let mut quotes = HashMap::new();
+        for symbol in symbols {
+            let url = format!(
+                "https://www.alphavantage.co/query?function=GLOBAL_QUOTE&symbol={}&apikey={}",
+                http::url_encode(symbol),
+                api_key
+            );
+            let json = get_json(&url)?;
+            if let Some(note) = json.get("Note").and_then(Value::as_str) {
+                // Sent instead of the quote when the rate limit is reached
+                return Err(BlockError("stocks".to_owned(), note.to_owned()));
+            }
+            if let Some(quote) = parse_alpha_vantage(&json) {
+                quotes.insert(symbol.clone(), quote);
+            }
+        }
+        Ok(quotes)
+    }
+}
+
+fn get_json(url: &str) -> Result<Value> {
+    let response = http::http_get_json(url, Some(Duration::from_secs(10)), vec![])?;
+    if !(200..300).contains(&response.code) {
+        return Err(BlockError(
+            "stocks".to_owned(),
+            format!("the quotes were refused with status {}", response.code),
+        ));
+    }
+    Ok(response.content)
+}
+
+fn parse_yahoo(json: &Value) -> Option<HashMap<String, Quote>> {
+    Some(
+        json.pointer("/quoteResponse/result")?
+            .as_array()?
+            .iter()
+            .filter_map(|result| {
+                Some((
+                    result["symbol"].as_str()?.to_string(),
+                    Quote {
+                        price: result["regularMarketPrice"].as_f64()?,
+                        change: result["regularMarketChangePercent"].as_f64()?,
+                    },
+                ))
+            })
+            .collect(),
+    )
+}
+
+fn parse_alpha_vantage(json: &Value) -> Option<Quote> {
+    let quote = json.get("Global Quote")?;
+    Some(Quote {
+        price: quote["05. price"].as_str()?.parse().ok()?,
+        change: quote["10. change percent"]
+            .as_str()?
+            .trim_end_matches('%')
+            .parse()
+            .ok()?,
+    })
+}
+
+struct Stock {
+    symbol: String,
+    text: TextWidget,
+}
+
+pub struct Stocks {
+    id: usize,
+    stocks: Vec<Stock>,
+    service: StockService,
+    update_interval: Duration,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StocksConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "StocksConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Service giving the quotes
+    #[serde(default)]
+    pub provider: StockService,
+
+    /// Symbols to show, e.g. `AAPL`
+    pub symbols: Vec<String>,
+
+    /// Format override
+    #[serde(default = "StocksConfig::default_format")]
+    pub format: String,
+}
+
+impl StocksConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(300)
+    }
+
+    fn default_format() -> String {
+        "{symbol} {price} {change}%".to_owned()
+    }
+}
+
+impl ConfigBlock for Stocks {
+    type Config = StocksConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{change}", "{price}", "{symbol}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let stocks = block_config
+            .symbols
+            .into_iter()
+            .enumerate()
+            .map(|(i, symbol)| Stock {
+                text: TextWidget::new(id, i, shared_config.clone()).with_text(&symbol),
+                symbol,
+            })
+            .collect();
+
+        Ok(Stocks {
+            id,
+            stocks,
+            service: block_config.provider,
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("stocks", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for Stocks {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let symbols: Vec<String> = self.stocks.iter().map(|s| s.symbol.clone()).collect();
+        let quotes = self.service.provider().quotes(&symbols)?;
+
+        for stock in &mut self.stocks {
+            let (values, state) = match quotes.get(&stock.symbol) {
+                Some(quote) => (
+                    map!(
+                        "{symbol}" => stock.symbol.clone(),
+                        "{price}" => format!("{:.2}", quote.price),
+                        "{change}" => format!("{:+.2}", quote.change)
+                    ),
+                    if quote.change > 0.0 {
+                        State::Good
+                    } else if quote.change < 0.0 {
+                        State::Critical
+                    } else {
+                        State::Idle
+                    },
+                ),
+                None => (
+                    map!(
+                        "{symbol}" => stock.symbol.clone(),
+                        "{price}" => "-".to_string(),
+                        "{change}" => "-".to_string()
+                    ),
+                    State::Warning,
+                ),
+            };
+            stock.text.set_text(self.format.render_static_str(&values)?);
+            stock.text.set_state(state);
+        }
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.stocks
+            .iter()
+            .map(|stock| &stock.text as &dyn I3BarWidget)
+            .collect()
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{parse_alpha_vantage, parse_yahoo, Quote};
+
+    #[test]
+    fn test_parse_yahoo() {
+        let json = json!({
+            "quoteResponse": {
+                "result": [
+                    {"symbol": "AAPL", "regularMarketPrice": 121.03, "regularMarketChangePercent": -1.25},
+                    {"symbol": "MSFT", "regularMarketPrice": 235.77, "regularMarketChangePercent": 0.5}
+                ],
+                "error": null
+            }
+        });
+        let quotes = parse_yahoo(&json).unwrap();
+        assert_eq!(quotes.len(), 2);
+        assert_eq!(
+            quotes["AAPL"],
+            Quote {
+                price: 121.03,
+                change: -1.25
+            }
+        );
+        assert_eq!(
+            quotes["MSFT"],
+            Quote {
+                price: 235.77,
+                change: 0.5
+            }
+        );
+        assert_eq!(parse_yahoo(&json!({})), None);
+    }
+
+    #[test]
+    fn test_parse_alpha_vantage() {
+        let json = json!({
+            "Global Quote": {
+                "01. symbol": "IBM",
+                "05. price": "130.5500",
+                "09. change": "-1.1000",
+                "10. change percent": "-0.8355%"
+            }
+        });
+        assert_eq!(
+            parse_alpha_vantage(&json),
+            Some(Quote {
+                price: 130.55,
+                change: -0.8355
+            })
+        );
+        // Unknown symbols have an empty quote
+        assert_eq!(parse_alpha_vantage(&json!({"Global Quote": {}})), None);
+    }
+}
@@ -0,0 +1,173 @@
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::formatting::{DurationFormat, TimeUnit, Value};
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Measured time, which only grows while running.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Watch {
+    Running { since: Instant, before: Duration },
+    Paused { elapsed: Duration },
+}
+
+impl Watch {
+    fn elapsed(self) -> Duration {
+        match self {
+            Watch::Running { since, before } => before + since.elapsed(),
+            Watch::Paused { elapsed } => elapsed,
+        }
+    }
+}
+
+pub struct Stopwatch {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    watch: Watch,
+    /// Elapsed time at the end of the last lap
+    last_lap_end: Duration,
+    /// Length of the last lap
+    lap: Duration,
+    laps: usize,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StopwatchConfig {
+    /// Format override
+    #[serde(default = "StopwatchConfig::default_format")]
+    pub format: String,
+}
+
+impl StopwatchConfig {
+    fn default_format() -> String {
+        "{elapsed}".to_owned()
+    }
+}
+
+impl Stopwatch {
+    fn set_text(&mut self) -> Result<()> {
+        let clock =
+            || DurationFormat::Clock(vec![TimeUnit::Hours, TimeUnit::Minutes, TimeUnit::Seconds]);
+        let values = map!(
+            "{elapsed}" => Value::duration(self.watch.elapsed()).duration_format(clock()),
+            "{lap}" => Value::duration(self.lap).duration_format(clock()),
+            "{laps}" => Value::text(self.laps.to_string())
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(match self.watch {
+            Watch::Running { .. } => State::Info,
+            Watch::Paused { .. } => State::Idle,
+        });
+        Ok(())
+    }
+}
+
+impl ConfigBlock for Stopwatch {
+    type Config = StopwatchConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{elapsed}", "{laps}", "{lap}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Stopwatch {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("stopwatch"),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("stopwatch", "Invalid format specified")?,
+            watch: Watch::Paused {
+                elapsed: Duration::from_secs(0),
+            },
+            last_lap_end: Duration::from_secs(0),
+            lap: Duration::from_secs(0),
+            laps: 0,
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Stopwatch {
+    fn update(&mut self) -> Result<Option<Update>> {
+        self.set_text()?;
+
+        // Only tick while the time goes by
+        Ok(match self.watch {
+            Watch::Running { .. } => Some(Duration::from_secs(1).into()),
+            Watch::Paused { .. } => None,
+        })
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        match event.button {
+            MouseButton::Left => {
+                self.watch = match self.watch {
+                    Watch::Running { .. } => Watch::Paused {
+                        elapsed: self.watch.elapsed(),
+                    },
+                    Watch::Paused { elapsed } => {
+                        // Start ticking again
+                        self.tx_update_request
+                            .send(Task {
+                                id: self.id,
+                                update_time: Instant::now(),
+                            })
+                            .internal_error("stopwatch", "could not request an update")?;
+                        Watch::Running {
+                            since: Instant::now(),
+                            before: elapsed,
+                        }
+                    }
+                }
+            }
+            MouseButton::Middle => {
+                let reset = Duration::from_secs(0);
+                self.watch = match self.watch {
+                    Watch::Running { .. } => Watch::Running {
+                        since: Instant::now(),
+                        before: reset,
+                    },
+                    Watch::Paused { .. } => Watch::Paused { elapsed: reset },
+                };
+                self.last_lap_end = reset;
+                self.lap = reset;
+                self.laps = 0;
+            }
+            MouseButton::Right => {
+                let elapsed = self.watch.elapsed();
+                self.lap = elapsed - self.last_lap_end;
+                self.last_lap_end = elapsed;
+                self.laps += 1;
+            }
+            _ => {}
+        }
+        self.set_text()
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
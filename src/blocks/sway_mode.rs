@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::reply::Event;
+use swayipc::{Connection, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct SwayMode {
+    id: usize,
+    text: TextWidget,
+    mode: Arc<Mutex<String>>,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SwayModeConfig {
+    /// Format override
+    #[serde(default = "SwayModeConfig::default_format")]
+    pub format: String,
+}
+
+impl SwayModeConfig {
+    fn default_format() -> String {
+        "{mode}".to_owned()
+    }
+}
+
+impl ConfigBlock for SwayMode {
+    type Config = SwayModeConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[("format", &["{mode}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let mode = Arc::new(Mutex::new(String::from("default")));
+        let mode_copy = mode.clone();
+
+        let _test_conn =
+            Connection::new().block_error("sway_mode", "failed to acquire connect to IPC")?;
+
+        thread::Builder::new()
+            .name("sway_mode".into())
+            .spawn(move || {
+                let conn = Connection::new().expect("failed to open connection with swayipc");
+                let events = conn
+                    .subscribe(&[EventType::Mode])
+                    .expect("could not subscribe to mode events");
+
+                for event in events {
+                    if let Event::Mode(e) =
+                        event.expect("could not read event in `sway_mode` block")
+                    {
+                        *mode_copy
+                            .lock()
+                            .expect("lock has been poisoned in `sway_mode` block") = e.change;
+                        if tx_update_request
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .block_error("sway_mode", "failed to start watching thread")?;
+
+        Ok(SwayMode {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_state(State::Critical),
+            mode,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("sway_mode", "Invalid format specified")?,
+        })
+    }
+}
+
+impl Block for SwayMode {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mode = self
+            .mode
+            .lock()
+            .block_error("sway_mode", "failed to acquire lock")?
+            .clone();
+        let values = map!("{mode}" => mode);
+        self.text.set_text(self.format.render_static_str(&values)?);
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        let mode = &*self
+            .mode
+            .lock()
+            .expect("lock has been poisoned in `sway_mode` block");
+
+        if mode == "default" {
+            vec![]
+        } else {
+            vec![&self.text]
+        }
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
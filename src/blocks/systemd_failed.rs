@@ -0,0 +1,183 @@
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use dbus::arg::ArgType;
+use dbus::ffidisp::{BusType, Connection};
+use dbus::Message;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::errors::*;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Changes of the state of units, e.g. from `active` to `failed`
+const UNIT_CHANGED_RULE: &str = "type='signal',\
+    sender='org.freedesktop.systemd1',\
+    interface='org.freedesktop.DBus.Properties',\
+    member='PropertiesChanged',\
+    arg0='org.freedesktop.systemd1.Unit'";
+
+/// Unit files being enabled, disabled or removed
+const UNIT_FILES_CHANGED_RULE: &str = "type='signal',\
+    sender='org.freedesktop.systemd1',\
+    interface='org.freedesktop.systemd1.Manager',\
+    member='UnitFilesChanged'";
+
+pub struct SystemdFailed {
+    id: usize,
+    text: TextWidget,
+    format: FormatTemplate,
+    connection: Connection,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SystemdFailedConfig {
+    /// Format override
+    #[serde(default = "SystemdFailedConfig::default_format")]
+    pub format: String,
+
+    /// Count the failed units of the user's service manager rather than the system's
+    #[serde(default)]
+    pub user: bool,
+}
+
+impl SystemdFailedConfig {
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+}
+
+fn bus_type(user: bool) -> BusType {
+    if user {
+        BusType::Session
+    } else {
+        BusType::System
+    }
+}
+
+fn manager_call(method: &str) -> Message {
+    Message::new_method_call(
+        "org.freedesktop.systemd1",
+        "/org/freedesktop/systemd1",
+        "org.freedesktop.systemd1.Manager",
+        method,
+    )
+    .unwrap()
+}
+
+impl ConfigBlock for SystemdFailed {
+    type Config = SystemdFailedConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{count}", "{units}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        send: Sender<Task>,
+    ) -> Result<Self> {
+        let user = block_config.user;
+        let connection = Connection::get_private(bus_type(user))
+            .block_error("systemd_failed", "failed to establish D-Bus connection")?;
+
+        thread::Builder::new()
+            .name("systemd_failed".into())
+            .spawn(move || {
+                let c = Connection::get_private(bus_type(user))
+                    .expect("Failed to establish D-Bus connection in thread");
+                // systemd only emits signals while some client is subscribed
+                c.send_with_reply_and_block(manager_call("Subscribe"), 5000)
+                    .ok();
+                c.add_match(UNIT_CHANGED_RULE).unwrap();
+                c.add_match(UNIT_FILES_CHANGED_RULE).unwrap();
+
+                loop {
+                    if c.incoming(60_000).next().is_some() {
+                        // A unit changing state sends a burst of signals, so wait for the end
+                        // of it to update once
+                        for _ in c.incoming(100) {}
+                        if send
+                            .send(Task {
+                                id,
+                                update_time: Instant::now(),
+                            })
+                            .is_err()
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Ok(SystemdFailed {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("systemd_failed", "Invalid format specified")?,
+            connection,
+        })
+    }
+}
+
+impl SystemdFailed {
+    /// Names of the units in the `failed` state.
+    fn failed_units(&self) -> Result<Vec<String>> {
+        let states: &[&str] = &["failed"];
+        let reply = self
+            .connection
+            .send_with_reply_and_block(manager_call("ListUnitsFiltered").append1(states), 5000)
+            .block_error("systemd_failed", "failed to list the units")?;
+
+        let mut reply = reply.iter_init();
+        let mut units = reply
+            .recurse(ArgType::Array)
+            .block_error("systemd_failed", "unexpected reply to ListUnitsFiltered")?;
+        let mut names = Vec::new();
+        while let Some(mut unit) = units.recurse(ArgType::Struct) {
+            if let Some(name) = unit.get::<&str>() {
+                names.push(name.to_string());
+            }
+            units.next();
+        }
+        Ok(names)
+    }
+}
+
+impl Block for SystemdFailed {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let units = self.failed_units()?;
+
+        let values = map!(
+            "{count}" => units.len().to_string(),
+            "{units}" => units.join(", ")
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text.set_state(if units.is_empty() {
+            State::Idle
+        } else {
+            State::Critical
+        });
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
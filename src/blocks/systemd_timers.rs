@@ -0,0 +1,243 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local};
+use crossbeam_channel::Sender;
+use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+use dbus::blocking::Connection;
+use nix::sys::time::TimeValLike;
+use nix::time::{clock_gettime, ClockId};
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::I3BarWidget;
+
+/// A unit as listed by `ListUnitsByPatterns`: its name, description, load, active and sub
+/// states, followed unit, object path, queued job and its type and path.
+type UnitStatus = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    dbus::Path<'static>,
+    u32,
+    String,
+    dbus::Path<'static>,
+);
+
+/// When a timer fires next, in microseconds since the epoch, from the times systemd gives on the
+/// realtime clock (calendar timers) and on the monotonic clock (timers relative to the boot or to
+/// the last activation). Either is 0 or `u64::MAX` when the timer has no such trigger.
+fn next_elapse(
+    realtime: u64,
+    monotonic: u64,
+    now_realtime: u64,
+    now_monotonic: u64,
+) -> Option<u64> {
+    let set = |usec: u64| Some(usec).filter(|&usec| usec != 0 && usec != u64::MAX);
+    let monotonic = set(monotonic).map(|usec| (now_realtime + usec).saturating_sub(now_monotonic));
+    match (set(realtime), monotonic) {
+        (Some(realtime), Some(monotonic)) => Some(realtime.min(monotonic)),
+        (realtime, monotonic) => realtime.or(monotonic),
+    }
+}
+
+/// The timers which are going to fire, with the time of their next run in microseconds since
+/// the epoch.
+fn upcoming_timers(
+    c: &Connection,
+    now_realtime: u64,
+    now_monotonic: u64,
+) -> Result<Vec<(String, u64)>> {
+    let manager = c.with_proxy(
+        "org.freedesktop.systemd1",
+        "/org/freedesktop/systemd1",
+        Duration::from_millis(5000),
+    );
+    let states: Vec<&str> = Vec::new();
+    let (units,): (Vec<UnitStatus>,) = manager
+        .method_call(
+            "org.freedesktop.systemd1.Manager",
+            "ListUnitsByPatterns",
+            (states, vec!["*.timer"]),
+        )
+        .block_error("systemd_timers", "failed to list the timers")?;
+
+    let mut timers = Vec::new();
+    for unit in units {
+        let timer = c.with_proxy(
+            "org.freedesktop.systemd1",
+            unit.6,
+            Duration::from_millis(5000),
+        );
+        let usec = |property: &str| -> u64 {
+            timer
+                .get("org.freedesktop.systemd1.Timer", property)
+                .unwrap_or(0)
+        };
+        if let Some(next) = next_elapse(
+            usec("NextElapseUSecRealtime"),
+            usec("NextElapseUSecMonotonic"),
+            now_realtime,
+            now_monotonic,
+        ) {
+            timers.push((unit.0, next));
+        }
+    }
+    Ok(timers)
+}
+
+pub struct SystemdTimers {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    system: bool,
+    user: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SystemdTimersConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "SystemdTimersConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "SystemdTimersConfig::default_format")]
+    pub format: String,
+
+    /// Include the timers of the system's service manager
+    #[serde(default = "SystemdTimersConfig::default_true")]
+    pub system: bool,
+
+    /// Include the timers of the user's service manager
+    #[serde(default = "SystemdTimersConfig::default_true")]
+    pub user: bool,
+}
+
+impl SystemdTimersConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{name} {next}".to_owned()
+    }
+
+    fn default_true() -> bool {
+        true
+    }
+}
+
+impl ConfigBlock for SystemdTimers {
+    type Config = SystemdTimersConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{count}", "{name}", "{next}", "{time}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(SystemdTimers {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("time"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("systemd_timers", "Invalid format specified")?,
+            system: block_config.system,
+            user: block_config.user,
+        })
+    }
+}
+
+impl Block for SystemdTimers {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let now_realtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let now_monotonic = clock_gettime(ClockId::CLOCK_MONOTONIC)
+            .block_error("systemd_timers", "failed to read the monotonic clock")?
+            .num_microseconds() as u64;
+
+        let mut timers = Vec::new();
+        if self.system {
+            let c = Connection::new_system()
+                .block_error("systemd_timers", "failed to establish D-Bus connection")?;
+            timers.extend(upcoming_timers(&c, now_realtime, now_monotonic)?);
+        }
+        if self.user {
+            let c = Connection::new_session()
+                .block_error("systemd_timers", "failed to establish D-Bus connection")?;
+            timers.extend(upcoming_timers(&c, now_realtime, now_monotonic)?);
+        }
+        let count = timers.len();
+        let next = timers.into_iter().min_by_key(|&(_, next)| next);
+
+        let values = match next {
+            Some((name, next)) => {
+                let time: DateTime<Local> = (UNIX_EPOCH + Duration::from_micros(next)).into();
+                map!(
+                    "{name}" => Value::text(name.trim_end_matches(".timer").to_string()),
+                    "{next}" => Value::duration(Duration::from_micros(next.saturating_sub(now_realtime))),
+                    "{time}" => Value::text(time.format("%a %H:%M").to_string()),
+                    "{count}" => Value::text(count.to_string())
+                )
+            }
+            None => map!(
+                "{name}" => Value::text("none".to_string()),
+                "{next}" => Value::text(String::new()),
+                "{time}" => Value::text(String::new()),
+                "{count}" => Value::text("0".to_string())
+            ),
+        };
+        self.text.set_text(self.format.render(&values)?);
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_elapse;
+
+    #[test]
+    fn test_next_elapse() {
+        // A calendar timer
+        assert_eq!(next_elapse(5_000, 0, 1_000, 100), Some(5_000));
+        // A timer relative to its last activation, due in 300µs
+        assert_eq!(next_elapse(0, 400, 1_000, 100), Some(1_300));
+        // Both kinds of triggers, the earliest wins
+        assert_eq!(next_elapse(5_000, 400, 1_000, 100), Some(1_300));
+        assert_eq!(next_elapse(1_200, 400, 1_000, 100), Some(1_200));
+        // An inactive timer
+        assert_eq!(next_elapse(0, u64::MAX, 1_000, 100), None);
+    }
+}
@@ -0,0 +1,279 @@
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Tailscale {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    socket: String,
+    exit_node: Option<String>,
+    format: FormatTemplate,
+    /// Whether the tailnet is up, as of the last update
+    running: bool,
+    /// Whether traffic goes through an exit node, as of the last update
+    using_exit_node: bool,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TailscaleConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "TailscaleConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Path of the socket of tailscaled
+    #[serde(default = "TailscaleConfig::default_socket")]
+    pub socket: String,
+
+    /// Host name of the exit node to toggle on left click
+    pub exit_node: Option<String>,
+
+    /// Format override
+    #[serde(default = "TailscaleConfig::default_format")]
+    pub format: String,
+}
+
+impl TailscaleConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_socket() -> String {
+        "/var/run/tailscale/tailscaled.sock".to_owned()
+    }
+
+    fn default_format() -> String {
+        "{status}{? via {exit_node}}".to_owned()
+    }
+}
+
+/// The parts of the reply to `/localapi/v0/status` shown by the block.
+#[derive(Debug, Default, PartialEq)]
+struct TailscaleStatus {
+    /// `Running`, `Stopped`, `NeedsLogin`, `Starting`…
+    backend_state: String,
+    /// MagicDNS name of this machine
+    dns_name: String,
+    /// Host name of the exit node in use, if any
+    exit_node: Option<String>,
+}
+
+/// Name of a node without the trailing dot of fully qualified names.
+fn dns_name(node: &JsonValue) -> String {
+    node["DNSName"]
+        .as_str()
+        .unwrap_or_default()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn parse_status(status: &JsonValue) -> TailscaleStatus {
+    let exit_node = status["Peer"].as_object().and_then(|peers| {
+        peers
+            .values()
+            .find(|peer| peer["ExitNode"].as_bool() == Some(true))
+            .and_then(|peer| peer["HostName"].as_str())
+            .map(String::from)
+    });
+    TailscaleStatus {
+        backend_state: status["BackendState"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        dns_name: dns_name(&status["Self"]),
+        exit_node,
+    }
+}
+
+impl Tailscale {
+    /// Sends an HTTP request to the local API of tailscaled and returns the JSON reply.
+    fn local_api(&self, method: &str, path: &str, body: Option<JsonValue>) -> Result<JsonValue> {
+        let mut stream = UnixStream::connect(&self.socket).block_error(
+            "tailscale",
+            "failed to connect to tailscaled, is it running?",
+        )?;
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        // HTTP/1.0 so that the reply is neither chunked nor kept alive
+        let request = format!(
+            "{} /localapi/v0/{} HTTP/1.0\r\n\
+            Host: local-tailscaled.sock\r\n\
+            Content-Type: application/json\r\n\
+            Content-Length: {}\r\n\
+            \r\n\
+            {}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        stream
+            .write_all(request.as_bytes())
+            .block_error("tailscale", "failed to send request to tailscaled")?;
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .block_error("tailscale", "failed to read reply of tailscaled")?;
+
+        let split = reply
+            .find("\r\n\r\n")
+            .block_error("tailscale", "invalid reply of tailscaled")?;
+        let (head, body) = (&reply[..split], &reply[split + 4..]);
+        let status = head.split_whitespace().nth(1).unwrap_or_default();
+        if !status.starts_with('2') {
+            return Err(BlockError(
+                "tailscale".to_string(),
+                format!("tailscaled replied {}: {}", status, body.trim()),
+            ));
+        }
+        serde_json::from_str(body).block_error("tailscale", "invalid JSON from tailscaled")
+    }
+
+    fn set_prefs(&self, prefs: JsonValue) -> Result<()> {
+        self.local_api("PATCH", "prefs", Some(prefs)).map(|_| ())
+    }
+
+    fn toggle_exit_node(&self) -> Result<()> {
+        let id = if self.using_exit_node {
+            String::new()
+        } else {
+            let name = match self.exit_node {
+                Some(ref name) => name.as_str(),
+                None => return Ok(()),
+            };
+            let status = self.local_api("GET", "status", None)?;
+            status["Peer"]
+                .as_object()
+                .and_then(|peers| {
+                    peers.values().find(|peer| {
+                        peer["HostName"].as_str() == Some(name)
+                            || dns_name(peer).split('.').next() == Some(name)
+                    })
+                })
+                .and_then(|peer| peer["ID"].as_str())
+                .map(String::from)
+                .block_error("tailscale", &format!("exit node {} not found", name))?
+        };
+        self.set_prefs(json!({"ExitNodeID": id, "ExitNodeIDSet": true}))
+    }
+}
+
+impl ConfigBlock for Tailscale {
+    type Config = TailscaleConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{exit_node}", "{name}", "{status}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Tailscale {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("net_vpn"),
+            update_interval: block_config.interval,
+            socket: block_config.socket,
+            exit_node: block_config.exit_node,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("tailscale", "Invalid format specified")?,
+            running: false,
+            using_exit_node: false,
+        })
+    }
+}
+
+impl Block for Tailscale {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let status = parse_status(&self.local_api("GET", "status", None)?);
+        self.running = status.backend_state == "Running";
+        self.using_exit_node = status.exit_node.is_some();
+
+        let values = map!(
+            "{status}" => status.backend_state.to_lowercase(),
+            "{name}" => status.dns_name,
+            "{exit_node}" => status.exit_node.unwrap_or_default()
+        );
+        self.text.set_text(self.format.render_static_str(&values)?);
+        self.text
+            .set_state(match (self.running, self.using_exit_node) {
+                (true, true) => State::Good,
+                (true, false) => State::Info,
+                (false, _) => State::Idle,
+            });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        match event.button {
+            MouseButton::Left => self.toggle_exit_node()?,
+            MouseButton::Right => self.set_prefs(json!({
+                "WantRunning": !self.running,
+                "WantRunningSet": true,
+            }))?,
+            _ => return Ok(()),
+        }
+        self.update()?;
+
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_status, TailscaleStatus};
+
+    #[test]
+    fn test_parse_status() {
+        let status = serde_json::json!({
+            "BackendState": "Running",
+            "Self": {"HostName": "laptop", "DNSName": "laptop.tail1234.ts.net."},
+            "Peer": {
+                "nodekey:1": {"ID": "n1", "HostName": "nas", "ExitNode": false},
+                "nodekey:2": {"ID": "n2", "HostName": "vps", "ExitNode": true},
+            },
+        });
+        assert_eq!(
+            parse_status(&status),
+            TailscaleStatus {
+                backend_state: "Running".to_string(),
+                dns_name: "laptop.tail1234.ts.net".to_string(),
+                exit_node: Some("vps".to_string()),
+            }
+        );
+
+        let status = serde_json::json!({"BackendState": "Stopped", "Self": {}, "Peer": null});
+        assert_eq!(parse_status(&status).exit_node, None);
+    }
+}
@@ -123,13 +123,19 @@ impl TaskwarriorConfig {
 impl ConfigBlock for Taskwarrior {
     type Config = TaskwarriorConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        ("format", &["{count}", "{filter_name}"]),
+        ("format_singular", &["{count}", "{filter_name}"]),
+        ("format_everything_done", &["{count}", "{filter_name}"]),
+    ];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
-        let output = TextWidget::new(id, 0, shared_config)
+        let output = TextWidget::new(id, 0, shared_config.clone())
             .with_icon("tasks")
             .with_text("-");
         // If the deprecated `filter_tags` option has been set,
@@ -148,17 +154,18 @@ impl ConfigBlock for Taskwarrior {
             update_interval: block_config.interval,
             warning_threshold: block_config.warning_threshold,
             critical_threshold: block_config.critical_threshold,
-            format: FormatTemplate::from_string(&block_config.format).block_error(
+            format: FormatTemplate::new(&block_config.format, &shared_config).block_error(
                 "taskwarrior",
                 "Invalid format specified for taskwarrior::format",
             )?,
-            format_singular: FormatTemplate::from_string(&block_config.format_singular)
+            format_singular: FormatTemplate::new(&block_config.format_singular, &shared_config)
                 .block_error(
                     "taskwarrior",
                     "Invalid format specified for taskwarrior::format_singular",
                 )?,
-            format_everything_done: FormatTemplate::from_string(
+            format_everything_done: FormatTemplate::new(
                 &block_config.format_everything_done,
+                &shared_config,
             )
             .block_error(
                 "taskwarrior",
@@ -208,6 +215,10 @@ fn get_number_of_tasks(filter: &str) -> Result<u32> {
 }
 
 impl Block for Taskwarrior {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         if !has_taskwarrior()? {
             self.output.set_text("?".to_string())
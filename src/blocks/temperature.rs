@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::process::Command;
 use std::time::Duration;
 
@@ -11,7 +12,7 @@ use crate::de::deserialize_duration;
 use crate::errors::*;
 use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
-use crate::util::{has_command, FormatTemplate};
+use crate::util::{has_command, read_file, severity, FormatTemplate};
 use crate::widgets::{text::TextWidget, I3BarWidget, Spacing, State};
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
@@ -27,6 +28,39 @@ impl Default for TemperatureScale {
     }
 }
 
+/// Sums the counters of the times the cores and the packages of the CPUs were throttled because
+/// they were too hot, or `None` when the kernel doesn't provide them (e.g. on AMD CPUs).
+fn throttle_count() -> Option<u64> {
+    let cpus = Path::new("/sys/devices/system/cpu").read_dir().ok()?;
+    let mut count = None;
+    for cpu in cpus.flatten() {
+        let name = cpu.file_name();
+        let name = name.to_string_lossy();
+        if !name.starts_with("cpu") || !name[3..].chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        for counter in &["core_throttle_count", "package_throttle_count"] {
+            let path = cpu.path().join("thermal_throttle").join(counter);
+            if let Some(value) = read_file("temperature", &path)
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+            {
+                count = Some(count.unwrap_or(0) + value);
+            }
+        }
+    }
+    count
+}
+
+/// The number of times the CPUs were throttled between two readings of the counters. The
+/// counters of a CPU going offline disappear, so they may decrease.
+fn throttle_events(previous: Option<u64>, current: Option<u64>) -> u64 {
+    match (previous, current) {
+        (Some(previous), Some(current)) => current.saturating_sub(previous),
+        _ => 0,
+    }
+}
+
 pub struct Temperature {
     id: usize,
     text: TextWidget,
@@ -42,6 +76,7 @@ pub struct Temperature {
     chip: Option<String>,
     inputs: Option<Vec<String>>,
     fallback_required: bool,
+    throttle_count: Option<u64>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -116,6 +151,9 @@ impl TemperatureConfig {
 impl ConfigBlock for Temperature {
     type Config = TemperatureConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{average}", "{max}", "{min}", "{throttled}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
@@ -125,7 +163,7 @@ impl ConfigBlock for Temperature {
         Ok(Temperature {
             id,
             update_interval: block_config.interval,
-            text: TextWidget::new(id, 0, shared_config)
+            text: TextWidget::new(id, 0, shared_config.clone())
                 .with_icon("thermometer")
                 .with_spacing(if block_config.collapsed {
                     Spacing::Hidden
@@ -159,11 +197,12 @@ impl ConfigBlock for Temperature {
                     TemperatureScale::Celsius => 80,
                     TemperatureScale::Fahrenheit => 176,
                 }),
-            format: FormatTemplate::from_string(&block_config.format)
+            format: FormatTemplate::new(&block_config.format, &shared_config)
                 .block_error("temperature", "Invalid format specified for temperature")?,
             chip: block_config.chip,
             inputs: block_config.inputs,
             fallback_required: !has_command("temperature", "sensors -j").unwrap_or(false),
+            throttle_count: throttle_count(),
         })
     }
 }
@@ -172,6 +211,10 @@ type SensorsOutput = HashMap<String, HashMap<String, serde_json::Value>>;
 type InputReadings = HashMap<String, f64>;
 
 impl Block for Temperature {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         let mut args = if self.fallback_required {
             vec!["-u"]
@@ -254,6 +297,10 @@ impl Block for Temperature {
             }
         }
 
+        let throttle_count = throttle_count();
+        let throttled = throttle_events(self.throttle_count, throttle_count);
+        self.throttle_count = throttle_count;
+
         if !temperatures.is_empty() {
             let max: i64 = *temperatures
                 .iter()
@@ -268,7 +315,8 @@ impl Block for Temperature {
 
             let values = map!("{average}" => avg,
                               "{min}" => min,
-                              "{max}" => max);
+                              "{max}" => max,
+                              "{throttled}" => throttled as i64);
 
             self.output = self.format.render_static_str(&values)?;
             if !self.collapsed {
@@ -276,6 +324,7 @@ impl Block for Temperature {
             }
 
             let state = match max {
+                _ if throttled > 0 && max <= self.maximum_warning => State::Warning,
                 m if m <= self.maximum_good => State::Good,
                 m if m <= self.maximum_idle => State::Idle,
                 m if m <= self.maximum_info => State::Info,
@@ -284,6 +333,11 @@ impl Block for Temperature {
             };
 
             self.text.set_state(state);
+            self.text.set_severity(Some(severity(
+                max as f64,
+                self.maximum_good as f64,
+                self.maximum_warning as f64,
+            )));
         }
 
         Ok(Some(self.update_interval.into()))
@@ -312,3 +366,19 @@ impl Block for Temperature {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::throttle_events;
+
+    #[test]
+    fn test_throttle_events() {
+        assert_eq!(throttle_events(Some(10), Some(14)), 4);
+        assert_eq!(throttle_events(Some(10), Some(10)), 0);
+        // A CPU went offline
+        assert_eq!(throttle_events(Some(10), Some(6)), 0);
+        // No counters
+        assert_eq!(throttle_events(None, None), 0);
+        assert_eq!(throttle_events(None, Some(6)), 0);
+    }
+}
@@ -1,4 +1,3 @@
-use std::convert::TryInto;
 use std::time::Duration;
 
 use chrono::{
@@ -13,17 +12,36 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::parse_locale;
+use crate::input::{I3BarEvent, MouseButton};
 use crate::scheduler::Task;
 use crate::widgets::text::TextWidget;
 use crate::widgets::I3BarWidget;
 
+/// A time zone shown by the block, the local one if `timezone` is `None`.
+struct Zone {
+    timezone: Option<Tz>,
+    label: Option<String>,
+}
+
 pub struct Time {
     id: usize,
     time: TextWidget,
     update_interval: Duration,
     format: String,
-    timezone: Option<Tz>,
-    locale: Option<String>,
+    zones: Vec<Zone>,
+    /// Index of the zone shown when cycling through them
+    cycle: Option<usize>,
+    separator: String,
+    locale: Option<Locale>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TimezoneConfig {
+    pub timezone: Tz,
+    /// Text shown before the time, e.g. a colleague's city
+    pub label: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -43,6 +61,19 @@ pub struct TimeConfig {
     #[serde(default = "TimeConfig::default_timezone")]
     pub timezone: Option<Tz>,
 
+    /// Time zones shown instead of `timezone`
+    #[serde(default)]
+    pub timezones: Vec<TimezoneConfig>,
+
+    /// Show one of the `timezones` at a time, changed by clicks and scrolling
+    #[serde(default)]
+    pub cycle: bool,
+
+    /// Text between the `timezones` when showing them all
+    #[serde(default = "TimeConfig::default_separator")]
+    pub separator: String,
+
+    /// Locale of the dates, overriding the global `locale`
     #[serde(default = "TimeConfig::default_locale")]
     pub locale: Option<String>,
 }
@@ -63,6 +94,10 @@ impl TimeConfig {
     fn default_locale() -> Option<String> {
         None
     }
+
+    fn default_separator() -> String {
+        " | ".to_owned()
+    }
 }
 
 impl ConfigBlock for Time {
@@ -74,6 +109,32 @@ impl ConfigBlock for Time {
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
+        let locale = match block_config.locale {
+            Some(name) => parse_locale(&name)?,
+            None => shared_config.locale,
+        };
+
+        let zones = if block_config.timezones.is_empty() {
+            vec![Zone {
+                timezone: block_config.timezone,
+                label: None,
+            }]
+        } else if block_config.timezone.is_some() {
+            return Err(BlockError(
+                "time".to_owned(),
+                "'timezone' and 'timezones' can't be used together".to_owned(),
+            ));
+        } else {
+            block_config
+                .timezones
+                .into_iter()
+                .map(|zone| Zone {
+                    timezone: Some(zone.timezone),
+                    label: zone.label,
+                })
+                .collect()
+        };
+
         Ok(Time {
             id,
             time: TextWidget::new(id, 0, shared_config)
@@ -81,36 +142,67 @@ impl ConfigBlock for Time {
                 .with_icon("time"),
             update_interval: block_config.interval,
             format: block_config.format,
-            timezone: block_config.timezone,
-            locale: block_config.locale,
+            zones,
+            cycle: if block_config.cycle { Some(0) } else { None },
+            separator: block_config.separator,
+            locale,
         })
     }
 }
 
-impl Block for Time {
-    fn update(&mut self) -> Result<Option<Update>> {
-        let time = match &self.locale {
-            Some(l) => {
-                let locale: Locale = l
-                    .as_str()
-                    .try_into()
-                    .block_error("time", "invalid locale")?;
-                match self.timezone {
-                    Some(tz) => Utc::now()
-                        .with_timezone(&tz)
-                        .format_localized(&self.format, locale),
-                    None => Local::now().format_localized(&self.format, locale),
-                }
-            }
-            None => match self.timezone {
+impl Time {
+    fn render(&self, zone: &Zone) -> String {
+        let time = match self.locale {
+            Some(locale) => match zone.timezone {
+                Some(tz) => Utc::now()
+                    .with_timezone(&tz)
+                    .format_localized(&self.format, locale),
+                None => Local::now().format_localized(&self.format, locale),
+            },
+            None => match zone.timezone {
                 Some(tz) => Utc::now().with_timezone(&tz).format(&self.format),
                 None => Local::now().format(&self.format),
             },
         };
-        self.time.set_text(format!("{}", time));
+        match zone.label {
+            Some(ref label) => format!("{} {}", label, time),
+            None => format!("{}", time),
+        }
+    }
+
+    fn set_text(&mut self) {
+        let text = match self.cycle {
+            Some(shown) => self.render(&self.zones[shown]),
+            None => self
+                .zones
+                .iter()
+                .map(|zone| self.render(zone))
+                .collect::<Vec<_>>()
+                .join(&self.separator),
+        };
+        self.time.set_text(text);
+    }
+}
+
+impl Block for Time {
+    fn update(&mut self) -> Result<Option<Update>> {
+        self.set_text();
         Ok(Some(self.update_interval.into()))
     }
 
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if let Some(shown) = self.cycle {
+            let count = self.zones.len();
+            self.cycle = Some(match event.button {
+                MouseButton::Left | MouseButton::WheelUp => (shown + 1) % count,
+                MouseButton::Right | MouseButton::WheelDown => (shown + count - 1) % count,
+                _ => shown,
+            });
+            self.set_text();
+        }
+        Ok(())
+    }
+
     fn view(&self) -> Vec<&dyn I3BarWidget> {
         vec![&self.time]
     }
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// Parses a line of `LIST VAR` such as `VAR ups battery.charge "100"` into the name and the
+/// value of the variable, whose quotes may escape `"` and `\` with a backslash.
+fn parse_var<'a>(line: &'a str, ups: &str) -> Option<(&'a str, String)> {
+    let rest = line
+        .strip_prefix("VAR ")?
+        .strip_prefix(ups)?
+        .strip_prefix(' ')?;
+    let space = rest.find(' ')?;
+    let (name, quoted) = (&rest[..space], rest[space + 1..].trim());
+    let quoted = quoted.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        value.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some((name, value))
+}
+
+/// Asks upsd for all the variables of `ups`.
+fn list_vars(address: &str, ups: &str) -> Result<HashMap<String, String>> {
+    let timeout = Some(Duration::from_secs(5));
+    let mut stream = TcpStream::connect(address)
+        .block_error("ups", &format!("failed to connect to upsd at {}", address))?;
+    stream
+        .set_read_timeout(timeout)
+        .and_then(|_| stream.set_write_timeout(timeout))
+        .block_error("ups", "failed to set the timeouts of the connection")?;
+    stream
+        .write_all(format!("LIST VAR {}\nLOGOUT\n", ups).as_bytes())
+        .block_error("ups", "failed to query upsd")?;
+
+    let mut vars = HashMap::new();
+    for line in BufReader::new(stream).lines() {
+        let line = line.block_error("ups", "failed to read the reply of upsd")?;
+        if let Some(error) = line.strip_prefix("ERR ") {
+            // e.g. `UNKNOWN-UPS` or `DATA-STALE`
+            return Err(BlockError("ups".to_string(), format!("upsd: {}", error)));
+        }
+        if line.starts_with("END LIST VAR") {
+            break;
+        }
+        if let Some((name, value)) = parse_var(&line, ups) {
+            vars.insert(name.to_string(), value);
+        }
+    }
+    Ok(vars)
+}
+
+pub struct Ups {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    address: String,
+    ups: String,
+    critical_runtime: Duration,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UpsConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "UpsConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "UpsConfig::default_format")]
+    pub format: String,
+
+    /// Name of the UPS, as configured in `ups.conf`
+    #[serde(default = "UpsConfig::default_ups")]
+    pub ups: String,
+
+    /// Host running upsd
+    #[serde(default = "UpsConfig::default_host")]
+    pub host: String,
+
+    /// Port upsd listens to
+    #[serde(default = "UpsConfig::default_port")]
+    pub port: u16,
+
+    /// Runtime left on battery, in seconds, below which the state is critical
+    #[serde(
+        default = "UpsConfig::default_critical_runtime",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub critical_runtime: Duration,
+}
+
+impl UpsConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_format() -> String {
+        "{charge}% {runtime}".to_owned()
+    }
+
+    fn default_ups() -> String {
+        "ups".to_owned()
+    }
+
+    fn default_host() -> String {
+        "localhost".to_owned()
+    }
+
+    fn default_port() -> u16 {
+        3493
+    }
+
+    fn default_critical_runtime() -> Duration {
+        Duration::from_secs(300)
+    }
+}
+
+impl ConfigBlock for Ups {
+    type Config = UpsConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{charge}", "{load}", "{runtime}", "{status}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Ups {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("ups"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("ups", "Invalid format specified")?,
+            address: format!("{}:{}", block_config.host, block_config.port),
+            ups: block_config.ups,
+            critical_runtime: block_config.critical_runtime,
+        })
+    }
+}
+
+impl Block for Ups {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let vars = list_vars(&self.address, &self.ups)?;
+        let var = |name: &str| vars.get(name).map(String::as_str).unwrap_or_default();
+        let runtime = var("battery.runtime")
+            .parse::<f64>()
+            .ok()
+            .map(|seconds| Duration::from_secs(seconds as u64));
+        // Flags such as `OL` (online), `OB` (on battery) and `LB` (low battery)
+        let status = var("ups.status");
+        let on_battery = status.split_whitespace().any(|flag| flag == "OB");
+        let low_battery = status.split_whitespace().any(|flag| flag == "LB");
+
+        let values = map!(
+            "{charge}" => Value::text(var("battery.charge").to_string()),
+            "{load}" => Value::text(var("ups.load").to_string()),
+            "{runtime}" => runtime.map_or_else(|| Value::text(String::new()), Value::duration),
+            "{status}" => Value::text(status.to_string())
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(
+            if on_battery
+                || low_battery
+                || runtime.map_or(false, |runtime| runtime < self.critical_runtime)
+            {
+                State::Critical
+            } else {
+                State::Idle
+            },
+        );
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_var;
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(
+            parse_var(r#"VAR ups battery.charge "100""#, "ups"),
+            Some(("battery.charge", "100".to_string()))
+        );
+        assert_eq!(
+            parse_var(r#"VAR ups ups.status "OB LB""#, "ups"),
+            Some(("ups.status", "OB LB".to_string()))
+        );
+        assert_eq!(
+            parse_var(r#"VAR ups device.model "Smart-UPS \"1500\" \\ RM""#, "ups"),
+            Some(("device.model", r#"Smart-UPS "1500" \ RM"#.to_string()))
+        );
+        // Another UPS, and the lines around the list
+        assert_eq!(parse_var(r#"VAR ups2 battery.charge "100""#, "ups"), None);
+        assert_eq!(parse_var("BEGIN LIST VAR ups", "ups"), None);
+    }
+}
@@ -8,8 +8,9 @@ use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
 use crate::de::deserialize_duration;
 use crate::errors::*;
+use crate::formatting::Value;
 use crate::scheduler::Task;
-use crate::util::read_file;
+use crate::util::{read_file, FormatTemplate};
 use crate::widgets::text::TextWidget;
 use crate::widgets::I3BarWidget;
 
@@ -17,6 +18,7 @@ pub struct Uptime {
     id: usize,
     text: TextWidget,
     update_interval: Duration,
+    format: FormatTemplate,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -28,34 +30,51 @@ pub struct UptimeConfig {
         deserialize_with = "deserialize_duration"
     )]
     pub interval: Duration,
+
+    /// Format override, with the placeholder {uptime}
+    #[serde(default = "UptimeConfig::default_format")]
+    pub format: String,
 }
 
 impl UptimeConfig {
     fn default_interval() -> Duration {
         Duration::from_secs(60)
     }
+
+    fn default_format() -> String {
+        "{uptime}".to_owned()
+    }
 }
 
 impl ConfigBlock for Uptime {
     type Config = UptimeConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{uptime}"])];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
-        let text = TextWidget::new(id, 0, shared_config).with_icon("uptime");
+        let text = TextWidget::new(id, 0, shared_config.clone()).with_icon("uptime");
 
         Ok(Uptime {
             id,
             update_interval: block_config.interval,
             text,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("uptime", "Invalid format specified for uptime")?,
         })
     }
 }
 
 impl Block for Uptime {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
     fn update(&mut self) -> Result<Option<Update>> {
         let uptime_raw = read_file("uptime", Path::new("/proc/uptime")).map_err(|e| {
             BlockError(
@@ -73,32 +92,10 @@ impl Block for Uptime {
             .map(|x| x as u32)
             .block_error("Uptime", "Failed to convert uptime float to integer)")?;
 
-        // split up seconds into more human readable portions
-        let weeks = (total_seconds / 604_800) as u32;
-        let rem_weeks = (total_seconds % 604_800) as u32;
-        let days = (rem_weeks / 86_400) as u32;
-        let rem_days = (rem_weeks % 86_400) as u32;
-        let hours = (rem_days / 3600) as u32;
-        let rem_hours = (rem_days % 3600) as u32;
-        let minutes = (rem_hours / 60) as u32;
-        let rem_minutes = (rem_hours % 60) as u32;
-        let seconds = rem_minutes as u32;
-
-        // Display the two largest units.
-        let text = if hours == 0 && days == 0 && weeks == 0 {
-            format!("{}m {}s", minutes, seconds)
-        } else if hours > 0 && days == 0 && weeks == 0 {
-            format!("{}h {}m", hours, minutes)
-        } else if days > 0 && weeks == 0 {
-            format!("{}d {}h", days, hours)
-        } else if days == 0 && weeks > 0 {
-            format!("{}w {}h", weeks, hours)
-        } else if weeks > 0 {
-            format!("{}w {}d", weeks, days)
-        } else {
-            unreachable!()
-        };
-        self.text.set_text(text);
+        // Shows the two largest units by default, e.g. `2d 3h`
+        let uptime = Duration::from_secs(total_seconds.into());
+        let values = map!("{uptime}" => Value::duration(uptime));
+        self.text.set_text(self.format.render(&values)?);
         Ok(Some(self.update_interval.into()))
     }
 
@@ -112,12 +112,15 @@ impl ConfigBlock for Watson {
                 for event in events {
                     match event.mask {
                         EventMask::CREATE if event.name == Some(&file_name) => {
-                            tx_update_request
+                            if tx_update_request
                                 .send(Task {
                                     id,
                                     update_time: Instant::now(),
                                 })
-                                .expect("unable to send task from watson watcher");
+                                .is_err()
+                            {
+                                return;
+                            }
                         }
                         _ => {}
                     }
@@ -1,9 +1,16 @@
 use std::collections::HashMap;
 use std::env;
-use std::time::Duration;
+use std::thread;
+use std::time::{Duration, Instant};
 
+use chrono::{
+    DateTime, Duration as ChronoDuration, FixedOffset, Local, NaiveDate, Offset, TimeZone, Utc,
+};
 use crossbeam_channel::Sender;
+use dbus::blocking::{stdintf::org_freedesktop_dbus::Properties, Connection};
+use dbus::Path;
 use serde_derive::Deserialize;
+use serde_json::Value;
 
 use crate::blocks::{Block, ConfigBlock, Update};
 use crate::config::SharedConfig;
@@ -19,38 +26,333 @@ const OPENWEATHERMAP_API_KEY_ENV: &str = "OPENWEATHERMAP_API_KEY";
 const OPENWEATHERMAP_CITY_ID_ENV: &str = "OPENWEATHERMAP_CITY_ID";
 const OPENWEATHERMAP_PLACE_ENV: &str = "OPENWEATHERMAP_PLACE";
 
+/// Placeholders which need a forecast
+const FORECAST_PLACEHOLDERS: &[&str] = &[
+    "{forecast_weather}",
+    "{forecast_icon}",
+    "{forecast_temp_3h}",
+    "{forecast_temp_min}",
+    "{forecast_temp_max}",
+    "{forecast_precipitation}",
+];
+
+/// met.no asks clients to identify themselves
+const USER_AGENT: &str = concat!(
+    "i3status-rust/",
+    env!("CARGO_PKG_VERSION"),
+    " github.com/greshake/i3status-rust"
+);
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(tag = "name", rename_all = "lowercase")]
 pub enum WeatherService {
-    OpenWeatherMap {
-        #[serde(default = "WeatherService::getenv_openweathermap_api_key")]
-        api_key: Option<String>,
-        #[serde(default = "WeatherService::getenv_openweathermap_city_id")]
-        city_id: Option<String>,
-        #[serde(default = "WeatherService::getenv_openweathermap_place")]
-        place: Option<String>,
-        coordinates: Option<(String, String)>,
-        units: OpenWeatherMapUnits,
-    },
+    OpenWeatherMap(OpenWeatherMap),
+    MetNo(MetNo),
+    OpenMeteo(OpenMeteo),
 }
 
 impl WeatherService {
-    fn getenv_openweathermap_api_key() -> Option<String> {
+    fn provider(&self) -> &dyn WeatherProvider {
+        match self {
+            WeatherService::OpenWeatherMap(provider) => provider,
+            WeatherService::MetNo(provider) => provider,
+            WeatherService::OpenMeteo(provider) => provider,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WeatherUnits {
+    Metric,
+    Imperial,
+}
+
+impl Default for WeatherUnits {
+    fn default() -> Self {
+        WeatherUnits::Metric
+    }
+}
+
+/// The location found by `autolocate`.
+struct Location {
+    /// Empty when the source doesn't name the place
+    city: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+/// The current weather, in the units of the configuration.
+#[derive(Debug, PartialEq)]
+struct WeatherInfo {
+    /// Conditions, named as by OpenWeatherMap, e.g. "Clouds"
+    weather: String,
+    temp: f64,
+    humidity: f64,
+    /// In m/s or mph
+    wind_speed: f64,
+    wind_direction: Option<f64>,
+    location: String,
+    forecast: Option<Forecast>,
+}
+
+/// The weather in the coming hours, in the units of the configuration.
+#[derive(Debug, PartialEq)]
+struct Forecast {
+    /// Conditions in 3 hours
+    weather: String,
+    /// Temperature in 3 hours
+    temp: f64,
+    /// Lowest temperature of the rest of the day
+    temp_min: f64,
+    /// Highest temperature of the rest of the day
+    temp_max: f64,
+    /// Highest probability of precipitation during the next 3 hours, in percent
+    precipitation: Option<f64>,
+}
+
+/// The date in the local time zone, which forecasts are compared with to find today's ones.
+#[derive(Clone, Copy)]
+struct Today {
+    date: NaiveDate,
+    offset: FixedOffset,
+}
+
+impl Today {
+    fn now() -> Self {
+        let now = Local::now();
+        Today {
+            date: now.date().naive_local(),
+            offset: now.offset().fix(),
+        }
+    }
+
+    fn contains(self, time: DateTime<Utc>) -> bool {
+        time.with_timezone(&self.offset).date().naive_local() == self.date
+    }
+}
+
+/// A service telling the current weather.
+trait WeatherProvider {
+    fn units(&self) -> WeatherUnits;
+
+    /// Gets the weather at the location of the configuration, or at the location found by
+    /// `autolocate` if there is one, and the forecast if `forecast` is set and the service has one.
+    fn current_weather(&self, location: Option<&Location>, forecast: bool) -> Result<WeatherInfo>;
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OpenWeatherMap {
+    #[serde(default = "OpenWeatherMap::getenv_api_key")]
+    api_key: Option<String>,
+    #[serde(default = "OpenWeatherMap::getenv_city_id")]
+    city_id: Option<String>,
+    #[serde(default = "OpenWeatherMap::getenv_place")]
+    place: Option<String>,
+    coordinates: Option<(String, String)>,
+    units: WeatherUnits,
+}
+
+impl OpenWeatherMap {
+    fn getenv_api_key() -> Option<String> {
         env::var(OPENWEATHERMAP_API_KEY_ENV).ok()
     }
-    fn getenv_openweathermap_city_id() -> Option<String> {
+    fn getenv_city_id() -> Option<String> {
         env::var(OPENWEATHERMAP_CITY_ID_ENV).ok()
     }
-    fn getenv_openweathermap_place() -> Option<String> {
+    fn getenv_place() -> Option<String> {
         env::var(OPENWEATHERMAP_PLACE_ENV).ok()
     }
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
-#[serde(rename_all = "lowercase")]
-pub enum OpenWeatherMapUnits {
-    Metric,
-    Imperial,
+impl WeatherProvider for OpenWeatherMap {
+    fn units(&self) -> WeatherUnits {
+        self.units
+    }
+
+    fn current_weather(&self, location: Option<&Location>, forecast: bool) -> Result<WeatherInfo> {
+        let api_key = match self.api_key {
+            Some(ref api_key) => api_key,
+            None => return configuration_error(&format!(
+                "Missing member 'service.api_key'. Add the member or configure with the environment variable {}",
+                OPENWEATHERMAP_API_KEY_ENV.to_string())),
+        };
+
+        let location_query = if let Some(location) = location {
+            if location.city.is_empty() {
+                format!("lat={}&lon={}", location.latitude, location.longitude)
+            } else {
+                format!("q={}", location.city)
+            }
+        } else if let Some(cid) = self.city_id.as_ref() {
+            format!("id={}", cid)
+        } else if let Some(p) = self.place.as_ref() {
+            format!("q={}", p)
+        } else if let Some((lat, lon)) = self.coordinates.as_ref() {
+            format!("lat={}&lon={}", lat, lon)
+        } else {
+            return configuration_error(&format!(
+                "Either 'service.city_id' or 'service.place' must be provided. Add one to your config file or set with the environment variables {} or {}",
+                OPENWEATHERMAP_CITY_ID_ENV.to_string(),
+                OPENWEATHERMAP_PLACE_ENV.to_string()));
+        };
+
+        // This uses the "Current Weather Data" API endpoint
+        // Refer to https://openweathermap.org/current
+        let units = match self.units {
+            WeatherUnits::Metric => "metric",
+            WeatherUnits::Imperial => "imperial",
+        };
+        let openweather_url = &format!(
+            "https://api.openweathermap.org/data/2.5/weather?{location_query}&appid={api_key}&units={units}",
+            location_query = location_query,
+            api_key = api_key,
+            units = units,
+        );
+
+        let json = get_json(openweather_url, vec![])?;
+
+        // Try to convert an API error into a block error.
+        if let Some(val) = json.get("message") {
+            return Err(BlockError(
+                "weather".to_string(),
+                format!("API Error: {}", val.as_str().unwrap()),
+            ));
+        };
+
+        let raw_weather = json
+            .pointer("/weather/0/main")
+            .and_then(|v| v.as_str())
+            .ok_or_else(malformed_json_error)?
+            .to_string();
+
+        let raw_temp = json
+            .pointer("/main/temp")
+            .and_then(|v| v.as_f64())
+            .ok_or_else(malformed_json_error)?;
+
+        let raw_humidity = json
+            .pointer("/main/humidity")
+            .map_or(Some(0.0), |v| v.as_f64()) // provide default value 0.0
+            .ok_or_else(malformed_json_error)?;
+
+        let raw_wind_speed: f64 = json
+            .pointer("/wind/speed")
+            .map_or(Some(0.0), |v| v.as_f64()) // provide default value 0.0
+            .ok_or_else(malformed_json_error)?; // error when conversion to f64 fails
+
+        let raw_wind_direction: Option<f64> = json
+            .pointer("/wind/deg")
+            .map_or(Some(None), |v| v.as_f64().map(Some)) // provide default value None
+            .ok_or_else(malformed_json_error)?; // error when conversion to f64 fails
+
+        let raw_location = json
+            .pointer("/name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(malformed_json_error)?;
+
+        // The "5 day weather forecast" API endpoint gives the weather every 3 hours
+        // Refer to https://openweathermap.org/forecast5
+        let forecast = if forecast {
+            let forecast_url = format!(
+                "https://api.openweathermap.org/data/2.5/forecast?{location_query}&appid={api_key}&units={units}&cnt=8",
+                location_query = location_query,
+                api_key = api_key,
+                units = units,
+            );
+            let json = get_json(&forecast_url, vec![])?;
+            Some(
+                parse_openweathermap_forecast(&json, raw_temp, Today::now())
+                    .ok_or_else(malformed_json_error)?,
+            )
+        } else {
+            None
+        };
+
+        Ok(WeatherInfo {
+            weather: raw_weather,
+            temp: raw_temp,
+            humidity: raw_humidity,
+            wind_speed: raw_wind_speed,
+            wind_direction: raw_wind_direction,
+            location: raw_location,
+            forecast,
+        })
+    }
+}
+
+/// The service of the Norwegian Meteorological Institute, which needs no API key.
+#[derive(Clone, Debug, Deserialize)]
+pub struct MetNo {
+    coordinates: Option<(String, String)>,
+    #[serde(default)]
+    units: WeatherUnits,
+}
+
+impl WeatherProvider for MetNo {
+    fn units(&self) -> WeatherUnits {
+        self.units
+    }
+
+    fn current_weather(&self, location: Option<&Location>, forecast: bool) -> Result<WeatherInfo> {
+        let (latitude, longitude) = coordinates(location, &self.coordinates)?;
+        // Refer to https://api.met.no/weatherapi/locationforecast/2.0/documentation
+        let url = format!(
+            "https://api.met.no/weatherapi/locationforecast/2.0/complete?lat={}&lon={}",
+            latitude, longitude
+        );
+        let json = get_json(&url, vec![("User-Agent", USER_AGENT)])?;
+        let mut weather = parse_met_no(&json, Today::now()).ok_or_else(malformed_json_error)?;
+        weather.location = location.map(|l| l.city.clone()).unwrap_or_default();
+        if !forecast {
+            weather.forecast = None;
+        }
+        if self.units == WeatherUnits::Imperial {
+            weather.temp = celsius_to_fahrenheit(weather.temp);
+            weather.wind_speed /= 0.447;
+            if let Some(ref mut forecast) = weather.forecast {
+                forecast.temp = celsius_to_fahrenheit(forecast.temp);
+                forecast.temp_min = celsius_to_fahrenheit(forecast.temp_min);
+                forecast.temp_max = celsius_to_fahrenheit(forecast.temp_max);
+            }
+        }
+        Ok(weather)
+    }
+}
+
+/// The service of Open-Meteo, which needs no API key.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OpenMeteo {
+    coordinates: Option<(String, String)>,
+    #[serde(default)]
+    units: WeatherUnits,
+}
+
+impl WeatherProvider for OpenMeteo {
+    fn units(&self) -> WeatherUnits {
+        self.units
+    }
+
+    fn current_weather(&self, location: Option<&Location>, forecast: bool) -> Result<WeatherInfo> {
+        let (latitude, longitude) = coordinates(location, &self.coordinates)?;
+        let (temperature_unit, windspeed_unit) = match self.units {
+            WeatherUnits::Metric => ("celsius", "ms"),
+            WeatherUnits::Imperial => ("fahrenheit", "mph"),
+        };
+        // Refer to https://open-meteo.com/en/docs
+        let url = format!(
+            "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current_weather=true&hourly=relativehumidity_2m,temperature_2m,weathercode,precipitation_probability&daily=temperature_2m_min,temperature_2m_max&timezone=auto&temperature_unit={}&windspeed_unit={}",
+            latitude, longitude, temperature_unit, windspeed_unit
+        );
+        let json = get_json(&url, vec![])?;
+        let mut weather = parse_open_meteo(&json).ok_or_else(malformed_json_error)?;
+        weather.location = location.map(|l| l.city.clone()).unwrap_or_default();
+        if !forecast {
+            weather.forecast = None;
+        }
+        Ok(weather)
+    }
 }
 
 pub struct Weather {
@@ -61,28 +363,342 @@ pub struct Weather {
     service: WeatherService,
     update_interval: Duration,
     autolocate: bool,
+    /// Whether the format shows the forecast
+    forecast: bool,
+    /// When the weather was last requested, so that clicks don't request it more often
+    last_fetch: Option<Instant>,
+    shared_config: SharedConfig,
 }
 
 fn malformed_json_error() -> Error {
     BlockError("weather".to_string(), "Malformed JSON.".to_string())
 }
 
-// TODO: might be good to allow for different geolocation services to be used, similar to how we have `service` for the weather API
-fn find_ip_location() -> Result<Option<String>> {
+/// Finds the location with GeoClue2, or else from the IP address.
+fn find_location() -> Option<Location> {
+    find_geoclue_location()
+        .ok()
+        .or_else(|| find_ip_location().ok().unwrap_or(None))
+}
+
+/// Asks GeoClue2 for the location, waiting a few seconds for its first fix.
+fn find_geoclue_location() -> Result<Location> {
+    const GEOCLUE: &str = "org.freedesktop.GeoClue2";
+    let timeout = Duration::from_secs(5);
+    let c =
+        Connection::new_system().block_error("weather", "Failed to establish D-Bus connection")?;
+
+    let manager = c.with_proxy(GEOCLUE, "/org/freedesktop/GeoClue2/Manager", timeout);
+    let (client_path,): (Path,) = manager
+        .method_call("org.freedesktop.GeoClue2.Manager", "GetClient", ())
+        .block_error("weather", "GeoClue2 is not available")?;
+
+    let client = c.with_proxy(GEOCLUE, client_path, timeout);
+    let client_interface = "org.freedesktop.GeoClue2.Client";
+    client
+        .set(client_interface, "DesktopId", "i3status-rs".to_string())
+        .block_error("weather", "Failed to configure the GeoClue2 client")?;
+    // City level accuracy is plenty for the weather
+    client
+        .set(client_interface, "RequestedAccuracyLevel", 4u32)
+        .block_error("weather", "Failed to configure the GeoClue2 client")?;
+    client
+        .method_call::<(), _, _, _>(client_interface, "Start", ())
+        .block_error("weather", "GeoClue2 refused to locate")?;
+
+    let started = Instant::now();
+    let location_path = loop {
+        let path: Path = client
+            .get(client_interface, "Location")
+            .block_error("weather", "Unexpected reply from GeoClue2")?;
+        // The location stays at "/" until the first fix
+        if &*path != "/" {
+            break path;
+        }
+        if started.elapsed() > timeout {
+            let _: std::result::Result<(), _> = client.method_call(client_interface, "Stop", ());
+            return Err(BlockError(
+                "weather".to_owned(),
+                "GeoClue2 did not find the location in time".to_owned(),
+            ));
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+
+    let location = c.with_proxy(GEOCLUE, location_path, timeout);
+    let location_interface = "org.freedesktop.GeoClue2.Location";
+    let latitude: f64 = location
+        .get(location_interface, "Latitude")
+        .block_error("weather", "Unexpected reply from GeoClue2")?;
+    let longitude: f64 = location
+        .get(location_interface, "Longitude")
+        .block_error("weather", "Unexpected reply from GeoClue2")?;
+    let city: String = location
+        .get(location_interface, "Description")
+        .unwrap_or_default();
+    let _: std::result::Result<(), _> = client.method_call(client_interface, "Stop", ());
+
+    Ok(Location {
+        city,
+        latitude,
+        longitude,
+    })
+}
+
+fn find_ip_location() -> Result<Option<Location>> {
     let http_call_result = http::http_get_json(
         "https://ipapi.co/json/",
         Some(Duration::from_secs(3)),
         vec![],
     )?;
 
-    let city = http_call_result
-        .content
-        .pointer("/city")
-        .map(|v| v.as_str())
-        .flatten()
-        .map(|s| s.to_string());
+    Ok(parse_ip_location(&http_call_result.content))
+}
+
+fn parse_ip_location(json: &Value) -> Option<Location> {
+    Some(Location {
+        city: json.get("city")?.as_str()?.to_string(),
+        latitude: json.get("latitude")?.as_f64()?,
+        longitude: json.get("longitude")?.as_f64()?,
+    })
+}
+
+/// Gets the JSON reply of a weather service.
+fn get_json(url: &str, headers: Vec<(&str, &str)>) -> Result<Value> {
+    let output = http::http_get_json(url, Some(Duration::from_secs(3)), headers)?;
+
+    // All 300-399 and >500 http codes should be considered as temporary error,
+    // and not result in block error, i.e. leave the output empty.
+    if (output.code >= 300 && output.code < 400) || output.code >= 500 {
+        return Err(BlockError(
+            "weather".to_owned(),
+            format!("Invalid result from curl: {}", output.code),
+        ));
+    };
+
+    Ok(output.content)
+}
+
+/// The coordinates found by `autolocate`, or else the configured ones.
+fn coordinates(
+    location: Option<&Location>,
+    configured: &Option<(String, String)>,
+) -> Result<(String, String)> {
+    match (location, configured) {
+        (Some(location), _) => Ok((
+            location.latitude.to_string(),
+            location.longitude.to_string(),
+        )),
+        (None, Some(coordinates)) => Ok(coordinates.clone()),
+        (None, None) => Err(ConfigurationError(
+            "weather".to_owned(),
+            (
+                "Missing member 'service.coordinates'".to_owned(),
+                "Missing member 'service.coordinates'".to_owned(),
+            ),
+        )),
+    }
+}
+
+/// Highest of `values`, if any.
+fn max(values: impl Iterator<Item = f64>) -> Option<f64> {
+    values.fold(None, |max: Option<f64>, value| {
+        Some(max.map_or(value, |max| max.max(value)))
+    })
+}
+
+/// Lowest and highest of `values` and `current`.
+fn min_max(values: impl Iterator<Item = f64>, current: f64) -> (f64, f64) {
+    values.fold((current, current), |(min, max), value| {
+        (min.min(value), max.max(value))
+    })
+}
+
+/// The forecast of OpenWeatherMap, every 3 hours, starting with the next 3 hours.
+fn parse_openweathermap_forecast(json: &Value, temp: f64, today: Today) -> Option<Forecast> {
+    let list = json.get("list")?.as_array()?;
+    let next = list.first()?;
+    let (temp_min, temp_max) = min_max(
+        list.iter()
+            .filter(|entry| {
+                entry
+                    .get("dt")
+                    .and_then(|dt| dt.as_i64())
+                    .map_or(false, |dt| today.contains(Utc.timestamp(dt, 0)))
+            })
+            .flat_map(|entry| {
+                vec![
+                    entry.pointer("/main/temp_min").and_then(|v| v.as_f64()),
+                    entry.pointer("/main/temp_max").and_then(|v| v.as_f64()),
+                ]
+            })
+            .flatten(),
+        temp,
+    );
+    Some(Forecast {
+        weather: next.pointer("/weather/0/main")?.as_str()?.to_string(),
+        temp: next.pointer("/main/temp")?.as_f64()?,
+        temp_min,
+        temp_max,
+        // The probability is between 0 and 1
+        precipitation: next
+            .get("pop")
+            .and_then(|v| v.as_f64())
+            .map(|pop| pop * 100.0),
+    })
+}
+
+/// The conditions of a symbol of met.no, e.g. `lightrainshowers_day` or `cloudy`.
+fn met_no_weather(symbol: &str) -> &'static str {
+    if symbol.contains("thunder") {
+        "Thunderstorm"
+    } else if symbol.contains("snow") || symbol.contains("sleet") {
+        "Snow"
+    } else if symbol.contains("rain") {
+        "Rain"
+    } else if symbol.starts_with("fog") {
+        "Fog"
+    } else if symbol.starts_with("clearsky") {
+        "Clear"
+    } else if symbol.contains("cloudy") || symbol.starts_with("fair") {
+        "Clouds"
+    } else {
+        ""
+    }
+}
+
+/// The current weather and forecast from a forecast of met.no, which is hourly for the next days.
+fn parse_met_no(json: &Value, today: Today) -> Option<WeatherInfo> {
+    let series: Vec<(DateTime<Utc>, &Value)> = json
+        .pointer("/properties/timeseries")?
+        .as_array()?
+        .iter()
+        .filter_map(|entry| {
+            let time = DateTime::parse_from_rfc3339(entry.get("time")?.as_str()?).ok()?;
+            Some((time.with_timezone(&Utc), entry.get("data")?))
+        })
+        .collect();
+    let symbol = |data: &Value| {
+        met_no_weather(
+            data.pointer("/next_1_hours/summary/symbol_code")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default(),
+        )
+    };
+    let temp = |data: &Value| {
+        data.pointer("/instant/details/air_temperature")
+            .and_then(|v| v.as_f64())
+    };
+
+    let (start, now) = *series.first()?;
+    let details = now.pointer("/instant/details")?;
+    let current_temp = temp(now)?;
+
+    let forecast = series
+        .iter()
+        .find(|(time, _)| *time >= start + ChronoDuration::hours(3))
+        .and_then(|&(_, later)| {
+            let (temp_min, temp_max) = min_max(
+                series
+                    .iter()
+                    .filter(|(time, _)| today.contains(*time))
+                    .filter_map(|(_, data)| temp(data)),
+                current_temp,
+            );
+            Some(Forecast {
+                weather: symbol(later).to_string(),
+                temp: temp(later)?,
+                temp_min,
+                temp_max,
+                precipitation: max(series.iter().take(3).filter_map(|(_, data)| {
+                    data.pointer("/next_1_hours/details/probability_of_precipitation")
+                        .and_then(|v| v.as_f64())
+                })),
+            })
+        });
+
+    Some(WeatherInfo {
+        weather: symbol(now).to_string(),
+        temp: current_temp,
+        humidity: details
+            .get("relative_humidity")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0),
+        wind_speed: details
+            .get("wind_speed")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0),
+        wind_direction: details.get("wind_from_direction").and_then(|v| v.as_f64()),
+        location: String::new(),
+        forecast,
+    })
+}
+
+/// The conditions of a WMO weather interpretation code.
+fn wmo_weather(code: u64) -> &'static str {
+    match code {
+        0 => "Clear",
+        1..=3 => "Clouds",
+        45 | 48 => "Fog",
+        51..=57 => "Drizzle",
+        61..=67 | 80..=82 => "Rain",
+        71..=77 | 85 | 86 => "Snow",
+        95..=99 => "Thunderstorm",
+        _ => "",
+    }
+}
+
+/// The forecast of Open-Meteo from the hourly values, `hour` being the index of the current hour.
+/// Days are in the time zone of the location.
+fn parse_open_meteo_forecast(json: &Value, hour: usize) -> Option<Forecast> {
+    let hourly = |name: &str, hour: usize| json.pointer(&format!("/hourly/{}/{}", name, hour));
+    Some(Forecast {
+        weather: wmo_weather(hourly("weathercode", hour + 3)?.as_u64()?).to_string(),
+        temp: hourly("temperature_2m", hour + 3)?.as_f64()?,
+        temp_min: json.pointer("/daily/temperature_2m_min/0")?.as_f64()?,
+        temp_max: json.pointer("/daily/temperature_2m_max/0")?.as_f64()?,
+        precipitation: max((hour + 1..=hour + 3)
+            .filter_map(|hour| hourly("precipitation_probability", hour).and_then(|v| v.as_f64()))),
+    })
+}
+
+/// The current weather and forecast from a forecast of Open-Meteo.
+fn parse_open_meteo(json: &Value) -> Option<WeatherInfo> {
+    let current = json.get("current_weather")?;
+    // Values other than the current weather are only given by the hour
+    let time = current.get("time")?.as_str()?;
+    let hour = json
+        .pointer("/hourly/time")
+        .and_then(|v| v.as_array())
+        .and_then(|times| times.iter().position(|t| t.as_str() == Some(time)));
+    let humidity = hour
+        .and_then(|hour| json.pointer(&format!("/hourly/relativehumidity_2m/{}", hour)))
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+    Some(WeatherInfo {
+        weather: wmo_weather(current.get("weathercode")?.as_u64()?).to_string(),
+        temp: current.get("temperature")?.as_f64()?,
+        humidity,
+        wind_speed: current.get("windspeed")?.as_f64()?,
+        wind_direction: current.get("winddirection").and_then(|v| v.as_f64()),
+        location: String::new(),
+        forecast: hour.and_then(|hour| parse_open_meteo_forecast(json, hour)),
+    })
+}
+
+fn weather_icon(weather: &str) -> &'static str {
+    match weather {
+        "Clear" => "weather_sun",
+        "Rain" | "Drizzle" => "weather_rain",
+        "Clouds" | "Fog" | "Mist" => "weather_clouds",
+        "Thunderstorm" => "weather_thunder",
+        "Snow" => "weather_snow",
+        _ => "weather_default",
+    }
+}
 
-    Ok(city)
+fn celsius_to_fahrenheit(celsius: f64) -> f64 {
+    celsius * 1.8 + 32.0
 }
 
 // Compute the Australian Apparent Temperature (AT),
@@ -92,11 +708,11 @@ fn australian_apparent_temp(
     raw_temp: f64,
     raw_humidity: f64,
     raw_wind_speed: f64,
-    units: OpenWeatherMapUnits,
+    units: WeatherUnits,
 ) -> f64 {
-    let metric = units == OpenWeatherMapUnits::Metric;
+    let metric = units == WeatherUnits::Metric;
 
-    let temp_celsius = if units == OpenWeatherMapUnits::Metric {
+    let temp_celsius = if units == WeatherUnits::Metric {
         raw_temp
     } else {
         // convert Fahrenheit to Celsius
@@ -140,7 +756,7 @@ fn convert_wind_direction(direction_opt: Option<f64>) -> String {
     }
 }
 
-fn configuration_error(msg: &str) -> Result<()> {
+fn configuration_error<T>(msg: &str) -> Result<T> {
     Err(ConfigurationError(
         "weather".to_owned(),
         (msg.to_owned(), msg.to_owned()),
@@ -149,142 +765,61 @@ fn configuration_error(msg: &str) -> Result<()> {
 
 impl Weather {
     fn update_weather(&mut self) -> Result<()> {
-        match &self.service {
-            WeatherService::OpenWeatherMap {
-                api_key: api_key_opt,
-                city_id,
-                place,
-                units,
-                coordinates,
-            } => {
-                if api_key_opt.is_none() {
-                    return configuration_error(&format!(
-                        "Missing member 'service.api_key'. Add the member or configure with the environment variable {}",
-                        OPENWEATHERMAP_API_KEY_ENV.to_string()));
-                }
+        let provider = self.service.provider();
 
-                let api_key = api_key_opt.as_ref().unwrap();
-
-                let geoip_city = if self.autolocate {
-                    find_ip_location().ok().unwrap_or(None) // If geo location fails, try other configuration methods
-                } else {
-                    None
-                };
-
-                let location_query = if let Some(city) = geoip_city {
-                    format!("q={}", city)
-                } else if let Some(cid) = city_id.as_ref() {
-                    format!("id={}", cid)
-                } else if let Some(p) = place.as_ref() {
-                    format!("q={}", p)
-                } else if let Some((lat, lon)) = coordinates {
-                    format!("lat={}&lon={}", lat, lon)
-                } else if self.autolocate {
-                    return configuration_error(
-                        "weather is configured to use geolocation, but it could not be obtained",
-                    );
-                } else {
-                    return configuration_error(&format!(
-                        "Either 'service.city_id' or 'service.place' must be provided. Add one to your config file or set with the environment variables {} or {}",
-                        OPENWEATHERMAP_CITY_ID_ENV.to_string(),
-                        OPENWEATHERMAP_PLACE_ENV.to_string()));
-                };
-
-                // This uses the "Current Weather Data" API endpoint
-                // Refer to https://openweathermap.org/current
-                let openweather_url = &format!(
-                    "https://api.openweathermap.org/data/2.5/weather?{location_query}&appid={api_key}&units={units}",
-                    location_query = location_query,
-                    api_key = api_key,
-                    units = match *units {
-                        OpenWeatherMapUnits::Metric => "metric",
-                        OpenWeatherMapUnits::Imperial => "imperial",
-                    },
-                );
-
-                let output =
-                    http::http_get_json(openweather_url, Some(Duration::from_secs(3)), vec![])?;
-
-                // All 300-399 and >500 http codes should be considered as temporary error,
-                // and not result in block error, i.e. leave the output empty.
-                if (output.code >= 300 && output.code < 400) || output.code >= 500 {
-                    return Err(BlockError(
-                        "weather".to_owned(),
-                        format!("Invalid result from curl: {}", output.code),
-                    ));
-                };
-
-                let json = output.content;
-
-                // Try to convert an API error into a block error.
-                if let Some(val) = json.get("message") {
-                    return Err(BlockError(
-                        "weather".to_string(),
-                        format!("API Error: {}", val.as_str().unwrap()),
-                    ));
-                };
-
-                let raw_weather = json
-                    .pointer("/weather/0/main")
-                    .ok_or_else(malformed_json_error)?
-                    .to_string();
-
-                let raw_temp = json
-                    .pointer("/main/temp")
-                    .and_then(|v| v.as_f64())
-                    .ok_or_else(malformed_json_error)?;
-
-                let raw_humidity = json
-                    .pointer("/main/humidity")
-                    .map_or(Some(0.0), |v| v.as_f64()) // provide default value 0.0
-                    .ok_or_else(malformed_json_error)?;
-
-                let raw_wind_speed: f64 = json
-                    .pointer("/wind/speed")
-                    .map_or(Some(0.0), |v| v.as_f64()) // provide default value 0.0
-                    .ok_or_else(malformed_json_error)?; // error when conversion to f64 fails
-
-                let raw_wind_direction: Option<f64> = json
-                    .pointer("/wind/deg")
-                    .map_or(Some(None), |v| v.as_f64().map(Some)) // provide default value None
-                    .ok_or_else(malformed_json_error)?; // error when conversion to f64 fails
-
-                let raw_location = json
-                    .pointer("/name")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())
-                    .ok_or_else(malformed_json_error)?;
-
-                self.weather.set_icon(match raw_weather.as_str() {
-                    "Clear" => "weather_sun",
-                    "Rain" | "Drizzle" => "weather_rain",
-                    "Clouds" | "Fog" | "Mist" => "weather_clouds",
-                    "Thunderstorm" => "weather_thunder",
-                    "Snow" => "weather_snow",
-                    _ => "weather_default",
-                });
-
-                let kmh_wind_speed = if *units == OpenWeatherMapUnits::Metric {
-                    raw_wind_speed * 3600.0 / 1000.0
-                } else {
-                    // convert mph to m/s, then km/h
-                    (raw_wind_speed * 0.447) * 3600.0 / 1000.0
-                };
-
-                let apparent_temp =
-                    australian_apparent_temp(raw_temp, raw_humidity, raw_wind_speed, *units);
-
-                self.weather_keys = map!("{weather}" => raw_weather,
-                                  "{temp}" => format!("{:.0}", raw_temp),
-                                  "{humidity}" => format!("{:.0}", raw_humidity),
-                                  "{apparent}" => format!("{:.0}",apparent_temp),
-                                  "{wind}" => format!("{:.1}", raw_wind_speed),
-                                  "{wind_kmh}" => format!("{:.1}", kmh_wind_speed),
-                                  "{direction}" => convert_wind_direction(raw_wind_direction),
-                                  "{location}" => raw_location);
-                Ok(())
-            }
-        }
+        let location = if self.autolocate {
+            find_location() // If geo location fails, try other configuration methods
+        } else {
+            None
+        };
+
+        let info = provider.current_weather(location.as_ref(), self.forecast)?;
+
+        self.weather.set_icon(weather_icon(&info.weather));
+
+        let units = provider.units();
+        let kmh_wind_speed = if units == WeatherUnits::Metric {
+            info.wind_speed * 3600.0 / 1000.0
+        } else {
+            // convert mph to m/s, then km/h
+            (info.wind_speed * 0.447) * 3600.0 / 1000.0
+        };
+
+        let apparent_temp =
+            australian_apparent_temp(info.temp, info.humidity, info.wind_speed, units);
+
+        self.weather_keys = map!("{weather}" => info.weather,
+                          "{temp}" => format!("{:.0}", info.temp),
+                          "{humidity}" => format!("{:.0}", info.humidity),
+                          "{apparent}" => format!("{:.0}",apparent_temp),
+                          "{wind}" => format!("{:.1}", info.wind_speed),
+                          "{wind_kmh}" => format!("{:.1}", kmh_wind_speed),
+                          "{direction}" => convert_wind_direction(info.wind_direction),
+                          "{location}" => info.location);
+
+        let forecast_keys = match info.forecast {
+            Some(forecast) => map!(
+                "{forecast_icon}" => self
+                    .shared_config
+                    .get_icon(weather_icon(&forecast.weather))
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                "{forecast_weather}" => forecast.weather,
+                "{forecast_temp_3h}" => format!("{:.0}", forecast.temp),
+                "{forecast_temp_min}" => format!("{:.0}", forecast.temp_min),
+                "{forecast_temp_max}" => format!("{:.0}", forecast.temp_max),
+                "{forecast_precipitation}" => forecast
+                    .precipitation
+                    .map_or_else(|| "-".to_string(), |p| format!("{:.0}", p))
+            ),
+            None => FORECAST_PLACEHOLDERS
+                .iter()
+                .map(|&key| (key, "-".to_string()))
+                .collect(),
+        };
+        self.weather_keys.extend(forecast_keys);
+        Ok(())
     }
 }
 
@@ -320,45 +855,84 @@ impl WeatherConfig {
 impl ConfigBlock for Weather {
     type Config = WeatherConfig;
 
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{apparent}",
+            "{direction}",
+            "{forecast_icon}",
+            "{forecast_precipitation}",
+            "{forecast_temp_3h}",
+            "{forecast_temp_max}",
+            "{forecast_temp_min}",
+            "{forecast_weather}",
+            "{humidity}",
+            "{location}",
+            "{temp}",
+            "{weather}",
+            "{wind_kmh}",
+            "{wind}",
+        ],
+    )];
+
     fn new(
         id: usize,
         block_config: Self::Config,
         shared_config: SharedConfig,
         _tx_update_request: Sender<Task>,
     ) -> Result<Self> {
+        let format = FormatTemplate::new(&block_config.format, &shared_config)?;
+        let forecast = FORECAST_PLACEHOLDERS
+            .iter()
+            .any(|key| format.has_placeholder(key));
+
         Ok(Weather {
             id,
-            weather: TextWidget::new(id, 0, shared_config),
+            weather: TextWidget::new(id, 0, shared_config.clone()),
             format: block_config.format,
             weather_keys: HashMap::new(),
             service: block_config.service,
             update_interval: block_config.interval,
             autolocate: block_config.autolocate,
+            forecast,
+            last_fetch: None,
+            shared_config,
         })
     }
 }
 
 impl Block for Weather {
     fn update(&mut self) -> Result<Option<Update>> {
-        match self.update_weather() {
-            Ok(_) => {
-                let fmt = FormatTemplate::from_string(&self.format)?;
-                self.weather
-                    .set_text(fmt.render_static_str(&self.weather_keys)?);
-                self.weather.set_state(State::Idle)
-            }
-            Err(BlockError(block, _)) | Err(InternalError(block, _, _)) if block == "curl" => {
-                // Ignore curl/api errors
-                self.weather.set_icon("weather_default");
-                self.weather.set_text("×".to_string());
-                self.weather.set_state(State::Warning)
-            }
-            Err(err) => {
-                self.weather.set_text(format!("weather error {}:", err));
-                self.weather.set_state(State::Critical);
+        // Updates from clicks reuse the weather until it is `interval` old
+        if self
+            .last_fetch
+            .map_or(true, |last| last.elapsed() >= self.update_interval)
+        {
+            self.last_fetch = Some(Instant::now());
+            match self.update_weather() {
+                Ok(_) => {}
+                Err(BlockError(block, _)) | Err(InternalError(block, _, _)) if block == "curl" => {
+                    // Ignore curl/api errors
+                    self.weather_keys.clear();
+                    self.weather.set_icon("weather_default");
+                    self.weather.set_text("×".to_string());
+                    self.weather.set_state(State::Warning)
+                }
+                Err(err) => {
+                    self.weather_keys.clear();
+                    self.weather.set_text(format!("weather error {}:", err));
+                    self.weather.set_state(State::Critical);
+                }
             }
         }
 
+        if !self.weather_keys.is_empty() {
+            let fmt = FormatTemplate::new(&self.format, &self.shared_config)?;
+            self.weather
+                .set_text(fmt.render_static_str(&self.weather_keys)?);
+            self.weather.set_state(State::Idle)
+        }
+
         Ok(Some(self.update_interval.into()))
     }
 
@@ -377,3 +951,157 @@ impl Block for Weather {
         self.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveDate};
+    use serde_json::{json, Value};
+
+    use super::{
+        parse_met_no, parse_open_meteo, parse_openweathermap_forecast, Forecast, Today, WeatherInfo,
+    };
+
+    fn today() -> Today {
+        Today {
+            date: NaiveDate::from_ymd(2021, 3, 31),
+            offset: FixedOffset::east(2 * 3600),
+        }
+    }
+
+    fn met_no_entry(time: &str, temp: f64, symbol: &str, precipitation: f64) -> Value {
+        json!({
+            "time": time,
+            "data": {
+                "instant": {
+                    "details": {
+                        "air_temperature": temp,
+                        "relative_humidity": 81.5,
+                        "wind_from_direction": 200.1,
+                        "wind_speed": 3.4
+                    }
+                },
+                "next_1_hours": {
+                    "summary": {"symbol_code": symbol},
+                    "details": {"probability_of_precipitation": precipitation}
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_parse_met_no() {
+        let json = json!({
+            "properties": {
+                "timeseries": [
+                    met_no_entry("2021-03-31T20:00:00Z", 12.3, "lightrainshowers_day", 40.0),
+                    met_no_entry("2021-03-31T21:00:00Z", 11.0, "rain", 70.0),
+                    // This is already tomorrow in the local time zone
+                    met_no_entry("2021-03-31T22:00:00Z", 8.0, "cloudy", 10.0),
+                    met_no_entry("2021-03-31T23:00:00Z", 7.5, "clearsky_night", 0.0),
+                ]
+            }
+        });
+        assert_eq!(
+            parse_met_no(&json, today()),
+            Some(WeatherInfo {
+                weather: "Rain".to_string(),
+                temp: 12.3,
+                humidity: 81.5,
+                wind_speed: 3.4,
+                wind_direction: Some(200.1),
+                location: String::new(),
+                forecast: Some(Forecast {
+                    weather: "Clear".to_string(),
+                    temp: 7.5,
+                    temp_min: 11.0,
+                    temp_max: 12.3,
+                    precipitation: Some(70.0),
+                }),
+            })
+        );
+        assert_eq!(parse_met_no(&json!({}), today()), None);
+    }
+
+    #[test]
+    fn test_parse_open_meteo() {
+        let json = json!({
+            "current_weather": {
+                "temperature": 12.3,
+                "windspeed": 3.4,
+                "winddirection": 200.0,
+                "weathercode": 3,
+                "time": "2021-03-31T10:00"
+            },
+            "hourly": {
+                "time": [
+                    "2021-03-31T09:00",
+                    "2021-03-31T10:00",
+                    "2021-03-31T11:00",
+                    "2021-03-31T12:00",
+                    "2021-03-31T13:00"
+                ],
+                "relativehumidity_2m": [85, 81, 80, 78, 75],
+                "temperature_2m": [11.0, 12.3, 13.1, 14.0, 14.6],
+                "weathercode": [3, 3, 61, 61, 95],
+                "precipitation_probability": [0, 5, 30, 60, 45]
+            },
+            "daily": {
+                "temperature_2m_min": [6.2],
+                "temperature_2m_max": [15.1]
+            }
+        });
+        assert_eq!(
+            parse_open_meteo(&json),
+            Some(WeatherInfo {
+                weather: "Clouds".to_string(),
+                temp: 12.3,
+                humidity: 81.0,
+                wind_speed: 3.4,
+                wind_direction: Some(200.0),
+                location: String::new(),
+                forecast: Some(Forecast {
+                    weather: "Thunderstorm".to_string(),
+                    temp: 14.6,
+                    temp_min: 6.2,
+                    temp_max: 15.1,
+                    precipitation: Some(60.0),
+                }),
+            })
+        );
+        assert_eq!(parse_open_meteo(&json!({})), None);
+    }
+
+    #[test]
+    fn test_parse_openweathermap_forecast() {
+        let entry = |dt: i64, temp: f64, weather: &str, pop: f64| {
+            json!({
+                "dt": dt,
+                "main": {"temp": temp, "temp_min": temp - 0.5, "temp_max": temp + 0.5},
+                "weather": [{"main": weather}],
+                "pop": pop
+            })
+        };
+        // At 18:00 and 21:00 on the day, and 00:00 the day after, in the local time zone
+        let json = json!({
+            "list": [
+                entry(1617206400, 10.0, "Rain", 0.8),
+                entry(1617217200, 8.0, "Clouds", 0.2),
+                entry(1617228000, 6.0, "Clear", 0.0),
+            ]
+        });
+        assert_eq!(
+            parse_openweathermap_forecast(&json, 12.0, today()),
+            Some(Forecast {
+                weather: "Rain".to_string(),
+                temp: 10.0,
+                temp_min: 7.5,
+                temp_max: 12.0,
+                precipitation: Some(80.0),
+            })
+        );
+        assert_eq!(
+            parse_openweathermap_forecast(&json!({}), 12.0, today()),
+            None
+        );
+    }
+}
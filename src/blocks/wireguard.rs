@@ -0,0 +1,284 @@
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// WireGuard initiates a new handshake every 2 minutes while there is traffic
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(180);
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WireguardToggle {
+    /// Clicking does nothing
+    None,
+    /// `wg-quick up` and `wg-quick down`
+    WgQuick,
+    /// `nmcli connection up` and `nmcli connection down`
+    NetworkManager,
+}
+
+impl Default for WireguardToggle {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+pub struct Wireguard {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    interface: String,
+    format: FormatTemplate,
+    format_down: FormatTemplate,
+    toggle: WireguardToggle,
+    up: bool,
+    tx_update_request: Sender<Task>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WireguardConfig {
+    /// Name of the WireGuard interface, e.g. `wg0`
+    pub interface: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "WireguardConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override when the interface is up
+    #[serde(default = "WireguardConfig::default_format")]
+    pub format: String,
+
+    /// Format override when the interface is down
+    #[serde(default = "WireguardConfig::default_format_down")]
+    pub format_down: String,
+
+    /// How to bring the interface up or down on click
+    #[serde(default)]
+    pub toggle: WireguardToggle,
+}
+
+impl WireguardConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn default_format() -> String {
+        "{interface} {handshake}".to_owned()
+    }
+
+    fn default_format_down() -> String {
+        "{interface} down".to_owned()
+    }
+}
+
+/// The peers of an interface, as shown by `wg show <interface> dump`.
+#[derive(Debug, Default, PartialEq)]
+struct WireguardPeers {
+    /// Endpoint of the peer with the latest handshake
+    endpoint: Option<String>,
+    /// Time of the latest handshake with any peer, in seconds since the epoch
+    latest_handshake: Option<u64>,
+    /// Bytes received from all peers
+    rx: u64,
+    /// Bytes sent to all peers
+    tx: u64,
+}
+
+/// Parses `wg show <interface> dump`. The first line describes the interface, and each next one
+/// a peer, with tab-separated fields: public key, preshared key, endpoint, allowed IPs, latest
+/// handshake, bytes received, bytes sent and persistent keepalive.
+fn parse_dump(dump: &str) -> WireguardPeers {
+    let mut peers = WireguardPeers::default();
+    for line in dump.lines().skip(1) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 {
+            continue;
+        }
+        peers.rx += fields[5].parse::<u64>().unwrap_or(0);
+        peers.tx += fields[6].parse::<u64>().unwrap_or(0);
+        // 0 if there was no handshake yet
+        let handshake = fields[4].parse::<u64>().unwrap_or(0);
+        if handshake > 0 && Some(handshake) > peers.latest_handshake {
+            peers.latest_handshake = Some(handshake);
+            peers.endpoint = Some(fields[2])
+                .filter(|endpoint| *endpoint != "(none)")
+                .map(String::from);
+        } else if peers.endpoint.is_none() && fields[2] != "(none)" {
+            peers.endpoint = Some(fields[2].to_string());
+        }
+    }
+    peers
+}
+
+impl ConfigBlock for Wireguard {
+    type Config = WireguardConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        (
+            "format",
+            &["{interface}", "{endpoint}", "{handshake}", "{rx}", "{tx}"],
+        ),
+        ("format_down", &["{interface}"]),
+    ];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Wireguard {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()).with_icon("net_vpn"),
+            update_interval: block_config.interval,
+            interface: block_config.interface,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("wireguard", "Invalid format specified")?,
+            format_down: FormatTemplate::new(&block_config.format_down, &shared_config)
+                .block_error("wireguard", "Invalid format_down specified")?,
+            toggle: block_config.toggle,
+            up: false,
+            tx_update_request,
+        })
+    }
+}
+
+impl Block for Wireguard {
+    fn update(&mut self) -> Result<Option<Update>> {
+        self.up = Path::new("/sys/class/net").join(&self.interface).exists();
+        if !self.up {
+            let values = map!("{interface}" => Value::text(self.interface.clone()));
+            self.text.set_text(self.format_down.render(&values)?);
+            self.text.set_state(State::Idle);
+            return Ok(Some(self.update_interval.into()));
+        }
+
+        let output = Command::new("wg")
+            .args(&["show", &self.interface, "dump"])
+            .output()
+            .block_error("wireguard", "failed to run wg")?;
+        if !output.status.success() {
+            return Err(BlockError(
+                "wireguard".to_string(),
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        let peers = parse_dump(&String::from_utf8_lossy(&output.stdout));
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let handshake_age = peers
+            .latest_handshake
+            .map(|handshake| Duration::from_secs(now.saturating_sub(handshake)));
+
+        let values = map!(
+            "{interface}" => Value::text(self.interface.clone()),
+            "{endpoint}" => Value::text(peers.endpoint.unwrap_or_default()),
+            "{handshake}" => handshake_age.map(Value::duration).unwrap_or_else(|| Value::text("never".to_string())),
+            "{rx}" => Value::bytes(peers.rx as f64),
+            "{tx}" => Value::bytes(peers.tx as f64)
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(match handshake_age {
+            Some(age) if age <= HANDSHAKE_TIMEOUT => State::Good,
+            _ => State::Warning,
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button != MouseButton::Left {
+            return Ok(());
+        }
+        let action = if self.up { "down" } else { "up" };
+        let mut command = match self.toggle {
+            WireguardToggle::None => return Ok(()),
+            WireguardToggle::WgQuick => {
+                let mut command = Command::new("wg-quick");
+                command.args(&[action, &self.interface]);
+                command
+            }
+            WireguardToggle::NetworkManager => {
+                let mut command = Command::new("nmcli");
+                command.args(&["connection", action, &self.interface]);
+                command
+            }
+        };
+
+        // Bringing the interface up may take a while, so update once it is done
+        let id = self.id;
+        let tx_update_request = self.tx_update_request.clone();
+        thread::Builder::new()
+            .name("wireguard".into())
+            .spawn(move || {
+                command.status().ok();
+                tx_update_request
+                    .send(Task {
+                        id,
+                        update_time: Instant::now(),
+                    })
+                    .ok();
+            })
+            .unwrap();
+
+        Ok(())
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_dump, WireguardPeers};
+
+    #[test]
+    fn test_parse_dump() {
+        let dump = "cFJYsQ0mS2eAqGgaZt1d3y9/Kj0RwQf9nXvz8J0Vb2k=\tHIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PRpjNnY=\t51820\toff
+xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=\t(none)\t203.0.113.7:51820\t10.0.0.0/24\t1700000000\t1048576\t524288\t25
+TrMvSoP4jYQlY6RIzBgbssQqY3vxI2Pi+y71lOWWXX0=\t(none)\t(none)\t10.0.1.2/32\t0\t0\t0\toff
+";
+        assert_eq!(
+            parse_dump(dump),
+            WireguardPeers {
+                endpoint: Some("203.0.113.7:51820".to_string()),
+                latest_handshake: Some(1_700_000_000),
+                rx: 1_048_576,
+                tx: 524_288,
+            }
+        );
+
+        let dump = "cFJYsQ0mS2eAqGgaZt1d3y9/Kj0RwQf9nXvz8J0Vb2k=\t(none)\t51820\toff\n";
+        assert_eq!(parse_dump(dump), WireguardPeers::default());
+    }
+}
@@ -0,0 +1,173 @@
+use std::thread;
+use std::time::Instant;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+use swayipc::{Connection, EventType};
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::{LogicalDirection, Scrolling, SharedConfig};
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Workspaces {
+    id: usize,
+    widgets: Vec<TextWidget>,
+    names: Vec<String>,
+    format: FormatTemplate,
+    output: Option<String>,
+    scrolling: Scrolling,
+    shared_config: SharedConfig,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspacesConfig {
+    /// Format override
+    #[serde(default = "WorkspacesConfig::default_format")]
+    pub format: String,
+
+    /// Only show workspaces on this output
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl WorkspacesConfig {
+    fn default_format() -> String {
+        "{name}".to_owned()
+    }
+}
+
+impl ConfigBlock for Workspaces {
+    type Config = WorkspacesConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] =
+        &[("format", &["{name}", "{num}"])];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        let _test_conn =
+            Connection::new().block_error("workspaces", "failed to acquire connect to IPC")?;
+
+        thread::Builder::new()
+            .name("workspaces".into())
+            .spawn(move || {
+                let conn = Connection::new().expect("failed to open connection with swayipc");
+                let events = conn
+                    .subscribe(&[EventType::Workspace])
+                    .expect("could not subscribe to workspace events");
+
+                for _event in events {
+                    if tx_update_request
+                        .send(Task {
+                            id,
+                            update_time: Instant::now(),
+                        })
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            })
+            .block_error("workspaces", "failed to start watching thread")?;
+
+        Ok(Workspaces {
+            id,
+            widgets: Vec::new(),
+            names: Vec::new(),
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("workspaces", "Invalid format specified")?,
+            output: block_config.output,
+            scrolling: shared_config.scrolling,
+            shared_config,
+        })
+    }
+}
+
+impl Workspaces {
+    fn run_command(&self, command: &str) -> Result<()> {
+        Connection::new()
+            .block_error("workspaces", "failed to acquire connect to IPC")?
+            .run_command(command)
+            .block_error("workspaces", "failed to run IPC command")?;
+        Ok(())
+    }
+}
+
+impl Block for Workspaces {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let mut workspaces = Connection::new()
+            .block_error("workspaces", "failed to acquire connect to IPC")?
+            .get_workspaces()
+            .block_error("workspaces", "failed to get workspaces")?;
+        if let Some(ref output) = self.output {
+            workspaces.retain(|w| &w.output == output);
+        }
+        workspaces.sort_by_key(|w| w.num);
+
+        self.widgets.clear();
+        self.names.clear();
+        for (instance, workspace) in workspaces.into_iter().enumerate() {
+            let values = map!(
+                "{name}" => workspace.name.clone(),
+                "{num}" => workspace.num.to_string()
+            );
+            let state = if workspace.urgent {
+                State::Critical
+            } else if workspace.focused {
+                State::Info
+            } else if workspace.visible {
+                State::Good
+            } else {
+                State::Idle
+            };
+            self.widgets.push(
+                TextWidget::new(self.id, instance, self.shared_config.clone())
+                    .with_text(&self.format.render_static_str(&values)?)
+                    .with_state(state),
+            );
+            self.names.push(workspace.name);
+        }
+
+        Ok(None)
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        self.widgets.iter().map(|w| w as &dyn I3BarWidget).collect()
+    }
+
+    fn click(&mut self, e: &I3BarEvent) -> Result<()> {
+        match e.button {
+            MouseButton::Left => {
+                if let Some(name) = e.instance.and_then(|i| self.names.get(i)) {
+                    self.run_command(&format!("workspace \"{}\"", name.replace('"', "\\\"")))?;
+                }
+            }
+            mb => {
+                use LogicalDirection::*;
+                match self.scrolling.to_logical_direction(mb) {
+                    Some(Up) => self.run_command("workspace prev_on_output")?,
+                    Some(Down) => self.run_command("workspace next_on_output")?,
+                    None => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
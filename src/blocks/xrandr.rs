@@ -213,7 +213,7 @@ impl Xrandr {
                 "{display}: {brightness}"
             };
 
-            if let Ok(fmt_template) = FormatTemplate::from_string(format_str) {
+            if let Ok(fmt_template) = FormatTemplate::new(format_str, &self.shared_config) {
                 self.text.set_text(fmt_template.render_static_str(&values)?);
             }
         }
@@ -0,0 +1,193 @@
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::formatting::Value;
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Zfs {
+    id: usize,
+    text: TextWidget,
+    update_interval: Duration,
+    pool: String,
+    format: FormatTemplate,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ZfsConfig {
+    /// Name of the pool
+    pub pool: String,
+
+    /// Update interval in seconds
+    #[serde(
+        default = "ZfsConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "ZfsConfig::default_format")]
+    pub format: String,
+}
+
+impl ZfsConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60)
+    }
+
+    fn default_format() -> String {
+        "{pool} {state} {capacity}".to_owned()
+    }
+}
+
+impl ConfigBlock for Zfs {
+    type Config = ZfsConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[(
+        "format",
+        &[
+            "{capacity}",
+            "{free}",
+            "{pool}",
+            "{scrub}",
+            "{size}",
+            "{state}",
+            "{used}",
+        ],
+    )];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Zfs {
+            id,
+            text: TextWidget::new(id, 0, shared_config.clone()),
+            update_interval: block_config.interval,
+            pool: block_config.pool,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("zfs", "Invalid format specified")?,
+        })
+    }
+}
+
+/// Runs `zpool` and returns its output.
+fn zpool(args: &[&str]) -> Result<String> {
+    let output = Command::new("zpool")
+        .args(args)
+        .output()
+        .block_error("zfs", "Failed to execute zpool.")?;
+    if !output.status.success() {
+        return Err(BlockError(
+            "zfs".to_string(),
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    String::from_utf8(output.stdout).block_error("zfs", "zpool produced invalid UTF-8")
+}
+
+/// The progress in percent of the scrub running on a pool, from the output of `zpool status`.
+fn scrub_progress(status: &str) -> Option<f64> {
+    let scan = status.find("scrub in progress")?;
+    let words: Vec<&str> = status[scan..].split_whitespace().collect();
+    words
+        .windows(2)
+        .find(|pair| pair[1].starts_with("done"))
+        .and_then(|pair| pair[0].strip_suffix('%'))
+        .and_then(|percent| percent.parse().ok())
+}
+
+impl Block for Zfs {
+    fn update(&mut self) -> Result<Option<Update>> {
+        let list = zpool(&[
+            "list",
+            "-Hp",
+            "-o",
+            "health,size,alloc,free,cap",
+            &self.pool,
+        ])?;
+        let fields: Vec<&str> = list.trim().split('\t').collect();
+        let (health, size, alloc, free, capacity) = match fields.as_slice() {
+            [health, size, alloc, free, capacity] => (*health, *size, *alloc, *free, *capacity),
+            _ => {
+                return Err(BlockError(
+                    "zfs".to_string(),
+                    format!("unexpected output of zpool list: {}", list),
+                ))
+            }
+        };
+        let bytes = |field: &str| -> Result<Value> {
+            field
+                .parse::<f64>()
+                .map(Value::bytes)
+                .block_error("zfs", "failed to parse the size of the pool")
+        };
+
+        let scrub = scrub_progress(&zpool(&["status", "-p", &self.pool])?);
+
+        let values = map!(
+            "{pool}" => Value::text(self.pool.clone()),
+            "{state}" => Value::text(health.to_string()),
+            "{size}" => bytes(size)?,
+            "{used}" => bytes(alloc)?,
+            "{free}" => bytes(free)?,
+            "{capacity}" => Value::text(format!("{}%", capacity)),
+            "{scrub}" => Value::text(scrub.map(|percent| format!("{:.0}%", percent)).unwrap_or_default())
+        );
+        self.text.set_text(self.format.render(&values)?);
+        self.text.set_state(match health {
+            "ONLINE" if scrub.is_some() => State::Info,
+            "ONLINE" => State::Idle,
+            _ => State::Critical,
+        });
+
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.text]
+    }
+
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scrub_progress;
+
+    #[test]
+    fn test_scrub_progress() {
+        let status = "  pool: tank
+ state: ONLINE
+  scan: scrub in progress since Sun Jul 25 16:07:49 2021
+\t403M scanned at 100M/s, 68.4M issued at 10.0M/s, 405M total
+\t0B repaired, 16.91% done, 00:00:33 to go
+config:
+";
+        assert_eq!(scrub_progress(status), Some(16.91));
+
+        let status = "  pool: tank
+ state: ONLINE
+  scan: scrub repaired 0B in 00:00:05 with 0 errors on Sun Jul 25 16:08:00 2021
+";
+        assert_eq!(scrub_progress(status), None);
+    }
+}
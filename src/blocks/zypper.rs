@@ -0,0 +1,251 @@
+use std::process::Command;
+use std::time::Duration;
+
+use crossbeam_channel::Sender;
+use regex::Regex;
+use serde_derive::Deserialize;
+
+use crate::blocks::{Block, ConfigBlock, Update};
+use crate::config::SharedConfig;
+use crate::de::deserialize_duration;
+use crate::errors::*;
+use crate::input::{I3BarEvent, MouseButton};
+use crate::scheduler::Task;
+use crate::util::FormatTemplate;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+pub struct Zypper {
+    id: usize,
+    output: TextWidget,
+    update_interval: Duration,
+    format: FormatTemplate,
+    format_singular: FormatTemplate,
+    format_up_to_date: FormatTemplate,
+    warning_updates_regex: Option<Regex>,
+    critical_updates_regex: Option<Regex>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ZypperConfig {
+    /// Update interval in seconds
+    #[serde(
+        default = "ZypperConfig::default_interval",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub interval: Duration,
+
+    /// Format override
+    #[serde(default = "ZypperConfig::default_format")]
+    pub format: String,
+
+    /// Alternative format override for when exactly 1 update is available
+    #[serde(default = "ZypperConfig::default_format")]
+    pub format_singular: String,
+
+    /// Alternative format override for when no updates are available
+    #[serde(default = "ZypperConfig::default_format")]
+    pub format_up_to_date: String,
+
+    /// Indicate a `warning` state for the block if the name of any pending update matches the
+    /// following regex. Default behaviour is that no updates are deemed warning
+    pub warning_updates_regex: Option<String>,
+
+    /// Indicate a `critical` state for the block if the name of any pending update matches the
+    /// following regex. Default behaviour is that no updates are deemed critical
+    pub critical_updates_regex: Option<String>,
+}
+
+impl ZypperConfig {
+    fn default_interval() -> Duration {
+        Duration::from_secs(60 * 10)
+    }
+
+    fn default_format() -> String {
+        "{count}".to_owned()
+    }
+}
+
+fn regex(regex: Option<String>, kind: &str) -> Result<Option<Regex>> {
+    match regex {
+        None => Ok(None),
+        Some(regex) => Regex::new(&regex).map(Some).map_err(|_| {
+            ConfigurationError(
+                "zypper".to_string(),
+                (
+                    format!("invalid {} updates regex", kind),
+                    "invalid regex".to_string(),
+                ),
+            )
+        }),
+    }
+}
+
+impl ConfigBlock for Zypper {
+    type Config = ZypperConfig;
+
+    const FORMATS: &'static [(&'static str, &'static [&'static str])] = &[
+        ("format", &["{count}", "{patches}", "{total}"]),
+        ("format_singular", &["{count}", "{patches}", "{total}"]),
+        ("format_up_to_date", &["{count}", "{patches}", "{total}"]),
+    ];
+
+    fn new(
+        id: usize,
+        block_config: Self::Config,
+        shared_config: SharedConfig,
+        _tx_update_request: Sender<Task>,
+    ) -> Result<Self> {
+        Ok(Zypper {
+            id,
+            output: TextWidget::new(id, 0, shared_config.clone()).with_icon("update"),
+            update_interval: block_config.interval,
+            format: FormatTemplate::new(&block_config.format, &shared_config)
+                .block_error("zypper", "Invalid format specified for zypper::format")?,
+            format_singular: FormatTemplate::new(&block_config.format_singular, &shared_config)
+                .block_error(
+                    "zypper",
+                    "Invalid format specified for zypper::format_singular",
+                )?,
+            format_up_to_date: FormatTemplate::new(&block_config.format_up_to_date, &shared_config)
+                .block_error(
+                    "zypper",
+                    "Invalid format specified for zypper::format_up_to_date",
+                )?,
+            warning_updates_regex: regex(block_config.warning_updates_regex, "warning")?,
+            critical_updates_regex: regex(block_config.critical_updates_regex, "critical")?,
+        })
+    }
+}
+
+/// The value of the attribute `name` of an XML tag such as `<update name="vim" kind="package">`.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let value = &tag[start..];
+    Some(&value[..value.find('"')?])
+}
+
+/// The names of the updates of `kind` listed by `zypper --xmlout`, which are either packages or
+/// patches.
+fn parse_updates<'a>(xml: &'a str, kind: &str) -> Vec<&'a str> {
+    xml.split("<update")
+        .skip(1)
+        // Skip `<update-list>` and the like
+        .filter(|tag| tag.starts_with(char::is_whitespace))
+        .map(|tag| &tag[..tag.find('>').unwrap_or_else(|| tag.len())])
+        .filter(|tag| attribute(tag, "kind") == Some(kind))
+        .filter_map(|tag| attribute(tag, "name"))
+        .collect()
+}
+
+fn zypper(command: &str) -> Result<String> {
+    // Without root, zypper uses the repositories as last refreshed by the system
+    let output = Command::new("zypper")
+        .args(&["--non-interactive", "--no-refresh", "--xmlout", command])
+        .output()
+        .block_error("zypper", &format!("Failed to run `zypper {}`", command))?;
+    String::from_utf8(output.stdout).block_error("zypper", "Problem capturing zypper output")
+}
+
+impl Block for Zypper {
+    fn format_mut(&mut self) -> Option<&mut FormatTemplate> {
+        Some(&mut self.format)
+    }
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        vec![&self.output]
+    }
+
+    fn update(&mut self) -> Result<Option<Update>> {
+        let packages_xml = zypper("list-updates")?;
+        let patches_xml = zypper("list-patches")?;
+        let mut updates = parse_updates(&packages_xml, "package");
+        let count = updates.len();
+        updates.extend(parse_updates(&patches_xml, "patch"));
+        let patches = updates.len() - count;
+
+        let matches = |regex: &Option<Regex>| {
+            regex.as_ref().map_or(false, |regex| {
+                updates.iter().any(|name| regex.is_match(name))
+            })
+        };
+        let warning = matches(&self.warning_updates_regex);
+        let critical = matches(&self.critical_updates_regex);
+
+        let formatting_map = map!(
+            "{count}" => count,
+            "{patches}" => patches,
+            "{total}" => count + patches
+        );
+        self.output.set_text(match count + patches {
+            0 => self.format_up_to_date.render_static_str(&formatting_map)?,
+            1 => self.format_singular.render_static_str(&formatting_map)?,
+            _ => self.format.render_static_str(&formatting_map)?,
+        });
+        self.output.set_state(match count + patches {
+            0 => State::Idle,
+            _ => {
+                if critical {
+                    State::Critical
+                } else if warning {
+                    State::Warning
+                } else {
+                    State::Info
+                }
+            }
+        });
+        Ok(Some(self.update_interval.into()))
+    }
+
+    fn click(&mut self, event: &I3BarEvent) -> Result<()> {
+        if event.button == MouseButton::Left {
+            self.update()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocks::zypper::parse_updates;
+
+    #[test]
+    fn test_parse_updates() {
+        let packages = r#"<?xml version='1.0'?>
+<stream>
+<update-status version="0.6">
+<update-list>
+<update name="vim" edition="9.0.0313-1.1" arch="x86_64" kind="package" >
+  <summary>Vi IMproved</summary>
+  <source url="http://download.opensuse.org/tumbleweed/repo/oss" alias="repo-oss"/>
+</update>
+<update name="kernel-default" edition="5.19.2-1.1" arch="x86_64" kind="package" >
+  <summary>The Standard Kernel</summary>
+</update>
+</update-list>
+</update-status>
+</stream>"#;
+        assert_eq!(
+            parse_updates(packages, "package"),
+            vec!["vim", "kernel-default"]
+        );
+        assert!(parse_updates(packages, "patch").is_empty());
+
+        let patches = r#"<?xml version='1.0'?>
+<stream>
+<update-status version="0.6">
+<update-list>
+<update name="openSUSE-2022-10101" edition="1" arch="noarch" kind="patch" status="needed" category="security" severity="important" pkgmanager="false" restart="false" interactive="false">
+</update>
+</update-list>
+</update-status>
+</stream>"#;
+        assert_eq!(parse_updates(patches, "patch"), vec!["openSUSE-2022-10101"]);
+        assert!(parse_updates("<stream></stream>", "package").is_empty());
+    }
+}
@@ -1,14 +1,21 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+use chrono::Locale;
 use serde::de::{Deserialize, Deserializer};
 use serde_derive::Deserialize;
 use toml::value;
 
+use crate::de::deserialize_locale;
 use crate::errors;
+use crate::errors::ResultExtInternal;
 use crate::icons::Icons;
 use crate::input::MouseButton;
 use crate::themes::Theme;
+use crate::util;
 
 #[derive(Debug)]
 pub struct SharedConfig {
@@ -16,6 +23,10 @@ pub struct SharedConfig {
     icons: Rc<Icons>,
     icons_format: String,
     pub scrolling: Scrolling,
+    pub output_format: OutputFormat,
+    pub locale: Option<Locale>,
+    /// Whether the formats of the block are Pango markup rather than plain text
+    pub pango_markup: bool,
 }
 
 impl SharedConfig {
@@ -25,6 +36,9 @@ impl SharedConfig {
             icons: Rc::new(config.icons.clone()),
             icons_format: config.icons_format.clone(),
             scrolling: config.scrolling,
+            output_format: config.output_format,
+            locale: config.locale,
+            pango_markup: false,
         }
     }
 
@@ -35,34 +49,78 @@ impl SharedConfig {
     pub fn theme_override(&mut self, overrides: &HashMap<String, String>) -> errors::Result<()> {
         let mut theme = self.theme.as_ref().clone();
         for entry in overrides {
-            match entry.0.as_str() {
-                "idle_fg" => theme.idle_fg = Some(entry.1.to_string()),
-                "idle_bg" => theme.idle_bg = Some(entry.1.to_string()),
-                "info_fg" => theme.info_fg = Some(entry.1.to_string()),
-                "info_bg" => theme.info_bg = Some(entry.1.to_string()),
-                "good_fg" => theme.good_fg = Some(entry.1.to_string()),
-                "good_bg" => theme.good_bg = Some(entry.1.to_string()),
-                "warning_fg" => theme.warning_fg = Some(entry.1.to_string()),
-                "warning_bg" => theme.warning_bg = Some(entry.1.to_string()),
-                "critical_fg" => theme.critical_fg = Some(entry.1.to_string()),
-                "critical_bg" => theme.critical_bg = Some(entry.1.to_string()),
+            let color = match entry.0.as_str() {
+                "idle_fg" => &mut theme.idle_fg,
+                "idle_bg" => &mut theme.idle_bg,
+                "info_fg" => &mut theme.info_fg,
+                "info_bg" => &mut theme.info_bg,
+                "good_fg" => &mut theme.good_fg,
+                "good_bg" => &mut theme.good_bg,
+                "warning_fg" => &mut theme.warning_fg,
+                "warning_bg" => &mut theme.warning_bg,
+                "critical_fg" => &mut theme.critical_fg,
+                "critical_bg" => &mut theme.critical_bg,
+                "idle_border" => &mut theme.idle_border,
+                "info_border" => &mut theme.info_border,
+                "good_border" => &mut theme.good_border,
+                "warning_border" => &mut theme.warning_border,
+                "critical_border" => &mut theme.critical_border,
                 x => {
                     return Err(errors::ConfigurationError(
                         format!("Theme element \"{}\" cannot be overriden", x),
                         (String::new(), String::new()),
                     ))
                 }
+            };
+            *color = Some(util::parse_color(entry.1).map_err(|error| {
+                errors::ConfigurationError(error, (String::new(), String::new()))
+            })?);
+        }
+
+        // Keep the text on overridden backgrounds readable, unless its color is overridden too
+        for (state, bg, fg) in [
+            ("idle", &theme.idle_bg, &mut theme.idle_fg),
+            ("info", &theme.info_bg, &mut theme.info_fg),
+            ("good", &theme.good_bg, &mut theme.good_fg),
+            ("warning", &theme.warning_bg, &mut theme.warning_fg),
+            ("critical", &theme.critical_bg, &mut theme.critical_fg),
+        ] {
+            if !overrides.contains_key(&format!("{}_bg", state))
+                || overrides.contains_key(&format!("{}_fg", state))
+            {
+                continue;
+            }
+            if let Some(bg) = bg {
+                *fg = util::readable_color(bg, fg.as_deref());
             }
         }
+
         self.theme = Rc::new(theme);
         Ok(())
     }
 
+    pub fn icons_override(&mut self, overrides: &HashMap<String, String>) {
+        let mut icons = self.icons.as_ref().clone();
+        icons.overrides.extend(overrides.clone());
+        self.icons = Rc::new(icons);
+    }
+
+    /// All icons, with the overrides applied.
+    pub fn icons_map(&self) -> HashMap<String, String> {
+        let mut icons = self.icons.set.clone();
+        icons.extend(self.icons.overrides.clone());
+        icons
+    }
+
+    pub fn icons_format(&self) -> &str {
+        &self.icons_format
+    }
+
     pub fn get_icon(&self, icon: &str) -> Option<String> {
         Some(
             self.icons_format
                 .clone()
-                .replace("{icon}", self.icons.0.get(icon)?),
+                .replace("{icon}", self.icons.get(icon)?),
         )
     }
 }
@@ -74,6 +132,9 @@ impl Default for SharedConfig {
             icons: Rc::new(Icons::default()),
             icons_format: " {icon} ".to_string(),
             scrolling: Scrolling::default(),
+            output_format: OutputFormat::default(),
+            locale: None,
+            pango_markup: false,
         }
     }
 }
@@ -85,6 +146,9 @@ impl Clone for SharedConfig {
             icons: Rc::clone(&self.icons),
             icons_format: self.icons_format.clone(),
             scrolling: self.scrolling,
+            output_format: self.output_format,
+            locale: self.locale,
+            pango_markup: self.pango_markup,
         }
     }
 }
@@ -108,14 +172,303 @@ pub struct Config {
     #[serde(default)]
     pub scrolling: Scrolling,
 
+    /// Protocol used to print the bar, "i3bar" or "waybar".
+    ///
+    /// The "waybar" format prints one JSON object per update, as expected by a Waybar custom
+    /// module with `"return-type": "json"`.
+    #[serde(default)]
+    pub output_format: OutputFormat,
+
+    /// Locale used for decimal separators and dates, e.g. "de_DE", or "auto" to follow the
+    /// environment. Numbers and dates are not localized by default.
+    #[serde(default, deserialize_with = "deserialize_locale")]
+    pub locale: Option<Locale>,
+
     #[serde(rename = "block", deserialize_with = "deserialize_blocks")]
     pub blocks: Vec<(String, value::Value)>,
+
+    /// Where each of `blocks` is defined, if the config was read by `Config::load`
+    #[serde(skip)]
+    pub block_sources: Vec<Option<BlockSource>>,
+}
+
+/// Where a block is defined in the config files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSource {
+    pub path: PathBuf,
+    /// Line of the `[[block]]` header, unless the block is an inline table
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for BlockSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.path.display(), line),
+            None => write!(f, "{}", self.path.display()),
+        }
+    }
 }
 
 impl Config {
     fn default_icons_format() -> String {
         " {icon} ".to_string()
     }
+
+    /// Reads the config file at `path`, merged with the files it includes and with the
+    /// `[bar.<name>]` section selected by `bar`.
+    pub fn load(path: &Path, bar: Option<&str>) -> errors::Result<Self> {
+        let (mut value, _) = load_value(path)?;
+        select_bar(&mut value, bar)?;
+        let mut config = Config::deserialize(value)
+            .configuration_error("failed to parse TOML from file contents")?;
+        config.block_sources = config
+            .blocks
+            .iter_mut()
+            .map(|(_, block)| take_block_source(block))
+            .collect();
+        Ok(config)
+    }
+}
+
+/// Applies the `[bar.<name>]` section of the config on top of the top-level settings, and drops
+/// the sections of the other bars.
+///
+/// A bar that lists its own `block`s replaces the top-level ones instead of extending them, so
+/// that each bar can have an independent set of blocks. Other settings are merged like includes.
+pub fn select_bar(value: &mut value::Value, bar: Option<&str>) -> errors::Result<()> {
+    let mut bars = match value.as_table_mut().and_then(|t| t.remove("bar")) {
+        Some(value::Value::Table(bars)) => bars,
+        Some(_) => {
+            return Err(errors::ConfigurationError(
+                "`bar` must be a table of bar sections".to_string(),
+                (String::new(), String::new()),
+            ))
+        }
+        None => value::Table::new(),
+    };
+    let name = match bar {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    let mut section = bars.remove(name).ok_or_else(|| {
+        errors::ConfigurationError(
+            format!("no `[bar.{}]` section in the config", name),
+            (String::new(), String::new()),
+        )
+    })?;
+    if let (Some(blocks), Some(table)) = (
+        section.as_table_mut().and_then(|t| t.remove("block")),
+        value.as_table_mut(),
+    ) {
+        table.insert("block".to_string(), blocks);
+    }
+    merge_values(value, section);
+    Ok(())
+}
+
+/// Key under which `load_value` records the `BlockSource` of each block. `Config::load` removes it
+/// again before the blocks are created.
+const SOURCE_KEY: &str = "__source";
+
+/// Records where each block of `value`, read from the file at `path`, is defined. The `[[block]]`
+/// headers in `contents` are matched with the blocks in order, so that if some blocks are inline
+/// tables, only the file is known.
+fn mark_block_sources(value: &mut value::Value, path: &Path, contents: &str) {
+    let header_lines = |header: &str| -> Vec<usize> {
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.split('#').next().unwrap_or_default().trim() == header)
+            .map(|(number, _)| number + 1)
+            .collect()
+    };
+    let mark = |blocks: Option<&mut value::Value>, lines: Vec<usize>| {
+        let blocks = match blocks {
+            Some(value::Value::Array(blocks)) => blocks,
+            _ => return,
+        };
+        let lines = if lines.len() == blocks.len() {
+            lines
+        } else {
+            Vec::new()
+        };
+        for (i, block) in blocks.iter_mut().enumerate() {
+            if let Some(block) = block.as_table_mut() {
+                let mut source = value::Table::new();
+                source.insert(
+                    "path".to_string(),
+                    path.to_string_lossy().into_owned().into(),
+                );
+                if let Some(&line) = lines.get(i) {
+                    source.insert("line".to_string(), (line as i64).into());
+                }
+                block.insert(SOURCE_KEY.to_string(), value::Value::Table(source));
+            }
+        }
+    };
+
+    let table = match value.as_table_mut() {
+        Some(table) => table,
+        None => return,
+    };
+    mark(table.get_mut("block"), header_lines("[[block]]"));
+    if let Some(value::Value::Table(bars)) = table.get_mut("bar") {
+        for (name, bar) in bars.iter_mut() {
+            let header = format!("[[bar.{}.block]]", name);
+            mark(bar.get_mut("block"), header_lines(&header));
+        }
+    }
+}
+
+/// Removes the source recorded by `mark_block_sources` from the config of a block.
+fn take_block_source(block: &mut value::Value) -> Option<BlockSource> {
+    let source = block.as_table_mut()?.remove(SOURCE_KEY)?;
+    Some(BlockSource {
+        path: PathBuf::from(source.get("path")?.as_str()?),
+        line: source
+            .get("line")
+            .and_then(value::Value::as_integer)
+            .map(|line| line as usize),
+    })
+}
+
+/// How deeply `include` directives may be nested, to break include cycles.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Reads the config file at `path` and merges in the files listed in its top-level `include`
+/// array, in order. Returns the merged config along with the paths of all included files.
+///
+/// Included files are merged on top of the including one: their `block`s are appended, tables
+/// (such as `[theme.overrides]`) are merged key by key, and other values are replaced.
+pub fn load_value(path: &Path) -> errors::Result<(value::Value, Vec<PathBuf>)> {
+    let mut included = Vec::new();
+    let value = load_value_with_includes(path, 0, &mut included)?;
+    Ok((value, included))
+}
+
+fn load_value_with_includes(
+    path: &Path,
+    depth: usize,
+    included: &mut Vec<PathBuf>,
+) -> errors::Result<value::Value> {
+    let mut value: value::Value = crate::util::deserialize_file(path)?;
+    mark_block_sources(
+        &mut value,
+        path,
+        &fs::read_to_string(path).unwrap_or_default(),
+    );
+    let includes = match value.as_table_mut().and_then(|t| t.remove("include")) {
+        Some(includes) => includes,
+        None => return Ok(value),
+    };
+
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err(errors::ConfigurationError(
+            format!("includes are nested too deeply in {}", path.display()),
+            (String::new(), String::new()),
+        ));
+    }
+
+    let patterns: Vec<String> = includes
+        .try_into()
+        .configuration_error("`include` must be an array of strings")?;
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    for pattern in patterns {
+        for file in expand_include(&pattern, base_dir) {
+            let fragment = load_value_with_includes(&file, depth + 1, included)?;
+            included.push(file);
+            merge_values(&mut value, fragment);
+        }
+    }
+
+    Ok(value)
+}
+
+/// Expands `~` and a `*`/`?` wildcard in the file name of an include pattern. Relative paths are
+/// resolved against the directory of the including file.
+fn expand_include(pattern: &str, base_dir: &Path) -> Vec<PathBuf> {
+    let pattern = match pattern.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(pattern),
+    };
+    let pattern = base_dir.join(pattern);
+
+    let file_pattern = match pattern.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains(|c| c == '*' || c == '?') => name.to_string(),
+        _ => return vec![pattern],
+    };
+    let dir = pattern.parent().unwrap_or_else(|| Path::new("."));
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| wildcard_match(&file_pattern, name))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut pattern_chars = pattern.chars();
+    match pattern_chars.next() {
+        None => name.is_empty(),
+        Some('*') => {
+            let rest = pattern_chars.as_str();
+            name.char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(name.len()))
+                .any(|i| wildcard_match(rest, &name[i..]))
+        }
+        Some(c) => {
+            let mut name_chars = name.chars();
+            match name_chars.next() {
+                Some(n) if c == '?' || c == n => {
+                    wildcard_match(pattern_chars.as_str(), name_chars.as_str())
+                }
+                _ => false,
+            }
+        }
+    }
+}
+
+fn merge_values(base: &mut value::Value, fragment: value::Value) {
+    let (base, fragment) = match (base.as_table_mut(), fragment) {
+        (Some(base), value::Value::Table(fragment)) => (base, fragment),
+        _ => return,
+    };
+    for (key, value) in fragment {
+        match (base.get_mut(&key), value) {
+            (Some(value::Value::Array(blocks)), value::Value::Array(more)) if key == "block" => {
+                blocks.extend(more)
+            }
+            (Some(existing), value) => {
+                // `theme = "name"` is shorthand for `[theme] name = "name"`, so that it can be
+                // merged with overrides from other files
+                if let (Some(name), true) = (existing.as_str().map(String::from), value.is_table())
+                {
+                    let mut table = value::Table::new();
+                    table.insert("name".to_string(), value::Value::String(name));
+                    *existing = value::Value::Table(table);
+                }
+                if existing.is_table() && value.is_table() {
+                    merge_values(existing, value);
+                } else {
+                    *existing = value;
+                }
+            }
+            (None, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
 }
 
 impl Default for Config {
@@ -125,7 +478,10 @@ impl Default for Config {
             theme: Theme::default(),
             icons_format: Config::default_icons_format(),
             scrolling: Scrolling::default(),
+            output_format: OutputFormat::default(),
+            locale: None,
             blocks: Vec::new(),
+            block_sources: Vec::new(),
         }
     }
 }
@@ -162,6 +518,19 @@ impl Default for Scrolling {
     }
 }
 
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    I3bar,
+    Waybar,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::I3bar
+    }
+}
+
 fn deserialize_blocks<'de, D>(deserializer: D) -> Result<Vec<(String, value::Value)>, D::Error>
 where
     D: Deserializer<'de>,
@@ -181,10 +550,11 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::config::Config;
+    use crate::config::{wildcard_match, Config, SharedConfig, SOURCE_KEY};
     use crate::util::deserialize_file;
     use assert_fs::prelude::{FileWriteStr, PathChild};
     use assert_fs::TempDir;
+    use std::collections::HashMap;
 
     #[test]
     fn test_load_config_legacy() {
@@ -207,6 +577,107 @@ mod tests {
         config.unwrap();
     }
 
+    #[test]
+    fn test_load_config_include() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file_path = temp_dir.child("config.toml");
+        config_file_path
+            .write_str(
+                concat!(
+                    "include = [\"blocks.d/*.toml\"]\n",
+                    "theme = \"solarized-dark\"\n",
+                    "[[block]]\n",
+                    "block = \"load\"\n",
+                )
+                .as_ref(),
+            )
+            .unwrap();
+        temp_dir
+            .child("blocks.d/10-time.toml")
+            .write_str(concat!("[[block]]\n", "block = \"time\"\n",).as_ref())
+            .unwrap();
+        temp_dir
+            .child("blocks.d/20-theme.toml")
+            .write_str(
+                concat!(
+                    "[theme.overrides]\n",
+                    "idle_bg = \"#123456\"\n",
+                    "[[block]]\n",
+                    "block = \"uptime\"\n",
+                )
+                .as_ref(),
+            )
+            .unwrap();
+        temp_dir
+            .child("blocks.d/ignored.txt")
+            .write_str("[[block]]\nblock = \"cpu\"\n")
+            .unwrap();
+
+        let config = Config::load(config_file_path.path(), None).unwrap();
+        let blocks: Vec<&str> = config.blocks.iter().map(|b| b.0.as_str()).collect();
+        assert_eq!(blocks, vec!["load", "time", "uptime"]);
+        assert_eq!(config.theme.idle_bg, Some("#123456".to_string()));
+        // The rest of the theme still comes from solarized-dark
+        assert_eq!(config.theme.idle_fg, Some("#93a1a1".to_string()));
+        // Blocks are located in the file they come from
+        let source = |i: usize| config.block_sources[i].as_ref().unwrap().to_string();
+        let theme_file_path = temp_dir.child("blocks.d/20-theme.toml");
+        assert_eq!(
+            source(0),
+            format!("{}:3", config_file_path.path().display())
+        );
+        assert_eq!(source(2), format!("{}:3", theme_file_path.path().display()));
+        assert!(config.blocks[2].1.get(SOURCE_KEY).is_none());
+    }
+
+    #[test]
+    fn test_load_config_bar() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file_path = temp_dir.child("config.toml");
+        config_file_path
+            .write_str(
+                concat!(
+                    "theme = \"solarized-dark\"\n",
+                    "[[block]]\n",
+                    "block = \"load\"\n",
+                    "[bar.external]\n",
+                    "theme = \"plain\"\n",
+                    "[[bar.external.block]]\n",
+                    "block = \"time\"\n",
+                    "[bar.laptop]\n",
+                    "[bar.laptop.theme.overrides]\n",
+                    "idle_bg = \"#123456\"\n",
+                )
+                .as_ref(),
+            )
+            .unwrap();
+
+        let config = Config::load(config_file_path.path(), None).unwrap();
+        let blocks: Vec<&str> = config.blocks.iter().map(|b| b.0.as_str()).collect();
+        assert_eq!(blocks, vec!["load"]);
+
+        let config = Config::load(config_file_path.path(), Some("external")).unwrap();
+        let blocks: Vec<&str> = config.blocks.iter().map(|b| b.0.as_str()).collect();
+        assert_eq!(blocks, vec!["time"]);
+        assert_eq!(config.theme.idle_bg, Some("#000000".to_string()));
+
+        // Without its own blocks, a bar keeps the top-level ones
+        let config = Config::load(config_file_path.path(), Some("laptop")).unwrap();
+        let blocks: Vec<&str> = config.blocks.iter().map(|b| b.0.as_str()).collect();
+        assert_eq!(blocks, vec!["load"]);
+        assert_eq!(config.theme.idle_bg, Some("#123456".to_string()));
+
+        assert!(Config::load(config_file_path.path(), Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.toml", "blocks.toml"));
+        assert!(wildcard_match("b?ocks*", "blocks.toml"));
+        assert!(!wildcard_match("*.toml", "blocks.txt"));
+        assert!(!wildcard_match("?", ""));
+    }
+
     #[test]
     fn test_load_config() {
         let temp_dir = TempDir::new().unwrap();
@@ -228,4 +699,82 @@ mod tests {
         let config: Result<Config, _> = deserialize_file(config_file_path.path());
         config.unwrap();
     }
+
+    #[test]
+    fn test_load_config_light_dark() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file_path = temp_dir.child("status.toml");
+        config_file_path
+            .write_str(
+                concat!(
+                    "[theme]\n",
+                    "light = \"solarized-light\"\n",
+                    "dark = \"solarized-dark\"\n",
+                    "dark_from = \"20:30\"\n",
+                    "[[block]]\n",
+                    "block = \"load\"\n",
+                )
+                .as_ref(),
+            )
+            .unwrap();
+        let config: Config = deserialize_file(config_file_path.path()).unwrap();
+        // Whichever of the two themes is in use depends on the desktop and the time of day
+        assert!(["#fdf6e3", "#002b36"].contains(&config.theme.idle_bg.unwrap().as_str()));
+
+        config_file_path
+            .write_str(concat!("[theme]\n", "light = \"solarized-light\"\n").as_ref())
+            .unwrap();
+        let config: Result<Config, _> = deserialize_file(config_file_path.path());
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_icons_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let config_file_path = temp_dir.child("status.toml");
+        config_file_path
+            .write_str(
+                concat!(
+                    "[icons]\n",
+                    "name = \"awesome\"\n",
+                    "[icons.overrides]\n",
+                    "bat_full = \"B\"\n",
+                )
+                .as_ref(),
+            )
+            .unwrap();
+        let config: Config = deserialize_file(config_file_path.path()).unwrap();
+        let mut shared_config = SharedConfig::new(&config);
+        assert_eq!(shared_config.get_icon("bat_full"), Some(" B ".to_string()));
+        // Icons without an override still come from the set
+        assert_eq!(
+            shared_config.get_icon("bat_empty"),
+            Some(" \u{f244} ".to_string())
+        );
+
+        let mut overrides = HashMap::new();
+        overrides.insert("bat_empty".to_string(), "E".to_string());
+        shared_config.icons_override(&overrides);
+        assert_eq!(shared_config.get_icon("bat_full"), Some(" B ".to_string()));
+        assert_eq!(shared_config.get_icon("bat_empty"), Some(" E ".to_string()));
+    }
+
+    #[test]
+    fn test_load_icons_from_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let icons_file_path = temp_dir.child("my-icons.toml");
+        icons_file_path.write_str("bat_full = \"B\"\n").unwrap();
+        let config_file_path = temp_dir.child("status.toml");
+        config_file_path
+            .write_str(&format!(
+                "icons = \"{}\"\n",
+                icons_file_path.path().display()
+            ))
+            .unwrap();
+        let config: Config = deserialize_file(config_file_path.path()).unwrap();
+        assert_eq!(
+            SharedConfig::new(&config).get_icon("bat_full"),
+            Some(" B ".to_string())
+        );
+    }
 }
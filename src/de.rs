@@ -2,7 +2,8 @@ use std::fmt;
 use std::time::Duration;
 
 use crate::blocks::Update;
-use chrono::{DateTime, Local};
+use crate::formatting::parse_locale;
+use chrono::{DateTime, Local, Locale};
 use serde::de::{self, Deserialize, Deserializer};
 
 pub fn deserialize_update<'de, D>(deserializer: D) -> Result<Update, D::Error>
@@ -47,6 +48,14 @@ where
     deserializer.deserialize_any(UpdateWrapper)
 }
 
+pub fn deserialize_locale<'de, D>(deserializer: D) -> Result<Option<Locale>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    parse_locale(&name).map_err(de::Error::custom)
+}
+
 pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
 where
     D: Deserializer<'de>,
@@ -0,0 +1,676 @@
+//! Typed values for format strings.
+//!
+//! Blocks that render their `FormatTemplate` with `Value`s let the user choose the unit and the
+//! prefix of each placeholder, e.g. `{speed_down*b}` to show a speed in bits or
+//! `{speed_down;Mi}` to use binary prefixes starting at mebi.
+//!
+//! Numbers use the decimal separator of the global `locale`, if any. Durations can be shown
+//! with the units chosen by the placeholder, e.g. `{remaining:dur(hm)}` for `1h 23m`.
+
+use std::convert::TryFrom;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use chrono::Locale;
+use lazy_static::lazy_static;
+use pure_rust_locales::locale_match;
+
+use crate::errors::*;
+use crate::util::leading_number;
+
+lazy_static! {
+    static ref DECIMAL_POINT: RwLock<&'static str> = RwLock::new(".");
+}
+
+/// Parses a locale name such as `de_DE` or `de_DE.UTF-8`. `auto` picks the locale of the
+/// environment from `LC_ALL`, `LC_TIME` or `LANG`, and gives `None` if it is unknown (e.g. `C`).
+pub fn parse_locale(name: &str) -> Result<Option<Locale>> {
+    if name == "auto" {
+        let name = ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+        return Ok(locale_from_name(&name));
+    }
+    locale_from_name(name).map(Some).ok_or_else(|| {
+        ConfigurationError(
+            format!("unknown locale '{}'", name),
+            (String::new(), String::new()),
+        )
+    })
+}
+
+/// Drops the encoding of a POSIX locale name, keeping its modifier: `de_DE.UTF-8@euro` is
+/// looked up as `de_DE@euro`.
+fn locale_from_name(name: &str) -> Option<Locale> {
+    let (language, modifier) = match name.find('@') {
+        Some(at) => name.split_at(at),
+        None => (name, ""),
+    };
+    let language = language.split('.').next().unwrap_or_default();
+    Locale::try_from(format!("{}{}", language, modifier).as_str()).ok()
+}
+
+fn decimal_point(locale: Option<Locale>) -> &'static str {
+    match locale {
+        Some(locale) => locale_match!(locale => LC_NUMERIC::DECIMAL_POINT),
+        None => ".",
+    }
+}
+
+/// Sets the locale used to format numbers. Blocks share it, so it is set once per config.
+pub fn set_locale(locale: Option<Locale>) {
+    *DECIMAL_POINT
+        .write()
+        .expect("lock has been poisoned in `formatting`") = decimal_point(locale);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Bytes,
+    Bits,
+    Seconds,
+}
+
+impl Unit {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "B" => Some(Unit::Bytes),
+            "b" => Some(Unit::Bits),
+            "s" => Some(Unit::Seconds),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Unit::Bytes => "B",
+            Unit::Bits => "b",
+            Unit::Seconds => "s",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prefix {
+    Pico,
+    Nano,
+    Micro,
+    Milli,
+    One,
+    Kilo,
+    Mega,
+    Giga,
+    Tera,
+}
+
+impl Prefix {
+    const ALL: [Prefix; 9] = [
+        Prefix::Pico,
+        Prefix::Nano,
+        Prefix::Micro,
+        Prefix::Milli,
+        Prefix::One,
+        Prefix::Kilo,
+        Prefix::Mega,
+        Prefix::Giga,
+        Prefix::Tera,
+    ];
+
+    /// Parses a prefix as written in the config, with `1` meaning no prefix.
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "T" => Some(Prefix::Tera),
+            "G" => Some(Prefix::Giga),
+            "M" => Some(Prefix::Mega),
+            "K" => Some(Prefix::Kilo),
+            "1" => Some(Prefix::One),
+            "m" => Some(Prefix::Milli),
+            "u" => Some(Prefix::Micro),
+            "n" => Some(Prefix::Nano),
+            "p" => Some(Prefix::Pico),
+            _ => None,
+        }
+    }
+
+    /// The power of 1000 (or 1024) this prefix stands for.
+    fn level(self) -> i32 {
+        self as i32 - Prefix::One as i32
+    }
+
+    fn from_level(level: i32) -> Self {
+        Prefix::ALL[(level + Prefix::One as i32) as usize]
+    }
+
+    fn symbol(self, binary: bool) -> &'static str {
+        match (self, binary) {
+            (Prefix::Tera, false) => "T",
+            (Prefix::Giga, false) => "G",
+            (Prefix::Mega, false) => "M",
+            (Prefix::Kilo, false) => "K",
+            (Prefix::Tera, true) => "Ti",
+            (Prefix::Giga, true) => "Gi",
+            (Prefix::Mega, true) => "Mi",
+            (Prefix::Kilo, true) => "Ki",
+            (Prefix::One, _) => "",
+            (Prefix::Milli, _) => "m",
+            (Prefix::Micro, _) => "u",
+            (Prefix::Nano, _) => "n",
+            (Prefix::Pico, _) => "p",
+        }
+    }
+}
+
+/// Formats `raw_value` to engineering notation with `total_digits` significant digits, using
+/// decimal (K = 1000) or binary (Ki = 1024) prefixes. Binary prefixes never go below one.
+pub fn format_prefixed(
+    raw_value: f64,
+    total_digits: usize,
+    min_prefix: Prefix,
+    binary: bool,
+) -> String {
+    let (base, min_level) = if binary {
+        (1024f64, min_prefix.level().max(0))
+    } else {
+        (1000f64, min_prefix.level())
+    };
+
+    let level = (raw_value.log(base).floor() as i32).clamp(min_level, Prefix::Tera.level());
+    let value = raw_value / base.powi(level);
+
+    let total_digits = total_digits as isize;
+    let decimals = (if value >= 100. {
+        total_digits - 3
+    } else if value >= 10. {
+        total_digits - 2
+    } else {
+        total_digits - 1
+    })
+    .max(0);
+
+    let number = format!("{:.*}", decimals as usize, value);
+    let decimal_point = *DECIMAL_POINT
+        .read()
+        .expect("lock has been poisoned in `formatting`");
+    format!(
+        "{}{}",
+        number.replacen('.', decimal_point, 1),
+        Prefix::from_level(level).symbol(binary)
+    )
+}
+
+/// A unit of a duration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeUnit {
+    Weeks,
+    Days,
+    Hours,
+    Minutes,
+    Seconds,
+}
+
+impl TimeUnit {
+    const ALL: [TimeUnit; 5] = [
+        TimeUnit::Weeks,
+        TimeUnit::Days,
+        TimeUnit::Hours,
+        TimeUnit::Minutes,
+        TimeUnit::Seconds,
+    ];
+
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "w" => Some(TimeUnit::Weeks),
+            "d" => Some(TimeUnit::Days),
+            "h" => Some(TimeUnit::Hours),
+            "m" => Some(TimeUnit::Minutes),
+            "s" => Some(TimeUnit::Seconds),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            TimeUnit::Weeks => "w",
+            TimeUnit::Days => "d",
+            TimeUnit::Hours => "h",
+            TimeUnit::Minutes => "m",
+            TimeUnit::Seconds => "s",
+        }
+    }
+
+    fn seconds(self) -> u64 {
+        match self {
+            TimeUnit::Weeks => 604_800,
+            TimeUnit::Days => 86_400,
+            TimeUnit::Hours => 3600,
+            TimeUnit::Minutes => 60,
+            TimeUnit::Seconds => 1,
+        }
+    }
+}
+
+/// How a duration is shown, chosen by a `dur` specifier.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DurationFormat {
+    /// `dur`: the largest non-zero unit and the next one, e.g. `2d 3h`
+    Largest,
+    /// `dur(hm)`: the given units, e.g. `1h 23m`
+    Units(Vec<TimeUnit>),
+    /// `dur(h:m)`: the given units as a clock, e.g. `01:23`
+    Clock(Vec<TimeUnit>),
+}
+
+impl DurationFormat {
+    /// Parses the arguments of a `dur(...)` specifier, e.g. `hm` or `h:m`. Units go from the
+    /// largest to the smallest.
+    pub fn from_string(args: &str) -> Result<Self> {
+        let invalid = || {
+            ConfigurationError(
+                format!("invalid duration format '{}' in format string", args),
+                (String::new(), String::new()),
+            )
+        };
+
+        let clock = args.contains(':');
+        let symbols: Vec<String> = if clock {
+            args.split(':').map(|s| s.trim().to_string()).collect()
+        } else {
+            args.trim().chars().map(|c| c.to_string()).collect()
+        };
+        let units = symbols
+            .iter()
+            .map(|symbol| TimeUnit::from_symbol(symbol))
+            .collect::<Option<Vec<_>>>()
+            .filter(|units| !units.is_empty())
+            .filter(|units| units.windows(2).all(|w| w[0].seconds() > w[1].seconds()))
+            .ok_or_else(invalid)?;
+
+        Ok(if clock {
+            DurationFormat::Clock(units)
+        } else {
+            DurationFormat::Units(units)
+        })
+    }
+
+    pub fn render(&self, duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        match self {
+            DurationFormat::Largest => {
+                // Below a minute, still show minutes and seconds
+                let start = TimeUnit::ALL
+                    .iter()
+                    .position(|unit| seconds >= unit.seconds())
+                    .unwrap_or(TimeUnit::ALL.len())
+                    .min(TimeUnit::ALL.len() - 2);
+                DurationFormat::Units(TimeUnit::ALL[start..start + 2].to_vec()).render(duration)
+            }
+            DurationFormat::Units(units) => units
+                .iter()
+                .zip(split_duration(units, seconds))
+                .map(|(unit, value)| format!("{}{}", value, unit.symbol()))
+                .collect::<Vec<_>>()
+                .join(" "),
+            DurationFormat::Clock(units) => split_duration(units, seconds)
+                .iter()
+                .map(|value| format!("{:02}", value))
+                .collect::<Vec<_>>()
+                .join(":"),
+        }
+    }
+}
+
+/// Splits `seconds` into `units`. The first unit holds everything above it, and anything below
+/// the last one is dropped.
+fn split_duration(units: &[TimeUnit], seconds: u64) -> Vec<u64> {
+    let mut rest = seconds;
+    units
+        .iter()
+        .enumerate()
+        .map(|(i, unit)| {
+            if i > 0 {
+                rest %= units[i - 1].seconds();
+            }
+            rest / unit.seconds()
+        })
+        .collect()
+}
+
+/// The unit and prefix requested by a placeholder, e.g. `*b` and `;Ki` in `{speed*b;Ki}`, and
+/// the format of a duration, e.g. `dur(hm)`.
+#[derive(Debug, Clone, Default)]
+pub struct Modifiers {
+    unit: Option<Unit>,
+    min_prefix: Option<Prefix>,
+    binary: bool,
+    duration: Option<DurationFormat>,
+}
+
+impl Modifiers {
+    /// Parses the unit and the prefix of a placeholder, without their leading `*` and `;`.
+    pub fn from_strings(unit: Option<&str>, prefix: Option<&str>) -> Result<Self> {
+        let invalid = |what: &str, value: &str| {
+            ConfigurationError(
+                format!("invalid {} '{}' in format string", what, value),
+                (String::new(), String::new()),
+            )
+        };
+
+        let unit = match unit {
+            Some(unit) => Some(Unit::from_symbol(unit).ok_or_else(|| invalid("unit", unit))?),
+            None => None,
+        };
+
+        let (min_prefix, binary) = match prefix {
+            Some(prefix) => {
+                let (symbol, binary) = match prefix.strip_suffix('i') {
+                    Some(symbol) => (symbol, true),
+                    None => (prefix, false),
+                };
+                let min_prefix = if symbol.is_empty() {
+                    None
+                } else {
+                    Some(Prefix::from_symbol(symbol).ok_or_else(|| invalid("prefix", prefix))?)
+                };
+                (min_prefix, binary)
+            }
+            None => (None, false),
+        };
+
+        Ok(Modifiers {
+            unit,
+            min_prefix,
+            binary,
+            duration: None,
+        })
+    }
+
+    pub fn with_duration(mut self, format: DurationFormat) -> Self {
+        self.duration = Some(format);
+        self
+    }
+
+    fn has_unit_or_prefix(&self) -> bool {
+        self.unit.is_some() || self.min_prefix.is_some() || self.binary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.has_unit_or_prefix() && self.duration.is_none()
+    }
+}
+
+/// A value passed by a block to `FormatTemplate::render`.
+#[derive(Debug, Clone)]
+pub struct Value {
+    inner: InnerValue,
+    icon: String,
+}
+
+#[derive(Debug, Clone)]
+enum InnerValue {
+    Text(String),
+    Number {
+        value: f64,
+        unit: Unit,
+        min_prefix: Prefix,
+        digits: usize,
+    },
+    Duration {
+        duration: Duration,
+        format: DurationFormat,
+    },
+}
+
+impl Value {
+    pub fn text(text: String) -> Self {
+        Value {
+            inner: InnerValue::Text(text),
+            icon: String::new(),
+        }
+    }
+
+    pub fn bytes(bytes: f64) -> Self {
+        Value::number(bytes, Unit::Bytes)
+    }
+
+    pub fn bits(bits: f64) -> Self {
+        Value::number(bits, Unit::Bits)
+    }
+
+    /// A short time such as a latency, shown with a prefix (e.g. `25.0ms`) rather than as a
+    /// duration.
+    pub fn seconds(seconds: f64) -> Self {
+        Value::number(seconds, Unit::Seconds)
+    }
+
+    pub fn duration(duration: Duration) -> Self {
+        Value {
+            inner: InnerValue::Duration {
+                duration,
+                format: DurationFormat::Largest,
+            },
+            icon: String::new(),
+        }
+    }
+
+    fn number(value: f64, unit: Unit) -> Self {
+        Value {
+            inner: InnerValue::Number {
+                value,
+                unit,
+                min_prefix: Prefix::One,
+                digits: 3,
+            },
+            icon: String::new(),
+        }
+    }
+
+    /// Sets an icon shown before the value.
+    pub fn icon(mut self, icon: String) -> Self {
+        self.icon = icon;
+        self
+    }
+
+    /// Sets the smallest prefix used for a number, unless the placeholder asks for another one.
+    pub fn min_prefix(mut self, prefix: Prefix) -> Self {
+        if let InnerValue::Number {
+            ref mut min_prefix, ..
+        } = self.inner
+        {
+            *min_prefix = prefix;
+        }
+        self
+    }
+
+    /// Sets the number of significant digits of a number.
+    pub fn digits(mut self, total_digits: usize) -> Self {
+        if let InnerValue::Number { ref mut digits, .. } = self.inner {
+            *digits = total_digits;
+        }
+        self
+    }
+
+    /// Sets how a duration is shown, unless the placeholder asks for another format.
+    pub fn duration_format(mut self, duration_format: DurationFormat) -> Self {
+        if let InnerValue::Duration { ref mut format, .. } = self.inner {
+            *format = duration_format;
+        }
+        self
+    }
+
+    /// The value as a number, in its base unit (seconds for durations). Text is parsed if it
+    /// starts with a number.
+    pub fn as_number(&self) -> Option<f64> {
+        match self.inner {
+            InnerValue::Text(ref text) => leading_number(text),
+            InnerValue::Number { value, .. } => Some(value),
+            InnerValue::Duration { duration, .. } => Some(duration.as_secs_f64()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self.inner {
+            InnerValue::Text(ref text) => text.is_empty(),
+            InnerValue::Number { .. } | InnerValue::Duration { .. } => false,
+        }
+    }
+
+    /// Formats the value as requested by a placeholder. Text ignores the format of durations, so
+    /// that blocks can show e.g. `×` for a duration they don't know.
+    pub fn format(&self, modifiers: &Modifiers) -> Result<String> {
+        match self.inner {
+            InnerValue::Text(ref text) => {
+                if modifiers.has_unit_or_prefix() {
+                    return Err(ConfigurationError(
+                        format!("'{}' has no unit or prefix to choose", text),
+                        (String::new(), String::new()),
+                    ));
+                }
+                Ok(format!("{}{}", self.icon, text))
+            }
+            InnerValue::Number {
+                value,
+                unit,
+                min_prefix,
+                digits,
+            } => {
+                if modifiers.duration.is_some() {
+                    return Err(ConfigurationError(
+                        format!("{} is not a duration", value),
+                        (String::new(), String::new()),
+                    ));
+                }
+                let (value, unit) = match (unit, modifiers.unit) {
+                    (Unit::Bytes, Some(Unit::Bits)) => (value * 8., Unit::Bits),
+                    (Unit::Bits, Some(Unit::Bytes)) => (value / 8., Unit::Bytes),
+                    (unit, Some(wanted)) if wanted != unit => {
+                        return Err(ConfigurationError(
+                            format!(
+                                "{}{} can't be shown in {}",
+                                value,
+                                unit.symbol(),
+                                wanted.symbol()
+                            ),
+                            (String::new(), String::new()),
+                        ));
+                    }
+                    _ => (value, unit),
+                };
+                let min_prefix = modifiers.min_prefix.unwrap_or(min_prefix);
+                Ok(format!(
+                    "{}{}{}",
+                    self.icon,
+                    format_prefixed(value, digits, min_prefix, modifiers.binary),
+                    unit.symbol()
+                ))
+            }
+            InnerValue::Duration {
+                duration,
+                ref format,
+            } => {
+                if modifiers.has_unit_or_prefix() {
+                    return Err(ConfigurationError(
+                        "a duration has no unit or prefix to choose".to_string(),
+                        (String::new(), String::new()),
+                    ));
+                }
+                let format = modifiers.duration.as_ref().unwrap_or(format);
+                Ok(format!("{}{}", self.icon, format.render(duration)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatting::{
+        decimal_point, format_prefixed, locale_from_name, parse_locale, DurationFormat, Modifiers,
+        Prefix, Value,
+    };
+    use chrono::Locale;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_prefixed() {
+        assert_eq!(format_prefixed(1007., 3, Prefix::One, false), "1.01K");
+        assert_eq!(format_prefixed(2048., 3, Prefix::One, true), "2.00Ki");
+        assert_eq!(format_prefixed(0.5, 3, Prefix::Milli, true), "0.50");
+        assert_eq!(format_prefixed(6., 3, Prefix::Kilo, false), "0.01K");
+    }
+
+    #[test]
+    fn test_value_modifiers() {
+        let speed = Value::bytes(1_500_000.).min_prefix(Prefix::Kilo);
+        let format = |unit, prefix| speed.format(&Modifiers::from_strings(unit, prefix).unwrap());
+        assert_eq!(format(None, None).unwrap(), "1.50MB");
+        assert_eq!(format(Some("b"), None).unwrap(), "12.0Mb");
+        assert_eq!(format(None, Some("G")).unwrap(), "0.00GB");
+        assert_eq!(format(None, Some("i")).unwrap(), "1.43MiB");
+
+        assert!(Modifiers::from_strings(Some("x"), None).is_err());
+        assert!(Modifiers::from_strings(None, Some("X")).is_err());
+        assert!(Value::text("up".to_string())
+            .format(&Modifiers::from_strings(Some("b"), None).unwrap())
+            .is_err());
+
+        let ping = Value::seconds(0.0251).min_prefix(Prefix::Milli);
+        assert_eq!(ping.format(&Modifiers::default()).unwrap(), "25.1ms");
+        assert!(ping
+            .format(&Modifiers::from_strings(Some("B"), None).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_duration_format() {
+        let duration = Duration::from_secs(2 * 86_400 + 3 * 3600 + 23 * 60 + 5);
+        let render = |format: &str| {
+            DurationFormat::from_string(format)
+                .unwrap()
+                .render(duration)
+        };
+        assert_eq!(render("hm"), "51h 23m");
+        assert_eq!(render("dhms"), "2d 3h 23m 5s");
+        assert_eq!(render("h:m"), "51:23");
+        assert_eq!(render("m:s"), "3083:05");
+        assert_eq!(DurationFormat::Largest.render(duration), "2d 3h");
+        assert_eq!(
+            DurationFormat::Largest.render(Duration::from_secs(42)),
+            "0m 42s"
+        );
+
+        assert!(DurationFormat::from_string("mh").is_err());
+        assert!(DurationFormat::from_string("h:x").is_err());
+        assert!(DurationFormat::from_string("").is_err());
+
+        let modifiers =
+            Modifiers::default().with_duration(DurationFormat::from_string("h:m").unwrap());
+        assert_eq!(
+            Value::duration(Duration::from_secs(4980))
+                .format(&modifiers)
+                .unwrap(),
+            "01:23"
+        );
+        assert_eq!(
+            Value::text("×".to_string()).format(&modifiers).unwrap(),
+            "×"
+        );
+        assert!(Value::bytes(1.).format(&modifiers).is_err());
+    }
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(locale_from_name("de_DE"), Some(Locale::de_DE));
+        assert_eq!(locale_from_name("de_DE.UTF-8"), Some(Locale::de_DE));
+        assert_eq!(
+            locale_from_name("de_DE.UTF-8@euro"),
+            Some(Locale::de_DE_euro)
+        );
+        assert_eq!(locale_from_name("C"), None);
+        assert!(parse_locale("xx_XX").is_err());
+
+        assert_eq!(decimal_point(Some(Locale::de_DE)), ",");
+        assert_eq!(decimal_point(Some(Locale::en_US)), ".");
+        assert_eq!(decimal_point(None), ".");
+    }
+}
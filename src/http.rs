@@ -11,6 +11,21 @@ pub struct HttpResponse<T> {
     pub headers: Vec<String>,
 }
 
+impl<T> HttpResponse<T> {
+    /// The value of the header `name` of the reply. Names are compared without case, as HTTP/2
+    /// servers send them in lower case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find_map(|header| {
+            let colon = header.find(':')?;
+            if header[..colon].eq_ignore_ascii_case(name) {
+                Some(header[colon + 1..].trim())
+            } else {
+                None
+            }
+        })
+    }
+}
+
 fn http_easy(mut easy: Easy) -> Result<HttpResponse<Vec<u8>>> {
     let mut buf: Vec<u8> = Vec::new();
     let mut headers: Vec<String> = Vec::new();
@@ -58,11 +73,44 @@ pub fn http_get_socket_json(path: std::path::PathBuf, url: &str) -> Result<HttpR
     })
 }
 
+/// Requests `url` without following redirects, and returns the status code of the reply.
+pub fn http_get_status(url: &str, timeout: Option<Duration>) -> Result<u32> {
+    let mut easy = curl::easy::Easy::new();
+
+    easy.url(url)?;
+
+    if let Some(t) = timeout {
+        easy.timeout(t)?;
+    }
+
+    easy.useragent("i3status")?;
+
+    Ok(http_easy(easy)?.code)
+}
+
 pub fn http_get_json(
     url: &str,
     timeout: Option<Duration>,
     request_headers: Vec<(&str, &str)>,
 ) -> Result<HttpResponse<Value>> {
+    let response = http_get_bytes(url, timeout, request_headers)?;
+
+    let content = serde_json::from_slice(&response.content)
+        .internal_error("curl", "could not parse json response from server")?;
+
+    Ok(HttpResponse {
+        code: response.code,
+        content,
+        headers: response.headers,
+    })
+}
+
+/// Like `http_get_json`, but returns the body as it is.
+pub fn http_get_bytes(
+    url: &str,
+    timeout: Option<Duration>,
+    request_headers: Vec<(&str, &str)>,
+) -> Result<HttpResponse<Vec<u8>>> {
     let mut easy = curl::easy::Easy::new();
 
     easy.url(url)?;
@@ -81,16 +129,39 @@ pub fn http_get_json(
 
     easy.http_headers(header_list)?;
 
-    let response = http_easy(easy)?;
+    http_easy(easy)
+}
 
-    let content = serde_json::from_slice(&response.content)
-        .internal_error("curl", "could not parse json response from server")?;
+/// Percent-encodes `text` for a query parameter of a URL.
+pub fn url_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
 
-    Ok(HttpResponse {
-        code: response.code,
-        content,
-        headers: response.headers,
-    })
+/// The value of an `Authorization` header for basic authentication.
+pub fn basic_auth(username: &str, password: &str) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let credentials = format!("{}:{}", username, password);
+    let mut encoded = String::from("Basic ");
+    for chunk in credentials.as_bytes().chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | (u32::from(byte) << (16 - 8 * i))
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
 }
 
 impl From<curl::Error> for errors::Error {
@@ -102,3 +173,45 @@ impl From<curl::Error> for errors::Error {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{basic_auth, url_encode, HttpResponse};
+
+    #[test]
+    fn test_header() {
+        let response = HttpResponse {
+            code: 200,
+            content: (),
+            headers: vec![
+                "HTTP/2 200\r\n".to_string(),
+                "last-modified: Wed, 31 Mar 2021 10:00:00 GMT\r\n".to_string(),
+                "X-Total: 60\r\n".to_string(),
+            ],
+        };
+        assert_eq!(
+            response.header("Last-Modified"),
+            Some("Wed, 31 Mar 2021 10:00:00 GMT")
+        );
+        assert_eq!(response.header("x-total"), Some("60"));
+        assert_eq!(response.header("Link"), None);
+    }
+    #[test]
+    fn test_url_encode() {
+        assert_eq!(
+            url_encode("assignee = currentUser()"),
+            "assignee%20%3D%20currentUser%28%29"
+        );
+        assert_eq!(url_encode("a-b_c.d~é"), "a-b_c.d~%C3%A9");
+    }
+
+    #[test]
+    fn test_basic_auth() {
+        assert_eq!(
+            basic_auth("Aladdin", "open sesame"),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        assert_eq!(basic_auth("a", "b"), "Basic YTpi");
+        assert_eq!(basic_auth("ab", "c"), "Basic YWI6Yw==");
+    }
+}
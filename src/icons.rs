@@ -26,6 +26,7 @@ lazy_static! {
         "backlight_11" => "BRIGHT",
         "backlight_12" => "BRIGHT",
         "backlight_13" => "BRIGHT",
+        "backup" => "BAK",
         "bat" => "BAT",
         "bat_charging" => "CHG",
         "bat_discharging" => "DCG",
@@ -38,16 +39,24 @@ lazy_static! {
         "bell" => "ON",
         "bell-slash" => "OFF",
         "bluetooth" => "BT",
+        "caffeine_off" => "IDLE",
+        "caffeine_on" => "AWAKE",
         "calendar" => "CAL",
+        "charge_limit" => "LIM",
         "cogs" => "LOAD",
+        "countdown" => "TIMER",
         "cpu" => "CPU",
         "disk_drive" => "DISK",
+        "dns" => "DNS",
         "docker" => "DOCKER",
+        "git" => "GIT",
         "github" => "GITHUB",
+        "gitlab" => "GITLAB",
         "gpu" => "GPU",
         "headphones" => "HEAD",
         "joystick" => "JOY",
         "keyboard" => "KBD",
+        "kubernetes" => "K8S",
         "mail" => "MAIL",
         "memory_mem" => "MEM",
         "memory_swap" => "SWAP",
@@ -59,6 +68,7 @@ lazy_static! {
         "music_prev" => "<",
         "net_down" => "DOWN",
         "net_loopback" => "LO",
+        "net_portal" => "PORTAL",
         "net_up" => "UP ",
         "net_vpn" => "VPN",
         "net_wired" => "ETH",
@@ -68,13 +78,20 @@ lazy_static! {
         "phone_disconnected" => "PHONE",
         "ping" => "PING",
         "pomodoro" => "POMODORO",
+        "power_profile" => "PWR",
         "resolution" => "RES",
+        "rss" => "RSS",
+        "scratchpad" => "SCRATCH",
+        "screencast" => "SHARE",
+        "ssh" => "SSH",
+        "stopwatch" => "SW",
         "tasks" => "TSK",
         "thermometer" => "TEMP",
         "time" => "TIME",
         "toggle_off" => "OFF",
         "toggle_on" => "ON",
         "update" => "UPD",
+        "ups" => "UPS",
         "uptime" => "UP",
         "volume_empty" => "VOL",
         "volume_full" => "VOL",
@@ -90,6 +107,7 @@ lazy_static! {
         "weather_snow" => "SNOW",
         "weather_sun" => "SUNNY",
         "weather_thunder" => "STORM",
+        "webcam" => "CAM",
         "xrandr" => "SCREEN"
     };
 
@@ -111,6 +129,7 @@ lazy_static! {
         "backlight_11" => "\u{1f312}",
         "backlight_12" => "\u{1f312}",
         "backlight_13" => "\u{1f312}",
+        "backup" => "\u{f0c7}", // fa-floppy-o
         "bat_charging" => "\u{f1e6}", // fa-plug
         "bat_discharging" => "\u{f242}", // fa-battery-half
         "bat_empty" => "\u{f244}", // fa-battery-empty
@@ -122,16 +141,24 @@ lazy_static! {
         "bell" => "\u{f0f3}", // fa-bell
         "bell-slash" => "\u{f1f7}", // fa-bell-slash-o
         "bluetooth" => "\u{f294}", // fa-bluetooth-b
+        "caffeine_off" => "\u{f186}", // fa-moon-o
+        "caffeine_on" => "\u{f0f4}", // fa-coffee
         "calendar" => "\u{f073}", // fa-calendar
+        "charge_limit" => "\u{f240}", // fa-battery-full
         "cogs" => "\u{f085}", // fa-cogs
+        "countdown" => "\u{f252}", // fa-hourglass-half
         "cpu" => "\u{f0e4}", // fa-dashboard
         "disk_drive" => "\u{f0a0}", // fa-hdd-o
+        "dns" => "\u{f0ac}", // fa-globe
         "docker" => "\u{f21a}", // fa-ship
+        "git" => "\u{f1d3}", // fa-git
         "github" => "\u{f09b}", // fa-github
+        "gitlab" => "\u{f296}", // fa-gitlab
         "gpu" => "\u{f26c}", // fa-television
         "headphones" => "\u{f025}", // fa-headphones
         "joystick" => "\u{f11b}", // fa-gamepad
         "keyboard" => "\u{f11c}", // fa-keyboard-o
+        "kubernetes" => "\u{f0c2}", // fa-cloud
         "mail" => "\u{f0e0}", // fa-envelope
         "memory_mem" => "\u{f2db}", // fa-microchip
         "memory_swap" => "\u{f0a0}", // fa-hdd-o
@@ -145,6 +172,7 @@ lazy_static! {
         "net_down" => "\u{2b07}",
         "net_loopback" => "LO",
         "net_modem" => "\u{f095}", // fa-phone
+        "net_portal" => "\u{f090}", // fa-sign-in
         "net_up" => "\u{2b06}",
         "net_vpn" => "\u{f023}", // fa-lock
         "net_wired" => "\u{f0ac}", // fa-globe
@@ -154,7 +182,13 @@ lazy_static! {
         "phone_disconnected" => "\u{1f4f5}",
         "ping" => "\u{21ba}",
         "pomodoro" => "\u{1f345}",
+        "power_profile" => "\u{f0e4}", // fa-tachometer
         "resolution" => "\u{f096}", // fa-square-o
+        "rss" => "\u{f09e}", // fa-rss
+        "scratchpad" => "\u{f2d2}", // fa-window-restore
+        "screencast" => "\u{f108}", // fa-desktop
+        "ssh" => "\u{f120}", // fa-terminal
+        "stopwatch" => "\u{f017}", // fa-clock-o
         "tasks" => "\u{f0ae}", // fa-tasks
         "thermometer" => "\u{f2c8}", // fa-thermometer-3
         "time" => "\u{f017}", // fa-clock-o
@@ -162,6 +196,7 @@ lazy_static! {
         "toggle_on" => "\u{f205}", // fa-toggle-on
         "unknown" => "\u{f128}", // fa-question
         "update" => "\u{f062}", // fa-arrow-up
+        "ups" => "\u{f1e6}", // fa-plug
         "uptime" => "\u{f017}", // fa-clock-o
         "volume_empty" => "\u{f026}", // fa-volume-off
         "volume_full" => "\u{f028}", // fa-volume-up
@@ -177,6 +212,7 @@ lazy_static! {
         "weather_snow" => "\u{f2dc}", // fa-snowflake-o
         "weather_sun" => "\u{f185}", // fa-sun-o
         "weather_thunder" => "\u{f0e7}", // fa-bolt
+        "webcam" => "\u{f030}", // fa-camera
         "xrandr" => "\u{f26c}" // fa-television
     };
 
@@ -198,6 +234,7 @@ lazy_static! {
         "backlight_11" => "\u{1f312}",
         "backlight_12" => "\u{1f312}",
         "backlight_13" => "\u{1f312}",
+        "backup" => "\u{f0c7}", // fa-save
         "bat_charging" => "\u{f1e6}",
         "bat_discharging" => "\u{f242}",
         "bat_empty" => "\u{f244}",
@@ -208,16 +245,24 @@ lazy_static! {
         "bell" => "\u{f0f3}",
         "bell-slash" => "\u{f1f6}",
         "bluetooth" => "\u{f294}",
+        "caffeine_off" => "\u{f186}",
+        "caffeine_on" => "\u{f0f4}",
         "calendar" => "\u{f073}",
+        "charge_limit" => "\u{f5df}",
         "cogs" => "\u{f085}",
+        "countdown" => "\u{f252}",
         "cpu" => "\u{f3fd}",
         "disk_drive" => "\u{f8b5}",
+        "dns" => "\u{f0ac}",
         "docker" => "\u{f21a}",
+        "git" => "\u{f1d3}", // fa-git
         "github" => "\u{f09b}",
+        "gitlab" => "\u{f296}",
         "gpu" => "\u{f26c}",
         "headphones" => "\u{f025}",
         "joystick" => "\u{f11b}",
         "keyboard" => "\u{f11c}",
+        "kubernetes" => "\u{f655}", // fa-dharmachakra
         "mail" => "\u{f0e0}",
         "memory_mem" => "\u{f2db}",
         "memory_swap" => "\u{f0a0}",
@@ -231,6 +276,7 @@ lazy_static! {
         "net_down" => "\u{f019}",
         "net_loopback" => "LO ",
         "net_modem" => "\u{f095}",
+        "net_portal" => "\u{f2f6}",
         "net_up" => "\u{f093}",
         "net_vpn" => "\u{f023}",
         "net_wired" => "\u{f6ff}",
@@ -240,7 +286,13 @@ lazy_static! {
         "phone_disconnected" => "\u{1f4f5}",
         "ping" => "\u{f362}",
         "pomodoro" => "\u{1f345}",
+        "power_profile" => "\u{f3fd}", // fa-tachometer-alt
         "resolution" => "\u{f096}", // fa-square-o
+        "rss" => "\u{f09e}",
+        "scratchpad" => "\u{f2d2}", // fa-window-restore
+        "screencast" => "\u{f108}",
+        "ssh" => "\u{f120}", // fa-terminal
+        "stopwatch" => "\u{f2f2}", // fa-stopwatch
         "tasks" => "\u{f0ae}",
         "thermometer" => "\u{f2c8}",
         "time" => "\u{f017}",
@@ -248,6 +300,7 @@ lazy_static! {
         "toggle_on" => "\u{f205}",
         "unknown" => "\u{f128}",
         "update" => "\u{f062}",
+        "ups" => "\u{f1e6}",
         "uptime" => "\u{f2f2}",
         "volume_empty" => "\u{f026}",
         "volume_full" => "\u{f028}",
@@ -263,6 +316,7 @@ lazy_static! {
         "weather_snow" => "\u{f2dc}",
         "weather_sun" => "\u{f185}",
         "weather_thunder" => "\u{f0e7}",
+        "webcam" => "\u{f030}",
         "xrandr" => "\u{f26c}"
     };
 
@@ -270,6 +324,7 @@ lazy_static! {
     // https://github.com/google/material-design-icons/blob/master/font/MaterialIcons-Regular.codepoints
     pub static ref MATERIAL: HashMap<String, String> = map_to_owned! {
         "" => "",
+        "backup" => "\u{e864}", // backup
         "bat_charging" => "\u{e1a3}", // battery_charging_full
         "bat_discharging" => "\u{e19c}", // battery_alert
         "bat_empty" => "\u{e19c}", // battery_alert
@@ -281,16 +336,24 @@ lazy_static! {
         "bell" => "\u{e7f4}", // notifications
         "bell-slash" => "\u{e7f8}", // notifications_paused
         "bluetooth" => "\u{e1a7}", // bluetooth
+        "caffeine_off" => "\u{e3a8}", // brightness_3
+        "caffeine_on" => "\u{e541}", // local_cafe
         "calendar" => "\u{e935}", // calendar_today
+        "charge_limit" => "\u{e1a4}", // battery_full
         "cogs" => "\u{e8b8}", // settings
+        "countdown" => "\u{e88b}", // hourglass_empty
         "cpu" => "\u{e640}", // network_check
         "disk_drive" => "\u{e1db}", // storage
+        "dns" => "\u{e80b}", // public
         "docker" => "\u{e532}", // directions_boat
+        "git" => "\u{e0b6}", // call_split
         "github" => "\u{e86f}", // code
+        "gitlab" => "\u{e86f}", // code
         "gpu" => "\u{e333}", // tv
         "headphones" => "\u{e60f}", // bluetooth_audio
         "joystick" => "\u{e30f}", // gamepad
         "keyboard" => "\u{e312}", // keyboard
+        "kubernetes" => "\u{e2bd}", // cloud
         "mail" => "\u{e0be}", // email
         "memory_mem" => "\u{e322}", // memory
         "memory_swap" => "\u{e8d4}", // swap_horiz
@@ -306,13 +369,20 @@ lazy_static! {
         "phone_disconnected" => "\u{e339}", // device_unknown
         "ping" => "\u{e62a}", // system_update
         "pomodoro" => "\u{1f345}",
+        "power_profile" => "\u{e9e4}", // speed
         "resolution" => "\u{f152}", // crop-square-rounded
+        "rss" => "\u{e0e5}", // rss_feed
+        "scratchpad" => "\u{e8ba}", // tab_unselected
+        "screencast" => "\u{e0e2}", // screen_share
+        "ssh" => "\u{e30a}", // computer
+        "stopwatch" => "\u{e425}", // timer
         "tasks" => "\u{e8f9}",
         "thermometer" => "\u{e1ff}", // device_thermostat
         "time" => "\u{e192}", // access_time
         "toggle_off" => "\u{e836}", // radio_button_on
         "toggle_on" => "\u{e837}", // radio_button_on
         "update" => "\u{e8d7}", // system_update_alt
+        "ups" => "\u{e63c}", // power
         "uptime" => "\u{e425}", // timer
         "volume_empty" => "\u{e04e}", // volume_mute
         "volume_full" => "\u{e050}", // volume_up
@@ -325,6 +395,7 @@ lazy_static! {
         "weather_clouds" => "\u{e42d}", // wb_cloudy
         "weather_default" => "\u{e42d}", // wb_cloudy
         "weather_sun" => "\u{e430}", // wb_sunny
+        "webcam" => "\u{e04b}", // videocam
         "xrandr" => "\u{e31e}"
     };
 
@@ -347,6 +418,7 @@ lazy_static! {
         "backlight_11" => "\u{e3ca}", // nf-weather-moon_alt_waxing_crescent_3
         "backlight_12" => "\u{e3c9}", // nf-weather-moon_alt_waxing_crescent_2
         "backlight_13" => "\u{e3c8}", // nf-weather-moon_alt_waxing_crescent_1
+        "backup" => "\u{f0c7}", // nf-fa-floppy_o
         "bat_charging" => "\u{f583}", // nf-mdi-battery_charging
         "bat_discharging" => "\u{f57d}", // nf-mdi-battery_50
         "bat_empty" => "\u{f58d}", // nf-mdi-battery_outline
@@ -357,16 +429,24 @@ lazy_static! {
         "bell" => "\u{f599}", // nf-mdi-bell
         "bell-slash" => "\u{f59a}", // nf-mdi-bell_off
         "bluetooth" => "\u{f5ae}", // nf-mdi-bluetooth
+        "caffeine_off" => "\u{f186}", // nf-fa-moon_o
+        "caffeine_on" => "\u{f0f4}", // nf-fa-coffee
         "calendar" => "\u{f5ec}", // nf-mdi-calendar
+        "charge_limit" => "\u{f578}", // nf-mdi-battery
         "cogs" => "\u{f992}", // nf-mdi-settings
+        "countdown" => "\u{f51f}", // nf-mdi-timer_sand
         "cpu" => "\u{f9c4}", // nf-mdi-speedometer
         "disk_drive" => "\u{f7c9}", // nf-mdi-harddisk
+        "dns" => "\u{f0ac}", // nf-fa-globe
         "docker" => "\u{f308}", // nf-linux-docker
+        "git" => "\u{e702}", // nf-dev-git
         "github" => "\u{f7a3}", // nf-mdi-github_circle
+        "gitlab" => "\u{f296}", // nf-fa-gitlab
         "gpu" => "\u{f878}", // nf-mdi-monitor
         "headphones" => "\u{f7ca}", // nf-mdi-headphones
         "joystick" => "\u{f796}", // nf-mdi-gamepad_variant
         "keyboard" => "\u{f80b}", // nf-mdi-keyboard
+        "kubernetes" => "\u{f0c2}", // nf-fa-cloud
         "mail" => "\u{f6ed}", // nf-mdi-email
         "memory_mem" => "\u{f85a}", // nf-mdi-memory
         "memory_swap" => "\u{f7c9}", // nf-mdi-harddisk
@@ -380,6 +460,7 @@ lazy_static! {
         "net_down" => "\u{f6d9}", // nf-mdi-download
         "net_loopback" => "\u{fbe9}", // nf-mdi-loop
         "net_modem" => "\u{f8f1}", // nf-mdi-phone
+        "net_portal" => "\u{f090}", // nf-fa-sign_in
         "net_up" => "\u{fa51}", // nf-mdi-upload
         "net_vpn" => "\u{fa81}", // nf-mdi-vpn
         "net_wired" => "\u{f6ff}", // nf-mdi-ethernet
@@ -389,7 +470,13 @@ lazy_static! {
         "phone_disconnected" => "\u{fb57}", // nf-mdi-phone_minus
         "ping" => "\u{fa1e}", // nf-mdi-timer_sand
         "pomodoro" => "\u{e001}", // nf-pom-pomodoro_done
+        "power_profile" => "\u{f0e4}", // nf-fa-tachometer
         "resolution" => "\u{f792}", // nf-mdi-fullscreen
+        "rss" => "\u{f46b}", // nf-mdi-rss
+        "scratchpad" => "\u{fab1}", // nf-mdi-window_restore
+        "screencast" => "\u{f108}", // nf-fa-desktop
+        "ssh" => "\u{f120}", // nf-fa-terminal
+        "stopwatch" => "\u{f51b}", // nf-mdi-timer
         "tasks" => "\u{fac6}", // nf-mdi-playlist_check
         "thermometer" => "\u{fa0e}", // nf-mdi-thermometer
         "time" => "\u{f64f}", // nf-mdi-clock
@@ -397,6 +484,7 @@ lazy_static! {
         "toggle_on" => "\u{fa20}", // nf-mdi-toggle_switch
         "unknown" => "\u{f685}", // nf-mdi-comment_question_outline | TODO: Make default?
         "update" => "\u{fbae}", // nf-mdi-update
+        "ups" => "\u{f6a5}", // nf-mdi-power_plug
         "uptime" => "\u{f652}", // nf-mdi-clock_in
         "volume_empty" => "\u{fa7e}", // nf-mdi-volume_low
         "volume_full" => "\u{fa7d}", // nf-mdi-volume_high
@@ -412,27 +500,40 @@ lazy_static! {
         "weather_snow" => "\u{fa97}", // nf-mdi-weather_snowy
         "weather_sun" => "\u{fa98}", // nf-mdi-weather_sunny
         "weather_thunder" => "\u{e31d}", // nf-weather-thunderstorm
+        "webcam" => "\u{f03d}", // nf-fa-video_camera
         "xrandr" => "\u{f879}" // nf-mdi-monitor_multiple
     };
 }
 
 #[derive(Debug, Clone)]
-pub struct Icons(pub HashMap<String, String>);
+pub struct Icons {
+    /// Icons of the set chosen by `name` or `file`
+    pub set: HashMap<String, String>,
+    /// Icons from `[icons.overrides]`, which take precedence over the set
+    pub overrides: HashMap<String, String>,
+}
 
 impl Default for Icons {
     fn default() -> Self {
-        Self(NONE.clone())
+        Self::from_set(NONE.clone())
     }
 }
 
 impl Icons {
+    fn from_set(set: HashMap<String, String>) -> Self {
+        Icons {
+            set,
+            overrides: HashMap::new(),
+        }
+    }
+
     pub fn from_name(name: &str) -> Option<Self> {
         match name {
-            "material" => Some(Icons(MATERIAL.clone())),
-            "material-nf" => Some(Icons(MATERIAL_NF.clone())),
-            "awesome" => Some(Icons(AWESOME.clone())),
-            "awesome5" => Some(Icons(AWESOME5.clone())),
-            "none" => Some(Icons(NONE.clone())),
+            "material" => Some(Icons::from_set(MATERIAL.clone())),
+            "material-nf" => Some(Icons::from_set(MATERIAL_NF.clone())),
+            "awesome" => Some(Icons::from_set(AWESOME.clone())),
+            "awesome5" => Some(Icons::from_set(AWESOME5.clone())),
+            "none" => Some(Icons::from_set(NONE.clone())),
             _ => None,
         }
     }
@@ -440,7 +541,23 @@ impl Icons {
     pub fn from_file(file: &str) -> Option<Self> {
         let file = util::find_file(file, Some("icons"), Some(".toml"))?;
         let icons: HashMap<String, String> = util::deserialize_file(&file).ok()?;
-        Some(Icons(icons))
+        Some(Icons::from_set(icons))
+    }
+
+    /// Looks up a built-in icon set, or loads the icon file `name` refers to if it is a path.
+    pub fn from_name_or_file(name: &str) -> Option<Self> {
+        Icons::from_name(name).or_else(|| {
+            if util::is_file_path(name) {
+                Icons::from_file(name)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Looks up an icon, preferring the overrides to the icon set.
+    pub fn get(&self, icon: &str) -> Option<&String> {
+        self.overrides.get(icon).or_else(|| self.set.get(icon))
     }
 }
 
@@ -475,7 +592,7 @@ impl<'de> Deserialize<'de> for Icons {
             where
                 E: de::Error,
             {
-                Icons::from_name(name)
+                Icons::from_name_or_file(name)
                     .ok_or_else(|| de::Error::custom(format!("Icon set \"{}\" not found.", name)))
             }
 
@@ -497,8 +614,8 @@ impl<'de> Deserialize<'de> for Icons {
                             if icons.is_some() {
                                 return Err(de::Error::duplicate_field("name or file"));
                             }
-                            let name = map.next_value()?;
-                            icons = Some(Icons::from_name(name).ok_or_else(|| {
+                            let name: String = map.next_value()?;
+                            icons = Some(Icons::from_name_or_file(&name).ok_or_else(|| {
                                 de::Error::custom(format!("Icon set \"{}\" not found.", name))
                             })?);
                         }
@@ -506,8 +623,8 @@ impl<'de> Deserialize<'de> for Icons {
                             if icons.is_some() {
                                 return Err(de::Error::duplicate_field("name or file"));
                             }
-                            let file = map.next_value()?;
-                            icons = Some(Icons::from_file(file).ok_or_else(|| {
+                            let file: String = map.next_value()?;
+                            icons = Some(Icons::from_file(&file).ok_or_else(|| {
                                 de::Error::custom(format!(
                                     "Failed to load icon set from file {}.",
                                     file
@@ -523,11 +640,7 @@ impl<'de> Deserialize<'de> for Icons {
                     }
                 }
                 let mut icons = icons.unwrap_or_default();
-                if let Some(overrides) = overrides {
-                    for icon in overrides {
-                        icons.0.insert(icon.0, icon.1);
-                    }
-                }
+                icons.overrides = overrides.unwrap_or_default();
                 Ok(icons)
             }
         }
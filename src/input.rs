@@ -8,7 +8,8 @@ use crossbeam_channel::Sender;
 use serde::{de, Deserializer};
 use serde_derive::Deserialize;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum MouseButton {
     Left,
     Middle,
@@ -20,6 +21,12 @@ pub enum MouseButton {
     Unknown,
 }
 
+impl Default for MouseButton {
+    fn default() -> Self {
+        MouseButton::Left
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct I3BarEventInternal {
     pub name: Option<String>,
@@ -0,0 +1,180 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+
+use crossbeam_channel::Sender;
+
+use crate::errors::*;
+
+/// Commands accepted on the IPC socket, one per line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IpcCommand {
+    /// Update a block immediately
+    Refresh(usize),
+    /// Hide a block, or show it again if it was hidden
+    Hide(usize),
+    /// Swap the `format` of a block with its `format_alt`
+    ToggleFormat(usize),
+    /// Reload the config file
+    Reload,
+}
+
+impl FromStr for IpcCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words.next().ok_or_else(|| "empty command".to_string())?;
+        let mut block_id = || {
+            words
+                .next()
+                .ok_or_else(|| format!("'{}' requires a block id", command))?
+                .parse::<usize>()
+                .map_err(|_| "block id must be a number".to_string())
+        };
+        match command {
+            "refresh" => Ok(IpcCommand::Refresh(block_id()?)),
+            "hide" => Ok(IpcCommand::Hide(block_id()?)),
+            "toggle-format" => Ok(IpcCommand::ToggleFormat(block_id()?)),
+            "reload" => Ok(IpcCommand::Reload),
+            other => Err(format!("unknown command '{}'", other)),
+        }
+    }
+}
+
+/// Directory holding the sockets of all running instances.
+fn socket_dir() -> PathBuf {
+    std::env::var("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join("i3status-rs")
+}
+
+/// The socket of a running instance, which is removed when dropped.
+pub struct IpcSocket {
+    path: PathBuf,
+}
+
+impl Drop for IpcSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Starts a thread that listens on `$XDG_RUNTIME_DIR/i3status-rs/<pid>.sock` and sends the
+/// received commands on the provided channel. The socket is removed once the returned
+/// `IpcSocket` is dropped.
+pub fn process_ipc(sender: Sender<IpcCommand>) -> Result<IpcSocket> {
+    let dir = socket_dir();
+    fs::create_dir_all(&dir).internal_error("ipc", "failed to create socket directory")?;
+    let path = dir.join(format!("{}.sock", std::process::id()));
+    // A previous instance with the same pid may have left its socket behind
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).internal_error("ipc", "failed to bind socket")?;
+
+    thread::Builder::new()
+        .name("ipc".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let mut writer = match stream.try_clone() {
+                    Ok(writer) => writer,
+                    Err(_) => continue,
+                };
+                for line in BufReader::new(stream).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    let reply = match line.parse::<IpcCommand>() {
+                        Ok(command) => {
+                            if sender.send(command).is_err() {
+                                return;
+                            }
+                            "ok".to_string()
+                        }
+                        Err(e) => format!("error: {}", e),
+                    };
+                    let _ = writeln!(writer, "{}", reply);
+                }
+            }
+        })
+        .internal_error("ipc", "failed to start ipc thread")?;
+
+    Ok(IpcSocket { path })
+}
+
+/// Sends `command` to every running instance and prints their replies.
+pub fn send_command(command: &str) -> Result<()> {
+    command
+        .parse::<IpcCommand>()
+        .configuration_error("invalid IPC command")?;
+
+    let entries = fs::read_dir(socket_dir())
+        .internal_error("ipc", "no running i3status-rs instance found")?;
+    let mut sent = false;
+    // An instance which fails to reply doesn't keep the command from the others
+    let mut failed = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "sock") {
+            continue;
+        }
+        let mut stream = match UnixStream::connect(&path) {
+            Ok(stream) => stream,
+            Err(_) => {
+                // The instance is gone, clean up after it
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+        };
+        let mut reply = String::new();
+        let result = writeln!(stream, "{}", command)
+            .and_then(|_| BufReader::new(stream).read_line(&mut reply));
+        match result {
+            Ok(_) => {
+                print!("{}", reply);
+                sent = true;
+            }
+            Err(error) => failed.push(format!("{}: {}", path.display(), error)),
+        }
+    }
+
+    if !failed.is_empty() {
+        Err(InternalError(
+            "ipc".to_string(),
+            format!("failed to send the command to {}", failed.join(", ")),
+            None,
+        ))
+    } else if sent {
+        Ok(())
+    } else {
+        Err(InternalError(
+            "ipc".to_string(),
+            "no running i3status-rs instance found".to_string(),
+            None,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ipc::IpcCommand;
+
+    #[test]
+    fn test_parse_ipc_command() {
+        assert_eq!("refresh 3".parse(), Ok(IpcCommand::Refresh(3)));
+        assert_eq!("hide 0".parse(), Ok(IpcCommand::Hide(0)));
+        assert_eq!("toggle-format 1".parse(), Ok(IpcCommand::ToggleFormat(1)));
+        assert_eq!("reload".parse(), Ok(IpcCommand::Reload));
+        assert!("refresh".parse::<IpcCommand>().is_err());
+        assert!("refresh pacman".parse::<IpcCommand>().is_err());
+        assert!("explode 1".parse::<IpcCommand>().is_err());
+    }
+}
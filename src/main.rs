@@ -5,9 +5,12 @@ mod util;
 pub mod blocks;
 mod config;
 mod errors;
+mod formatting;
 mod http;
 mod icons;
 mod input;
+mod ipc;
+mod reload;
 mod scheduler;
 mod signals;
 mod subprocess;
@@ -22,20 +25,23 @@ use std::ops::DerefMut;
 #[cfg(feature = "pulseaudio")]
 use libpulse_binding as pulse;
 
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use clap::{crate_authors, crate_description, App, Arg, ArgMatches};
 use crossbeam_channel::{select, Receiver, Sender};
 
-use crate::blocks::create_block;
+use crate::blocks::base_block::shown_on_output;
 use crate::blocks::Block;
-use crate::config::Config;
+use crate::blocks::{check_block, create_block};
 use crate::config::SharedConfig;
+use crate::config::{Config, OutputFormat};
 use crate::errors::*;
 use crate::input::{process_events, I3BarEvent};
+use crate::ipc::{process_ipc, send_command, IpcCommand};
+use crate::reload::{watch_color_scheme, watch_config, ReloadError};
 use crate::scheduler::{Task, UpdateScheduler};
 use crate::signals::process_signals;
-use crate::util::deserialize_file;
 use crate::widgets::text::TextWidget;
 use crate::widgets::{I3BarWidget, State};
 
@@ -63,6 +69,20 @@ fn main() {
                 .required(false)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("bar")
+                .help("Use the `[bar.<NAME>]` section of the config file")
+                .long("bar")
+                .value_name("NAME")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .help("Name of the output of the bar, for the `output` options of the blocks")
+                .long("output")
+                .value_name("OUTPUT")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("exit-on-error")
                 .help("Exit rather than printing errors to i3bar and continuing")
@@ -75,6 +95,19 @@ fn main() {
                 .long("never-pause")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("send")
+                .help("Send a command (e.g. \"refresh 2\") to all running instances and exit")
+                .long("send")
+                .value_name("COMMAND")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("check")
+                .help("Check the config file and the formats of its blocks, without starting them, and exit")
+                .long("check")
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("one-shot")
                 .help("Print blocks once and exit")
@@ -104,6 +137,21 @@ fn main() {
     let matches = builder.get_matches();
     let exit_on_error = matches.is_present("exit-on-error");
 
+    if let Some(command) = matches.value_of("send") {
+        if let Err(error) = send_command(command) {
+            eprintln!("{:?}", error);
+            ::std::process::exit(1);
+        }
+        return;
+    }
+
+    if matches.is_present("check") {
+        if !check_config(&config_path(&matches), matches.value_of("bar")) {
+            ::std::process::exit(1);
+        }
+        return;
+    }
+
     // Run and match for potential error
     if let Err(error) = run(&matches) {
         if exit_on_error {
@@ -125,59 +173,77 @@ fn main() {
     }
 }
 
-fn run(matches: &ArgMatches) -> Result<()> {
-    // Now we can start to run the i3bar protocol
-    let initialise = if matches.is_present("never-pause") {
-        "\"version\": 1, \"click_events\": true, \"stop_signal\": 0"
-    } else {
-        "\"version\": 1, \"click_events\": true"
-    };
-    print!("{{{}}}\n[", initialise);
+fn config_path(matches: &ArgMatches) -> PathBuf {
+    match matches.value_of("config") {
+        Some(config_path) => PathBuf::from(config_path),
+        None => util::xdg_config_home().join("i3status-rust/config.toml"),
+    }
+}
 
+fn run(matches: &ArgMatches) -> Result<()> {
     // Read & parse the config file
-    let config_path = match matches.value_of("config") {
-        Some(config_path) => std::path::PathBuf::from(config_path),
-        None => util::xdg_config_home().join("i3status-rust/config.toml"),
-    };
-    let config: Config = deserialize_file(&config_path)?;
+    let config_path = config_path(matches);
+    let bar = matches.value_of("bar");
+    let config = Config::load(&config_path, bar);
 
-    // Update request channel
-    let (tx_update_requests, rx_update_requests): (Sender<Task>, Receiver<Task>) =
-        crossbeam_channel::unbounded();
+    // Now we can start to run the i3bar protocol. This is done even if the config is invalid,
+    // so that the error can be shown on the bar.
+    let output_format = config
+        .as_ref()
+        .map(|config| config.output_format)
+        .unwrap_or_default();
+    if output_format == OutputFormat::I3bar {
+        let initialise = if matches.is_present("never-pause") {
+            "\"version\": 1, \"click_events\": true, \"stop_signal\": 0"
+        } else {
+            "\"version\": 1, \"click_events\": true"
+        };
+        print!("{{{}}}\n[", initialise);
+    }
+    let config = config?;
 
     // In dev build, we might diverge into profiling blocks here
     #[cfg(feature = "profiling")]
     {
         if let Some(name) = matches.value_of("profile") {
-            return profile_config(
-                name,
-                matches.value_of("profile-runs").unwrap(),
-                &config,
-                tx_update_requests,
-            );
+            return profile_config(name, matches.value_of("profile-runs").unwrap(), &config);
         }
     }
 
-    let shared_config = SharedConfig::new(&config);
-
-    // Initialize the blocks
-    let mut blocks: Vec<Box<dyn Block>> = Vec::new();
-    for &(ref block_name, ref block_config) in &config.blocks {
-        blocks.push(create_block(
-            blocks.len(),
-            block_name,
-            block_config.clone(),
-            shared_config.clone(),
-            tx_update_requests.clone(),
-        )?);
-    }
-
+    let output = matches.value_of("output");
+    let (mut blocks, mut block_configs, mut shared_config, mut rx_update_requests) =
+        init_blocks(&config, output)?;
     let mut scheduler = UpdateScheduler::new(&blocks);
 
-    // We wait for click events in a separate thread, to avoid blocking to wait for stdin
+    // The config file and the color scheme of the desktop are watched in separate threads, so
+    // changes can be applied in place
+    let (tx_reload, rx_reload): (Sender<()>, Receiver<()>) = crossbeam_channel::unbounded();
+    watch_config(
+        config_path.clone(),
+        bar.map(String::from),
+        tx_reload.clone(),
+    );
+    watch_color_scheme(config_path.clone(), bar.map(String::from), tx_reload);
+
+    // We wait for click events in a separate thread, to avoid blocking to wait for stdin.
+    // Waybar does not send click events, but handles them with its own `on-click` commands.
     let (tx_clicks, rx_clicks): (Sender<I3BarEvent>, Receiver<I3BarEvent>) =
         crossbeam_channel::unbounded();
-    process_events(tx_clicks);
+    if output_format == OutputFormat::I3bar {
+        process_events(tx_clicks);
+    }
+
+    // We wait for IPC commands in a separate thread
+    let (tx_ipc, rx_ipc): (Sender<IpcCommand>, Receiver<IpcCommand>) =
+        crossbeam_channel::unbounded();
+    // The bar works without IPC, so failing to set it up is not fatal
+    let _ipc_socket = match process_ipc(tx_ipc) {
+        Ok(socket) => Some(socket),
+        Err(error) => {
+            eprintln!("{:?}", error);
+            None
+        }
+    };
 
     // We wait for signals in a separate thread
     let (tx_signals, rx_signals): (Sender<i32>, Receiver<i32>) = crossbeam_channel::unbounded();
@@ -189,6 +255,9 @@ fn run(matches: &ArgMatches) -> Result<()> {
 
     let one_shot = matches.is_present("one-shot");
     loop {
+        let mut reload_requested = false;
+        let mut quit_requested = false;
+
         // We use the message passing concept of channel selection
         // to avoid busy wait
         select! {
@@ -203,10 +272,12 @@ fn run(matches: &ArgMatches) -> Result<()> {
             },
             // Receive async update requests
             recv(rx_update_requests) -> request => if let Ok(req) = request {
-                // Process immediately and forget
-                blocks.get_mut(req.id)
+                // Process immediately, and keep updating the block if it asks for it while
+                // nothing is scheduled for it, e.g. after a block stopped ticking
+                let update = blocks.get_mut(req.id)
                     .internal_error("scheduler", "could not get required block")?
                     .update()?;
+                scheduler.reschedule(req.id, update);
                 util::print_blocks(&blocks, &shared_config)?;
             },
             // Receive update timer events
@@ -215,6 +286,35 @@ fn run(matches: &ArgMatches) -> Result<()> {
                 // redraw the blocks, state changed
                 util::print_blocks(&blocks, &shared_config)?;
             },
+            // Receive IPC commands
+            recv(rx_ipc) -> res => if let Ok(command) = res {
+                match command {
+                    IpcCommand::Refresh(id) => {
+                        // Like an update request, so that the error policy of the block applies
+                        // and its next update is scheduled
+                        if let Some(block) = blocks.get_mut(id) {
+                            let update = block.update()?;
+                            scheduler.reschedule(id, update);
+                        }
+                    },
+                    IpcCommand::Hide(id) => {
+                        if let Some(block) = blocks.get_mut(id) {
+                            block.toggle_hidden();
+                        }
+                    },
+                    IpcCommand::ToggleFormat(id) => {
+                        if let Some(block) = blocks.get_mut(id) {
+                            block.toggle_format()?;
+                        }
+                    },
+                    IpcCommand::Reload => reload_requested = true,
+                };
+                util::print_blocks(&blocks, &shared_config)?;
+            },
+            // Receive config file changes
+            recv(rx_reload) -> res => if res.is_ok() {
+                reload_requested = true;
+            },
             // Receive signal events
             recv(rx_signals) -> res => if let Ok(sig) = res {
                 match sig {
@@ -226,9 +326,12 @@ fn run(matches: &ArgMatches) -> Result<()> {
                         util::print_blocks(&blocks, &shared_config)?;
                     },
                     signal_hook::consts::SIGUSR2 => {
-                        //USR2 signal that should reload the config
-                        //TODO not implemented
-                        //unimplemented!("SIGUSR2 is meant to be used to reload the config toml, but this feature is yet not implemented");
+                        //USR2 signal that reloads the config
+                        reload_requested = true;
+                    },
+                    signal_hook::consts::SIGINT | signal_hook::consts::SIGTERM => {
+                        // Return, so that the IPC socket is removed
+                        quit_requested = true;
                     },
                     _ => {
                         //Real time signal that updates only the blocks listening
@@ -236,11 +339,32 @@ fn run(matches: &ArgMatches) -> Result<()> {
                         for block in blocks.iter_mut() {
                             block.signal(sig)?;
                         }
+                        util::print_blocks(&blocks, &shared_config)?;
                     },
                 };
             }
         }
 
+        if quit_requested {
+            break Ok(());
+        }
+
+        // The blocks and their update request channel are replaced here, outside of `select!`,
+        // which borrows the channels it waits on
+        if reload_requested {
+            reload_config(
+                &config_path,
+                bar,
+                output,
+                &mut blocks,
+                &mut block_configs,
+                &mut shared_config,
+                &mut rx_update_requests,
+                &mut scheduler,
+            );
+            util::print_blocks(&blocks, &shared_config)?;
+        }
+
         // Set the time-to-next-update timer
         if let Some(time) = scheduler.time_to_next_update() {
             ttnu = crossbeam_channel::after(time)
@@ -251,6 +375,129 @@ fn run(matches: &ArgMatches) -> Result<()> {
     }
 }
 
+/// Creates the blocks of `config` which are shown on `output`, along with their configs and a
+/// fresh channel for their update requests.
+#[allow(clippy::type_complexity)]
+fn init_blocks(
+    config: &Config,
+    output: Option<&str>,
+) -> Result<(
+    Vec<Box<dyn Block>>,
+    Vec<(String, toml::Value)>,
+    SharedConfig,
+    Receiver<Task>,
+)> {
+    let (tx_update_requests, rx_update_requests): (Sender<Task>, Receiver<Task>) =
+        crossbeam_channel::unbounded();
+
+    let shared_config = SharedConfig::new(config);
+    formatting::set_locale(config.locale);
+
+    let mut blocks: Vec<Box<dyn Block>> = Vec::new();
+    let mut block_configs = Vec::new();
+    for &(ref block_name, ref block_config) in &config.blocks {
+        if !shown_on_output(block_config, output)? {
+            continue;
+        }
+        blocks.push(create_block(
+            blocks.len(),
+            block_name,
+            block_config.clone(),
+            shared_config.clone(),
+            tx_update_requests.clone(),
+        )?);
+        block_configs.push((block_name.clone(), block_config.clone()));
+    }
+
+    Ok((blocks, block_configs, shared_config, rx_update_requests))
+}
+
+/// Replaces the running blocks with the ones from the config file. If the new config is invalid,
+/// the current blocks are kept and the error is shown for a while at the end of the bar.
+///
+/// The update request channel is replaced as well, so that requests of the old blocks can never
+/// reach the new ones, and the threads of the old blocks stop once their requests fail. Blocks
+/// are recreated from scratch, but a new block with the same config as an old one keeps the state
+/// the user gave it, e.g. whether it is hidden.
+#[allow(clippy::too_many_arguments)]
+fn reload_config(
+    config_path: &Path,
+    bar: Option<&str>,
+    output: Option<&str>,
+    blocks: &mut Vec<Box<dyn Block>>,
+    block_configs: &mut Vec<(String, toml::Value)>,
+    shared_config: &mut SharedConfig,
+    rx_update_requests: &mut Receiver<Task>,
+    scheduler: &mut UpdateScheduler,
+) {
+    match Config::load(config_path, bar).and_then(|config| init_blocks(&config, output)) {
+        Ok((mut new_blocks, new_block_configs, new_shared_config, new_rx_update_requests)) => {
+            // Each old block gives its state to at most one new block
+            let mut states: Vec<_> = block_configs
+                .iter()
+                .zip(blocks.iter())
+                .map(|(config, block)| Some((config, block.state())))
+                .collect();
+            for (config, block) in new_block_configs.iter().zip(new_blocks.iter_mut()) {
+                let old = states
+                    .iter_mut()
+                    .find(|old| matches!(old, Some((old_config, _)) if *old_config == config));
+                if let Some((_, state)) = old.and_then(Option::take) {
+                    block.restore_state(state);
+                }
+            }
+
+            *blocks = new_blocks;
+            *block_configs = new_block_configs;
+            *shared_config = new_shared_config;
+            *rx_update_requests = new_rx_update_requests;
+            *scheduler = UpdateScheduler::new(blocks);
+        }
+        Err(error) => {
+            formatting::set_locale(shared_config.locale);
+            // Replace the error of a previous reload, which is always the last block
+            blocks.truncate(block_configs.len());
+            let id = blocks.len();
+            let error_block = ReloadError::new(id, &error, shared_config.clone());
+            scheduler.schedule(Task {
+                id,
+                update_time: error_block.expires(),
+            });
+            blocks.push(Box::new(error_block));
+        }
+    }
+}
+
+/// Checks the config of every block without starting it, printing all errors along with the file
+/// and line of the offending block. Returns whether the config is valid.
+fn check_config(config_path: &Path, bar: Option<&str>) -> bool {
+    let config = match Config::load(config_path, bar) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}: {:?}", config_path.display(), error);
+            return false;
+        }
+    };
+
+    let shared_config = SharedConfig::new(&config);
+    let mut valid = true;
+    for (id, &(ref block_name, ref block_config)) in config.blocks.iter().enumerate() {
+        if let Err(error) = check_block(block_name, block_config.clone(), shared_config.clone()) {
+            valid = false;
+            match config.block_sources.get(id) {
+                Some(Some(source)) => eprint!("{}: ", source),
+                _ => eprint!("{}: ", config_path.display()),
+            }
+            eprintln!("block #{} ({}): {:?}", id, block_name, error);
+        }
+    }
+
+    if valid {
+        println!("{}: OK", config_path.display());
+    }
+    valid
+}
+
 #[cfg(feature = "profiling")]
 fn profile(iterations: i32, name: &str, block: &mut dyn Block) {
     let mut bar = progress::Bar::new();
@@ -277,11 +524,13 @@ fn profile(iterations: i32, name: &str, block: &mut dyn Block) {
 }
 
 #[cfg(feature = "profiling")]
-fn profile_config(name: &str, runs: &str, config: &Config, update: Sender<Task>) -> Result<()> {
+fn profile_config(name: &str, runs: &str, config: &Config) -> Result<()> {
     let profile_runs = runs
         .parse::<i32>()
         .configuration_error("failed to parse --profile-runs as an integer")?;
     let shared_config = SharedConfig::new(&config);
+    formatting::set_locale(config.locale);
+    let (update, _) = crossbeam_channel::unbounded();
     for &(ref block_name, ref block_config) in &config.blocks {
         if block_name == name {
             let mut block =
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::Sender;
+use dbus::ffidisp::{BusType, Connection};
+use inotify::{Inotify, WatchDescriptor, WatchMask};
+
+use crate::blocks::Block;
+use crate::config::{self, SharedConfig};
+use crate::errors::*;
+use crate::themes::{ColorScheme, ColorSchemeSchedule, SETTING_CHANGED_RULE};
+use crate::util;
+use crate::widgets::text::TextWidget;
+use crate::widgets::{I3BarWidget, State};
+
+/// How long the error of a failed reload stays on the bar.
+pub const RELOAD_ERROR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Starts a thread that watches the config file, as well as any theme or icon file it includes,
+/// and sends a message on the provided channel whenever one of them changes. Theme and icon files
+/// set in the `[bar.<name>]` section selected by `bar` are watched too.
+pub fn watch_config(config_path: PathBuf, bar: Option<String>, sender: Sender<()>) {
+    thread::Builder::new()
+        .name("config_watcher".into())
+        .spawn(move || {
+            let mut buffer = [0; 1024];
+            loop {
+                // The set of included files may change along with the config, so the watches are
+                // rebuilt after every change.
+                let mut notify = Inotify::init().expect("failed to start inotify");
+                let mut watched: HashMap<WatchDescriptor, Vec<OsString>> = HashMap::new();
+                for file in watched_files(&config_path, bar.as_deref()) {
+                    let (dir, name) = match (file.parent(), file.file_name()) {
+                        (Some(dir), Some(name)) => (dir, name),
+                        _ => continue,
+                    };
+                    let dir = if dir.as_os_str().is_empty() {
+                        Path::new(".")
+                    } else {
+                        dir
+                    };
+                    // Most editors replace the file rather than write to it, so we have to watch
+                    // the parent directory.
+                    if let Ok(wd) =
+                        notify.add_watch(dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)
+                    {
+                        watched.entry(wd).or_default().push(name.to_owned());
+                    }
+                }
+
+                loop {
+                    let mut events = notify
+                        .read_events_blocking(&mut buffer)
+                        .expect("error while reading inotify events");
+                    let changed = events.any(|event| match (watched.get(&event.wd), event.name) {
+                        (Some(names), Some(name)) => names.iter().any(|n| n.as_os_str() == name),
+                        _ => false,
+                    });
+                    if changed {
+                        break;
+                    }
+                }
+
+                sender
+                    .send(())
+                    .expect("could not communicate with channel in config watcher");
+            }
+        })
+        .unwrap();
+}
+
+/// How often the time of day is checked against the `light_from` and `dark_from` options.
+const COLOR_SCHEME_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Starts a thread that sends a message on the provided channel whenever the theme selected by
+/// the `light` and `dark` options of the `[theme]` section changes, so that the config is
+/// reloaded with the other theme. The desktop settings portal is watched for changes of its
+/// color scheme, and the time of day is checked every minute in case it has no preference.
+pub fn watch_color_scheme(config_path: PathBuf, bar: Option<String>, sender: Sender<()>) {
+    thread::Builder::new()
+        .name("color_scheme_watcher".into())
+        .spawn(move || {
+            // Without a session bus, only the time of day can be followed
+            let con = Connection::get_private(BusType::Session).ok();
+            if let Some(ref con) = con {
+                con.add_match(SETTING_CHANGED_RULE).ok();
+            }
+
+            let mut current = color_scheme(&config_path, bar.as_deref());
+            loop {
+                match con {
+                    Some(ref con) => {
+                        con.incoming(COLOR_SCHEME_INTERVAL.as_millis() as u32)
+                            .next();
+                    }
+                    None => thread::sleep(COLOR_SCHEME_INTERVAL),
+                }
+
+                // Enabling or disabling the option is a change of the config file, which is
+                // reloaded anyway
+                let scheme = color_scheme(&config_path, bar.as_deref());
+                if current.is_some() && scheme.is_some() && scheme != current {
+                    sender
+                        .send(())
+                        .expect("could not communicate with channel in color scheme watcher");
+                }
+                current = scheme;
+            }
+        })
+        .unwrap();
+}
+
+/// The color scheme to use, if the config switches between a light and a dark theme.
+fn color_scheme(config_path: &Path, bar: Option<&str>) -> Option<ColorScheme> {
+    let (mut config, _) = config::load_value(config_path).ok()?;
+    config::select_bar(&mut config, bar).ok()?;
+    let schedule = ColorSchemeSchedule::from_table(config.get("theme")?)?;
+    Some(schedule.current())
+}
+
+/// The config file itself, the files it includes and the theme and icon files it refers to.
+fn watched_files(config_path: &Path, bar: Option<&str>) -> Vec<PathBuf> {
+    let mut files = vec![config_path.to_path_buf()];
+    if let Ok((mut config, included)) = config::load_value(config_path) {
+        files.extend(included);
+        config::select_bar(&mut config, bar).ok();
+        for &(key, subdir) in &[("theme", "themes"), ("icons", "icons")] {
+            // Names such as `theme = "~/my-theme.toml"` may refer to files as well
+            let names: Vec<&str> = match config.get(key) {
+                Some(value) if value.is_str() => value.as_str().into_iter().collect(),
+                Some(table) => ["name", "light", "dark"]
+                    .iter()
+                    .filter_map(|&field| table.get(field).and_then(|name| name.as_str()))
+                    .collect(),
+                None => Vec::new(),
+            };
+            let file = config
+                .get(key)
+                .and_then(|table| table.get("file"))
+                .and_then(|file| file.as_str());
+            for file in names
+                .into_iter()
+                .filter(|name| util::is_file_path(name))
+                .chain(file)
+            {
+                if let Some(path) = util::find_file(file, Some(subdir), Some(".toml")) {
+                    files.push(path);
+                }
+            }
+        }
+    }
+    files
+}
+
+/// A block displaying why the config could not be reloaded. It hides itself after
+/// `RELOAD_ERROR_TIMEOUT`, while the bar keeps running with the previous config.
+pub struct ReloadError {
+    id: usize,
+    text: TextWidget,
+    expires: Instant,
+}
+
+impl ReloadError {
+    pub fn new(id: usize, error: &Error, shared_config: SharedConfig) -> Self {
+        ReloadError {
+            id,
+            text: TextWidget::new(id, 0, shared_config)
+                .with_state(State::Critical)
+                .with_text(&format!("Failed to reload config: {}", error)),
+            expires: Instant::now() + RELOAD_ERROR_TIMEOUT,
+        }
+    }
+
+    pub fn expires(&self) -> Instant {
+        self.expires
+    }
+}
+
+impl Block for ReloadError {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn view(&self) -> Vec<&dyn I3BarWidget> {
+        if Instant::now() < self.expires {
+            vec![&self.text]
+        } else {
+            vec![]
+        }
+    }
+}
@@ -2,7 +2,6 @@ use crate::blocks::Update;
 use std::cmp;
 use std::collections::BinaryHeap;
 use std::fmt;
-use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::blocks::Block;
@@ -59,6 +58,23 @@ impl UpdateScheduler {
         UpdateScheduler { schedule }
     }
 
+    pub fn schedule(&mut self, task: Task) {
+        self.schedule.push(task);
+    }
+
+    /// Schedules the next update asked for by an update outside of the schedule, such as one
+    /// requested by the block itself, unless the block already has an update scheduled.
+    pub fn reschedule(&mut self, id: usize, update: Option<Update>) {
+        if let Some(Update::Every(d)) = update {
+            if !self.schedule.iter().any(|task| task.id == id) {
+                self.schedule.push(Task {
+                    id,
+                    update_time: Instant::now() + d,
+                });
+            }
+        }
+    }
+
     pub fn time_to_next_update(&self) -> Option<Duration> {
         if let Some(peeked) = self.schedule.peek() {
             let next_update = peeked.update_time;
@@ -96,13 +112,15 @@ impl UpdateScheduler {
             )
         }
 
+        // The main loop multiplexes clicks, signals and update requests on a single thread,
+        // so never block it here: if the timer fired early, requeue the tasks and let the
+        // caller wait on `time_to_next_update()` again.
         let now = Instant::now();
         if t.update_time > now {
-            thread::sleep(t.update_time - now);
+            self.schedule.extend(tasks_next);
+            return Ok(());
         }
 
-        let now = Instant::now();
-
         for task in tasks_next {
             if let Some(dur) = blocks
                 .get_mut(task.id as usize)
@@ -17,9 +17,18 @@ pub fn process_signals(sender: Sender<i32>) {
                 let mut signals = (sigmin..sigmax).collect::<Vec<_>>();
                 signals.push(signal_hook::consts::SIGUSR1);
                 signals.push(signal_hook::consts::SIGUSR2);
+                signals.push(signal_hook::consts::SIGINT);
+                signals.push(signal_hook::consts::SIGTERM);
                 let mut signals = signal_hook::iterator::Signals::new(&signals).unwrap();
                 for sig in signals.forever() {
-                    sender.send(sig).unwrap();
+                    // Once the bar stopped, e.g. to keep showing an error, nothing handles the
+                    // signals anymore, so they have to terminate the process by themselves
+                    if sender.send(sig).is_err()
+                        && (sig == signal_hook::consts::SIGINT
+                            || sig == signal_hook::consts::SIGTERM)
+                    {
+                        let _ = signal_hook::low_level::emulate_default_handler(sig);
+                    }
                 }
             }
         })
@@ -1,12 +1,17 @@
 use std::default::Default;
 use std::fmt;
 
+use chrono::{Local, NaiveTime};
+use dbus::arg::{RefArg, Variant};
+use dbus::ffidisp::{BusType, Connection};
+use dbus::Message;
 use lazy_static::lazy_static;
 use serde_derive::Deserialize;
 
 use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
 
 use crate::util;
+use crate::widgets::State;
 
 lazy_static! {
     pub static ref SLICK: Theme = Theme {
@@ -26,6 +31,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: Some(String::from("#111111")),
         alternating_tint_fg: Some(String::from("#111111")),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref SOLARIZED_DARK: Theme = Theme {
@@ -45,6 +58,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref SOLARIZED_LIGHT: Theme = Theme {
@@ -64,6 +85,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref MODERN: Theme = Theme {
@@ -83,6 +112,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref PLAIN: Theme = Theme {
@@ -102,6 +139,14 @@ lazy_static! {
         separator_fg: Some(String::from("#a9a9a9")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref BAD_WOLF: Theme = Theme {
@@ -121,6 +166,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref GRUVBOX_LIGHT: Theme = Theme {
@@ -140,6 +193,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref GRUVBOX_DARK: Theme = Theme {
@@ -159,6 +220,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref SPACE_VILLAIN: Theme = Theme {
@@ -178,6 +247,14 @@ lazy_static! {
         separator_fg: Some(String::from("auto")),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref SEMI_NATIVE: Theme = Theme {
@@ -197,6 +274,14 @@ lazy_static! {
         separator_fg: None.to_owned(),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
     pub static ref NATIVE: Theme = Theme {
@@ -216,6 +301,14 @@ lazy_static! {
         separator_fg: None.to_owned(),
         alternating_tint_bg: None.to_owned(),
         alternating_tint_fg: None.to_owned(),
+        gradient: false,
+        powerline: false,
+        idle_border: None,
+        info_border: None,
+        good_border: None,
+        warning_border: None,
+        critical_border: None,
+        border_width: None,
     };
 
 }
@@ -238,6 +331,19 @@ pub struct Theme {
     pub separator_fg: Option<String>,
     pub alternating_tint_bg: Option<String>,
     pub alternating_tint_fg: Option<String>,
+    /// Interpolate the colors of blocks that report how severe their value is, from good to
+    /// warning to critical, rather than switching between them
+    pub gradient: bool,
+    /// Color the separators like powerline arrows, with the background of the block before the
+    /// separator and the foreground taken from the background of the block after it
+    pub powerline: bool,
+    pub idle_border: Option<String>,
+    pub info_border: Option<String>,
+    pub good_border: Option<String>,
+    pub warning_border: Option<String>,
+    pub critical_border: Option<String>,
+    /// Width in pixels of each side of the borders, 1 if unset
+    pub border_width: Option<usize>,
 }
 
 impl Default for Theme {
@@ -264,6 +370,16 @@ pub struct ThemeFromFile {
     pub separator_fg: Option<String>,
     pub alternating_tint_bg: Option<String>,
     pub alternating_tint_fg: Option<String>,
+    #[serde(default)]
+    pub gradient: bool,
+    #[serde(default)]
+    pub powerline: bool,
+    pub idle_border: Option<String>,
+    pub info_border: Option<String>,
+    pub good_border: Option<String>,
+    pub warning_border: Option<String>,
+    pub critical_border: Option<String>,
+    pub border_width: Option<usize>,
 }
 
 impl Into<Theme> for ThemeFromFile {
@@ -285,6 +401,14 @@ impl Into<Theme> for ThemeFromFile {
             separator_fg: self.separator_fg,
             alternating_tint_bg: self.alternating_tint_bg,
             alternating_tint_fg: self.alternating_tint_fg,
+            gradient: self.gradient,
+            powerline: self.powerline,
+            idle_border: self.idle_border,
+            info_border: self.info_border,
+            good_border: self.good_border,
+            warning_border: self.warning_border,
+            critical_border: self.critical_border,
+            border_width: self.border_width,
         }
     }
 }
@@ -307,10 +431,173 @@ impl Theme {
         }
     }
 
+    /// Looks up a built-in theme, or loads the theme file `name` refers to if it is a path.
+    pub fn from_name_or_file(name: &str) -> Option<Theme> {
+        Theme::from_name(name).or_else(|| {
+            if util::is_file_path(name) {
+                Theme::from_file(name)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The background and foreground colors for a value of the given severity, from 0 (good) to
+    /// 1 (critical), blended from the colors of the good, warning and critical states.
+    pub fn gradient_colors(&self, severity: f64) -> (Option<String>, Option<String>) {
+        let severity = severity.clamp(0., 1.);
+        let (from, to, t) = if severity < 0.5 {
+            (State::Good, State::Warning, severity * 2.)
+        } else {
+            (State::Warning, State::Critical, severity * 2. - 1.)
+        };
+        let (from_bg, from_fg) = from.theme_keys(self);
+        let (to_bg, to_fg) = to.theme_keys(self);
+        (
+            util::mix_colors(from_bg.as_deref(), to_bg.as_deref(), t),
+            util::mix_colors(from_fg.as_deref(), to_fg.as_deref(), t),
+        )
+    }
+
     pub fn from_file(file: &str) -> Option<Theme> {
         let file = util::find_file(file, Some("themes"), Some(".toml"))?;
         let theme: ThemeFromFile = util::deserialize_file(&file).ok()?;
-        Some(theme.into())
+        let mut theme: Theme = theme.into();
+        theme.resolve_colors().ok()?;
+        Some(theme)
+    }
+
+    /// Converts the colors given in another notation than hex, see `util::parse_color`.
+    fn resolve_colors(&mut self) -> Result<(), String> {
+        for color in [
+            &mut self.idle_bg,
+            &mut self.idle_fg,
+            &mut self.info_bg,
+            &mut self.info_fg,
+            &mut self.good_bg,
+            &mut self.good_fg,
+            &mut self.warning_bg,
+            &mut self.warning_fg,
+            &mut self.critical_bg,
+            &mut self.critical_fg,
+            &mut self.separator_bg,
+            &mut self.separator_fg,
+            &mut self.alternating_tint_bg,
+            &mut self.alternating_tint_fg,
+            &mut self.idle_border,
+            &mut self.info_border,
+            &mut self.good_border,
+            &mut self.warning_border,
+            &mut self.critical_border,
+        ]
+        .iter_mut()
+        {
+            if let Some(color) = color.as_mut() {
+                *color = util::parse_color(color)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which of the `light` and `dark` themes of the `[theme]` section is in use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// Rule of the match for changes of the desktop settings, see `ColorSchemeSchedule::current`.
+pub const SETTING_CHANGED_RULE: &str = "type='signal',\
+     path='/org/freedesktop/portal/desktop',\
+     interface='org.freedesktop.portal.Settings',\
+     member='SettingChanged'";
+
+/// When to switch between the `light` and `dark` themes if the desktop has no preference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorSchemeSchedule {
+    pub light_from: NaiveTime,
+    pub dark_from: NaiveTime,
+}
+
+impl Default for ColorSchemeSchedule {
+    fn default() -> Self {
+        ColorSchemeSchedule {
+            light_from: NaiveTime::from_hms(7, 0, 0),
+            dark_from: NaiveTime::from_hms(19, 0, 0),
+        }
+    }
+}
+
+impl ColorSchemeSchedule {
+    /// Reads the schedule from a raw `[theme]` section, if it sets both `light` and `dark`.
+    pub fn from_table(table: &toml::Value) -> Option<Self> {
+        if table.get("light").is_none() || table.get("dark").is_none() {
+            return None;
+        }
+        let default = Self::default();
+        let time = |key| {
+            table
+                .get(key)
+                .and_then(|time| time.as_str())
+                .and_then(parse_time_of_day)
+        };
+        Some(ColorSchemeSchedule {
+            light_from: time("light_from").unwrap_or(default.light_from),
+            dark_from: time("dark_from").unwrap_or(default.dark_from),
+        })
+    }
+
+    /// The scheme preferred by the desktop, as set by the `color-scheme` key of the
+    /// freedesktop settings portal, or else the one for the current time of day.
+    pub fn current(&self) -> ColorScheme {
+        portal_color_scheme().unwrap_or_else(|| self.at(Local::now().time()))
+    }
+
+    fn at(&self, time: NaiveTime) -> ColorScheme {
+        let light = if self.light_from <= self.dark_from {
+            time >= self.light_from && time < self.dark_from
+        } else {
+            time >= self.light_from || time < self.dark_from
+        };
+        if light {
+            ColorScheme::Light
+        } else {
+            ColorScheme::Dark
+        }
+    }
+}
+
+/// Parses a time of day like `"19:30"`.
+fn parse_time_of_day(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+/// Reads `org.freedesktop.appearance color-scheme` from the settings portal. Returns `None` if
+/// the portal is not running or the desktop has no preference.
+fn portal_color_scheme() -> Option<ColorScheme> {
+    let c = Connection::get_private(BusType::Session).ok()?;
+    let m = Message::new_method_call(
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Settings",
+        "Read",
+    )
+    .ok()?
+    .append2("org.freedesktop.appearance", "color-scheme");
+    let r = c.send_with_reply_and_block(m, 1000).ok()?;
+    let value: Variant<Box<dyn RefArg>> = r.get1()?;
+
+    // Older versions of the portal wrap the value in a second variant
+    fn unwrap_u64(value: &dyn RefArg) -> Option<u64> {
+        value
+            .as_u64()
+            .or_else(|| value.as_iter()?.next().and_then(unwrap_u64))
+    }
+    match unwrap_u64(&value)? {
+        1 => Some(ColorScheme::Dark),
+        2 => Some(ColorScheme::Light),
+        _ => None,
     }
 }
 
@@ -332,6 +619,12 @@ pub struct ThemeOverrides {
     separator_fg: Option<String>,
     alternating_tint_bg: Option<String>,
     alternating_tint_fg: Option<String>,
+    idle_border: Option<String>,
+    info_border: Option<String>,
+    good_border: Option<String>,
+    warning_border: Option<String>,
+    critical_border: Option<String>,
+    border_width: Option<usize>,
 }
 
 impl<'de> Deserialize<'de> for Theme {
@@ -340,11 +633,17 @@ impl<'de> Deserialize<'de> for Theme {
         D: Deserializer<'de>,
     {
         #[derive(Deserialize)]
-        #[serde(field_identifier, rename_all = "lowercase")]
+        #[serde(field_identifier, rename_all = "snake_case")]
         enum Field {
             Name,
             File,
             Overrides,
+            Gradient,
+            Powerline,
+            Light,
+            Dark,
+            LightFrom,
+            DarkFrom,
         }
 
         struct ThemeVisitor;
@@ -365,7 +664,7 @@ impl<'de> Deserialize<'de> for Theme {
             where
                 E: de::Error,
             {
-                Theme::from_name(name)
+                Theme::from_name_or_file(name)
                     .ok_or_else(|| de::Error::custom(format!("Theme \"{}\" not found.", name)))
             }
 
@@ -381,14 +680,19 @@ impl<'de> Deserialize<'de> for Theme {
             {
                 let mut theme = None;
                 let mut overrides: Option<ThemeOverrides> = None;
+                let mut gradient = None;
+                let mut powerline = None;
+                let mut light = None;
+                let mut dark = None;
+                let mut schedule = ColorSchemeSchedule::default();
                 while let Some(key) = map.next_key()? {
                     match key {
                         Field::Name => {
                             if theme.is_some() {
                                 return Err(de::Error::duplicate_field("name or file"));
                             }
-                            let name = map.next_value()?;
-                            theme = Some(Theme::from_name(name).ok_or_else(|| {
+                            let name: String = map.next_value()?;
+                            theme = Some(Theme::from_name_or_file(&name).ok_or_else(|| {
                                 de::Error::custom(format!("Theme \"{}\" not found.", name))
                             })?);
                         }
@@ -396,8 +700,8 @@ impl<'de> Deserialize<'de> for Theme {
                             if theme.is_some() {
                                 return Err(de::Error::duplicate_field("name or file"));
                             }
-                            let file = map.next_value()?;
-                            theme = Some(Theme::from_file(file).ok_or_else(|| {
+                            let file: String = map.next_value()?;
+                            theme = Some(Theme::from_file(&file).ok_or_else(|| {
                                 de::Error::custom(format!(
                                     "Failed to load theme from file {}.",
                                     file
@@ -410,9 +714,69 @@ impl<'de> Deserialize<'de> for Theme {
                             }
                             overrides = Some(map.next_value()?);
                         }
+                        Field::Gradient => {
+                            if gradient.is_some() {
+                                return Err(de::Error::duplicate_field("gradient"));
+                            }
+                            gradient = Some(map.next_value()?);
+                        }
+                        Field::Powerline => {
+                            if powerline.is_some() {
+                                return Err(de::Error::duplicate_field("powerline"));
+                            }
+                            powerline = Some(map.next_value()?);
+                        }
+                        Field::Light | Field::Dark => {
+                            let (slot, field) = match key {
+                                Field::Light => (&mut light, "light"),
+                                _ => (&mut dark, "dark"),
+                            };
+                            if slot.is_some() {
+                                return Err(de::Error::duplicate_field(field));
+                            }
+                            let name: String = map.next_value()?;
+                            *slot = Some(Theme::from_name_or_file(&name).ok_or_else(|| {
+                                de::Error::custom(format!("Theme \"{}\" not found.", name))
+                            })?);
+                        }
+                        Field::LightFrom | Field::DarkFrom => {
+                            let time: String = map.next_value()?;
+                            let time = parse_time_of_day(&time).ok_or_else(|| {
+                                de::Error::custom(format!(
+                                    "Invalid time of day \"{}\", expected \"HH:MM\".",
+                                    time
+                                ))
+                            })?;
+                            match key {
+                                Field::LightFrom => schedule.light_from = time,
+                                _ => schedule.dark_from = time,
+                            }
+                        }
+                    }
+                }
+                let mut theme = match (theme, light, dark) {
+                    (theme, None, None) => theme.unwrap_or_default(),
+                    (None, Some(light), Some(dark)) => match schedule.current() {
+                        ColorScheme::Light => light,
+                        ColorScheme::Dark => dark,
+                    },
+                    (Some(_), _, _) => {
+                        return Err(de::Error::custom(
+                            "`light` and `dark` can't be combined with `name` or `file`.",
+                        ))
+                    }
+                    _ => {
+                        return Err(de::Error::custom(
+                            "`light` and `dark` have to be set together.",
+                        ))
                     }
+                };
+                if let Some(gradient) = gradient {
+                    theme.gradient = gradient;
+                }
+                if let Some(powerline) = powerline {
+                    theme.powerline = powerline;
                 }
-                let mut theme = theme.unwrap_or_default();
                 if let Some(overrides) = overrides {
                     theme.idle_bg = overrides.idle_bg.or(theme.idle_bg);
                     theme.idle_fg = overrides.idle_fg.or(theme.idle_fg);
@@ -431,7 +795,14 @@ impl<'de> Deserialize<'de> for Theme {
                         overrides.alternating_tint_bg.or(theme.alternating_tint_bg);
                     theme.alternating_tint_fg =
                         overrides.alternating_tint_fg.or(theme.alternating_tint_fg);
+                    theme.idle_border = overrides.idle_border.or(theme.idle_border);
+                    theme.info_border = overrides.info_border.or(theme.info_border);
+                    theme.good_border = overrides.good_border.or(theme.good_border);
+                    theme.warning_border = overrides.warning_border.or(theme.warning_border);
+                    theme.critical_border = overrides.critical_border.or(theme.critical_border);
+                    theme.border_width = overrides.border_width.or(theme.border_width);
                 }
+                theme.resolve_colors().map_err(de::Error::custom)?;
                 Ok(theme)
             }
         }
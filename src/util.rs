@@ -1,21 +1,27 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
 use std::io::BufReader;
+use std::iter::Peekable;
 use std::path::{Path, PathBuf};
 use std::prelude::v1::String;
-use std::process::Command;
+use std::process::{Command, Stdio};
+use std::str::Chars;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use regex::Regex;
 use serde::de::DeserializeOwned;
 
+use crate::blocks::base_block::Separator;
 use crate::blocks::Block;
-use crate::config::SharedConfig;
+use crate::config::{OutputFormat, SharedConfig};
 use crate::errors::*;
+use crate::formatting::{DurationFormat, Modifiers, Value};
 
 use crate::widgets::i3block_data::I3BlockData;
+use crate::widgets::State;
 
 pub const USR_SHARE_PATH: &str = "/usr/share/i3status-rust";
 
@@ -33,29 +39,29 @@ pub fn find_file(file: &str, subdir: Option<&str>, extension: Option<&str>) -> O
         }
     }
 
-    let full_path = PathBuf::from(&file);
-
-    let mut xdg_path = xdg_config_home().join("i3status-rust");
-    if let Some(subdir) = subdir {
-        xdg_path = xdg_path.join(subdir);
+    let full_path = match file.strip_prefix("~/") {
+        Some(rest) => PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(rest),
+        None => PathBuf::from(&file),
+    };
+    if full_path.exists() {
+        return Some(full_path);
     }
-    xdg_path = xdg_path.join(&file);
 
-    let mut share_path = PathBuf::from(USR_SHARE_PATH);
-    if let Some(subdir) = subdir {
-        share_path = share_path.join(subdir);
-    }
-    share_path = share_path.join(&file);
+    [xdg_config_home(), xdg_data_home()]
+        .iter()
+        .map(|dir| dir.join("i3status-rust"))
+        .chain(std::iter::once(PathBuf::from(USR_SHARE_PATH)))
+        .map(|dir| match subdir {
+            Some(subdir) => dir.join(subdir).join(&file),
+            None => dir.join(&file),
+        })
+        .find(|path| path.exists())
+}
 
-    if full_path.exists() {
-        Some(full_path)
-    } else if xdg_path.exists() {
-        Some(xdg_path)
-    } else if share_path.exists() {
-        Some(share_path)
-    } else {
-        None
-    }
+/// Whether the name of a theme or an icon set is the path of a file rather than a built-in one,
+/// as in `icons = "~/my-icons.toml"`.
+pub fn is_file_path(name: &str) -> bool {
+    name.contains('/') || name.ends_with(".toml")
 }
 
 pub fn escape_pango_text(text: String) -> String {
@@ -70,48 +76,6 @@ pub fn escape_pango_text(text: String) -> String {
         .collect()
 }
 
-/// Format `raw_value` to engineering notation
-pub fn format_number(raw_value: f64, total_digits: usize, min_suffix: &str, unit: &str) -> String {
-    let min_exp_level = match min_suffix {
-        "T" => 4,
-        "G" => 3,
-        "M" => 2,
-        "K" => 1,
-        "1" => 0,
-        "m" => -1,
-        "u" => -2,
-        "n" => -3,
-        _ => -4,
-    };
-
-    let exp_level = (raw_value.log10().div_euclid(3.) as i32).clamp(min_exp_level, 4);
-    let value = raw_value / (10f64).powi(exp_level * 3);
-
-    let suffix = match exp_level {
-        4 => "T",
-        3 => "G",
-        2 => "M",
-        1 => "K",
-        0 => "",
-        -1 => "m",
-        -2 => "u",
-        -3 => "n",
-        _ => "p",
-    };
-
-    let total_digits = total_digits as isize;
-    let decimals = (if value >= 100. {
-        total_digits - 3
-    } else if value >= 10. {
-        total_digits - 2
-    } else {
-        total_digits - 1
-    })
-    .max(0);
-
-    format!("{:.*}{}{}", decimals as usize, value, suffix, unit)
-}
-
 pub fn battery_level_to_icon(charge_level: Result<u64>) -> &'static str {
     match charge_level {
         Ok(0..=5) => "bat_empty",
@@ -132,6 +96,14 @@ pub fn xdg_config_home() -> PathBuf {
     PathBuf::from(&config_path)
 }
 
+pub fn xdg_data_home() -> PathBuf {
+    let data_path = std::env::var("XDG_DATA_HOME").unwrap_or(format!(
+        "{}/.local/share",
+        std::env::var("HOME").unwrap_or_else(|_| "".to_string())
+    ));
+    PathBuf::from(&data_path)
+}
+
 pub fn deserialize_file<T>(path: &Path) -> Result<T>
 where
     T: DeserializeOwned,
@@ -161,6 +133,47 @@ pub fn read_file(blockname: &str, path: &Path) -> Result<String> {
     Ok(content)
 }
 
+/// Writes `value` to the sysfs files at `paths`. Those are usually only writable by root, so
+/// unless they are writable the value is written by running `<helper> tee` instead, with e.g.
+/// `pkexec` as the helper.
+pub fn write_sysfs(block_name: &str, paths: &[PathBuf], value: &str, helper: &str) -> Result<()> {
+    let writable: Vec<File> = paths
+        .iter()
+        .filter_map(|path| OpenOptions::new().write(true).open(path).ok())
+        .collect();
+    if writable.len() == paths.len() {
+        for mut file in writable {
+            file.write_all(value.as_bytes())
+                .block_error(block_name, "failed to write to sysfs")?;
+        }
+        return Ok(());
+    }
+
+    let mut child = Command::new(helper)
+        .arg("tee")
+        .args(paths)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .block_error(block_name, &format!("failed to run {}", helper))?;
+    child
+        .stdin
+        .take()
+        .block_error(block_name, "failed to open the stdin of the helper")?
+        .write_all(value.as_bytes())
+        .block_error(block_name, "failed to write to the helper")?;
+    let status = child
+        .wait()
+        .block_error(block_name, "failed to wait for the helper")?;
+    if !status.success() {
+        return Err(BlockError(
+            block_name.to_string(),
+            format!("{} failed to write to sysfs", helper),
+        ));
+    }
+    Ok(())
+}
+
 pub fn has_command(block_name: &str, command: &str) -> Result<bool> {
     let exit_status = Command::new("sh")
         .args(&[
@@ -200,9 +213,13 @@ macro_rules! map_to_owned (
 );
 
 pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<()> {
+    if config.output_format == OutputFormat::Waybar {
+        return print_blocks_waybar(blocks);
+    }
+
     let mut last_bg: Option<String> = None;
 
-    let mut rendered_blocks = vec![];
+    let mut rendered_blocks: Vec<Vec<I3BlockData>> = vec![];
 
     /* To always start with the same alternating tint on the right side of the
      * bar it is easiest to calculate the number of visible blocks here and
@@ -243,27 +260,45 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
             })
             .collect::<Vec<I3BlockData>>();
 
+        if let Some(first) = rendered_widgets.first_mut() {
+            first.short_text = block.short_text(&first.full_text);
+            if let Some(min_width) = block.min_width() {
+                first.min_width = Some(min_width.clone());
+            }
+            if let Some(align) = block.align() {
+                first.align = Some(align);
+            }
+        }
+
         alternator = !alternator;
 
+        let separator = block.separator();
+
         if config.theme.native_separators == Some(true) {
             // Re-add native separator on last widget for native theme
             rendered_widgets.last_mut().unwrap().separator = None;
             rendered_widgets.last_mut().unwrap().separator_block_width = None;
-        }
 
-        // Serialize and concatenate widgets
-        let block_str = rendered_widgets
-            .iter()
-            .map(|w| w.render())
-            .collect::<Vec<String>>()
-            .join(",");
+            // Native separators are drawn after a block, so the one in front of this block
+            // belongs to the previous one. Custom glyphs are not supported by i3bar.
+            if separator == Some(&Separator::Enabled(false)) {
+                if let Some(previous) = rendered_blocks.last_mut() {
+                    if let Some(last) = previous.last_mut() {
+                        last.separator = Some(false);
+                    }
+                }
+            }
 
-        if config.theme.native_separators == Some(true) {
-            // Skip separator block for native theme
-            rendered_blocks.push(block_str.to_string());
+            rendered_blocks.push(rendered_widgets);
             continue;
         }
 
+        let separator_text = match separator {
+            Some(Separator::Enabled(false)) => None,
+            Some(Separator::Custom(text)) => Some(text.clone()),
+            _ => Some(config.theme.separator.clone()),
+        };
+
         // The first widget's BG is used to get the FG color for the current separator
         let first_bg = rendered_widgets
             .first()
@@ -272,25 +307,30 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
             .clone()
             .internal_error("util", "couldn't get background color")?;
 
-        let sep_fg = if config.theme.separator_fg == Some("auto".to_string()) {
-            Some(first_bg.to_string())
-        } else {
-            config.theme.separator_fg.clone()
-        };
-
-        // The separator's BG is the last block's last widget's BG
-        let sep_bg = if config.theme.separator_bg == Some("auto".to_string()) {
-            last_bg
-        } else {
-            config.theme.separator_bg.clone()
-        };
-
-        let mut separator = I3BlockData::default();
-        separator.full_text = config.theme.separator.clone();
-        separator.background = sep_bg;
-        separator.color = sep_fg;
-
-        rendered_blocks.push(format!("{},{}", separator.render(), block_str));
+        if let Some(separator_text) = separator_text {
+            let sep_fg = if config.theme.powerline
+                || config.theme.separator_fg == Some("auto".to_string())
+            {
+                Some(first_bg.to_string())
+            } else {
+                config.theme.separator_fg.clone()
+            };
+
+            // The separator's BG is the last block's last widget's BG
+            let sep_bg = if config.theme.powerline
+                || config.theme.separator_bg == Some("auto".to_string())
+            {
+                last_bg
+            } else {
+                config.theme.separator_bg.clone()
+            };
+
+            let mut separator = I3BlockData::default();
+            separator.full_text = separator_text;
+            separator.background = sep_bg;
+            separator.color = sep_fg;
+            rendered_widgets.insert(0, separator);
+        }
 
         // The last widget's BG is used to get the BG color for the next separator
         last_bg = Some(
@@ -301,9 +341,79 @@ pub fn print_blocks(blocks: &[Box<dyn Block>], config: &SharedConfig) -> Result<
                 .clone()
                 .internal_error("util", "couldn't get background color")?,
         );
+
+        rendered_blocks.push(rendered_widgets);
+    }
+
+    // Serialize and concatenate widgets
+    let rendered = rendered_blocks
+        .iter()
+        .flatten()
+        .map(|widget| widget.render())
+        .collect::<Vec<String>>()
+        .join(",");
+    println!("[{}],", rendered);
+
+    Ok(())
+}
+
+/// Prints all blocks as a single Waybar custom module update. Widget colors are kept through
+/// Pango markup, and the most severe widget state becomes the CSS class of the module.
+fn print_blocks_waybar(blocks: &[Box<dyn Block>]) -> Result<()> {
+    fn severity(state: State) -> u8 {
+        match state {
+            State::Idle => 0,
+            State::Info => 1,
+            State::Good => 2,
+            State::Warning => 3,
+            State::Critical => 4,
+        }
+    }
+
+    let mut texts = Vec::new();
+    let mut state = State::Idle;
+    let mut percentage = None;
+
+    for block in blocks.iter() {
+        let widgets = block.view();
+        if widgets.is_empty() {
+            continue;
+        }
+        percentage = percentage.or_else(|| block.percentage());
+
+        let mut text = String::new();
+        for widget in widgets {
+            let data = widget.get_data();
+            if let Some(widget_state) = data.state {
+                if severity(widget_state) > severity(state) {
+                    state = widget_state;
+                }
+            }
+            let mut attributes = String::new();
+            if let Some(ref color) = data.color {
+                attributes.push_str(&format!(" foreground='{}'", color));
+            }
+            if let Some(ref background) = data.background {
+                attributes.push_str(&format!(" background='{}'", background));
+            }
+            if attributes.is_empty() {
+                text.push_str(&data.full_text);
+            } else {
+                text.push_str(&format!("<span{}>{}</span>", attributes, data.full_text));
+            }
+        }
+        texts.push(text);
     }
 
-    println!("[{}],", rendered_blocks.join(","));
+    let mut output = serde_json::json!({
+        "text": texts.join(""),
+        "tooltip": texts.join("\n"),
+        "class": format!("{:?}", state).to_lowercase(),
+    });
+    if let Some(percentage) = percentage {
+        output["percentage"] = percentage.into();
+    }
+    println!("{}", output);
 
     Ok(())
 }
@@ -326,6 +436,238 @@ pub fn color_to_rgba(color: (u8, u8, u8, u8)) -> String {
     )
 }
 
+/// Converts a color of a theme to `#RRGGBBAA`. Besides hex colors, it can be given as
+/// `hsl(200, 50%, 40%)`, `hsla(200, 50%, 40%, 0.5)`, `hsv(200, 50%, 40%)` or by its CSS name.
+/// The special value `auto` of separators is kept as is.
+pub fn parse_color(color: &str) -> ::std::result::Result<String, String> {
+    let color = color.trim();
+    if color.starts_with('#') || color == "auto" {
+        return Ok(color.to_string());
+    }
+
+    let lower = color.to_lowercase();
+    if let Some(&(_, hex)) = CSS_COLORS.iter().find(|&&(name, _)| name == lower) {
+        return Ok(hex.to_string());
+    }
+
+    let invalid = || format!("invalid color \"{}\"", color);
+    let (function, args) = lower
+        .strip_suffix(')')
+        .and_then(|rest| {
+            let mut parts = rest.splitn(2, '(');
+            Some((parts.next()?.trim(), parts.next()?))
+        })
+        .ok_or_else(invalid)?;
+    let args = args
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| {
+            let (number, scale) = match arg.strip_suffix('%') {
+                Some(number) => (number, 100.),
+                None => (arg.strip_suffix("deg").unwrap_or(arg), 1.),
+            };
+            number.parse::<f64>().map(|number| number / scale)
+        })
+        .collect::<::std::result::Result<Vec<f64>, _>>()
+        .map_err(|_| invalid())?;
+
+    let (rgb, alpha) = match (function, args.as_slice()) {
+        ("hsl", &[h, s, l]) => (hsl_to_rgb(h, s, l), 1.),
+        ("hsla", &[h, s, l, a]) => (hsl_to_rgb(h, s, l), a),
+        ("hsv", &[h, s, v]) => (hsv_to_rgb(h, s, v), 1.),
+        ("hsva", &[h, s, v, a]) => (hsv_to_rgb(h, s, v), a),
+        _ => return Err(invalid()),
+    };
+    let channel = |value: f64| (value.clamp(0., 1.) * 255.).round() as u8;
+    Ok(color_to_rgba((
+        channel(rgb.0),
+        channel(rgb.1),
+        channel(rgb.2),
+        channel(alpha),
+    )))
+}
+
+/// Converts a color from hue (in degrees), saturation and lightness (from 0 to 1) to RGB
+/// components from 0 to 1.
+pub fn hsl_to_rgb(h: f64, s: f64, l: f64) -> (f64, f64, f64) {
+    let (s, l) = (s.clamp(0., 1.), l.clamp(0., 1.));
+    let v = l + s * l.min(1. - l);
+    let s_v = if v == 0. { 0. } else { 2. * (1. - l / v) };
+    hsv_to_rgb(h, s_v, v)
+}
+
+/// Converts a color from hue (in degrees), saturation and value (from 0 to 1) to RGB components
+/// from 0 to 1.
+pub fn hsv_to_rgb(h: f64, s: f64, v: f64) -> (f64, f64, f64) {
+    let (s, v) = (s.clamp(0., 1.), v.clamp(0., 1.));
+    let h = h.rem_euclid(360.) / 60.;
+    let c = v * s;
+    let x = c * (1. - (h % 2. - 1.).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.),
+        1 => (x, c, 0.),
+        2 => (0., c, x),
+        3 => (0., x, c),
+        4 => (x, 0., c),
+        _ => (c, 0., x),
+    };
+    let m = v - c;
+    (r + m, g + m, b + m)
+}
+
+/// The named colors of CSS.
+const CSS_COLORS: &[(&str, &str)] = &[
+    ("aliceblue", "#F0F8FFFF"),
+    ("antiquewhite", "#FAEBD7FF"),
+    ("aqua", "#00FFFFFF"),
+    ("aquamarine", "#7FFFD4FF"),
+    ("azure", "#F0FFFFFF"),
+    ("beige", "#F5F5DCFF"),
+    ("bisque", "#FFE4C4FF"),
+    ("black", "#000000FF"),
+    ("blanchedalmond", "#FFEBCDFF"),
+    ("blue", "#0000FFFF"),
+    ("blueviolet", "#8A2BE2FF"),
+    ("brown", "#A52A2AFF"),
+    ("burlywood", "#DEB887FF"),
+    ("cadetblue", "#5F9EA0FF"),
+    ("chartreuse", "#7FFF00FF"),
+    ("chocolate", "#D2691EFF"),
+    ("coral", "#FF7F50FF"),
+    ("cornflowerblue", "#6495EDFF"),
+    ("cornsilk", "#FFF8DCFF"),
+    ("crimson", "#DC143CFF"),
+    ("cyan", "#00FFFFFF"),
+    ("darkblue", "#00008BFF"),
+    ("darkcyan", "#008B8BFF"),
+    ("darkgoldenrod", "#B8860BFF"),
+    ("darkgray", "#A9A9A9FF"),
+    ("darkgreen", "#006400FF"),
+    ("darkgrey", "#A9A9A9FF"),
+    ("darkkhaki", "#BDB76BFF"),
+    ("darkmagenta", "#8B008BFF"),
+    ("darkolivegreen", "#556B2FFF"),
+    ("darkorange", "#FF8C00FF"),
+    ("darkorchid", "#9932CCFF"),
+    ("darkred", "#8B0000FF"),
+    ("darksalmon", "#E9967AFF"),
+    ("darkseagreen", "#8FBC8FFF"),
+    ("darkslateblue", "#483D8BFF"),
+    ("darkslategray", "#2F4F4FFF"),
+    ("darkslategrey", "#2F4F4FFF"),
+    ("darkturquoise", "#00CED1FF"),
+    ("darkviolet", "#9400D3FF"),
+    ("deeppink", "#FF1493FF"),
+    ("deepskyblue", "#00BFFFFF"),
+    ("dimgray", "#696969FF"),
+    ("dimgrey", "#696969FF"),
+    ("dodgerblue", "#1E90FFFF"),
+    ("firebrick", "#B22222FF"),
+    ("floralwhite", "#FFFAF0FF"),
+    ("forestgreen", "#228B22FF"),
+    ("fuchsia", "#FF00FFFF"),
+    ("gainsboro", "#DCDCDCFF"),
+    ("ghostwhite", "#F8F8FFFF"),
+    ("gold", "#FFD700FF"),
+    ("goldenrod", "#DAA520FF"),
+    ("gray", "#808080FF"),
+    ("green", "#008000FF"),
+    ("greenyellow", "#ADFF2FFF"),
+    ("grey", "#808080FF"),
+    ("honeydew", "#F0FFF0FF"),
+    ("hotpink", "#FF69B4FF"),
+    ("indianred", "#CD5C5CFF"),
+    ("indigo", "#4B0082FF"),
+    ("ivory", "#FFFFF0FF"),
+    ("khaki", "#F0E68CFF"),
+    ("lavender", "#E6E6FAFF"),
+    ("lavenderblush", "#FFF0F5FF"),
+    ("lawngreen", "#7CFC00FF"),
+    ("lemonchiffon", "#FFFACDFF"),
+    ("lightblue", "#ADD8E6FF"),
+    ("lightcoral", "#F08080FF"),
+    ("lightcyan", "#E0FFFFFF"),
+    ("lightgoldenrodyellow", "#FAFAD2FF"),
+    ("lightgray", "#D3D3D3FF"),
+    ("lightgreen", "#90EE90FF"),
+    ("lightgrey", "#D3D3D3FF"),
+    ("lightpink", "#FFB6C1FF"),
+    ("lightsalmon", "#FFA07AFF"),
+    ("lightseagreen", "#20B2AAFF"),
+    ("lightskyblue", "#87CEFAFF"),
+    ("lightslategray", "#778899FF"),
+    ("lightslategrey", "#778899FF"),
+    ("lightsteelblue", "#B0C4DEFF"),
+    ("lightyellow", "#FFFFE0FF"),
+    ("lime", "#00FF00FF"),
+    ("limegreen", "#32CD32FF"),
+    ("linen", "#FAF0E6FF"),
+    ("magenta", "#FF00FFFF"),
+    ("maroon", "#800000FF"),
+    ("mediumaquamarine", "#66CDAAFF"),
+    ("mediumblue", "#0000CDFF"),
+    ("mediumorchid", "#BA55D3FF"),
+    ("mediumpurple", "#9370DBFF"),
+    ("mediumseagreen", "#3CB371FF"),
+    ("mediumslateblue", "#7B68EEFF"),
+    ("mediumspringgreen", "#00FA9AFF"),
+    ("mediumturquoise", "#48D1CCFF"),
+    ("mediumvioletred", "#C71585FF"),
+    ("midnightblue", "#191970FF"),
+    ("mintcream", "#F5FFFAFF"),
+    ("mistyrose", "#FFE4E1FF"),
+    ("moccasin", "#FFE4B5FF"),
+    ("navajowhite", "#FFDEADFF"),
+    ("navy", "#000080FF"),
+    ("oldlace", "#FDF5E6FF"),
+    ("olive", "#808000FF"),
+    ("olivedrab", "#6B8E23FF"),
+    ("orange", "#FFA500FF"),
+    ("orangered", "#FF4500FF"),
+    ("orchid", "#DA70D6FF"),
+    ("palegoldenrod", "#EEE8AAFF"),
+    ("palegreen", "#98FB98FF"),
+    ("paleturquoise", "#AFEEEEFF"),
+    ("palevioletred", "#DB7093FF"),
+    ("papayawhip", "#FFEFD5FF"),
+    ("peachpuff", "#FFDAB9FF"),
+    ("peru", "#CD853FFF"),
+    ("pink", "#FFC0CBFF"),
+    ("plum", "#DDA0DDFF"),
+    ("powderblue", "#B0E0E6FF"),
+    ("purple", "#800080FF"),
+    ("rebeccapurple", "#663399FF"),
+    ("red", "#FF0000FF"),
+    ("rosybrown", "#BC8F8FFF"),
+    ("royalblue", "#4169E1FF"),
+    ("saddlebrown", "#8B4513FF"),
+    ("salmon", "#FA8072FF"),
+    ("sandybrown", "#F4A460FF"),
+    ("seagreen", "#2E8B57FF"),
+    ("seashell", "#FFF5EEFF"),
+    ("sienna", "#A0522DFF"),
+    ("silver", "#C0C0C0FF"),
+    ("skyblue", "#87CEEBFF"),
+    ("slateblue", "#6A5ACDFF"),
+    ("slategray", "#708090FF"),
+    ("slategrey", "#708090FF"),
+    ("snow", "#FFFAFAFF"),
+    ("springgreen", "#00FF7FFF"),
+    ("steelblue", "#4682B4FF"),
+    ("tan", "#D2B48CFF"),
+    ("teal", "#008080FF"),
+    ("thistle", "#D8BFD8FF"),
+    ("tomato", "#FF6347FF"),
+    ("turquoise", "#40E0D0FF"),
+    ("violet", "#EE82EEFF"),
+    ("wheat", "#F5DEB3FF"),
+    ("white", "#FFFFFFFF"),
+    ("whitesmoke", "#F5F5F5FF"),
+    ("yellow", "#FFFF00FF"),
+    ("yellowgreen", "#9ACD32FF"),
+    ("transparent", "#00000000"),
+];
+
 // TODO: Allow for other non-additive tints
 pub fn add_colors(
     a: Option<&str>,
@@ -348,32 +690,93 @@ pub fn add_colors(
     }
 }
 
+/// The relative luminance of a color as defined by WCAG, from 0 for black to 1 for white.
+pub fn relative_luminance(color: &str) -> Option<f64> {
+    let (r, g, b, _) = color_from_rgba(color).ok()?;
+    let linear = |channel: u8| {
+        let c = channel as f64 / 255.;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Some(0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b))
+}
+
+/// The contrast ratio of two colors as defined by WCAG, from 1 to 21.
+pub fn contrast_ratio(a: &str, b: &str) -> Option<f64> {
+    let (a, b) = (relative_luminance(a)?, relative_luminance(b)?);
+    Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+}
+
+/// A foreground color that is readable on `bg`: `fg` if it contrasts enough with it, else black
+/// or white, whichever contrasts more. Returns `fg` as is if `bg` isn't a hex color.
+pub fn readable_color(bg: &str, fg: Option<&str>) -> Option<String> {
+    const MIN_CONTRAST: f64 = 4.5;
+    let bg_luminance = match relative_luminance(bg) {
+        Some(luminance) => luminance,
+        None => return fg.map(String::from),
+    };
+    match fg {
+        Some(fg) if contrast_ratio(bg, fg).map_or(true, |ratio| ratio >= MIN_CONTRAST) => {
+            Some(fg.to_string())
+        }
+        // Black contrasts more than white above the luminance where both are equal
+        _ if bg_luminance > 0.179 => Some("#000000".to_string()),
+        _ => Some("#FFFFFF".to_string()),
+    }
+}
+
+/// Blends the colors `a` and `b`, from `a` if `t` is 0 to `b` if it is 1. Colors that can't be
+/// blended, e.g. if one of them is missing, switch halfway instead.
+pub fn mix_colors(a: Option<&str>, b: Option<&str>, t: f64) -> Option<String> {
+    let t = t.clamp(0., 1.);
+    match (a.map(color_from_rgba), b.map(color_from_rgba)) {
+        (Some(Ok(a)), Some(Ok(b))) => {
+            let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+            Some(color_to_rgba((
+                mix(a.0, b.0),
+                mix(a.1, b.1),
+                mix(a.2, b.2),
+                mix(a.3, b.3),
+            )))
+        }
+        _ if t < 0.5 => a.map(String::from),
+        _ => b.map(String::from),
+    }
+}
+
+/// How far `value` is from `good` (0) towards `critical` (1), clamped to this range. `critical`
+/// may be below `good`, e.g. for a battery level.
+pub fn severity(value: f64, good: f64, critical: f64) -> f64 {
+    if good == critical {
+        return if value >= critical { 1. } else { 0. };
+    }
+    ((value - good) / (critical - good)).clamp(0., 1.)
+}
+
 pub fn format_percent_bar(percent: f32) -> String {
+    format_percent_bar_width(percent, 10)
+}
+
+/// Like `format_percent_bar`, with a bar `width` characters wide.
+pub fn format_percent_bar_width(percent: f32, width: usize) -> String {
+    // (x * one eighth block) https://en.wikipedia.org/wiki/Block_Elements
+    static BARS: [char; 8] = [
+        '\u{2581}', '\u{2582}', '\u{2583}', '\u{2584}', '\u{2585}', '\u{2586}', '\u{2587}',
+        '\u{2588}',
+    ];
+
     let percent = percent.min(100.0);
     let percent = percent.max(0.0);
+    let bucket = 100.0 / width as f32;
 
-    (0..10)
+    (0..width)
         .map(|index| {
-            let bucket_min = (index * 10) as f32;
-            let fraction = percent - bucket_min;
-            //println!("Fraction: {}", fraction);
-            if fraction < 1.25 {
-                '\u{2581}' // 1/8 block for empty so the whole bar is always visible
-            } else if fraction < 2.5 {
-                '\u{2582}' // 2/8 block
-            } else if fraction < 3.75 {
-                '\u{2583}' // 3/8 block
-            } else if fraction < 5.0 {
-                '\u{2584}' // 4/8 block
-            } else if fraction < 6.25 {
-                '\u{2585}' // 5/8 block
-            } else if fraction < 7.5 {
-                '\u{2586}' // 6/8 block
-            } else if fraction < 8.75 {
-                '\u{2587}' // 7/8 block
-            } else {
-                '\u{2588}' // Full block
-            }
+            // 1/8 block for empty so the whole bar is always visible
+            let fraction = (percent - index as f32 * bucket) / bucket;
+            BARS[((fraction * 8.0) as usize).min(7)]
         })
         .collect()
 }
@@ -414,70 +817,739 @@ pub fn format_vec_to_bar_graph(content: &[f64], min: Option<f64>, max: Option<f6
 #[derive(Debug, Clone)]
 pub struct FormatTemplate {
     tokens: Vec<FormatToken>,
+    /// The `short_format` of the block, rendered along with this template
+    short: Option<Box<FormatTemplate>>,
+    /// The last full and short texts, if there is a short template
+    last_render: RefCell<Option<(String, String)>>,
 }
 
 #[derive(Debug, Clone)]
 enum FormatToken {
     Text(String),
-    Var(String),
+    Var(Placeholder),
+    /// A `{?...}` section, shown only if all its placeholders have a value. Its `|`-separated
+    /// alternatives are tried in order.
+    Section(Vec<Vec<FormatToken>>),
 }
 
-impl FormatTemplate {
-    pub fn from_string(s: &str) -> Result<Self> {
-        //valid var tokens: {} containing any amount of alphanumericals
-        let re = Regex::new(r"\{[a-zA-Z0-9_-]+?\}").internal_error("util", "invalid regex")?;
+#[derive(Debug, Clone)]
+struct Placeholder {
+    /// The name of the placeholder, including braces
+    key: String,
+    modifiers: Modifiers,
+    spec: Option<FormatSpec>,
+    /// Set by a `color(...)` specifier, e.g. `{utilization:color(good<50,warning<80,critical)}`
+    thresholds: Option<Vec<Threshold>>,
+    /// Set by a `bar` or `graph` specifier
+    graph: Option<Graph>,
+}
+
+/// Draws the value of a placeholder with unicode blocks instead of text.
+#[derive(Debug, Clone)]
+enum Graph {
+    /// `bar(width)`: a bar filled according to the value, as a percentage
+    Bar(usize),
+    /// `graph(length)`: the history of the last values. It is kept in the template, as each
+    /// rendering adds a value.
+    History(RefCell<Vec<f64>>),
+}
+
+impl Graph {
+    const DEFAULT_LENGTH: usize = 10;
+
+    fn render(&self, value: f64) -> String {
+        match self {
+            Graph::Bar(width) => format_percent_bar_width(value as f32, *width),
+            Graph::History(history) => {
+                let mut history = history.borrow_mut();
+                history.remove(0);
+                history.push(value);
+                format_vec_to_bar_graph(&history, None, None)
+            }
+        }
+    }
+}
+
+impl Placeholder {
+    /// Renders the placeholder from its value, as text and as a number if it is one.
+    fn render<T: Display>(&self, value: &T, number: Option<f64>) -> String {
+        match (&self.graph, number) {
+            (Some(graph), Some(number)) => {
+                let graph = graph.render(number);
+                match self.spec {
+                    Some(ref spec) => spec.apply(&graph),
+                    None => graph,
+                }
+            }
+            _ => match self.spec {
+                Some(ref spec) => spec.apply(value),
+                None => format!("{}", value),
+            },
+        }
+    }
+}
+
+/// Removes the first `name` or `name(args)` from `spec`. Returns `None` if it isn't there, and
+/// the arguments if there are some.
+fn take_function(spec: &mut String, name: &str) -> Result<Option<Option<String>>> {
+    let start = match spec.find(name) {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let after = start + name.len();
+    if !spec[after..].starts_with('(') {
+        spec.replace_range(start..after, "");
+        return Ok(Some(None));
+    }
+
+    let end = spec[after..].find(')').ok_or_else(|| {
+        ConfigurationError(
+            format!("unclosed `{}(` in format string", name),
+            (String::new(), String::new()),
+        )
+    })? + after;
+    let args = spec[after + 1..end].to_string();
+    spec.replace_range(start..=end, "");
+    Ok(Some(Some(args)))
+}
+
+/// One entry of a `color(...)` specifier: the state of the block when the value of the
+/// placeholder is within `bound`.
+#[derive(Debug, Clone)]
+struct Threshold {
+    state: State,
+    bound: Bound,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    Below(f64),
+    Above(f64),
+    Always,
+}
+
+impl Threshold {
+    /// Parses the comma-separated entries inside `color(...)`, e.g. `good<50,warning<80,critical`.
+    fn parse_list(list: &str) -> Result<Vec<Self>> {
+        let invalid = |entry: &str| {
+            ConfigurationError(
+                format!("invalid threshold '{}' in format string", entry),
+                (String::new(), String::new()),
+            )
+        };
+
+        list.split(',')
+            .map(|entry| {
+                let entry = entry.trim();
+                let (state, bound) = match entry.find(|c: char| c == '<' || c == '>') {
+                    Some(idx) => {
+                        let limit = entry[idx + 1..]
+                            .trim()
+                            .parse::<f64>()
+                            .map_err(|_| invalid(entry))?;
+                        let bound = if entry[idx..].starts_with('<') {
+                            Bound::Below(limit)
+                        } else {
+                            Bound::Above(limit)
+                        };
+                        (entry[..idx].trim(), bound)
+                    }
+                    None => (entry, Bound::Always),
+                };
+                let state = match state {
+                    "idle" => State::Idle,
+                    "info" => State::Info,
+                    "good" => State::Good,
+                    "warning" => State::Warning,
+                    "critical" => State::Critical,
+                    _ => return Err(invalid(entry)),
+                };
+                Ok(Threshold { state, bound })
+            })
+            .collect()
+    }
+
+    fn matches(&self, value: f64) -> bool {
+        match self.bound {
+            Bound::Below(limit) => value < limit,
+            Bound::Above(limit) => value > limit,
+            Bound::Always => true,
+        }
+    }
+}
+
+/// The part of a placeholder after the colon, e.g. `>8.2` in `{speed:>8.2}`. It follows the
+/// syntax of Rust's `format!`: `[[fill]align][width][.precision]`.
+#[derive(Debug, Clone, Copy)]
+struct FormatSpec {
+    fill: char,
+    align: Alignment,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Alignment::Left),
+            '^' => Some(Alignment::Center),
+            '>' => Some(Alignment::Right),
+            _ => None,
+        }
+    }
+}
 
-        let mut tokens = vec![];
-        let mut start: usize = 0;
+impl FormatSpec {
+    fn from_string(spec: &str) -> Result<Self> {
+        let invalid = || {
+            ConfigurationError(
+                format!("invalid format specifier '{}'", spec),
+                (String::new(), String::new()),
+            )
+        };
 
-        for re_match in re.find_iter(&s) {
-            if re_match.start() != start {
-                tokens.push(FormatToken::Text(s[start..re_match.start()].to_string()));
+        let mut chars = spec.chars().peekable();
+        let mut fill = ' ';
+        let mut align = Alignment::Left;
+        let mut rest = chars.clone();
+        match (rest.next(), rest.next().and_then(Alignment::from_char)) {
+            (Some(c), Some(a)) => {
+                fill = c;
+                align = a;
+                chars = rest;
+            }
+            (Some(c), None) => {
+                if let Some(a) = Alignment::from_char(c) {
+                    align = a;
+                    chars.next();
+                }
             }
-            tokens.push(FormatToken::Var(re_match.as_str().to_string()));
-            start = re_match.end();
+            (None, _) => (),
         }
 
-        if start != s.len() {
-            tokens.push(FormatToken::Text(s[start..].to_string()));
+        let width = parse_number(&mut chars);
+        let precision = if chars.peek() == Some(&'.') {
+            chars.next();
+            Some(parse_number(&mut chars).ok_or_else(invalid)?)
+        } else {
+            None
+        };
+        if chars.next().is_some() {
+            return Err(invalid());
         }
 
-        Ok(FormatTemplate { tokens })
+        Ok(FormatSpec {
+            fill,
+            align,
+            width,
+            precision,
+        })
+    }
+
+    fn apply<T: Display>(&self, value: &T) -> String {
+        let value = match self.precision {
+            Some(precision) => format!("{:.*}", precision, value),
+            None => format!("{}", value),
+        };
+        let padding = self
+            .width
+            .unwrap_or(0)
+            .saturating_sub(value.chars().count());
+        let (before, after) = match self.align {
+            Alignment::Left => (0, padding),
+            Alignment::Center => (padding / 2, padding - padding / 2),
+            Alignment::Right => (padding, 0),
+        };
+
+        let mut padded = String::with_capacity(value.len() + padding);
+        padded.extend(std::iter::repeat(self.fill).take(before));
+        padded.push_str(&value);
+        padded.extend(std::iter::repeat(self.fill).take(after));
+        padded
+    }
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        digits.push(c);
+        chars.next();
+    }
+    digits.parse::<usize>().ok()
+}
+
+impl FormatTemplate {
+    /// Parses a format of a block, keeping its text as Pango markup if the block has
+    /// `markup = "pango"`.
+    pub fn new(s: &str, shared_config: &SharedConfig) -> Result<Self> {
+        Self::parse(s, shared_config.pango_markup)
+    }
+
+    /// Parses a format whose text is escaped, as it is plain text.
+    pub fn from_string(s: &str) -> Result<Self> {
+        Self::parse(s, false)
+    }
+
+    fn parse(s: &str, pango: bool) -> Result<Self> {
+        //valid var tokens: {} containing any amount of alphanumericals, optionally followed by a
+        //unit, a prefix and a format specifier, e.g. {speed*b;K:>8}
+        let re = Regex::new(
+            r"^\{([a-zA-Z0-9_-]+?)(?:\*([a-zA-Z]*))?(?:;([a-zA-Z0-9]*))?(?::([^{}]*))?\}",
+        )
+        .internal_error("util", "invalid regex")?;
+
+        let mut rest = s;
+        let mut alternatives = parse_tokens(&re, &mut rest, false, pango)?;
+        Ok(FormatTemplate {
+            tokens: alternatives.remove(0),
+            short: None,
+            last_render: RefCell::new(None),
+        })
+    }
+
+    /// Renders `short` with the same values as this template from now on, for the `short_text`
+    /// of the block.
+    pub fn set_short(&mut self, short: FormatTemplate) {
+        self.short = Some(Box::new(short));
+    }
+
+    /// The last text rendered by this template and by its short template, if it has one.
+    pub fn last_render(&self) -> Option<(String, String)> {
+        self.last_render.borrow().clone()
+    }
+
+    fn render_short(
+        &self,
+        rendered: &str,
+        render: impl FnOnce(&FormatTemplate) -> Result<String>,
+    ) -> Result<()> {
+        if let Some(ref short) = self.short {
+            *self.last_render.borrow_mut() = Some((rendered.to_string(), render(short)?));
+        }
+        Ok(())
     }
 
     pub fn render_static_str<T: Display>(&self, vars: &HashMap<&str, T>) -> Result<String> {
         let mut rendered = String::new();
+        render_tokens(
+            &self.tokens,
+            &|placeholder| {
+                let value = lookup(vars, &placeholder.key)?;
+                if !placeholder.modifiers.is_empty() {
+                    return Err(ConfigurationError(
+                        format!(
+                            "{} has no unit, prefix or duration to choose",
+                            placeholder.key
+                        ),
+                        (String::new(), String::new()),
+                    ));
+                }
+                let plain = format!("{}", value);
+                let text = placeholder.render(value, leading_number(&plain));
+                Ok((text, !plain.is_empty()))
+            },
+            &mut rendered,
+        )?;
+        self.render_short(&rendered, |short| short.render_static_str(vars))?;
+        Ok(rendered)
+    }
+
+    /// Renders the template with typed values, whose unit and prefix can be chosen by each
+    /// placeholder.
+    pub fn render(&self, vars: &HashMap<&str, Value>) -> Result<String> {
+        let mut rendered = String::new();
+        render_tokens(
+            &self.tokens,
+            &|placeholder| {
+                let value = lookup(vars, &placeholder.key)?;
+                let text = value.format(&placeholder.modifiers)?;
+                let text = placeholder.render(&text, value.as_number());
+                Ok((text, !value.is_empty()))
+            },
+            &mut rendered,
+        )?;
+        self.render_short(&rendered, |short| short.render(vars))?;
+        Ok(rendered)
+    }
+
+    /// The state picked by the first `color(...)` specifier of the template, if any, from the
+    /// number at the start of the value of its placeholder (e.g. `42` for `42%`).
+    pub fn state_static_str<T: Display>(&self, vars: &HashMap<&str, T>) -> Option<State> {
+        self.state_with(&|key| vars.get(key).and_then(|v| leading_number(&v.to_string())))
+    }
+
+    /// Like `state_static_str`, for typed values. Quantities are compared in their base unit,
+    /// e.g. bytes per second for speeds.
+    pub fn state(&self, vars: &HashMap<&str, Value>) -> Option<State> {
+        self.state_with(&|key| vars.get(key).and_then(Value::as_number))
+    }
+
+    fn state_with(&self, number: &dyn Fn(&str) -> Option<f64>) -> Option<State> {
+        fn find(tokens: &[FormatToken]) -> Option<&Placeholder> {
+            tokens.iter().find_map(|token| match token {
+                FormatToken::Text(_) => None,
+                FormatToken::Var(placeholder) => {
+                    placeholder.thresholds.as_ref().map(|_| placeholder)
+                }
+                FormatToken::Section(alternatives) => {
+                    alternatives.iter().find_map(|tokens| find(tokens))
+                }
+            })
+        }
 
-        for token in &self.tokens {
-            match token {
-                FormatToken::Text(text) => rendered.push_str(&text),
-                FormatToken::Var(ref key) => rendered.push_str(&format!(
-                    "{}",
-                    vars.get(&**key).internal_error(
-                        "util",
-                        &format!("Unknown placeholder in format string: {}", key),
-                    )?
-                )),
+        let placeholder = find(&self.tokens)?;
+        let value = number(&placeholder.key)?;
+        placeholder
+            .thresholds
+            .as_ref()?
+            .iter()
+            .find(|threshold| threshold.matches(value))
+            .map(|threshold| threshold.state)
+    }
+
+    /// The placeholders used by the template, e.g. `{ip}`, in order of appearance.
+    pub fn placeholders(&self) -> Vec<&str> {
+        fn collect<'a>(tokens: &'a [FormatToken], keys: &mut Vec<&'a str>) {
+            for token in tokens {
+                match token {
+                    FormatToken::Text(_) => {}
+                    FormatToken::Var(placeholder) => keys.push(&placeholder.key),
+                    FormatToken::Section(alternatives) => {
+                        for tokens in alternatives {
+                            collect(tokens, keys);
+                        }
+                    }
+                }
             }
         }
+        let mut keys = Vec::new();
+        collect(&self.tokens, &mut keys);
+        keys
+    }
 
-        Ok(rendered)
+    /// Whether the template uses the placeholder `key`, e.g. `{ip}`, so that blocks can skip
+    /// computing values that are never shown.
+    pub fn has_placeholder(&self, key: &str) -> bool {
+        fn contains(tokens: &[FormatToken], key: &str) -> bool {
+            tokens.iter().any(|token| match token {
+                FormatToken::Text(_) => false,
+                FormatToken::Var(placeholder) => placeholder.key == key,
+                FormatToken::Section(alternatives) => {
+                    alternatives.iter().any(|tokens| contains(tokens, key))
+                }
+            })
+        }
+        contains(&self.tokens, key)
+    }
+}
+
+/// Parses the number at the start of `text`, ignoring anything after it such as a unit.
+pub fn leading_number(text: &str) -> Option<f64> {
+    let text = text.trim_start();
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && c == '-')))
+        .map_or(text.len(), |(i, _)| i);
+    text[..end].parse().ok()
+}
+
+fn lookup<'a, T>(vars: &'a HashMap<&str, T>, key: &str) -> Result<&'a T> {
+    vars.get(key).internal_error(
+        "util",
+        &format!("Unknown placeholder in format string: {}", key),
+    )
+}
+
+fn invalid_function(name: &str, args: &str) -> Error {
+    ConfigurationError(
+        format!(
+            "invalid arguments '{}' for `{}` in format string",
+            args, name
+        ),
+        (String::new(), String::new()),
+    )
+}
+
+/// Parses tokens up to the end of `s`, or up to the closing brace of the conditional section
+/// being parsed if `in_section` is set. Returns the `|`-separated alternatives of the section.
+/// Text is escaped unless it is Pango markup.
+fn parse_tokens(
+    re: &Regex,
+    s: &mut &str,
+    in_section: bool,
+    pango: bool,
+) -> Result<Vec<Vec<FormatToken>>> {
+    let mut alternatives = vec![];
+    let mut tokens = vec![];
+    let mut text = String::new();
+
+    loop {
+        let current = *s;
+        if let Some(captures) = re.captures(current) {
+            if !text.is_empty() {
+                tokens.push(FormatToken::Text(std::mem::take(&mut text)));
+            }
+            let mut modifiers = Modifiers::from_strings(
+                captures.get(2).map(|m| m.as_str()),
+                captures.get(3).map(|m| m.as_str()),
+            )?;
+            let mut spec = captures
+                .get(4)
+                .map_or_else(String::new, |m| m.as_str().to_string());
+            match take_function(&mut spec, "dur")? {
+                Some(Some(args)) => {
+                    modifiers = modifiers.with_duration(DurationFormat::from_string(&args)?)
+                }
+                Some(None) => modifiers = modifiers.with_duration(DurationFormat::Largest),
+                None => (),
+            }
+            let thresholds = match take_function(&mut spec, "color")? {
+                Some(Some(list)) => Some(Threshold::parse_list(&list)?),
+                Some(None) => return Err(invalid_function("color", "")),
+                None => None,
+            };
+            let length = |args: Option<String>, name| match args {
+                Some(args) => args
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&length| length > 0)
+                    .ok_or_else(|| invalid_function(name, &args)),
+                None => Ok(Graph::DEFAULT_LENGTH),
+            };
+            let graph = match take_function(&mut spec, "bar")? {
+                Some(args) => Some(Graph::Bar(length(args, "bar")?)),
+                None => match take_function(&mut spec, "graph")? {
+                    Some(args) => Some(Graph::History(RefCell::new(vec![
+                        0.;
+                        length(args, "graph")?
+                    ]))),
+                    None => None,
+                },
+            };
+            let spec = if spec.is_empty() {
+                None
+            } else {
+                Some(FormatSpec::from_string(&spec)?)
+            };
+            tokens.push(FormatToken::Var(Placeholder {
+                key: format!("{{{}}}", &captures[1]),
+                modifiers,
+                spec,
+                thresholds,
+                graph,
+            }));
+            *s = &current[captures.get(0).unwrap().end()..];
+            continue;
+        }
+
+        if let Some(inner) = current.strip_prefix("{?") {
+            if !text.is_empty() {
+                tokens.push(FormatToken::Text(std::mem::take(&mut text)));
+            }
+            *s = inner;
+            tokens.push(FormatToken::Section(parse_tokens(re, s, true, pango)?));
+            continue;
+        }
+
+        let mut chars = current.chars();
+        match chars.next() {
+            Some('}') if in_section => {
+                *s = chars.as_str();
+                break;
+            }
+            Some('|') if in_section => {
+                if !text.is_empty() {
+                    tokens.push(FormatToken::Text(std::mem::take(&mut text)));
+                }
+                alternatives.push(std::mem::take(&mut tokens));
+            }
+            Some(c) if pango => text.push(c),
+            Some(c) => text.push_str(&escape_pango_text(c.to_string())),
+            None if in_section => {
+                return Err(ConfigurationError(
+                    "unclosed `{?` section in format string".to_string(),
+                    (String::new(), String::new()),
+                ))
+            }
+            None => break,
+        }
+        *s = chars.as_str();
+    }
+
+    if !text.is_empty() {
+        tokens.push(FormatToken::Text(text));
     }
+    alternatives.push(tokens);
+    Ok(alternatives)
+}
+
+/// Renders `tokens` into `rendered`, using `render_var` to get the text of a placeholder and
+/// whether its value is non-empty. Returns whether all the placeholders had a non-empty value,
+/// which is what decides if a conditional section is shown.
+fn render_tokens(
+    tokens: &[FormatToken],
+    render_var: &dyn Fn(&Placeholder) -> Result<(String, bool)>,
+    rendered: &mut String,
+) -> Result<bool> {
+    let mut complete = true;
+
+    for token in tokens {
+        match token {
+            FormatToken::Text(text) => rendered.push_str(&text),
+            FormatToken::Var(placeholder) => {
+                let (text, non_empty) = render_var(placeholder)?;
+                complete &= non_empty;
+                rendered.push_str(&text);
+            }
+            FormatToken::Section(ref alternatives) => {
+                // Show the first alternative whose placeholders all have a value, if any
+                for alternative in alternatives {
+                    let mut section = String::new();
+                    if render_tokens(alternative, render_var, &mut section)? {
+                        rendered.push_str(&section);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(complete)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::util::{color_from_rgba, format_number, has_command};
+    use crate::config::SharedConfig;
+    use crate::formatting::Value;
+    use crate::util::{
+        color_from_rgba, contrast_ratio, has_command, mix_colors, parse_color, readable_color,
+        severity, FormatTemplate,
+    };
+    use crate::widgets::State;
+    use std::time::Duration;
 
     #[test]
-    fn test_format_number() {
-        assert_eq!(format_number(1.0, 3, "", "s"), "1.00s");
-        assert_eq!(format_number(1.007, 3, "", "s"), "1.01s");
-        assert_eq!(format_number(1.007, 4, "K", "s"), "0.001Ks");
-        assert_eq!(format_number(1007., 3, "K", "s"), "1.01Ks");
-        assert_eq!(format_number(107_000., 3, "", "s"), "107Ks");
-        assert_eq!(format_number(107., 3, "", "s"), "107s");
-        assert_eq!(format_number(0.000_123_123, 3, "", "N"), "123uN");
+    fn test_format_template_spec() {
+        let vars = map!("{speed}" => 3.14159, "{count}" => 42.);
+        let template =
+            FormatTemplate::from_string("{speed:>8.2}|{count:<5}|{count:*^6.0}").unwrap();
+        assert_eq!(
+            template.render_static_str(&vars).unwrap(),
+            "    3.14|42   |**42**"
+        );
+
+        let vars = map!("{title}" => "a long window title");
+        let template = FormatTemplate::from_string("[{title:.6}]").unwrap();
+        assert_eq!(template.render_static_str(&vars).unwrap(), "[a long]");
+
+        assert!(FormatTemplate::from_string("{title:x}").is_err());
+        assert!(FormatTemplate::from_string("{title:5.}").is_err());
+    }
+
+    #[test]
+    fn test_format_template_thresholds() {
+        let template =
+            FormatTemplate::from_string("{utilization:>4color(good<50,warning<80,critical)}")
+                .unwrap();
+        let vars = map!("{utilization}" => "42%");
+        assert_eq!(template.render_static_str(&vars).unwrap(), " 42%");
+        assert!(matches!(
+            template.state_static_str(&vars),
+            Some(State::Good)
+        ));
+        let vars = map!("{utilization}" => "95%");
+        assert!(matches!(
+            template.state_static_str(&vars),
+            Some(State::Critical)
+        ));
+
+        let template = FormatTemplate::from_string("{1m:color(info>2)}").unwrap();
+        let vars = map!("{1m}" => "0.52");
+        assert!(template.state_static_str(&vars).is_none());
+
+        assert!(FormatTemplate::from_string("{1m:color(bad<2)}").is_err());
+        assert!(FormatTemplate::from_string("{1m:color(info<x)}").is_err());
+    }
+
+    #[test]
+    fn test_format_template_graph() {
+        let template = FormatTemplate::from_string("{usage:bar(4)} {usage:graph(3)}").unwrap();
+        let vars = map!("{usage}" => "50%");
+        assert_eq!(template.render_static_str(&vars).unwrap(), "██▁▁ ▁▁█");
+        let vars = map!("{usage}" => "25%");
+        assert_eq!(template.render_static_str(&vars).unwrap(), "█▁▁▁ ▁█▄");
+
+        // Values that aren't numbers are shown as text
+        let vars = map!("{usage}" => "N/A");
+        assert_eq!(template.render_static_str(&vars).unwrap(), "N/A N/A");
+
+        assert!(FormatTemplate::from_string("{usage:bar(0)}").is_err());
+        assert!(FormatTemplate::from_string("{usage:graph(3}").is_err());
+    }
+
+    #[test]
+    fn test_format_template_section() {
+        let template = FormatTemplate::from_string("{?{artist} - }{title}").unwrap();
+        let vars = map!("{artist}" => "Queen", "{title}" => "Bohemian Rhapsody");
+        assert_eq!(
+            template.render_static_str(&vars).unwrap(),
+            "Queen - Bohemian Rhapsody"
+        );
+        let vars = map!("{artist}" => "", "{title}" => "Bohemian Rhapsody");
+        assert_eq!(
+            template.render_static_str(&vars).unwrap(),
+            "Bohemian Rhapsody"
+        );
+
+        let template = FormatTemplate::from_string("{?{ssid} {ip}|{ip}|down}").unwrap();
+        let vars = map!("{ssid}" => "", "{ip}" => "10.0.0.2");
+        assert_eq!(template.render_static_str(&vars).unwrap(), "10.0.0.2");
+        let vars = map!("{ssid}" => "", "{ip}" => "");
+        assert_eq!(template.render_static_str(&vars).unwrap(), "down");
+
+        // Outside of sections, braces and bars are plain text
+        let template = FormatTemplate::from_string("{a} | {b").unwrap();
+        let vars = map!("{a}" => "x");
+        assert_eq!(template.render_static_str(&vars).unwrap(), "x | {b");
+
+        assert!(FormatTemplate::from_string("{?{title}").is_err());
+    }
+
+    #[test]
+    fn test_format_template_markup() {
+        let vars = map!("{title}" => "Tom & Jerry");
+        let template = FormatTemplate::from_string("<b>{title}</b> & co").unwrap();
+        assert_eq!(
+            template.render_static_str(&vars).unwrap(),
+            "&lt;b&gt;Tom & Jerry&lt;/b&gt; &amp; co"
+        );
+
+        let mut shared_config = SharedConfig::default();
+        shared_config.pango_markup = true;
+        let template = FormatTemplate::new("<b>{title}</b>", &shared_config).unwrap();
+        assert_eq!(
+            template.render_static_str(&vars).unwrap(),
+            "<b>Tom & Jerry</b>"
+        );
+    }
+
+    #[test]
+    fn test_format_template_duration() {
+        let values = map!("{time}" => Value::duration(Duration::from_secs(4980)));
+        let render = |format: &str| FormatTemplate::from_string(format)?.render(&values);
+        assert_eq!(render("{time}").unwrap(), "1h 23m");
+        assert_eq!(render("{time:dur(h:m)}").unwrap(), "01:23");
+        assert_eq!(render("{time:>8dur(ms)}").unwrap(), "  83m 0s");
+        assert!(render("{time:dur(x)}").is_err());
+        assert!(render("{time*B}").is_err());
     }
 
     #[test]
@@ -509,6 +1581,27 @@ mod tests {
         assert_eq!(rgba.unwrap(), (0xAA, 0xBB, 0xCC, 0x00));
     }
 
+    #[test]
+    fn test_mix_colors() {
+        assert_eq!(
+            mix_colors(Some("#000000"), Some("#FF8040"), 0.5),
+            Some("#804020FF".to_string())
+        );
+        assert_eq!(
+            mix_colors(Some("#000000"), Some("#FF8040"), 2.),
+            Some("#FF8040FF".to_string())
+        );
+        assert_eq!(mix_colors(None, Some("#FF8040"), 0.25), None);
+        assert_eq!(
+            mix_colors(None, Some("#FF8040"), 0.75),
+            Some("#FF8040".to_string())
+        );
+
+        assert_eq!(severity(50., 0., 100.), 0.5);
+        assert_eq!(severity(30., 60., 10.), 0.6);
+        assert_eq!(severity(120., 0., 100.), 1.);
+    }
+
     #[test]
     fn test_color_from_rgba_invalid() {
         let invalid = "invalid";
@@ -521,4 +1614,38 @@ mod tests {
         let rgba = color_from_rgba(invalid);
         assert!(rgba.is_err());
     }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#123456").unwrap(), "#123456");
+        assert_eq!(parse_color("auto").unwrap(), "auto");
+        assert_eq!(parse_color("RebeccaPurple").unwrap(), "#663399FF");
+        assert_eq!(parse_color("hsl(200, 50%, 40%)").unwrap(), "#337799FF");
+        assert_eq!(
+            parse_color("hsla(200deg 50% 40% 0.5)").unwrap(),
+            "#33779980"
+        );
+        assert_eq!(parse_color("hsv(120, 100%, 50%)").unwrap(), "#008000FF");
+        assert_eq!(parse_color("hsl(0, 0%, 100%)").unwrap(), "#FFFFFFFF");
+        assert!(parse_color("hsl(200, 50%)").is_err());
+        assert!(parse_color("notacolor").is_err());
+    }
+
+    #[test]
+    fn test_readable_color() {
+        assert_eq!(
+            contrast_ratio("#000000", "#FFFFFF").map(f64::round),
+            Some(21.)
+        );
+        assert_eq!(
+            readable_color("#FFFF00", Some("#FFFFFF")),
+            Some("#000000".to_string())
+        );
+        assert_eq!(readable_color("#000080", None), Some("#FFFFFF".to_string()));
+        // Readable colors are kept
+        assert_eq!(
+            readable_color("#000080", Some("#FFFF00")),
+            Some("#FFFF00".to_string())
+        );
+    }
 }
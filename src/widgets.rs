@@ -41,6 +41,18 @@ impl State {
             Critical => (&theme.critical_bg, &theme.critical_fg),
         }
     }
+
+    /// The color of the border of a widget in this state, if the theme draws one.
+    pub fn theme_border(self, theme: &Theme) -> &Option<String> {
+        use self::State::*;
+        match self {
+            Idle => &theme.idle_border,
+            Info => &theme.info_border,
+            Good => &theme.good_border,
+            Warning => &theme.warning_border,
+            Critical => &theme.critical_border,
+        }
+    }
 }
 
 impl FromStr for State {
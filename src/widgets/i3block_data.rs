@@ -1,3 +1,7 @@
+use serde_derive::Deserialize;
+
+use crate::widgets::State;
+
 /// Represent block as described in https://i3wm.org/docs/i3bar-protocol.html
 
 #[derive(Debug, Clone)]
@@ -19,6 +23,9 @@ pub struct I3BlockData {
     pub separator: Option<bool>,
     pub separator_block_width: Option<usize>,
     pub markup: Option<String>,
+    /// The state the widget is rendered in. This is not part of the i3bar protocol and is only
+    /// used by other output formats.
+    pub state: Option<State>,
 }
 
 macro_rules! json_add_str {
@@ -81,20 +88,35 @@ impl I3BlockData {
         retval.push('}');
         retval
     }
+
+    /// Sets the border of the theme, `width` pixels wide on each side. Without a border, the
+    /// debug border is kept if it is enabled.
+    pub fn set_border(&mut self, border: Option<String>, width: Option<usize>) {
+        let width = width.filter(|_| border.is_some());
+        self.border = border.or_else(default_border);
+        self.border_top = width;
+        self.border_right = width;
+        self.border_bottom = width;
+        self.border_left = width;
+    }
+}
+
+fn default_border() -> Option<String> {
+    if cfg!(feature = "debug_borders") {
+        Some("#ff0000".to_string())
+    } else {
+        None
+    }
 }
 
 impl Default for I3BlockData {
     fn default() -> Self {
-        #[cfg(not(feature = "debug_borders"))]
-        let border = None;
-        #[cfg(feature = "debug_borders")]
-        let border = Some("#ff0000".to_string());
         Self {
             full_text: String::new(),
             short_text: None,
             color: None,
             background: None,
-            border,
+            border: default_border(),
             border_top: None,
             border_right: None,
             border_bottom: None,
@@ -107,18 +129,21 @@ impl Default for I3BlockData {
             separator: Some(false),
             separator_block_width: Some(0),
             markup: Some("pango".to_string()),
+            state: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum I3BlockAlign {
     Center,
     Right,
     Left,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
 pub enum I3BlockMinWidth {
     Pixels(usize),
     Text(String),
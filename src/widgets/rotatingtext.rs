@@ -178,6 +178,11 @@ impl RotatingTextWidget {
         };
         self.inner.background = key_bg.clone();
         self.inner.color = key_fg.clone();
+        self.inner.set_border(
+            self.state.theme_border(&self.shared_config.theme).clone(),
+            self.shared_config.theme.border_width,
+        );
+        self.inner.state = Some(self.state);
     }
 
     pub fn next(&mut self) -> Result<(bool, Option<Duration>)> {
@@ -8,6 +8,8 @@ pub struct TextWidget {
     content: Option<String>,
     icon: Option<String>,
     state: State,
+    /// How close to critical the value shown is, from 0 to 1, for `gradient` themes
+    severity: Option<f64>,
     spacing: Spacing,
     shared_config: SharedConfig,
     inner: I3BlockData,
@@ -27,6 +29,7 @@ impl TextWidget {
             content: None,
             icon: None,
             state: State::Idle,
+            severity: None,
             spacing: Spacing::Normal,
             shared_config,
             inner,
@@ -75,13 +78,27 @@ impl TextWidget {
         self.update();
     }
 
+    /// Sets how close to critical the value shown is, from 0 (good) to 1 (critical). With a
+    /// `gradient` theme, the colors are blended from it rather than picked from the state.
+    pub fn set_severity(&mut self, severity: Option<f64>) {
+        self.severity = severity;
+        self.update();
+    }
+
     pub fn set_spacing(&mut self, spacing: Spacing) {
         self.spacing = spacing;
         self.update();
     }
 
     fn update(&mut self) {
-        let (key_bg, key_fg) = self.state.theme_keys(&self.shared_config.theme);
+        let theme = &self.shared_config.theme;
+        let (key_bg, key_fg) = match self.severity {
+            Some(severity) if theme.gradient => theme.gradient_colors(severity),
+            _ => {
+                let (key_bg, key_fg) = self.state.theme_keys(theme);
+                (key_bg.clone(), key_fg.clone())
+            }
+        };
 
         // When rendered inline, remove the leading space
         self.inner.full_text = format!(
@@ -98,8 +115,11 @@ impl TextWidget {
                 _ => String::from(" "),
             }
         );
-        self.inner.background = key_bg.clone();
-        self.inner.color = key_fg.clone();
+        self.inner.background = key_bg;
+        self.inner.color = key_fg;
+        self.inner
+            .set_border(self.state.theme_border(theme).clone(), theme.border_width);
+        self.inner.state = Some(self.state);
     }
 }
 